@@ -0,0 +1,186 @@
+//! Analysis of decrypted codes.
+//!
+//! A [`Heatmap`] bins the addresses written by decrypted codes across the 32
+//! MB of EE RAM. Front-ends can visualize the bins to spot corrupted lists at
+//! a glance: real cheats cluster in a few regions, while garbage decrypts to
+//! uniformly random addresses. Use one heatmap per game, or
+//! [`merge`](struct.Heatmap.html#method.merge) them for a whole collection.
+//!
+//! # Example
+//! ```
+//! use codebreaker::analysis::Heatmap;
+//!
+//! let codes = [(0x2043AFCC, 0x2411FFFF), (0x201F6024, 0x00000000), (0x2096F5B8, 0x000000BE)];
+//!
+//! let mut heatmap = Heatmap::new();
+//! heatmap.add_codes(codes.iter().copied());
+//! assert_eq!(3, heatmap.total());
+//! assert_eq!(3, heatmap.spread());
+//! assert_eq!(1, heatmap.bins()[Heatmap::bin(0x0043AFCC)]);
+//! ```
+
+use core::ops::Range;
+
+use crate::num_code_lines;
+
+/// The number of bins of a [`Heatmap`].
+pub const BINS: usize = 256;
+
+/// The number of bytes covered by a single bin.
+pub const BIN_SIZE: u32 = RAM_SIZE / BINS as u32;
+
+const RAM_SIZE: u32 = 0x0200_0000;
+
+/// A histogram of write addresses.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Heatmap {
+    bins: [u32; BINS],
+    total: u32,
+}
+
+/// Does the same as [`new`](#method.new).
+impl Default for Heatmap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Heatmap {
+    /// Returns an empty heatmap.
+    pub const fn new() -> Self {
+        Self {
+            bins: [0; BINS],
+            total: 0,
+        }
+    }
+
+    /// Returns the index of the bin an address belongs to.
+    pub const fn bin(addr: u32) -> usize {
+        ((addr & (RAM_SIZE - 1)) / BIN_SIZE) as usize
+    }
+
+    /// Returns the address range covered by a bin.
+    pub const fn bin_range(bin: usize) -> Range<u32> {
+        let start = bin as u32 * BIN_SIZE;
+        start..start + BIN_SIZE
+    }
+
+    /// Adds a single write address.
+    pub fn add(&mut self, addr: u32) {
+        self.bins[Self::bin(addr)] += 1;
+        self.total += 1;
+    }
+
+    /// Adds the write addresses of a list of decrypted codes.
+    ///
+    /// Multi-line codes are taken into account. Code types that do not write
+    /// to memory, like conditionals and hooks, are skipped.
+    pub fn add_codes<I>(&mut self, codes: I)
+    where
+        I: IntoIterator<Item = (u32, u32)>,
+    {
+        let mut skip = 0;
+        for (addr, val) in codes {
+            if skip > 0 {
+                skip -= 1;
+                continue;
+            }
+            skip = num_code_lines(addr) - 1;
+            if let Some(addr) = write_address(addr, val) {
+                self.add(addr);
+            }
+        }
+    }
+
+    /// Adds all bins of another heatmap.
+    pub fn merge(&mut self, other: &Self) {
+        for (a, b) in self.bins.iter_mut().zip(other.bins.iter()) {
+            *a += b;
+        }
+        self.total += other.total;
+    }
+
+    /// Returns the bins.
+    pub const fn bins(&self) -> &[u32; BINS] {
+        &self.bins
+    }
+
+    /// Returns the number of write addresses added.
+    pub const fn total(&self) -> u32 {
+        self.total
+    }
+
+    /// Returns the number of bins with at least one write address.
+    ///
+    /// A spread close to the total number of addresses hints at a list that
+    /// has not been decrypted correctly.
+    pub fn spread(&self) -> usize {
+        self.bins.iter().filter(|&&n| n > 0).count()
+    }
+}
+
+// Returns the address written by the first line of a code, if any.
+pub(crate) const fn write_address(addr: u32, val: u32) -> Option<u32> {
+    match addr >> 28 {
+        // constant writes, serial writes, copy bytes, pointer writes, boolean ops
+        0 | 1 | 2 | 4 | 5 | 6 | 7 => Some(addr & 0x01ff_ffff),
+        // increment/decrement carries the address in the value
+        3 => Some(val & 0x01ff_ffff),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::code;
+    use crate::std_alloc::Vec;
+
+    #[test]
+    fn test_bin() {
+        assert_eq!(0, Heatmap::bin(0x0000_0000));
+        assert_eq!(0, Heatmap::bin(BIN_SIZE - 1));
+        assert_eq!(1, Heatmap::bin(BIN_SIZE));
+        assert_eq!(BINS - 1, Heatmap::bin(0x01ff_ffff));
+        assert_eq!(BIN_SIZE..2 * BIN_SIZE, Heatmap::bin_range(1));
+    }
+
+    #[test]
+    fn test_add_codes() {
+        #[rustfmt::skip]
+        let codes: Vec<(u32, u32)> = [
+            "BEEFC0DF B16B00B5",
+            "01234567 89ABCDEF", // extra seed value
+            "0031789A 00000063",
+            "30400000 00100000",
+            "00200000 00000000", // second line of 32-bit increment
+            "D0100000 00000001", // conditional
+            "40410000 00020001",
+            "00000001 00000000", // second line of serial write
+        ]
+        .iter()
+        .map(|line| code::parse(line))
+        .collect();
+
+        let mut heatmap = Heatmap::new();
+        heatmap.add_codes(codes);
+        assert_eq!(3, heatmap.total());
+        assert_eq!(3, heatmap.spread());
+        assert_eq!(1, heatmap.bins()[Heatmap::bin(0x0031_789a)]);
+        assert_eq!(1, heatmap.bins()[Heatmap::bin(0x0010_0000)]);
+        assert_eq!(1, heatmap.bins()[Heatmap::bin(0x0041_0000)]);
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut a = Heatmap::new();
+        a.add(0x0010_0000);
+        let mut b = Heatmap::new();
+        b.add(0x0010_0004);
+        b.add(0x01f0_0000);
+        a.merge(&b);
+        assert_eq!(3, a.total());
+        assert_eq!(2, a.bins()[Heatmap::bin(0x0010_0000)]);
+        assert_eq!(1, a.bins()[Heatmap::bin(0x01f0_0000)]);
+    }
+}
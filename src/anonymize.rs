@@ -0,0 +1,135 @@
+//! Anonymization of code lists for bug reports.
+//!
+//! [`anonymize`] replaces the values that make up the content of a cheat with
+//! pseudo-random ones, so a list that triggers a bug can be shared publicly
+//! without distributing someone else's work. Code types, addresses, and the
+//! layout of multi-line codes stay the same, which is usually what matters
+//! for reproducing a bug.
+//!
+//! # Example
+//! ```
+//! use codebreaker::anonymize::anonymize;
+//!
+//! let mut codes = [(0x2043AFCC, 0x2411FFFF), (0x1043AFD0, 0x00000063)];
+//! anonymize(&mut codes, 42);
+//! assert_eq!(0x2043AFCC, codes[0].0);
+//! assert_eq!(0x1043AFD0, codes[1].0);
+//! assert_eq!(0, codes[1].1 >> 16);
+//! ```
+
+use crate::num_code_lines;
+
+/// Replaces the written and compared values of decrypted codes in place.
+///
+/// The same seed always gives the same result. Values of writes and serial
+/// writes are replaced, as are the values compared by conditionals. All other
+/// fields, and codes of other types, are left alone.
+pub fn anonymize(codes: &mut [(u32, u32)], seed: u64) {
+    let mut rng = SplitMix64(seed);
+    let mut continuation = 0;
+    let mut serial = false;
+
+    for code in codes.iter_mut() {
+        let (addr, val) = *code;
+        let (addr_mask, val_mask) = if continuation > 0 {
+            continuation -= 1;
+            if serial {
+                // The value of the first write; the increment is kept
+                (0xffff_ffff, 0)
+            } else {
+                (0, 0)
+            }
+        } else {
+            continuation = num_code_lines(addr) - 1;
+            serial = addr >> 28 == 0x4;
+            masks(addr, val)
+        };
+
+        let r = rng.next();
+        code.0 = replace(addr, addr_mask, (r >> 32) as u32);
+        code.1 = replace(val, val_mask, r as u32);
+    }
+}
+
+// Returns the bits of the first line of a code holding values.
+const fn masks(addr: u32, val: u32) -> (u32, u32) {
+    match addr >> 28 {
+        0x0 => (0, 0xff),
+        0x1 => (0, 0xffff),
+        0x2 | 0xc => (0, 0xffff_ffff),
+        0xd => (0, compare_mask(val)),
+        0xe => (compare_mask(addr), 0),
+        _ => (0, 0),
+    }
+}
+
+// Conditionals flag 8-bit comparisons with bit 24.
+const fn compare_mask(field: u32) -> u32 {
+    if field & 0x0100_0000 != 0 {
+        0xff
+    } else {
+        0xffff
+    }
+}
+
+const fn replace(x: u32, mask: u32, random: u32) -> u32 {
+    (x & !mask) | (random & mask)
+}
+
+// SplitMix64, more than good enough to scramble values.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[rustfmt::skip]
+    const CODES: [(u32, u32); 10] = [
+        (0xbeef_c0df, 0x1234_5678), (0x0000_0000, 0x1111_1111), // seed line
+        (0x0031_789a, 0x0000_0063),
+        (0x1031_789a, 0x0000_ffff),
+        (0x4041_0000, 0x0004_0001), (0x0000_0063, 0x0000_0001), // serial write
+        (0xd04a_0000, 0x0100_0001),
+        (0xe002_0001, 0x1030_0000),
+        (0x3040_0000, 0x0031_78a0), (0x0000_0001, 0x0000_0000), // 32-bit increment
+    ];
+
+    #[test]
+    fn test_anonymize() {
+        let mut codes = CODES;
+        anonymize(&mut codes, 1);
+        for (i, (a, b)) in CODES.iter().zip(codes.iter()).enumerate() {
+            let (addr_mask, val_mask) = match i {
+                2 => (0, 0xff),
+                3 => (0, 0xffff),
+                5 => (0xffff_ffff, 0),
+                6 => (0, 0xff),
+                7 => (0xffff, 0),
+                _ => (0, 0),
+            };
+            assert_eq!(a.0 & !addr_mask, b.0 & !addr_mask, "line {}", i);
+            assert_eq!(a.1 & !val_mask, b.1 & !val_mask, "line {}", i);
+        }
+        assert_ne!(CODES, codes);
+    }
+
+    #[test]
+    fn test_deterministic() {
+        let (mut a, mut b, mut c) = (CODES, CODES, CODES);
+        anonymize(&mut a, 7);
+        anonymize(&mut b, 7);
+        anonymize(&mut c, 8);
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+    }
+}
@@ -0,0 +1,256 @@
+//! Audit trail of processed codes.
+//!
+//! A [`Recorder`] decrypts codes like
+//! [`auto_decrypt_code`](../struct.Codebreaker.html#method.auto_decrypt_code)
+//! and keeps an [`Entry`] for every line: the code before and after
+//! decryption, the scheme used, scheme changes of the processor, and
+//! warnings. The log can be exported as JSON to settle whether a converter
+//! really corrupted a list.
+//!
+//! # Example
+//! ```
+//! use codebreaker::audit::{Recorder, Transition};
+//! use codebreaker::{Codebreaker, Scheme};
+//!
+//! let mut rec = Recorder::new(Codebreaker::new());
+//! rec.decrypt_code(0x2A973DBD, 0x00000000);
+//! rec.decrypt_code(0xB4336FA9, 0x4DFEFB79);
+//! rec.decrypt_code(0x973E0B2A, 0xA7D4AF10);
+//!
+//! let entries = rec.entries();
+//! assert_eq!(3, entries.len());
+//! assert_eq!(Scheme::V1, entries[0].scheme);
+//! assert_eq!(Some(Transition { from: Scheme::V1, to: Scheme::V7 }), entries[1].transition);
+//! assert_eq!((0x2096F5B8, 0x000000BE), entries[2].decrypted);
+//! ```
+
+use crate::repair::is_plausible;
+use crate::std_alloc::{String, Vec};
+use crate::{Codebreaker, Error, Scheme};
+
+use core::fmt::{self, Write};
+
+/// A change of the scheme the processor applies to the following codes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Transition {
+    /// The scheme before processing the line
+    pub from: Scheme,
+    /// The scheme after processing the line
+    pub to: Scheme,
+}
+
+/// A problem noticed while processing a line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Warning {
+    /// The code was passed through unchanged because it contradicts the
+    /// pinned scheme.
+    Rejected(Error),
+    /// The decrypted code does not look like a valid code, see
+    /// [`is_plausible`](../repair/fn.is_plausible.html).
+    Implausible,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Rejected(err) => write!(f, "rejected: {}", err),
+            Self::Implausible => f.write_str("decrypted code is implausible"),
+        }
+    }
+}
+
+/// A processed line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Entry {
+    /// Index of the line
+    pub line: usize,
+    /// The code as passed in
+    pub original: (u32, u32),
+    /// The decrypted code
+    pub decrypted: (u32, u32),
+    /// The scheme used for decryption
+    pub scheme: Scheme,
+    /// The scheme change caused by the line, if any
+    pub transition: Option<Transition>,
+    /// The problem noticed, if any
+    pub warning: Option<Warning>,
+}
+
+/// A processor that records every line it decrypts.
+#[derive(Debug, Clone)]
+pub struct Recorder {
+    cb: Codebreaker,
+    entries: Vec<Entry>,
+}
+
+impl Recorder {
+    /// Returns a recorder decrypting codes with the given processor.
+    pub const fn new(cb: Codebreaker) -> Self {
+        Self {
+            cb,
+            entries: Vec::new(),
+        }
+    }
+
+    /// Decrypts a code, records it, and returns the result.
+    ///
+    /// Codes that contradict a [pinned](../struct.Codebreaker.html#method.pin_scheme)
+    /// scheme are returned unchanged and recorded with a warning.
+    pub fn decrypt_code(&mut self, addr: u32, val: u32) -> (u32, u32) {
+        let from = self.cb.scheme();
+        let mut code = (addr, val);
+
+        let (scheme, warning) = match self.cb.detect_and_decrypt(&mut code.0, &mut code.1) {
+            Ok(scheme) if !is_plausible(code) => (scheme, Some(Warning::Implausible)),
+            Ok(scheme) => (scheme, None),
+            Err(err) => (Scheme::Raw, Some(Warning::Rejected(err))),
+        };
+
+        let to = self.cb.scheme();
+        self.entries.push(Entry {
+            line: self.entries.len(),
+            original: (addr, val),
+            decrypted: code,
+            scheme,
+            transition: if from == to {
+                None
+            } else {
+                Some(Transition { from, to })
+            },
+            warning,
+        });
+        code
+    }
+
+    /// Returns the processor in its current state.
+    pub const fn codebreaker(&self) -> &Codebreaker {
+        &self.cb
+    }
+
+    /// Returns all recorded entries.
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    /// Returns the entries that have a warning.
+    pub fn warnings(&self) -> impl Iterator<Item = &Entry> {
+        self.entries.iter().filter(|e| e.warning.is_some())
+    }
+
+    /// Writes the log as a JSON object.
+    pub fn write_json<W: Write>(&self, w: &mut W) -> fmt::Result {
+        w.write_str("{\"entries\":[")?;
+        for (i, e) in self.entries.iter().enumerate() {
+            if i > 0 {
+                w.write_char(',')?;
+            }
+            write!(
+                w,
+                "{{\"line\":{},\"original\":\"{:08X} {:08X}\",\"decrypted\":\"{:08X} {:08X}\",\"scheme\":\"{}\"",
+                e.line, e.original.0, e.original.1, e.decrypted.0, e.decrypted.1, e.scheme
+            )?;
+            if let Some(t) = e.transition {
+                write!(w, ",\"transition\":{{\"from\":\"{}\",\"to\":\"{}\"}}", t.from, t.to)?;
+            }
+            if let Some(warning) = e.warning {
+                // Warning messages contain no quotes or backslashes
+                write!(w, ",\"warning\":\"{}\"", warning)?;
+            }
+            w.write_char('}')?;
+        }
+        w.write_str("]}")
+    }
+
+    /// Returns the log as a JSON string.
+    ///
+    /// # Example
+    /// ```
+    /// use codebreaker::audit::Recorder;
+    /// use codebreaker::Codebreaker;
+    ///
+    /// let mut rec = Recorder::new(Codebreaker::new());
+    /// rec.decrypt_code(0x2043AFCC, 0x2411FFFF);
+    /// assert_eq!(
+    ///     r#"{"entries":[{"line":0,"original":"2043AFCC 2411FFFF","decrypted":"2043AFCC 2411FFFF","scheme":"RAW"}]}"#,
+    ///     rec.to_json()
+    /// );
+    /// ```
+    pub fn to_json(&self) -> String {
+        let mut s = String::new();
+        // Writing to a String cannot fail
+        let _ = self.write_json(&mut s);
+        s
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::code;
+
+    #[test]
+    fn test_decrypt_code() {
+        #[rustfmt::skip]
+        let tests = [
+            ("2043AFCC 2411FFFF", "2043AFCC 2411FFFF", Scheme::Raw, None, None),
+            ("2A973DBD 00000000", "201F6024 00000000", Scheme::V1, Some((Scheme::Raw, Scheme::V1)), None),
+            ("B4336FA9 4DFEFB79", "BEEFC0DE 00000000", Scheme::V1, Some((Scheme::V1, Scheme::V7)), None),
+            ("973E0B2A A7D4AF10", "2096F5B8 000000BE", Scheme::V7, None, None),
+            ("2A973DBD 00000000", "52C9647A 97439DDF", Scheme::V7, None, Some(Warning::Implausible)),
+        ];
+
+        let mut rec = Recorder::new(Codebreaker::new());
+        for t in tests.iter() {
+            let code = code::parse(t.0);
+            assert_eq!(t.1, code::format(rec.decrypt_code(code.0, code.1)));
+        }
+        for (i, (e, t)) in rec.entries().iter().zip(tests.iter()).enumerate() {
+            assert_eq!(i, e.line);
+            assert_eq!(t.0, code::format(e.original));
+            assert_eq!(t.1, code::format(e.decrypted));
+            assert_eq!(t.2, e.scheme);
+            assert_eq!(t.3.map(|(from, to)| Transition { from, to }), e.transition);
+            assert_eq!(t.4, e.warning);
+        }
+        assert_eq!(1, rec.warnings().count());
+        assert_eq!(Scheme::V7, rec.codebreaker().scheme());
+    }
+
+    #[test]
+    fn test_decrypt_code_rejected() {
+        let mut cb = Codebreaker::new();
+        cb.pin_scheme(Scheme::V1);
+        let mut rec = Recorder::new(cb);
+
+        assert_eq!((0x2096_f5b8, 0x0000_00be), rec.decrypt_code(0x2096_f5b8, 0x0000_00be));
+        let e = rec.entries()[0];
+        assert_eq!(
+            Some(Warning::Rejected(Error::SchemeMismatch {
+                pinned: Scheme::V1,
+                found: Scheme::Raw
+            })),
+            e.warning
+        );
+        assert_eq!(None, e.transition);
+    }
+
+    #[test]
+    fn test_to_json() {
+        let mut rec = Recorder::new(Codebreaker::new());
+        assert_eq!(r#"{"entries":[]}"#, rec.to_json());
+
+        rec.decrypt_code(0x2a97_3dbd, 0x0000_0000);
+        rec.decrypt_code(0x2a97_3dbd, 0x0000_0001);
+        assert_eq!(
+            concat!(
+                r#"{"entries":["#,
+                r#"{"line":0,"original":"2A973DBD 00000000","decrypted":"201F6024 00000000","scheme":"V1","#,
+                r#""transition":{"from":"RAW","to":"V1"}},"#,
+                r#"{"line":1,"original":"2A973DBD 00000001","decrypted":"201F6024 00000001","scheme":"V1"}"#,
+                r#"]}"#
+            ),
+            rec.to_json()
+        );
+    }
+}
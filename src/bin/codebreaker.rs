@@ -0,0 +1,1407 @@
+//! Command-line tool to encrypt and decrypt lists of CodeBreaker PS2 codes.
+//!
+//! ```text
+//! codebreaker encrypt [--filter] [FILE]
+//! codebreaker decrypt [--filter] [FILE]
+//! codebreaker convert [--filter] [--from FORMAT] --to FORMAT [FILE]
+//! codebreaker describe [FILE]
+//! codebreaker diff OLD NEW
+//! codebreaker search QUERY [FILE]...
+//! codebreaker verify [FILE]...
+//! codebreaker repl
+//! codebreaker formats
+//! codebreaker serve --stdio
+//! ```
+//!
+//! Reads codes from FILE, or from standard input if FILE is missing or `-`,
+//! and writes the result to standard output. Comments, blank lines, and cheat
+//! names are kept. Each line is written as soon as it is read, so the tool
+//! can be used as a filter in pipelines and editors.
+//!
+//! With `--filter`, `encrypt`, `decrypt`, and `convert` take any text, like
+//! a forum post, and only change the lines that hold a code. All other
+//! lines are copied as they are, even ones that look like a broken code.
+//!
+//! `convert` decrypts codes of one format and encrypts them for another. The
+//! formats are `raw`, `cb1` for CB v1 - v6, and `cb7` for CB v7+. Without
+//! `--from`, the format of each code is detected. Beefcodes and their seed
+//! lines are left out unless the target is `cb7`, as only CB v7+ uses them.
+//! A `cb7` list starts with a beefcode encrypted like by CB v1, e.g.
+//! `B4336FA9 4DFEFB79`, which is added before the first code if the input
+//! has none.
+//!
+//! `describe` decrypts codes and explains what each of them does, e.g.
+//! `32-bit write of 0x2411FFFF to 0x0043AFCC`.
+//!
+//! `verify` decrypts codes and reports structural problems, like truncated
+//! multi-line codes, misaligned writes, or unknown code types, as errors or
+//! warnings with their line numbers. Each file is followed by a summary
+//! line like `codes.txt: 2 errors, 1 warning`, so whole cheat repositories
+//! can be checked at once.
+//!
+//! `search` decrypts the codes of each file, taken as the cheats of a game
+//! titled by the file name, and prints the codes matching a query like
+//! `type:write16 addr:0x004A0000..0x004B0000 value:>100`. See the `query`
+//! module of the library for the syntax.
+//!
+//! `diff` decrypts the codes of two files and shows them side by side, marking
+//! changed, removed, and added codes. The output is colored if it goes to a
+//! terminal and `NO_COLOR` is not set.
+//!
+//! `repl` reads codes typed one per line and decrypts them with a single
+//! processor, the way a list would be, showing each code with its format
+//! and what it does. `scheme FORMAT` pins the processor to a format, `reset`
+//! starts over with a new processor, `state` shows what the processor keeps
+//! track of, and `quit` or end of input leaves the loop.
+//!
+//! `formats` lists the formats of code lists the library supports, with the
+//! device that uses each, whether it can be read and written, and whether
+//! `convert` takes it. With `--json`, it writes a `format` object for each.
+//!
+//! `serve --stdio` keeps running for editors and bots, answering a JSON
+//! request on each line of standard input with a JSON response on a line of
+//! standard output. A request has an `op`, which is `encrypt`, `decrypt`, or
+//! `convert` with a `text` of lines to process, `open` to start a session,
+//! or `close` to end one. `convert` takes the formats in `from` and `to`,
+//! and all three take `filter` like `--filter`. The `id` of a request is
+//! copied to its response, which holds the `text` of the result, the
+//! `session` handle opened, or an `error`:
+//!
+//! ```text
+//! {"id": 1, "op": "open", "command": "decrypt"}
+//! {"id":1,"session":1}
+//! {"id": 2, "op": "decrypt", "session": 1, "text": "B4336FA9 4DFEFB79"}
+//! {"id":2,"text":"BEEFC0DE 00000000\n"}
+//! {"id": 3, "op": "decrypt", "session": 1, "text": "973E0B2A A7D4AF10"}
+//! {"id":3,"text":"2096F5B8 000000BE\n"}
+//! ```
+//!
+//! A session processes requests for the command it was opened with as one
+//! list, like the lines of a file. Requests without a session start from
+//! scratch. A request that fails leaves its session unchanged.
+//!
+//! With `--json`, every command but `repl` and `serve` writes JSON Lines instead of
+//! text: one object per line, whose `type` tells what it holds. `line`
+//! objects give the `input` and `output` lines of `encrypt`, `decrypt`, and
+//! `convert`, with the detected `scheme` of input codes when decrypting.
+//! `code`, `finding`, `hit`, and `change` objects are the codes of
+//! `describe`, the problems of `verify`, the matches of `search`, and the
+//! rows of `diff`. Each input ends with a `summary` object of counts. Lines
+//! are counted from 1, and errors are still reported on standard error.
+//!
+//! The exit status is 0 on success, 1 if a command fails, and 2 if the
+//! arguments are invalid. `verify` fails if any file has errors, but not for
+//! warnings alone.
+
+#![deny(clippy::all, clippy::nursery)]
+#![deny(nonstandard_style, rust_2018_idioms)]
+#![forbid(unsafe_code)]
+
+use codebreaker::cb7::is_beefcode;
+use codebreaker::describe::describe;
+use codebreaker::diff::{self, Change, Renderer};
+use codebreaker::firmware::Profile;
+use codebreaker::formats::{self, Format};
+use codebreaker::game::{Cheat, Game};
+use codebreaker::lint::{lint, Severity};
+use codebreaker::query::Query;
+use codebreaker::text::{self, Converter, Policy};
+use codebreaker::{Code, Codebreaker, Scheme};
+
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::io::{self, BufRead, BufReader, IsTerminal, Write};
+use std::{env, fs, process};
+
+const USAGE: &str = "usage: codebreaker encrypt [--filter] [FILE]
+       codebreaker decrypt [--filter] [FILE]
+       codebreaker convert [--filter] [--from FORMAT] --to FORMAT [FILE]
+       codebreaker describe [FILE]
+       codebreaker diff OLD NEW
+       codebreaker search QUERY [FILE]...
+       codebreaker verify [FILE]...
+       codebreaker repl
+       codebreaker formats
+       codebreaker serve --stdio
+
+options: --json    write JSON Lines instead of text, except for repl and serve
+         --filter  copy lines without a code as they are
+formats: raw, cb1, cb7, see codebreaker formats
+exit status: 0 on success, 1 on failure or errors found by verify,
+             2 on invalid arguments";
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let result = parse(&args).and_then(|(command, options, paths)| {
+        let stdout = io::stdout();
+        let color = stdout.is_terminal() && env::var_os("NO_COLOR").is_none();
+        let mut output = stdout.lock();
+        match (command, paths.as_slice()) {
+            (Command::Describe, &[]) => explain(&read_input("-")?, "-", options, &mut output),
+            (Command::Describe, &[path]) => explain(&read_input(path)?, path, options, &mut output),
+            (Command::Verify, &[]) => verify(&["-"], options, &mut output),
+            (Command::Verify, files) => verify(files, options, &mut output),
+            (Command::Search, &[query]) => search(query, &["-"], options, &mut output),
+            (Command::Search, &[query, ref files @ ..]) => search(query, files, options, &mut output),
+            (Command::Diff, &[old, new]) => compare(&read_input(old)?, &read_input(new)?, color, options, &mut output),
+            (Command::Repl, &[]) => repl(io::stdin().lock(), io::stdin().is_terminal(), &mut output),
+            (Command::Formats, &[]) => list_formats(options, &mut output),
+            (Command::Serve, &[]) => serve(io::stdin().lock(), &mut output),
+            (_, &[]) | (_, &["-"]) => run(command, options, io::stdin().lock(), "-", &mut output),
+            (_, &[path]) => {
+                let file = fs::File::open(path).map_err(|err| Failure::Error(format!("{}: {}", path, err)))?;
+                run(command, options, BufReader::new(file), path, &mut output)
+            }
+            _ => Err(Failure::Usage),
+        }
+    });
+    match result {
+        Ok(()) => {}
+        Err(Failure::Usage) => {
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
+        Err(Failure::Error(msg)) => {
+            eprintln!("codebreaker: {}", msg);
+            process::exit(1);
+        }
+        Err(Failure::Invalid) => process::exit(1),
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Failure {
+    Usage,
+    Error(String),
+    // The codes have errors, which are already reported
+    Invalid,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Command {
+    Encrypt,
+    Decrypt,
+    // Without a source scheme, the scheme of each code is detected
+    Convert { from: Option<Scheme>, to: Scheme },
+    Describe,
+    Diff,
+    // The first argument is the query
+    Search,
+    Verify,
+    Repl,
+    Formats,
+    Serve,
+}
+
+// Options that apply to several commands.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct Options {
+    // Write JSON Lines instead of text
+    json: bool,
+    // Copy lines without a code unchanged
+    filter: bool,
+}
+
+// Returns the command, its options, and the input files given by the
+// arguments.
+fn parse(args: &[String]) -> Result<(Command, Options, Vec<&str>), Failure> {
+    let (name, mut args) = args.split_first().ok_or(Failure::Usage)?;
+    let (mut from, mut to, mut paths) = (None, None, Vec::new());
+    let mut options = Options::default();
+    let mut stdio = false;
+    while let Some((arg, rest)) = args.split_first() {
+        args = rest;
+        match arg.as_str() {
+            "--from" | "--to" if name == "convert" => {
+                let (value, rest) = args.split_first().ok_or(Failure::Usage)?;
+                args = rest;
+                let scheme = Some(parse_format(value).map_err(Failure::Error)?);
+                if arg == "--from" {
+                    from = scheme;
+                } else {
+                    to = scheme;
+                }
+            }
+            "--json" if name != "repl" && name != "serve" => options.json = true,
+            "--stdio" if name == "serve" => stdio = true,
+            "--filter" if ["encrypt", "decrypt", "convert"].contains(&name.as_str()) => options.filter = true,
+            _ if arg == "-" || !arg.starts_with('-') => paths.push(arg.as_str()),
+            _ => return Err(Failure::Usage),
+        }
+    }
+    let command = match name.as_str() {
+        "encrypt" => Command::Encrypt,
+        "decrypt" => Command::Decrypt,
+        "convert" => Command::Convert {
+            from,
+            to: to.ok_or(Failure::Usage)?,
+        },
+        "describe" => Command::Describe,
+        "diff" => Command::Diff,
+        "search" => Command::Search,
+        "verify" => Command::Verify,
+        "repl" => Command::Repl,
+        "formats" => Command::Formats,
+        // Standard input and output are the only transport so far
+        "serve" if stdio => Command::Serve,
+        _ => return Err(Failure::Usage),
+    };
+    let count = match command {
+        Command::Diff => 2..=2,
+        Command::Search => 1..=usize::MAX,
+        Command::Verify => 0..=usize::MAX,
+        Command::Repl | Command::Formats | Command::Serve => 0..=0,
+        _ => 0..=1,
+    };
+    if !count.contains(&paths.len()) {
+        return Err(Failure::Usage);
+    }
+    Ok((command, options, paths))
+}
+
+// Returns the scheme of a format of plain code lists, the ones that
+// commands other than formats can read and write.
+fn parse_format(name: &str) -> Result<Scheme, String> {
+    match formats::find(name) {
+        Some(Format {
+            scheme: Some(scheme), ..
+        }) => Ok(scheme),
+        Some(_) => Err(format!("format {:?} is not a list of codes", name)),
+        None => Err(format!("unknown format {:?}", name)),
+    }
+}
+
+const fn format_name(scheme: Scheme) -> &'static str {
+    match scheme {
+        Scheme::Raw => "raw",
+        Scheme::V1 => "cb1",
+        Scheme::V7 => "cb7",
+        _ => "unknown",
+    }
+}
+
+// A command that runs line by line, with the state it keeps between lines.
+#[derive(Debug, Clone, Copy)]
+struct Worker {
+    command: Command,
+    filter: bool,
+    converter: Converter,
+    cb: Codebreaker,
+    // Encrypts the codes converted for the target format
+    target: Codebreaker,
+    // Lines left of the current code, and whether the code is left out
+    lines: usize,
+    left_out: bool,
+}
+
+impl Worker {
+    fn new(command: Command, filter: bool) -> Self {
+        // Filters keep malformed codes instead of failing on them
+        let policy = if filter { Policy::PassThrough } else { Policy::Abort };
+        let cb = match command {
+            Command::Convert { from: Some(from), .. } => Codebreaker::with_scheme(from),
+            _ => Codebreaker::new(),
+        };
+        let target = match command {
+            // Like with new(), the leading beefcode of a cb7 list is encrypted
+            // with V1 and keys the V7 codes after it
+            Command::Convert { to: Scheme::V7, .. } => Codebreaker::new(),
+            Command::Convert { to, .. } => Codebreaker::with_scheme(to),
+            _ => Codebreaker::new(),
+        };
+        Self {
+            command,
+            filter,
+            converter: Converter::new().annotations(true).unknown_lines(policy),
+            cb,
+            target,
+            lines: 0,
+            left_out: false,
+        }
+    }
+
+    // Returns the output of an input line.
+    fn process(&mut self, line: &str) -> Result<String, text::Error> {
+        let converter = self.converter;
+        let text = match self.command {
+            Command::Encrypt => converter.encrypt(&mut self.cb, line)?.text,
+            Command::Decrypt => converter.decrypt(&mut self.cb, line)?.text,
+            Command::Convert { to, .. } => {
+                let raw = converter.decrypt(&mut self.cb, line)?.text;
+                let mut text = String::new();
+                if let Some(code) = raw.get(..17).and_then(|code| code.parse::<Code>().ok()) {
+                    if self.lines == 0 {
+                        self.lines = Profile::V7.lines(code.addr);
+                        self.left_out = to != Scheme::V7 && is_beefcode(code.addr);
+                        // A cb7 list without a beefcode of its own gets the
+                        // default one before its first code
+                        if to == Scheme::V7 && !self.target.beefcode_seen() && !is_beefcode(code.addr) {
+                            text = converter.auto_encrypt(&mut self.target, "BEEFC0DE 00000000")?.text;
+                        }
+                    }
+                    self.lines -= 1;
+                    if self.left_out {
+                        return Ok(String::new());
+                    }
+                }
+                text + &converter.auto_encrypt(&mut self.target, &raw)?.text
+            }
+            Command::Describe
+            | Command::Diff
+            | Command::Search
+            | Command::Verify
+            | Command::Repl
+            | Command::Formats
+            | Command::Serve => {
+                unreachable!("{:?} reads its whole input", self.command)
+            }
+        };
+        if self.filter && code_of(line).is_none() {
+            // Names and comments would be trimmed
+            return Ok(format!("{}\n", line));
+        }
+        Ok(text)
+    }
+}
+
+// Runs the command on the input line by line and writes each result
+// before reading the next line. Errors name the input.
+fn run<R, W>(command: Command, options: Options, input: R, name: &str, output: &mut W) -> Result<(), Failure>
+where
+    R: BufRead,
+    W: Write,
+{
+    let mut worker = Worker::new(command, options.filter);
+    let (mut count, mut codes) = (0, 0);
+    for (index, line) in input.lines().enumerate() {
+        let line = line.map_err(|err| Failure::Error(format!("{}: {}", name, err)))?;
+        // The processor before the line tells how its code was encrypted
+        let mut before = worker.cb;
+        let text = worker.process(&line).map_err(|mut err| {
+            err.line = index;
+            Failure::Error(format!("{}: {}", name, err))
+        })?;
+        let code = code_of(&line);
+        if options.json {
+            let scheme = match (command, code) {
+                (Command::Decrypt | Command::Convert { .. }, Some(code)) => Some(format_name(
+                    before.auto_decrypt_code_detailed(code.addr, code.val).scheme,
+                )),
+                _ => None,
+            };
+            count += 1;
+            codes += usize::from(code.is_some());
+            let output_lines: Vec<_> = text.lines().collect();
+            emit(
+                output,
+                json!({
+                    "type": "line",
+                    "file": name,
+                    "line": index + 1,
+                    "input": line,
+                    "output": output_lines,
+                    "scheme": scheme,
+                }),
+            )?;
+        } else {
+            output
+                .write_all(text.as_bytes())
+                .and_then(|_| output.flush())
+                .map_err(|err| Failure::Error(err.to_string()))?;
+        }
+    }
+    if options.json {
+        emit(
+            output,
+            json!({"type": "summary", "file": name, "lines": count, "codes": codes}),
+        )?;
+    }
+    Ok(())
+}
+
+// Writes a JSON object on a line of its own.
+fn emit<W: Write>(output: &mut W, record: Value) -> Result<(), Failure> {
+    writeln!(output, "{}", record)
+        .and_then(|_| output.flush())
+        .map_err(|err| Failure::Error(err.to_string()))
+}
+
+// Returns the code of an input line, which can be followed by a comment.
+fn code_of(line: &str) -> Option<Code> {
+    strip_comment(line).trim().parse().ok()
+}
+
+// Returns a line up to its first comment marker.
+fn strip_comment(line: &str) -> &str {
+    let end = ["//", "#", ";"].iter().filter_map(|m| line.find(m)).min();
+    &line[..end.unwrap_or(line.len())]
+}
+
+const REPL_HELP: &str = "enter a code to decrypt it, or one of these commands:
+  scheme FORMAT  pin the processor to raw, cb1, or cb7
+  reset          start over with a new processor
+  state          show the scheme and whether a beefcode was seen
+  quit           leave";
+
+// Decrypts the codes typed on each line with a single processor and runs
+// the commands in between. Mistakes are reported, but do not end the loop.
+fn repl<R, W>(input: R, prompt: bool, output: &mut W) -> Result<(), Failure>
+where
+    R: BufRead,
+    W: Write,
+{
+    let mut cb = Codebreaker::new();
+    let mut pinned = false;
+    // Decrypted lines of the current multi-line code
+    let mut pending = Vec::new();
+    let mut lines = input.lines();
+    loop {
+        if prompt {
+            write!(output, "> ")
+                .and_then(|_| output.flush())
+                .map_err(|err| Failure::Error(err.to_string()))?;
+        }
+        let line = match lines.next() {
+            Some(line) => line.map_err(|err| Failure::Error(err.to_string()))?,
+            None => break,
+        };
+        let words: Vec<_> = line.split_whitespace().collect();
+        let reply = match words.as_slice() {
+            [] => continue,
+            ["quit"] | ["exit"] => break,
+            ["help"] => REPL_HELP.to_string(),
+            ["reset"] => {
+                cb.reset();
+                pinned = false;
+                pending.clear();
+                "reset".to_string()
+            }
+            ["state"] => format!(
+                "scheme {}{}, {}",
+                format_name(cb.scheme()),
+                if pinned { " (pinned)" } else { "" },
+                if cb.beefcode_seen() {
+                    "beefcode seen"
+                } else {
+                    "no beefcode"
+                }
+            ),
+            ["scheme", name] => match parse_format(name) {
+                Ok(scheme) => {
+                    cb.pin_scheme(scheme);
+                    pinned = true;
+                    format!("scheme {} (pinned)", name)
+                }
+                Err(msg) => format!("error: {}", msg),
+            },
+            _ => match line.parse::<Code>() {
+                Ok(code) => {
+                    let decrypted = cb.auto_decrypt_code_detailed(code.addr, code.val);
+                    pending.push(decrypted.decrypted);
+                    let mut reply = format!("{}  {}", Code::from(decrypted.decrypted), format_name(decrypted.scheme));
+                    if pending.len() == Profile::V7.lines(pending[0].0) {
+                        for description in describe(&pending) {
+                            reply.push_str(&format!("  {}", description));
+                        }
+                        pending.clear();
+                    }
+                    reply
+                }
+                Err(err) => format!("error: {} (try help)", err),
+            },
+        };
+        writeln!(output, "{}", reply).map_err(|err| Failure::Error(err.to_string()))?;
+    }
+    Ok(())
+}
+
+// Answers the JSON request on each line of the input with a JSON response.
+fn serve<R, W>(input: R, output: &mut W) -> Result<(), Failure>
+where
+    R: BufRead,
+    W: Write,
+{
+    let (mut sessions, mut opened) = (BTreeMap::new(), 0);
+    for line in input.lines() {
+        let line = line.map_err(|err| Failure::Error(err.to_string()))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match serde_json::from_str::<Value>(&line) {
+            Ok(request) => {
+                let mut response =
+                    answer(&request, &mut sessions, &mut opened).unwrap_or_else(|msg| json!({ "error": msg }));
+                response["id"] = request.get("id").cloned().unwrap_or(Value::Null);
+                response
+            }
+            Err(err) => json!({"id": null, "error": format!("invalid request: {}", err)}),
+        };
+        emit(output, response)?;
+    }
+    Ok(())
+}
+
+// Answers a request, or returns why it cannot be answered. Sessions are
+// numbered from 1 in the order they are opened.
+fn answer(request: &Value, sessions: &mut BTreeMap<u64, Worker>, opened: &mut u64) -> Result<Value, String> {
+    let op = request["op"].as_str().ok_or("request has no op")?;
+    let session = match request.get("session") {
+        None | Some(Value::Null) => None,
+        Some(session) => Some(session.as_u64().ok_or("session is not a number")?),
+    };
+    let filter = request["filter"].as_bool().unwrap_or(false);
+    match op {
+        "open" => {
+            let command = request["command"].as_str().ok_or("open has no command")?;
+            let worker = Worker::new(command_of(command, request)?, filter);
+            *opened += 1;
+            sessions.insert(*opened, worker);
+            Ok(json!({ "session": *opened }))
+        }
+        "close" => {
+            let handle = session.ok_or("close has no session")?;
+            sessions
+                .remove(&handle)
+                .ok_or_else(|| format!("unknown session {}", handle))?;
+            Ok(json!({}))
+        }
+        "encrypt" | "decrypt" | "convert" => {
+            let text = request["text"].as_str().ok_or_else(|| format!("{} has no text", op))?;
+            let mut worker = match session {
+                Some(handle) => {
+                    let worker = *sessions
+                        .get(&handle)
+                        .ok_or_else(|| format!("unknown session {}", handle))?;
+                    if command_name(worker.command) != op {
+                        return Err(format!("session {} is for {}", handle, command_name(worker.command)));
+                    }
+                    worker
+                }
+                None => Worker::new(command_of(op, request)?, filter),
+            };
+            let mut result = String::new();
+            for (index, line) in text.lines().enumerate() {
+                let output = worker.process(line).map_err(|mut err| {
+                    err.line = index;
+                    err.to_string()
+                })?;
+                result.push_str(&output);
+            }
+            if let Some(handle) = session {
+                sessions.insert(handle, worker);
+            }
+            Ok(json!({ "text": result }))
+        }
+        _ => Err(format!("unknown op {:?}", op)),
+    }
+}
+
+// Returns the command of a request, with the formats of convert.
+fn command_of(name: &str, request: &Value) -> Result<Command, String> {
+    let format = |key: &str| match request.get(key) {
+        None | Some(Value::Null) => Ok(None),
+        Some(format) => {
+            let name = format.as_str().ok_or_else(|| format!("{} is not a string", key))?;
+            parse_format(name).map(Some)
+        }
+    };
+    match name {
+        "encrypt" => Ok(Command::Encrypt),
+        "decrypt" => Ok(Command::Decrypt),
+        "convert" => Ok(Command::Convert {
+            from: format("from")?,
+            to: format("to")?.ok_or("convert has no to")?,
+        }),
+        _ => Err(format!("unknown command {:?}", name)),
+    }
+}
+
+const fn command_name(command: Command) -> &'static str {
+    match command {
+        Command::Encrypt => "encrypt",
+        Command::Decrypt => "decrypt",
+        Command::Convert { .. } => "convert",
+        Command::Describe => "describe",
+        Command::Diff => "diff",
+        Command::Search => "search",
+        Command::Verify => "verify",
+        Command::Repl => "repl",
+        Command::Formats => "formats",
+        Command::Serve => "serve",
+    }
+}
+
+// Writes the formats of the library, with the capabilities of each.
+fn list_formats<W: Write>(options: Options, output: &mut W) -> Result<(), Failure> {
+    let mut s = String::new();
+    for format in formats::formats() {
+        let convert = format.scheme.is_some();
+        if options.json {
+            emit(
+                output,
+                json!({
+                    "type": "format",
+                    "name": format.name,
+                    "description": format.description,
+                    "device": format.device,
+                    "read": format.read,
+                    "write": format.write,
+                    "convert": convert,
+                }),
+            )?;
+            continue;
+        }
+        let capabilities: Vec<_> = [(format.read, "read"), (format.write, "write"), (convert, "convert")]
+            .iter()
+            .filter(|&&(supported, _)| supported)
+            .map(|&(_, capability)| capability)
+            .collect();
+        s.push_str(&format!(
+            "{:<6} {:<20} {} ({})\n",
+            format.name,
+            format.device,
+            format.description,
+            capabilities.join(", ")
+        ));
+    }
+    output
+        .write_all(s.as_bytes())
+        .map_err(|err| Failure::Error(err.to_string()))
+}
+
+// Returns the contents of a file, or of standard input for `-`.
+fn read_input(path: &str) -> Result<String, Failure> {
+    let result = if path == "-" {
+        io::read_to_string(io::stdin())
+    } else {
+        fs::read_to_string(path)
+    };
+    result.map_err(|err| Failure::Error(format!("{}: {}", path, err)))
+}
+
+// Writes the decrypted codes of a list, each followed by what it does.
+// Lines of a code after the first one are written without explanation.
+fn explain<W: Write>(text: &str, name: &str, options: Options, output: &mut W) -> Result<(), Failure> {
+    let converter = Converter::new().annotations(true);
+    let decrypted = converter
+        .decrypt(&mut Codebreaker::new(), text)
+        .map_err(|err| Failure::Error(format!("{}: {}", name, err)))?
+        .text;
+    let lines: Vec<_> = decrypted
+        .lines()
+        .map(|line| line.get(..17).and_then(|code| code.parse::<Code>().ok()))
+        .collect();
+    let codes: Vec<(u32, u32)> = lines.iter().flatten().map(|&code| code.into()).collect();
+    if options.json {
+        // Annotated conversions keep every line, so the indices match the input
+        let numbers: Vec<_> = lines.iter().enumerate().filter(|(_, code)| code.is_some()).collect();
+        for description in describe(&codes) {
+            let (number, _) = numbers[description.line];
+            let code_lines: Vec<_> = codes[description.line..description.line + description.lines]
+                .iter()
+                .map(|&code| Code::from(code).to_string())
+                .collect();
+            emit(
+                output,
+                json!({
+                    "type": "code",
+                    "file": name,
+                    "line": number + 1,
+                    "code": code_lines,
+                    "description": description.to_string(),
+                }),
+            )?;
+        }
+        let summary = json!({"type": "summary", "file": name, "lines": lines.len(), "codes": codes.len()});
+        return emit(output, summary);
+    }
+    let mut descriptions = describe(&codes).into_iter().peekable();
+    let mut index = 0;
+    let mut s = String::new();
+    for (line, code) in decrypted.lines().zip(lines) {
+        match code {
+            Some(code) => {
+                match descriptions.next_if(|d| d.line == index) {
+                    Some(description) => s.push_str(&format!("{}  {}\n", code, description)),
+                    None => s.push_str(&format!("{}\n", code)),
+                }
+                index += 1;
+            }
+            None => s.push_str(&format!("{}\n", line)),
+        }
+    }
+    output
+        .write_all(s.as_bytes())
+        .map_err(|err| Failure::Error(err.to_string()))
+}
+
+// Checks each file and fails if any of them has errors.
+fn verify<W: Write>(files: &[&str], options: Options, output: &mut W) -> Result<(), Failure> {
+    let mut failed = false;
+    for &name in files {
+        let (errors, _) = check(&read_input(name)?, name, options, output)?;
+        failed |= errors > 0;
+    }
+    if failed {
+        Err(Failure::Invalid)
+    } else {
+        Ok(())
+    }
+}
+
+// Writes the problems of the decrypted codes of a list, followed by a
+// summary, and returns the number of errors and warnings.
+fn check<W: Write>(text: &str, name: &str, options: Options, output: &mut W) -> Result<(usize, usize), Failure> {
+    let converter = Converter::new().annotations(true);
+    let decrypted = converter
+        .decrypt(&mut Codebreaker::new(), text)
+        .map_err(|err| Failure::Error(format!("{}: {}", name, err)))?
+        .text;
+    // Annotated conversions keep every line, so the indices match the input
+    let (lines, codes): (Vec<usize>, Vec<(u32, u32)>) = decrypted
+        .lines()
+        .enumerate()
+        .filter_map(|(index, line)| Some((index, <(u32, u32)>::from(line.get(..17)?.parse::<Code>().ok()?))))
+        .unzip();
+    let (mut errors, mut warnings) = (0, 0);
+    let mut s = String::new();
+    for mut finding in lint(&codes, &Profile::V7) {
+        match finding.severity() {
+            Severity::Error => errors += 1,
+            Severity::Warning => warnings += 1,
+        }
+        finding.line = lines[finding.line];
+        if options.json {
+            emit(
+                output,
+                json!({
+                    "type": "finding",
+                    "file": name,
+                    "line": finding.line + 1,
+                    "severity": finding.severity().to_string(),
+                    "message": finding.problem.to_string(),
+                }),
+            )?;
+        } else {
+            s.push_str(&format!("{}: {}\n", name, finding));
+        }
+    }
+    if options.json {
+        emit(
+            output,
+            json!({"type": "summary", "file": name, "errors": errors, "warnings": warnings}),
+        )?;
+        return Ok((errors, warnings));
+    }
+    s.push_str(&format!(
+        "{}: {}, {}\n",
+        name,
+        plural(errors, "error"),
+        plural(warnings, "warning")
+    ));
+    output
+        .write_all(s.as_bytes())
+        .map_err(|err| Failure::Error(err.to_string()))?;
+    Ok((errors, warnings))
+}
+
+fn plural(n: usize, word: &str) -> String {
+    if n == 1 {
+        format!("1 {}", word)
+    } else {
+        format!("{} {}s", n, word)
+    }
+}
+
+// Writes the codes of the files that match the query, with their file,
+// line, and cheat.
+fn search<W: Write>(query: &str, files: &[&str], options: Options, output: &mut W) -> Result<(), Failure> {
+    let query = Query::parse(query).map_err(|err| Failure::Error(format!("invalid query: {}", err)))?;
+    let mut games = Vec::new();
+    let mut lines = Vec::new();
+    for &name in files {
+        let (game, game_lines) = read_game(&read_input(name)?, name)?;
+        games.push(game);
+        lines.push(game_lines);
+    }
+    let mut s = String::new();
+    let hits = query.search(&games);
+    for hit in hits.iter() {
+        let cheat = &games[hit.game].cheats[hit.cheat];
+        let line = lines[hit.game][hit.cheat][hit.line];
+        let code = Code::from(cheat.codes[hit.line]);
+        if options.json {
+            emit(
+                output,
+                json!({
+                    "type": "hit",
+                    "file": files[hit.game],
+                    "line": line + 1,
+                    "cheat": cheat.name,
+                    "code": code.to_string(),
+                }),
+            )?;
+        } else {
+            s.push_str(&format!(
+                "{}: line {}: {}: {}\n",
+                files[hit.game],
+                line + 1,
+                cheat.name,
+                code
+            ));
+        }
+    }
+    if options.json {
+        return emit(
+            output,
+            json!({"type": "summary", "files": files.len(), "hits": hits.len()}),
+        );
+    }
+    output
+        .write_all(s.as_bytes())
+        .map_err(|err| Failure::Error(err.to_string()))
+}
+
+// Returns the decrypted cheats of a list as a game, and the index of the
+// input line of each code. Codes before the first cheat name belong to a
+// cheat without a name.
+fn read_game(text: &str, name: &str) -> Result<(Game, Vec<Vec<usize>>), Failure> {
+    let converter = Converter::new().annotations(true);
+    let decrypted = converter
+        .decrypt(&mut Codebreaker::new(), text)
+        .map_err(|err| Failure::Error(format!("{}: {}", name, err)))?
+        .text;
+    let mut game = Game::new(name);
+    let mut lines: Vec<Vec<usize>> = Vec::new();
+    // Annotated conversions keep every line, so the indices match the input
+    for (index, line) in decrypted.lines().enumerate() {
+        if let Some(code) = line.get(..17).and_then(|code| code.parse::<Code>().ok()) {
+            if game.cheats.is_empty() {
+                game.cheats.push(Cheat::default());
+                lines.push(Vec::new());
+            }
+            game.cheats.last_mut().unwrap().codes.push(code.into());
+            lines.last_mut().unwrap().push(index);
+        } else {
+            // Names can be followed by a comment
+            let name = strip_comment(line).trim();
+            if !name.is_empty() {
+                game.cheats.push(Cheat::new(name, Vec::new()));
+                lines.push(Vec::new());
+            }
+        }
+    }
+    Ok((game, lines))
+}
+
+// Writes a diff of the decrypted codes of two lists.
+fn compare<W: Write>(old: &str, new: &str, color: bool, options: Options, output: &mut W) -> Result<(), Failure> {
+    let (old, new) = (decrypted_codes(old), decrypted_codes(new));
+    let changes = diff::diff(&old, &new);
+    if options.json {
+        let mut counts = [0; 4];
+        for change in changes.iter() {
+            let (kind, index, old, new) = match *change {
+                Change::Same(code) => ("same", 0, Some(code), Some(code)),
+                Change::Changed(old, new) => ("changed", 1, Some(old), Some(new)),
+                Change::Removed(code) => ("removed", 2, Some(code), None),
+                Change::Added(code) => ("added", 3, None, Some(code)),
+            };
+            counts[index] += 1;
+            let (old, new) = (old.map(|code| code.to_string()), new.map(|code| code.to_string()));
+            emit(output, json!({"type": "change", "kind": kind, "old": old, "new": new}))?;
+        }
+        let [same, changed, removed, added] = counts;
+        return emit(
+            output,
+            json!({"type": "summary", "same": same, "changed": changed, "removed": removed, "added": added}),
+        );
+    }
+    output
+        .write_all(Renderer::new().color(color).render(&changes).as_bytes())
+        .map_err(|err| Failure::Error(err.to_string()))
+}
+
+// Returns the decrypted codes of a list, leaving out all other lines.
+fn decrypted_codes(text: &str) -> Vec<(u32, u32)> {
+    let converter = Converter::new().annotations(true).unknown_lines(Policy::Drop);
+    // Malformed codes are dropped, so the conversion cannot fail
+    let decrypted = converter
+        .decrypt(&mut Codebreaker::new(), text)
+        .map(|conversion| conversion.text)
+        .unwrap_or_default();
+    // Codes are written first on their lines, followed by any comment
+    decrypted
+        .lines()
+        .filter_map(|line| line.get(..17)?.parse::<Code>().ok())
+        .map(Into::into)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|&arg| arg.to_string()).collect()
+    }
+
+    // Parses the arguments, leaving out the options.
+    fn command(args: &[String]) -> Result<(Command, Vec<&str>), Failure> {
+        parse(args).map(|(command, _, paths)| (command, paths))
+    }
+
+    fn filter(command: Command, input: &str) -> Result<String, Failure> {
+        filter_with(command, Options::default(), input)
+    }
+
+    fn filter_with(command: Command, options: Options, input: &str) -> Result<String, Failure> {
+        let mut output = Vec::new();
+        run(command, options, input.as_bytes(), "-", &mut output)?;
+        Ok(String::from_utf8(output).unwrap())
+    }
+
+    fn json(output: Vec<u8>) -> Vec<Value> {
+        String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    const JSON: Options = Options {
+        json: true,
+        filter: false,
+    };
+    const FILTER: Options = Options {
+        json: false,
+        filter: true,
+    };
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Ok((Command::Encrypt, vec![])), command(&args(&["encrypt"])));
+        assert_eq!(Ok((Command::Decrypt, vec!["-"])), command(&args(&["decrypt", "-"])));
+        assert_eq!(Err(Failure::Usage), command(&args(&[])));
+        assert_eq!(Err(Failure::Usage), command(&args(&["convert"])));
+        assert_eq!(Err(Failure::Usage), command(&args(&["decrypt", "a", "b"])));
+        assert_eq!(Err(Failure::Usage), command(&args(&["decrypt", "--to", "raw"])));
+
+        assert_eq!(
+            Ok((
+                Command::Convert {
+                    from: Some(Scheme::V7),
+                    to: Scheme::Raw
+                },
+                vec!["codes.txt"]
+            )),
+            command(&args(&["convert", "--from", "cb7", "codes.txt", "--to", "raw"]))
+        );
+        assert_eq!(
+            Ok((
+                Command::Convert {
+                    from: None,
+                    to: Scheme::V1
+                },
+                vec![]
+            )),
+            command(&args(&["convert", "--to", "cb1"]))
+        );
+        assert_eq!(Err(Failure::Usage), command(&args(&["convert"])));
+        assert_eq!(Err(Failure::Usage), command(&args(&["convert", "--to"])));
+        assert_eq!(
+            Err(Failure::Error("unknown format \"ar2\"".into())),
+            command(&args(&["convert", "--to", "ar2"]))
+        );
+        assert_eq!(Ok((Command::Describe, vec![])), command(&args(&["describe"])));
+        assert_eq!(Err(Failure::Usage), command(&args(&["describe", "a", "b"])));
+        assert_eq!(Ok((Command::Verify, vec!["a"])), command(&args(&["verify", "a"])));
+        assert_eq!(
+            Ok((Command::Verify, vec!["a", "b"])),
+            command(&args(&["verify", "a", "b"]))
+        );
+        assert_eq!(
+            Ok((Command::Search, vec!["type:if", "a", "b"])),
+            command(&args(&["search", "type:if", "a", "b"]))
+        );
+        assert_eq!(Err(Failure::Usage), command(&args(&["search"])));
+        assert_eq!(Ok((Command::Diff, vec!["a", "b"])), command(&args(&["diff", "a", "b"])));
+        assert_eq!(Err(Failure::Usage), command(&args(&["diff", "a"])));
+        assert_eq!(Err(Failure::Usage), command(&args(&["diff", "a", "b", "c"])));
+    }
+
+    #[test]
+    fn test_parse_options() {
+        assert_eq!(
+            Ok((Command::Decrypt, Options::default(), vec!["a"])),
+            parse(&args(&["decrypt", "a"]))
+        );
+        assert_eq!(
+            Ok((Command::Decrypt, JSON, vec!["a"])),
+            parse(&args(&["decrypt", "a", "--json"]))
+        );
+        assert_eq!(Ok((Command::Verify, JSON, vec![])), parse(&args(&["verify", "--json"])));
+        assert_eq!(Err(Failure::Usage), parse(&args(&["repl", "--json"])));
+        assert_eq!(
+            Ok((Command::Encrypt, FILTER, vec![])),
+            parse(&args(&["encrypt", "--filter"]))
+        );
+        assert_eq!(Err(Failure::Usage), parse(&args(&["verify", "--filter"])));
+        assert_eq!(Ok((Command::Serve, vec![])), command(&args(&["serve", "--stdio"])));
+        assert_eq!(Err(Failure::Usage), command(&args(&["serve"])));
+        assert_eq!(Err(Failure::Usage), command(&args(&["serve", "--stdio", "--json"])));
+    }
+
+    #[test]
+    fn test_run() {
+        let decrypted = "Inf HP\n201F6024 00000000\nBEEFC0DE 00000000\n2096F5B8 000000BE\n";
+        let encrypted = "Inf HP\n2A973DBD 00000000\nB4336FA9 4DFEFB79\n973E0B2A A7D4AF10\n";
+        assert_eq!(Ok(encrypted.to_string()), filter(Command::Encrypt, decrypted));
+        assert_eq!(Ok(decrypted.to_string()), filter(Command::Decrypt, encrypted));
+        assert_eq!(
+            Err(Failure::Error("-: line 3: code has no value".into())),
+            filter(Command::Decrypt, "Inf HP\n2A973DBD 00000000\n2A973DBD\n")
+        );
+    }
+
+    #[test]
+    fn test_convert() {
+        let convert = |from, to, input| filter(Command::Convert { from, to }, input);
+        let raw = "Inf HP\n2043AFCC 2411FFFF\n";
+        let v1 = "Inf HP\n2AFF014C 2411FFFF\n";
+        let v7 = "Inf HP\n397951B0 41569FE0\n";
+        let keyed = "Inf HP\nB4336FA9 4DFEFB79\n397951B0 41569FE0\n";
+        assert_eq!(Ok(keyed.to_string()), convert(None, Scheme::V7, v1));
+        assert_eq!(Ok(keyed.to_string()), convert(None, Scheme::V7, raw));
+        assert_eq!(Ok(v1.to_string()), convert(Some(Scheme::V7), Scheme::V1, v7));
+        assert_eq!(Ok(v1.to_string()), convert(Some(Scheme::V7), Scheme::V1, keyed));
+        assert_eq!(Ok(raw.to_string()), convert(Some(Scheme::V1), Scheme::Raw, v1));
+        assert_eq!(Ok(v1.to_string()), convert(Some(Scheme::Raw), Scheme::V1, raw));
+
+        // Only cb7 keeps the beefcodes
+        let v7 = "B4336FA9 4DFEFB79\nInf HP\n973E0B2A A7D4AF10\n";
+        let raw = "Inf HP\n2096F5B8 000000BE\n";
+        assert_eq!(Ok(raw.to_string()), convert(None, Scheme::Raw, v7));
+        let v1 = convert(None, Scheme::V1, v7).unwrap();
+        assert_eq!("Inf HP\n2A03B60A 000000BE\n", v1);
+        assert_eq!(Ok(raw.to_string()), filter(Command::Decrypt, &v1));
+        assert_eq!(Ok(v7.to_string()), convert(None, Scheme::V7, v7));
+        assert_eq!(Ok(v7.to_string()), convert(Some(Scheme::V7), Scheme::V7, v7));
+        assert_eq!(Ok(raw.to_string()), convert(Some(Scheme::V7), Scheme::Raw, v7));
+
+        // Codes survive conversion to cb7 and back
+        let converted = convert(None, Scheme::V7, "Inf HP\n2043AFCC 2411FFFF\n").unwrap();
+        assert_eq!(
+            Ok("Inf HP\nBEEFC0DE 00000000\n2043AFCC 2411FFFF\n".to_string()),
+            filter(Command::Decrypt, &converted)
+        );
+
+        let seeded = "BEEFC0DF 00000000\n12345678 9ABCDEF0\n2096F5B8 000000BE\n";
+        assert_eq!(
+            Ok("2096F5B8 000000BE\n".to_string()),
+            convert(Some(Scheme::Raw), Scheme::Raw, seeded)
+        );
+    }
+
+    #[test]
+    fn test_explain() {
+        let input = "Inf HP\n2A973DBD 00000000 // player 1\n\nB4336FA9 4DFEFB79\n";
+        let mut output = Vec::new();
+        assert_eq!(Ok(()), explain(input, "-", Options::default(), &mut output));
+        assert_eq!(
+            "Inf HP\n201F6024 00000000  32-bit write of 0x00000000 to 0x001F6024\n\n\
+             BEEFC0DE 00000000  encryption key 0x00000000\n",
+            String::from_utf8(output).unwrap()
+        );
+        assert_eq!(
+            Err(Failure::Error("codes.txt: line 1: code has no value".into())),
+            explain("2A973DBD\n", "codes.txt", Options::default(), &mut Vec::new())
+        );
+    }
+
+    #[test]
+    fn test_check() {
+        let mut output = Vec::new();
+        assert_eq!(
+            Ok((0, 0)),
+            check("Inf HP\n2043AFCC 2411FFFF\n", "-", Options::default(), &mut output)
+        );
+        assert_eq!("-: 0 errors, 0 warnings\n", String::from_utf8(output).unwrap());
+
+        let input = "Inf HP\n2043AFCE 2411FFFF\n\n8043AFCC 00000000\n4043AFCC 00030001\n";
+        let mut output = Vec::new();
+        assert_eq!(Ok((2, 1)), check(input, "codes.txt", Options::default(), &mut output));
+        assert_eq!(
+            "codes.txt: line 2: error: address 0043AFCE is not aligned for 32-bit access\n\
+             codes.txt: line 4: warning: unknown code type 8\n\
+             codes.txt: line 5: error: serial write is missing 1 of 2 lines\n\
+             codes.txt: 2 errors, 1 warning\n",
+            String::from_utf8(output).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_verify() {
+        let dir = env::temp_dir().join(format!("codebreaker-verify-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (good, bad) = (dir.join("good.txt"), dir.join("bad.txt"));
+        fs::write(&good, "Inf HP\n8043AFCC 00000000\n").unwrap();
+        fs::write(&bad, "Inf HP\n2043AFCE 2411FFFF\n").unwrap();
+        let (good, bad) = (good.to_str().unwrap(), bad.to_str().unwrap());
+
+        // Warnings alone pass
+        let mut output = Vec::new();
+        assert_eq!(Ok(()), verify(&[good], Options::default(), &mut output));
+        assert!(String::from_utf8(output).unwrap().ends_with(": 0 errors, 1 warning\n"));
+
+        // Every file is checked, even after one with errors
+        let mut output = Vec::new();
+        assert_eq!(
+            Err(Failure::Invalid),
+            verify(&[bad, good], Options::default(), &mut output)
+        );
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains(&format!("{}: 1 error, 0 warnings\n", bad)));
+        assert!(output.ends_with(&format!("{}: 0 errors, 1 warning\n", good)));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_repl() {
+        let input = "2A973DBD 00000000\n\nstate\nB4336FA9 4DFEFB79\nstate\n27160181 317EB6D3\n\
+                     244FE902 8C2EDCD6\nfoo\nscheme ar2\nreset\nscheme cb1\n2096F5B8 000000BE\nquit\n\
+                     2A973DBD 00000000\n";
+        let mut output = Vec::new();
+        assert_eq!(Ok(()), repl(input.as_bytes(), false, &mut output));
+        assert_eq!(
+            "201F6024 00000000  cb1  32-bit write of 0x00000000 to 0x001F6024\n\
+             scheme cb1, no beefcode\n\
+             BEEFC0DE 00000000  cb1  encryption key 0x00000000\n\
+             scheme cb7, beefcode seen\n\
+             4043AFCC 00030001  cb7\n\
+             2411FFFF 00000004  cb7  serial write of 0x2411FFFF to 0x0043AFCC, 3 times every 4 bytes, \
+             adding 0x00000004\n\
+             error: \"foo\" is not an 8-digit hex number (try help)\n\
+             error: unknown format \"ar2\"\n\
+             reset\n\
+             scheme cb1 (pinned)\n\
+             2096F5B8 000000BE  raw  32-bit write of 0x000000BE to 0x0096F5B8\n",
+            String::from_utf8(output).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_filter() {
+        let post = "Hi all,\n  here is Inf HP:\n\n2A973DBD 00000000 // works\n2A973DBD\n  ; cheers\n";
+        assert_eq!(
+            Ok("Hi all,\n  here is Inf HP:\n\n201F6024 00000000 // works\n2A973DBD\n  ; cheers\n".to_string()),
+            filter_with(Command::Decrypt, FILTER, post)
+        );
+        assert_eq!(
+            Err(Failure::Error("-: line 5: code has no value".into())),
+            filter(Command::Decrypt, post)
+        );
+        assert_eq!(
+            Ok("Hi all,\n  here is Inf HP:\n\n2A973DBD 00000000 // works\n2A973DBD\n  ; cheers\n".to_string()),
+            filter_with(
+                Command::Convert {
+                    from: None,
+                    to: Scheme::V1
+                },
+                FILTER,
+                post
+            )
+        );
+    }
+
+    #[test]
+    fn test_serve() {
+        let requests = [
+            json!({"id": 1, "op": "open", "command": "decrypt"}),
+            json!({"id": 2, "op": "decrypt", "session": 1, "text": "B4336FA9 4DFEFB79"}),
+            // A failed request leaves the session as it was
+            json!({"id": 3, "op": "decrypt", "session": 1, "text": "973E0B2A A7D4AF10\n2A973DBD"}),
+            json!({"id": 4, "op": "decrypt", "session": 1, "text": "973E0B2A A7D4AF10"}),
+            json!({"id": 5, "op": "encrypt", "session": 1, "text": "2096F5B8 000000BE"}),
+            json!({"id": 6, "op": "decrypt", "text": "2A973DBD 00000000"}),
+            json!({"id": 7, "op": "convert", "to": "cb1", "text": "Inf HP\n2043AFCC 2411FFFF", "filter": true}),
+            json!({"id": 8, "op": "close", "session": 1}),
+            json!({"id": 9, "op": "close", "session": 1}),
+            json!({"id": 10, "op": "open", "command": "convert", "from": "ps2rd"}),
+            json!({"id": 11, "op": "open", "command": "encrypt"}),
+            json!({"op": "serve"}),
+        ];
+        let mut input: String = requests.iter().map(|request| format!("{}\n", request)).collect();
+        input.push_str("\nnot json\n");
+        let mut output = Vec::new();
+        assert_eq!(Ok(()), serve(input.as_bytes(), &mut output));
+        assert_eq!(
+            vec![
+                json!({"id": 1, "session": 1}),
+                json!({"id": 2, "text": "BEEFC0DE 00000000\n"}),
+                json!({"id": 3, "error": "line 2: code has no value"}),
+                json!({"id": 4, "text": "2096F5B8 000000BE\n"}),
+                json!({"id": 5, "error": "session 1 is for decrypt"}),
+                json!({"id": 6, "text": "201F6024 00000000\n"}),
+                json!({"id": 7, "text": "Inf HP\n2AFF014C 2411FFFF\n"}),
+                json!({"id": 8}),
+                json!({"id": 9, "error": "unknown session 1"}),
+                json!({"id": 10, "error": "format \"ps2rd\" is not a list of codes"}),
+                json!({"id": 11, "session": 2}),
+                json!({"id": null, "error": "unknown op \"serve\""}),
+                json!({"id": null, "error": "invalid request: expected ident at line 1 column 2"}),
+            ],
+            json(output)
+        );
+    }
+
+    #[test]
+    fn test_list_formats() {
+        let mut output = Vec::new();
+        assert_eq!(Ok(()), list_formats(Options::default(), &mut output));
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.starts_with("raw    CodeBreaker          decrypted codes (read, write, convert)\n"));
+        assert!(output.contains("\nps2rd  ps2rd                cheat file of games and their cheats (read, write)\n"));
+
+        let mut output = Vec::new();
+        assert_eq!(Ok(()), list_formats(JSON, &mut output));
+        assert_eq!(
+            json!({
+                "type": "format",
+                "name": "cb7",
+                "description": "codes encrypted for CB v7+",
+                "device": "CodeBreaker v7+",
+                "read": true,
+                "write": true,
+                "convert": true,
+            }),
+            json(output)[2]
+        );
+
+        assert_eq!(
+            Err(Failure::Error("format \"ps2rd\" is not a list of codes".into())),
+            parse(&args(&["convert", "--to", "ps2rd"]))
+        );
+    }
+
+    #[test]
+    fn test_json() {
+        let input = "Inf HP\n2A973DBD 00000000 // player 1\n";
+        let output = filter_with(Command::Decrypt, JSON, input).unwrap();
+        assert_eq!(
+            vec![
+                json!({"type": "line", "file": "-", "line": 1, "input": "Inf HP", "output": ["Inf HP"], "scheme": null}),
+                json!({
+                    "type": "line",
+                    "file": "-",
+                    "line": 2,
+                    "input": "2A973DBD 00000000 // player 1",
+                    "output": ["201F6024 00000000 // player 1"],
+                    "scheme": "cb1",
+                }),
+                json!({"type": "summary", "file": "-", "lines": 2, "codes": 1}),
+            ],
+            json(output.into_bytes())
+        );
+
+        let output = filter_with(
+            Command::Convert {
+                from: None,
+                to: Scheme::V7,
+            },
+            JSON,
+            "2043AFCC 2411FFFF\n",
+        )
+        .unwrap();
+        assert_eq!(
+            json!(["B4336FA9 4DFEFB79", "397951B0 41569FE0"]),
+            json(output.into_bytes())[0]["output"]
+        );
+
+        let mut output = Vec::new();
+        assert_eq!(Ok(()), explain(input, "-", JSON, &mut output));
+        assert_eq!(
+            vec![
+                json!({
+                    "type": "code",
+                    "file": "-",
+                    "line": 2,
+                    "code": ["201F6024 00000000"],
+                    "description": "32-bit write of 0x00000000 to 0x001F6024",
+                }),
+                json!({"type": "summary", "file": "-", "lines": 2, "codes": 1}),
+            ],
+            json(output)
+        );
+
+        let mut output = Vec::new();
+        assert_eq!(Ok((1, 0)), check("2043AFCE 2411FFFF\n", "-", JSON, &mut output));
+        assert_eq!(
+            vec![
+                json!({
+                    "type": "finding",
+                    "file": "-",
+                    "line": 1,
+                    "severity": "error",
+                    "message": "address 0043AFCE is not aligned for 32-bit access",
+                }),
+                json!({"type": "summary", "file": "-", "errors": 1, "warnings": 0}),
+            ],
+            json(output)
+        );
+
+        let mut output = Vec::new();
+        assert_eq!(Ok(()), search("type:if", &[], JSON, &mut output));
+        assert_eq!(vec![json!({"type": "summary", "files": 0, "hits": 0})], json(output));
+
+        let mut output = Vec::new();
+        assert_eq!(Ok(()), compare(input, "2043AFCC 2411FFFF\n", false, JSON, &mut output));
+        assert_eq!(
+            vec![
+                json!({"type": "change", "kind": "changed", "old": "201F6024 00000000", "new": "2043AFCC 2411FFFF"}),
+                json!({"type": "summary", "same": 0, "changed": 1, "removed": 0, "added": 0}),
+            ],
+            json(output)
+        );
+    }
+
+    #[test]
+    fn test_read_game() {
+        let input = "2043AFCC 2411FFFF\n\nInf HP // player 1\n2A973DBD 00000000\n; more\n\nMax MP\n";
+        let (game, lines) = read_game(input, "ffx.txt").unwrap();
+        assert_eq!("ffx.txt", game.title);
+        assert_eq!(
+            vec![
+                Cheat::new("", vec![(0x2043_afcc, 0x2411_ffff)]),
+                Cheat::new("Inf HP", vec![(0x201f_6024, 0x0000_0000)]),
+                Cheat::new("Max MP", vec![]),
+            ],
+            game.cheats
+        );
+        assert_eq!(vec![vec![0], vec![3], vec![]], lines);
+    }
+
+    #[test]
+    fn test_search() {
+        let mut output = Vec::new();
+        assert_eq!(
+            Err(Failure::Error("invalid query: unknown key \"size\"".into())),
+            search("size:2", &[], Options::default(), &mut output)
+        );
+        assert_eq!(Ok(()), search("game:nothing", &[], Options::default(), &mut output));
+        assert!(output.is_empty());
+    }
+
+    #[test]
+    fn test_compare() {
+        let old = "Inf HP\n2AFF014C 2411FFFF // player 1\n";
+        let new = "Inf HP\n2043AFCC 2411FFFF\nBAD\n2A973DBD 00000000\n";
+        let mut output = Vec::new();
+        assert_eq!(Ok(()), compare(old, new, false, Options::default(), &mut output));
+        assert_eq!(
+            "2043AFCC 2411FFFF   2043AFCC 2411FFFF\n                  > 201F6024 00000000\n",
+            String::from_utf8(output).unwrap()
+        );
+    }
+}
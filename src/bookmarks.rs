@@ -0,0 +1,240 @@
+//! Export of cheat-targeted addresses to reverse-engineering tools.
+//!
+//! The addresses a cheat writes to are usually the interesting variables of
+//! a game. This module lists them as CSV, or as scripts that add them as
+//! bookmarks to Ghidra or as comments to IDA.
+//!
+//! # Example
+//! ```
+//! use codebreaker::bookmarks::{targets, write_csv};
+//! use codebreaker::cheat::Cheat;
+//!
+//! let cheat = Cheat::builder("Inf Health").write16(0x0043AFCC, 0xFFFF).build().unwrap();
+//! assert_eq!(1, targets(&[cheat.clone()]).len());
+//!
+//! let mut csv = String::new();
+//! write_csv(&mut csv, &[cheat]).unwrap();
+//! assert_eq!("address,width,value,cheat\n0x0043AFCC,16,0xFFFF,Inf Health\n", csv);
+//! ```
+
+use crate::cheat::Cheat;
+use crate::op::{Addr, Op, Value, Width};
+use crate::std_alloc::Vec;
+
+use core::fmt::{self, Write};
+
+/// An address written by a cheat.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Target<'a> {
+    /// The address written to
+    pub addr: Addr,
+    /// The value and width written
+    pub val: Value,
+    /// The name of the cheat
+    pub cheat: &'a str,
+}
+
+/// Returns all addresses written by the cheats, including writes inside
+/// conditionals, in the order of the cheats.
+///
+/// Serial writes contribute one target per written address.
+pub fn targets(cheats: &[Cheat]) -> Vec<Target<'_>> {
+    let mut targets = Vec::new();
+    for cheat in cheats.iter() {
+        collect(&cheat.ops, &cheat.name, &mut targets);
+    }
+    targets
+}
+
+fn collect<'a>(ops: &[Op], cheat: &'a str, targets: &mut Vec<Target<'a>>) {
+    for op in ops.iter() {
+        match *op {
+            Op::Write { addr, val } => targets.push(Target { addr, val, cheat }),
+            Op::SerialWrite {
+                addr,
+                val,
+                count,
+                step,
+                inc,
+            } => {
+                for i in 0..count {
+                    // Writes beyond EE RAM would not be encodable anyway
+                    if let Some(Ok(addr)) = i.checked_mul(step).map(|offset| addr.offset(offset)) {
+                        let val = Value::word(val.wrapping_add(i.wrapping_mul(inc)));
+                        targets.push(Target { addr, val, cheat });
+                    }
+                }
+            }
+            Op::If { ref then, .. } => collect(then, cheat, targets),
+        }
+    }
+}
+
+/// Writes the targets of the cheats as CSV with a header line.
+pub fn write_csv<W: Write>(w: &mut W, cheats: &[Cheat]) -> fmt::Result {
+    w.write_str("address,width,value,cheat\n")?;
+    for t in targets(cheats).iter() {
+        write!(w, "0x{},{},0x{},", t.addr, t.val.width().bytes() * 8, t.val)?;
+        if t.cheat.contains([',', '"', '\n', '\r']) {
+            write!(w, "\"{}\"", Escaped(t.cheat, '"'))?;
+        } else {
+            w.write_str(t.cheat)?;
+        }
+        w.write_char('\n')?;
+    }
+    Ok(())
+}
+
+/// Writes a Ghidra Python script that bookmarks the targets of the cheats.
+pub fn write_ghidra_script<W: Write>(w: &mut W, cheats: &[Cheat]) -> fmt::Result {
+    w.write_str("# Bookmarks for cheat targets, run in Ghidra's Script Manager\n")?;
+    for t in targets(cheats).iter() {
+        writeln!(
+            w,
+            "createBookmark(toAddr(0x{}), \"Cheat\", \"{}: {} = 0x{}\")",
+            t.addr,
+            Escaped(t.cheat, '\\'),
+            width_name(t.val.width()),
+            t.val
+        )?;
+    }
+    Ok(())
+}
+
+/// Writes an IDAPython script that comments the targets of the cheats.
+pub fn write_ida_script<W: Write>(w: &mut W, cheats: &[Cheat]) -> fmt::Result {
+    w.write_str("# Comments for cheat targets, run with File > Script file\nimport idc\n")?;
+    for t in targets(cheats).iter() {
+        writeln!(
+            w,
+            "idc.set_cmt(0x{}, \"{}: {} = 0x{}\", 1)",
+            t.addr,
+            Escaped(t.cheat, '\\'),
+            width_name(t.val.width()),
+            t.val
+        )?;
+    }
+    Ok(())
+}
+
+const fn width_name(width: Width) -> &'static str {
+    match width {
+        Width::Byte => "write8",
+        Width::Half => "write16",
+        Width::Word => "write32",
+    }
+}
+
+// Escapes double quotes, with another quote for CSV or a backslash for
+// Python. Python also needs backslashes, line feeds, and carriage returns
+// escaped.
+struct Escaped<'a>(&'a str, char);
+
+impl fmt::Display for Escaped<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for c in self.0.chars() {
+            match (c, self.1) {
+                ('"', esc) => {
+                    f.write_char(esc)?;
+                    f.write_char('"')?;
+                }
+                ('\\', '\\') => f.write_str("\\\\")?,
+                ('\n', '\\') => f.write_str("\\n")?,
+                ('\r', '\\') => f.write_str("\\r")?,
+                (c, _) => f.write_char(c)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::std_alloc::String;
+
+    fn cheats() -> Vec<Cheat> {
+        vec![
+            Cheat::builder("Inf \"HP\"")
+                .write8(0x0031_789a, 0x63)
+                .if_eq16(0x004a_0000, 1)
+                .then(|b| b.write32(0x0043_afd0, 0x2411_ffff))
+                .build()
+                .unwrap(),
+            Cheat {
+                name: "Items, all".into(),
+                ops: vec![Op::SerialWrite {
+                    addr: Addr::new(0x01ff_fff8).unwrap(),
+                    val: 1,
+                    count: 3,
+                    step: 4,
+                    inc: 1,
+                }],
+            },
+        ]
+    }
+
+    #[test]
+    fn test_targets() {
+        let cheats = cheats();
+        let targets = targets(&cheats);
+        assert_eq!(4, targets.len());
+        assert_eq!(0x0043_afd0, targets[1].addr.get());
+        assert_eq!(Value::word(0x2411_ffff), targets[1].val);
+        // the third serial write would be outside of EE RAM
+        assert_eq!(0x01ff_fffc, targets[3].addr.get());
+        assert_eq!(Value::word(2), targets[3].val);
+        assert_eq!("Items, all", targets[3].cheat);
+    }
+
+    #[test]
+    fn test_write_csv() {
+        let mut s = String::new();
+        write_csv(&mut s, &cheats()).unwrap();
+        assert_eq!(
+            "address,width,value,cheat\n\
+             0x0031789A,8,0x63,\"Inf \"\"HP\"\"\"\n\
+             0x0043AFD0,32,0x2411FFFF,\"Inf \"\"HP\"\"\"\n\
+             0x01FFFFF8,32,0x00000001,\"Items, all\"\n\
+             0x01FFFFFC,32,0x00000002,\"Items, all\"\n",
+            s
+        );
+    }
+
+    #[test]
+    fn test_write_scripts() {
+        let cheats = &cheats()[..1];
+
+        let mut s = String::new();
+        write_ghidra_script(&mut s, cheats).unwrap();
+        assert_eq!(
+            "# Bookmarks for cheat targets, run in Ghidra's Script Manager\n\
+             createBookmark(toAddr(0x0031789A), \"Cheat\", \"Inf \\\"HP\\\": write8 = 0x63\")\n\
+             createBookmark(toAddr(0x0043AFD0), \"Cheat\", \"Inf \\\"HP\\\": write32 = 0x2411FFFF\")\n",
+            s
+        );
+
+        let mut s = String::new();
+        write_ida_script(&mut s, cheats).unwrap();
+        assert_eq!(
+            "# Comments for cheat targets, run with File > Script file\n\
+             import idc\n\
+             idc.set_cmt(0x0031789A, \"Inf \\\"HP\\\": write8 = 0x63\", 1)\n\
+             idc.set_cmt(0x0043AFD0, \"Inf \\\"HP\\\": write32 = 0x2411FFFF\", 1)\n",
+            s
+        );
+    }
+
+    #[test]
+    fn test_escape_line_breaks() {
+        let cheats = [Cheat::builder("HP\\\r\nMP").write8(0x0031_789a, 0x63).build().unwrap()];
+
+        let mut s = String::new();
+        write_csv(&mut s, &cheats).unwrap();
+        assert_eq!("address,width,value,cheat\n0x0031789A,8,0x63,\"HP\\\r\nMP\"\n", s);
+
+        let mut s = String::new();
+        write_ida_script(&mut s, &cheats).unwrap();
+        assert!(s.ends_with("idc.set_cmt(0x0031789A, \"HP\\\\\\r\\nMP: write8 = 0x63\", 1)\n"));
+    }
+}
@@ -1,5 +1,7 @@
 //! Encrypt and decrypt cheat codes for CodeBreaker PS2 v1 - v6.
 
+use crate::Codebreaker;
+
 /// Encrypts a code and returns the result.
 ///
 /// # Example
@@ -72,8 +74,31 @@ pub fn decrypt_code_mut(addr: &mut u32, val: &mut u32) {
     *val = code.1;
 }
 
+/// Checks that a list of decrypted codes comes back unchanged when
+/// encrypted with CB v1 and decrypted like with
+/// [`auto_decrypt_code`](../struct.Codebreaker.html#method.auto_decrypt_code).
+///
+/// A code outside of EE RAM may look raw once encrypted, and an encrypted
+/// beefcode switches decryption to V7. Returns the index of the first code
+/// that does not come back, or `None` if all do.
+///
+/// # Example
+/// ```
+/// use codebreaker::cb1;
+///
+/// assert_eq!(None, cb1::verify_roundtrip(&[(0x1023CED8, 0x000003E7), (0x201F6024, 0x00000000)]));
+/// assert_eq!(Some(1), cb1::verify_roundtrip(&[(0x1023CED8, 0x000003E7), (0x2A000000, 0x00000000)]));
+/// ```
+pub fn verify_roundtrip(codes: &[(u32, u32)]) -> Option<usize> {
+    let mut cb = Codebreaker::new();
+    codes.iter().position(|&(addr, val)| {
+        let (a, v) = encrypt_code(addr, val);
+        cb.auto_decrypt_code(a, v) != (addr, val)
+    })
+}
+
 #[rustfmt::skip]
-const SEEDS: [[u32; 16]; 3] = [
+pub(crate) const SEEDS: [[u32; 16]; 3] = [
     [
         0x0a0b_8d9b, 0x0a01_33f8, 0x0af7_33ec, 0x0a15_c574,
         0x0a50_ac20, 0x0a92_0fb9, 0x0a59_9f0b, 0x0a4a_a0e3,
@@ -169,4 +194,11 @@ mod tests {
             assert_eq!(t.decrypted, code::format(code));
         }
     }
+
+    #[test]
+    fn test_verify_roundtrip() {
+        let codes: Vec<_> = tests().iter().map(|t| code::parse(t.decrypted)).collect();
+        assert_eq!(None, verify_roundtrip(&codes));
+        assert_eq!(None, verify_roundtrip(&[]));
+    }
 }
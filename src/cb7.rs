@@ -1,14 +1,17 @@
 //! Encrypt and decrypt cheat codes for CodeBreaker PS2 v7+.
 
+use crate::primitives::*;
 use crate::rc4::Rc4;
 
 use core::fmt;
 
-use bytemuck::{bytes_of, bytes_of_mut, cast_slice};
+use bytemuck::{bytes_of, bytes_of_mut};
 
 /// A processor for CB v7+ codes.
 #[derive(Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Cb7 {
+    #[cfg_attr(feature = "serde", serde(with = "serde_seeds"))]
     seeds: [[u8; 256]; 5],
     key: [u32; 5],
     beefcodf: bool,
@@ -75,37 +78,20 @@ impl Cb7 {
     pub fn beefcode(&mut self, addr: u32, val: u32) {
         assert!(is_beefcode(addr));
 
-        // Easily access all bytes of val as indices into seeds
-        let mut idx = [0; 4];
-        val.to_le_bytes()
-            .iter()
-            .zip(&mut idx)
-            .for_each(|(b, i)| *i = *b as usize);
-
         // Set up key and seeds
         if !self.initialized {
             self.key.copy_from_slice(&RC4_KEY);
 
             if val != 0 {
                 self.seeds.copy_from_slice(&SEEDS);
-                for i in 0..4 {
-                    self.key[i] = u32::from(self.seeds[(i + 3) % 4][idx[3]]) << 24
-                        | u32::from(self.seeds[(i + 2) % 4][idx[2]]) << 16
-                        | u32::from(self.seeds[(i + 1) % 4][idx[1]]) << 8
-                        | u32::from(self.seeds[i % 4][idx[0]]);
-                }
+                self.key[..4].copy_from_slice(&derive_key(&self.seeds, val));
             } else {
                 self.seeds.copy_from_slice(&ZERO_SEEDS);
             }
 
             self.initialized = true;
         } else if val != 0 {
-            for i in 0..4 {
-                self.key[i] = u32::from(self.seeds[(i + 3) % 4][idx[3]]) << 24
-                    | u32::from(self.seeds[(i + 2) % 4][idx[2]]) << 16
-                    | u32::from(self.seeds[(i + 1) % 4][idx[1]]) << 8
-                    | u32::from(self.seeds[i % 4][idx[0]]);
-            }
+            self.key[..4].copy_from_slice(&derive_key(&self.seeds, val));
         } else {
             // Special case for 2x BEEFC0DE 00000000 in a row
             self.seeds.copy_from_slice(&ZERO_SEEDS);
@@ -116,20 +102,19 @@ impl Cb7 {
         }
 
         // Use key to encrypt seeds with RC4
-        let k = bytes_of_mut(&mut self.key);
-        for i in 0..5 {
-            let mut rc4 = Rc4::new(k);
-            // Encrypt seeds
-            rc4.crypt(&mut self.seeds[i]);
-            // Encrypt original key for next round
-            rc4.crypt(k);
-        }
+        scramble_seeds(&mut self.key, &mut self.seeds);
 
         // Since we don't know the extra seed value of BEEFC0DF yet,
         // all we can do is set a flag.
         self.beefcodf = addr & 1 != 0;
     }
 
+    /// Returns true if the last beefcode was `BEEFC0DF` and the next code is
+    /// its extra seed value.
+    pub const fn expects_seed(&self) -> bool {
+        self.beefcodf
+    }
+
     /// Encrypts a code and returns the result.
     ///
     /// # Example
@@ -162,25 +147,17 @@ impl Cb7 {
         let oldval = *val;
 
         // Step 1: Multiplication, modulo (2^32)
-        *addr = mul_encrypt(*addr, self.key[0].wrapping_sub(self.key[1]));
-        *val = mul_encrypt(*val, self.key[2].wrapping_add(self.key[3]));
+        let a = mul_encrypt(*addr, self.key[0].wrapping_sub(self.key[1]));
+        let v = mul_encrypt(*val, self.key[2].wrapping_add(self.key[3]));
 
         // Step 2: RC4
-        let mut code = [*addr, *val];
-        let mut rc4 = Rc4::new(bytes_of(&self.key));
-        rc4.crypt(bytes_of_mut(&mut code));
-        *addr = code[0];
-        *val = code[1];
+        let (a, v) = rc4_crypt(&self.key, a, v);
 
         // Step 3: RSA
-        rsa_crypt(addr, val, RSA_ENC_KEY, RSA_MODULUS);
+        let (a, v) = rsa_crypt(a, v, RSA_ENC_KEY);
 
         // Step 4: Encryption loop of 64 cycles, using the generated seeds
-        let s: &[u32] = cast_slice(&self.seeds);
-        for i in 0..64 {
-            *addr = (addr.wrapping_add(s[2 * 64 + i]) ^ s[i]).wrapping_sub(*val ^ s[4 * 64 + i]);
-            *val = (val.wrapping_sub(s[3 * 64 + i]) ^ s[64 + i]).wrapping_add(*addr ^ s[4 * 64 + i]);
-        }
+        (*addr, *val) = seed_encrypt(a, v, &self.seeds);
 
         // BEEFC0DE
         if is_beefcode(oldaddr) {
@@ -226,25 +203,17 @@ impl Cb7 {
     /// ```
     pub fn decrypt_code_mut(&mut self, addr: &mut u32, val: &mut u32) {
         // Step 1: Decryption loop of 64 cycles, using the generated seeds
-        let s: &[u32] = cast_slice(&self.seeds);
-        for i in (0..64).rev() {
-            *val = (val.wrapping_sub(*addr ^ s[4 * 64 + i]) ^ s[64 + i]).wrapping_add(s[3 * 64 + i]);
-            *addr = (addr.wrapping_add(*val ^ s[4 * 64 + i]) ^ s[i]).wrapping_sub(s[2 * 64 + i]);
-        }
+        let (a, v) = seed_decrypt(*addr, *val, &self.seeds);
 
         // Step 2: RSA
-        rsa_crypt(addr, val, RSA_DEC_KEY, RSA_MODULUS);
+        let (a, v) = rsa_crypt(a, v, RSA_DEC_KEY);
 
         // Step 3: RC4
-        let mut code = [*addr, *val];
-        let mut rc4 = Rc4::new(bytes_of(&self.key));
-        rc4.crypt(bytes_of_mut(&mut code));
-        *addr = code[0];
-        *val = code[1];
+        let (a, v) = rc4_crypt(&self.key, a, v);
 
         // Step 4: Multiplication with multiplicative inverse, modulo (2^32)
-        *addr = mul_decrypt(*addr, self.key[0].wrapping_sub(self.key[1]));
-        *val = mul_decrypt(*val, self.key[2].wrapping_add(self.key[3]));
+        *addr = mul_decrypt(a, self.key[0].wrapping_sub(self.key[1]));
+        *val = mul_decrypt(v, self.key[2].wrapping_add(self.key[3]));
 
         // BEEFC0DF uses two codes. If the previous code was the first of the
         // two, use the current one to decrypt the seeds.
@@ -262,6 +231,42 @@ impl Cb7 {
     }
 }
 
+/// Checks that each code of a list decrypts back to itself once encrypted,
+/// starting with the key and seeds of the given beefcode.
+///
+/// Beefcodes in the list change the encryption of the following codes like
+/// with [`Codebreaker`](../struct.Codebreaker.html). The extra seed of a
+/// `BEEFC0DF` that looks like a beefcode itself is taken as a new beefcode
+/// when encrypting, but as the seed when decrypting, so the codes after it
+/// fail. Returns the index of the first code that does not survive the round
+/// trip, or `None` if all do.
+///
+/// # Panics
+///
+/// Panics if the address of `beefcode` is not that of a beefcode.
+///
+/// # Example
+/// ```
+/// use codebreaker::cb7;
+///
+/// let codes = [(0x9029BEAC, 0x0C0A9225), (0xBEEFC0DE, 0xDEADFACE), (0x2096F5B8, 0x000000BE)];
+/// assert_eq!(None, cb7::verify_roundtrip((0xBEEFC0DE, 0x00000000), &codes));
+///
+/// let codes = [(0xBEEFC0DF, 0x00000000), (0xBEEFC0DE, 0x12345678), (0x2096F5B8, 0x000000BE)];
+/// assert_eq!(Some(2), cb7::verify_roundtrip((0xBEEFC0DE, 0x00000000), &codes));
+/// ```
+pub fn verify_roundtrip(beefcode: (u32, u32), codes: &[(u32, u32)]) -> Option<usize> {
+    let mut enc = Cb7::new();
+    enc.beefcode(beefcode.0, beefcode.1);
+    let mut dec = enc;
+
+    // Both directions apply beefcodes themselves
+    codes.iter().position(|&(addr, val)| {
+        let (a, v) = enc.encrypt_code(addr, val);
+        dec.decrypt_code(a, v) != (addr, val)
+    })
+}
+
 /// Returns true if the code address indicates a "beefcode". In that case, the
 /// [`beefcode`](struct.Cb7.html#method.beefcode) method should be invoked.
 ///
@@ -278,78 +283,40 @@ pub const fn is_beefcode(addr: u32) -> bool {
     addr & 0xffff_fffe == BEEFCODE
 }
 
-// Multiplication, modulo (2^32)
-#[inline(always)]
-const fn mul_encrypt(a: u32, b: u32) -> u32 {
-    a.wrapping_mul(b | 1)
-}
-
-// Multiplication with multiplicative inverse, modulo (2^32)
-#[inline(always)]
-const fn mul_decrypt(a: u32, b: u32) -> u32 {
-    a.wrapping_mul(mod_inverse(b | 1))
-}
-
-// Computes the multiplicative inverse of x modulo (2^32). x must be odd!
-// The code is based on Newton's method as explained in this blog post:
-// https://lemire.me/blog/2017/09/18/computing-the-inverse-of-odd-integers/
-const fn mod_inverse(x: u32) -> u32 {
-    let mut y = x;
-    // Call this recurrence formula 4 times for 32-bit values:
-    // f(y) = y * (2 - y * x) modulo 2^32
-    y = y.wrapping_mul(2u32.wrapping_sub(y.wrapping_mul(x)));
-    y = y.wrapping_mul(2u32.wrapping_sub(y.wrapping_mul(x)));
-    y = y.wrapping_mul(2u32.wrapping_sub(y.wrapping_mul(x)));
-    y = y.wrapping_mul(2u32.wrapping_sub(y.wrapping_mul(x)));
-    y
-}
+// Serde only implements its traits for arrays of up to 32 elements.
+#[cfg(feature = "serde")]
+mod serde_seeds {
+    use crate::std_alloc::Vec;
 
-// RSA encryption/decryption
-fn rsa_crypt(addr: &mut u32, val: &mut u32, rsakey: u64, modulus: u64) {
-    use num_bigint::BigUint;
+    use serde::de::{Deserialize, Deserializer, Error};
+    use serde::ser::Serializer;
 
-    let code = BigUint::from_slice(&[*val, *addr]);
-    let m = BigUint::from(modulus);
+    pub fn serialize<S: Serializer>(seeds: &[[u8; 256]; 5], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(seeds.iter().map(|seed| &seed[..]))
+    }
 
-    // Exponentiation is only invertible if code < modulus
-    if code < m {
-        let digits = code.modpow(&BigUint::from(rsakey), &m).to_u32_digits();
-        *addr = digits[1];
-        *val = digits[0];
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<[[u8; 256]; 5], D::Error> {
+        let rows = Vec::<Vec<u8>>::deserialize(deserializer)?;
+        if rows.len() != 5 {
+            return Err(D::Error::invalid_length(rows.len(), &"5 seeds"));
+        }
+        let mut seeds = [[0; 256]; 5];
+        for (seed, row) in seeds.iter_mut().zip(rows) {
+            if row.len() != 256 {
+                return Err(D::Error::invalid_length(row.len(), &"256 bytes"));
+            }
+            seed.copy_from_slice(&row);
+        }
+        Ok(seeds)
     }
 }
 
-const BEEFCODE: u32 = 0xbeef_c0de;
-
-const RC4_KEY: [u32; 5] = [0xd0db_a9d7, 0x13a0_a96c, 0x8041_0df0, 0x2ccd_be1f, 0xe570_a86b];
-
-const RSA_DEC_KEY: u64 = 11;
-// This is how I calculated the encryption key e from d (some number theory):
-//
-//   d = 11
-//   n = 18446744073709551605
-//   e = d^(-1) mod phi(n)
-//
-//   n factored:
-//   n = 5 * 2551 * 1446236305269271
-//     = p*q*r, only single prime factors
-//
-//   phi(n) = phi(p*q*r)
-//          = phi(p) * phi(q) * phi(r), phi(p) = p - 1
-//          = (p-1)*(q-1)*(r-1)
-//          = (5-1) * (2551-1) * (1446236305269271-1)
-//          = 4 * 2550 * 1446236305269270
-//          = 14751610313746554000
-//
-//   e = 11^(-1) mod 14751610313746554000
-//   e = 2682110966135737091
-const RSA_ENC_KEY: u64 = 2_682_110_966_135_737_091;
-const RSA_MODULUS: u64 = 18_446_744_073_709_551_605; // 0xffff_ffff_ffff_fff5
+pub(crate) const BEEFCODE: u32 = 0xbeef_c0de;
 
 const ZERO_SEEDS: [[u8; 256]; 5] = [[0; 256]; 5];
 
 #[rustfmt::skip]
-const SEEDS: [[u8; 256]; 5] = [
+pub(crate) const SEEDS: [[u8; 256]; 5] = [
     [
         0x84, 0x01, 0x21, 0xa4, 0xfa, 0x4d, 0x50, 0x8d, 0x75, 0x33, 0xc5, 0xf7, 0x4a, 0x6d, 0x7c, 0xa6,
         0x1c, 0xf8, 0x40, 0x18, 0xa1, 0xb3, 0xa2, 0xf9, 0x6a, 0x19, 0x63, 0x66, 0x29, 0xae, 0x10, 0x75,
@@ -448,53 +415,6 @@ mod tests {
     use crate::code;
     use crate::std_alloc::Vec;
 
-    fn mul_tests() -> Vec<(u32, u32, u32)> {
-        vec![
-            (0x0000_0000, 0xa686_d3b6, 0x0000_0000),
-            (0x000e_0000, 0xa686_d3b6, 0xac62_0000),
-            (0x0067_bd20, 0x4fd9_31ff, 0x2008_02e0),
-            (0x2ba0_a76e, 0xa686_d3b6, 0x2405_0002),
-            (0x4adf_d954, 0x4fd9_31ff, 0x9029_beac),
-            (0x7c01_6806, 0x2912_dedd, 0x0000_00be),
-            (0xa942_2f21, 0xa686_d3b6, 0x03d2_03e7),
-            (0xfff5_76e0, 0xa686_d3b6, 0x27bd_0020),
-        ]
-    }
-
-    #[test]
-    fn test_mul_encrypt() {
-        for t in mul_tests().iter() {
-            assert_eq!(t.0, mul_encrypt(t.2, t.1));
-        }
-    }
-
-    #[test]
-    fn test_mul_decrypt() {
-        for t in mul_tests().iter() {
-            assert_eq!(t.2, mul_decrypt(t.0, t.1));
-        }
-    }
-
-    #[test]
-    fn test_mod_inverse() {
-        let tests = vec![
-            (0x0d31_3243, 0x6c7b_2a6b),
-            (0x0efd_8231, 0xd4c0_96d1),
-            (0x2912_dedd, 0xe09d_e975),
-            (0x4fd9_31ff, 0x9a62_cdff),
-            (0x5a53_abb5, 0x58f4_2a9d),
-            (0x9ab2_af6d, 0x1043_b265),
-            (0xa686_d3b7, 0x57ed_7a07),
-            (0xec35_a92f, 0xd274_3dcf),
-            (0x0000_0000, 0x0000_0000), // Technically, 0 has no inverse
-            (0x0000_0001, 0x0000_0001),
-            (0xffff_ffff, 0xffff_ffff),
-        ];
-        for t in tests.iter() {
-            assert_eq!(t.1, mod_inverse(t.0));
-        }
-    }
-
     struct Test {
         beefcode: &'static str,
         decrypted: Vec<&'static str>,
@@ -661,4 +581,49 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_verify_roundtrip() {
+        for t in tests().iter() {
+            let beefcode = code::parse(t.beefcode);
+            let codes: Vec<_> = t.decrypted.iter().map(|line| code::parse(line)).collect();
+            assert_eq!(None, verify_roundtrip(beefcode, &codes));
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        for t in tests().iter() {
+            let beefcode = code::parse(t.beefcode);
+            let mut cb7 = Cb7::new();
+            cb7.beefcode(beefcode.0, beefcode.1);
+            let json = serde_json::to_string(&cb7).unwrap();
+            let mut cb7: Cb7 = serde_json::from_str(&json).unwrap();
+            for (i, line) in t.encrypted.iter().enumerate() {
+                let mut code = code::parse(line);
+                cb7.decrypt_code_mut(&mut code.0, &mut code.1);
+                assert_eq!(t.decrypted[i], code::format(code));
+
+                if is_beefcode(code.0) {
+                    cb7.beefcode(code.0, code.1)
+                }
+            }
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_seeds() {
+        let value = serde_json::to_value(Cb7::new()).unwrap();
+        assert!(serde_json::from_value::<Cb7>(value.clone()).is_ok());
+
+        let mut rows = value.clone();
+        rows["seeds"].as_array_mut().unwrap().pop();
+        assert!(serde_json::from_value::<Cb7>(rows).is_err());
+
+        let mut bytes = value;
+        bytes["seeds"][4].as_array_mut().unwrap().pop();
+        assert!(serde_json::from_value::<Cb7>(bytes).is_err());
+    }
 }
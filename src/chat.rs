@@ -0,0 +1,229 @@
+//! Helpers for chat bots that convert posted codes.
+//!
+//! Community bots on Discord or IRC all do the same: pick the codes out of a
+//! message, detect how they are encrypted, convert them, and reply with the
+//! result. [`reply`] does all of that in one call.
+//!
+//! # Example
+//! ```
+//! use codebreaker::chat::reply;
+//! use codebreaker::Scheme;
+//!
+//! let message = "Inf HP for me:\n```\n2A973DBD 00000000\n```";
+//! assert_eq!(
+//!     "Converted 1 code from V1 to RAW:\n```\n201F6024 00000000\n```\n",
+//!     reply(message, Scheme::Raw)
+//! );
+//! ```
+
+use crate::audit::Recorder;
+use crate::cb7::is_beefcode;
+use crate::firmware::Profile;
+use crate::std_alloc::{String, Vec};
+use crate::{Codebreaker, Scheme};
+
+use core::fmt::Write;
+
+const FENCE: &str = "```";
+
+/// Returns the codes of a chat message.
+///
+/// If the message has code blocks fenced with backticks, only their lines
+/// are searched; otherwise all lines are. A line holds a code if it starts
+/// with two 8-digit hex numbers. Anything after them, like a comment, is
+/// ignored, as are all other lines, and a leading byte order mark.
+pub fn extract_codes(message: &str) -> Vec<(u32, u32)> {
+    let message = message.strip_prefix('\u{feff}').unwrap_or(message);
+    let fenced = message.contains(FENCE);
+    let mut inside = false;
+    let mut codes = Vec::new();
+
+    for line in message.lines() {
+        let line = line.trim();
+        if let Some(rest) = line.strip_prefix(FENCE) {
+            // Handle one-line blocks like ```2043AFCC 2411FFFF```
+            match rest.strip_suffix(FENCE) {
+                Some(inner) => codes.extend(parse_code(inner)),
+                None => inside = !inside,
+            }
+            continue;
+        }
+        if inside || !fenced {
+            codes.extend(parse_code(line));
+        }
+    }
+    codes
+}
+
+fn parse_code(line: &str) -> Option<(u32, u32)> {
+    let mut fields = line.split_whitespace();
+    Some((parse_hex(fields.next()?)?, parse_hex(fields.next()?)?))
+}
+
+fn parse_hex(s: &str) -> Option<u32> {
+    if s.len() == 8 && s.bytes().all(|b| b.is_ascii_hexdigit()) {
+        u32::from_str_radix(s, 16).ok()
+    } else {
+        None
+    }
+}
+
+/// Returns a reply to a chat message with its codes converted to `target`.
+///
+/// The schemes of the codes are detected automatically. The converted codes
+/// are wrapped in a code block and followed by one line per warning. V7 codes
+/// are encrypted like those published on CMGSCCC.com, without a leading
+/// beefcode. For other targets, beefcodes and their seed lines are left out
+/// with a warning, as only V7 uses them.
+pub fn reply(message: &str, target: Scheme) -> String {
+    let codes = extract_codes(message);
+    if codes.is_empty() {
+        return String::from("No codes found.\n");
+    }
+
+    let mut rec = Recorder::new(Codebreaker::new());
+    for &(addr, val) in codes.iter() {
+        rec.decrypt_code(addr, val);
+    }
+
+    let mut sources: Vec<Scheme> = Vec::new();
+    for entry in rec.entries().iter() {
+        if !sources.contains(&entry.scheme) {
+            sources.push(entry.scheme);
+        }
+    }
+
+    let mut cb = match target {
+        Scheme::V7 => Codebreaker::new_v7(),
+        _ => Codebreaker::new(),
+    };
+
+    // Writing to a String cannot fail
+    let mut s = String::new();
+    let plural = if codes.len() > 1 { "s" } else { "" };
+    let _ = write!(s, "Converted {} code{} from ", codes.len(), plural);
+    for (i, scheme) in sources.iter().enumerate() {
+        let _ = write!(s, "{}{}", if i == 0 { "" } else { "+" }, scheme);
+    }
+    let _ = writeln!(s, " to {}:\n{}", target, FENCE);
+    let mut left_out = Vec::new();
+    let (mut lines, mut keep) = (0, true);
+    for entry in rec.entries().iter() {
+        if lines == 0 {
+            lines = Profile::V7.lines(entry.decrypted.0);
+            keep = target == Scheme::V7 || !is_beefcode(entry.decrypted.0);
+            if !keep {
+                left_out.push(entry.line);
+            }
+        }
+        lines -= 1;
+        if !keep {
+            continue;
+        }
+        let (addr, val) = match target {
+            Scheme::Raw => entry.decrypted,
+            _ => cb.encrypt_code(entry.decrypted.0, entry.decrypted.1),
+        };
+        let _ = writeln!(s, "{:08X} {:08X}", addr, val);
+    }
+    let _ = writeln!(s, "{}", FENCE);
+    for entry in rec.warnings() {
+        if let Some(warning) = entry.warning {
+            let _ = writeln!(s, "Warning: line {}: {}", entry.line + 1, warning);
+        }
+    }
+    for line in left_out {
+        let _ = writeln!(
+            s,
+            "Warning: line {}: left out encryption key, which {} does not use",
+            line + 1,
+            target
+        );
+    }
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_codes() {
+        let message = "\
+Try these:
+2043AFCC 2411FFFF
+```
+2A973DBD 00000000 // inf hp
+not a code 12345678
+2A973DBD0 00000000
+```
+```B4336FA9 4DFEFB79```";
+        assert_eq!(
+            vec![(0x2a97_3dbd, 0x0000_0000), (0xb433_6fa9, 0x4dfe_fb79)],
+            extract_codes(message)
+        );
+        assert_eq!(
+            vec![(0x2043_afcc, 0x2411_ffff)],
+            extract_codes("\u{feff}2043afcc 2411ffff  \r\nthis")
+        );
+        assert!(extract_codes("no codes here").is_empty());
+    }
+
+    #[test]
+    fn test_reply() {
+        let message = "```\n2043AFCC 2411FFFF\n2A973DBD 00000000\n```";
+        assert_eq!(
+            "Converted 2 codes from RAW+V1 to RAW:\n\
+             ```\n\
+             2043AFCC 2411FFFF\n\
+             201F6024 00000000\n\
+             ```\n",
+            reply(message, Scheme::Raw)
+        );
+        assert_eq!(
+            "Converted 2 codes from RAW+V1 to V1:\n\
+             ```\n\
+             2AFF014C 2411FFFF\n\
+             2A973DBD 00000000\n\
+             ```\n",
+            reply(message, Scheme::V1)
+        );
+    }
+
+    #[test]
+    fn test_reply_v7() {
+        let reply = reply("2043AFCC 2411FFFF", Scheme::V7);
+        let codes = extract_codes(&reply);
+        assert_eq!(1, codes.len());
+
+        let mut cb = Codebreaker::new_v7();
+        assert_eq!((0x2043_afcc, 0x2411_ffff), cb.decrypt_code(codes[0].0, codes[0].1));
+    }
+
+    #[test]
+    fn test_reply_from_v7() {
+        let message = "B4336FA9 4DFEFB79\n973E0B2A A7D4AF10";
+        assert_eq!(
+            "Converted 2 codes from V1+V7 to RAW:\n\
+             ```\n\
+             2096F5B8 000000BE\n\
+             ```\n\
+             Warning: line 1: left out encryption key, which RAW does not use\n",
+            reply(message, Scheme::Raw)
+        );
+
+        let reply = reply(message, Scheme::V1);
+        assert!(reply.starts_with("Converted 2 codes from V1+V7 to V1:\n"));
+        assert!(reply.ends_with("Warning: line 1: left out encryption key, which V1 does not use\n"));
+        let codes = extract_codes(&reply);
+        assert_eq!(vec![crate::cb1::encrypt_code(0x2096_f5b8, 0x0000_00be)], codes);
+    }
+
+    #[test]
+    fn test_reply_warnings() {
+        assert_eq!("No codes found.\n", reply("hello", Scheme::Raw));
+
+        let reply = reply("```\n52C9647A 97439DDF\n```", Scheme::Raw);
+        assert!(reply.ends_with("```\nWarning: line 1: decrypted code is implausible\n"));
+    }
+}
@@ -0,0 +1,268 @@
+//! Authoring cheats in Rust.
+//!
+//! A [`Builder`] collects [operations](../op/enum.Op.html) and turns them
+//! into a [`Cheat`], which can then be encoded to raw or encrypted codes.
+//!
+//! # Example
+//! ```
+//! use codebreaker::cheat::Cheat;
+//! use codebreaker::Codebreaker;
+//!
+//! let cheat = Cheat::builder("Inf Health")
+//!     .write16(0x0043AFCC, 0xFFFF)
+//!     .if_eq16(0x004A0000, 1)
+//!     .then(|b| b.write32(0x0043AFD0, 0x2411FFFF))
+//!     .build()
+//!     .unwrap();
+//!
+//! assert_eq!(
+//!     vec![(0x1043AFCC, 0x0000FFFF), (0xD04A0000, 0x00000001), (0x2043AFD0, 0x2411FFFF)],
+//!     cheat.to_codes().unwrap()
+//! );
+//! assert_eq!(
+//!     vec![(0x1A05132F, 0x0000FFFF), (0xDA283E36, 0xB20F6279), (0x2AFB014C, 0x2411FFFF)],
+//!     cheat.encrypt(&mut Codebreaker::new()).unwrap()
+//! );
+//! ```
+
+use crate::op::{Addr, Cond, Op, Value};
+use crate::std_alloc::{String, Vec};
+use crate::{Codebreaker, Error};
+
+/// A named list of operations.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cheat {
+    /// The name of the cheat
+    pub name: String,
+    /// The operations of the cheat
+    pub ops: Vec<Op>,
+}
+
+impl Cheat {
+    /// Returns a builder for a cheat with the given name.
+    pub fn builder(name: &str) -> Builder {
+        Builder {
+            name: name.into(),
+            ops: Vec::new(),
+            error: None,
+        }
+    }
+
+    /// Returns the decrypted codes of the cheat.
+    pub fn to_codes(&self) -> Result<Vec<(u32, u32)>, Error> {
+        let mut codes = Vec::new();
+        for op in self.ops.iter() {
+            op.encode(&mut codes)?;
+        }
+        Ok(codes)
+    }
+
+    /// Returns the codes of the cheat encrypted with `cb`.
+    ///
+    /// `cb` is expected to carry the state from encrypting all previous
+    /// cheats of the list.
+    pub fn encrypt(&self, cb: &mut Codebreaker) -> Result<Vec<(u32, u32)>, Error> {
+        let mut codes = self.to_codes()?;
+        for code in codes.iter_mut() {
+            cb.encrypt_code_mut(&mut code.0, &mut code.1);
+        }
+        Ok(codes)
+    }
+}
+
+/// Builder returned by [`Cheat::builder`].
+///
+/// Invalid addresses and values are reported by [`build`](#method.build).
+#[derive(Debug, Clone)]
+pub struct Builder {
+    name: String,
+    ops: Vec<Op>,
+    error: Option<Error>,
+}
+
+impl Builder {
+    /// Writes an 8-bit value.
+    pub fn write8(self, addr: u32, val: u8) -> Self {
+        self.write(addr, Value::byte(val))
+    }
+
+    /// Writes a 16-bit value.
+    pub fn write16(self, addr: u32, val: u16) -> Self {
+        self.write(addr, Value::half(val))
+    }
+
+    /// Writes a 32-bit value.
+    pub fn write32(self, addr: u32, val: u32) -> Self {
+        self.write(addr, Value::word(val))
+    }
+
+    /// Adds an operation as is.
+    pub fn op(mut self, op: Op) -> Self {
+        self.ops.push(op);
+        self
+    }
+
+    /// Starts a conditional on an 8-bit value.
+    pub const fn if8(self, addr: u32, cond: Cond, val: u8) -> IfBuilder {
+        self.cond(addr, cond, Value::byte(val))
+    }
+
+    /// Starts a conditional on a 16-bit value.
+    pub const fn if16(self, addr: u32, cond: Cond, val: u16) -> IfBuilder {
+        self.cond(addr, cond, Value::half(val))
+    }
+
+    /// Starts a conditional checking that an 8-bit value is equal.
+    pub const fn if_eq8(self, addr: u32, val: u8) -> IfBuilder {
+        self.if8(addr, Cond::Eq, val)
+    }
+
+    /// Starts a conditional checking that a 16-bit value is equal.
+    pub const fn if_eq16(self, addr: u32, val: u16) -> IfBuilder {
+        self.if16(addr, Cond::Eq, val)
+    }
+
+    /// Starts a conditional checking that a 16-bit value is not equal.
+    pub const fn if_ne16(self, addr: u32, val: u16) -> IfBuilder {
+        self.if16(addr, Cond::Ne, val)
+    }
+
+    /// Returns the cheat, or the first error encountered while building it.
+    pub fn build(self) -> Result<Cheat, Error> {
+        if let Some(err) = self.error {
+            return Err(err);
+        }
+        Ok(Cheat {
+            name: self.name,
+            ops: self.ops,
+        })
+    }
+
+    fn write(mut self, addr: u32, val: Value) -> Self {
+        match Addr::new_aligned(addr, val.width()) {
+            Ok(addr) => self.ops.push(Op::Write { addr, val }),
+            Err(err) => self.fail(err),
+        }
+        self
+    }
+
+    const fn cond(self, addr: u32, cond: Cond, val: Value) -> IfBuilder {
+        IfBuilder {
+            parent: self,
+            addr: Addr::new_aligned(addr, val.width()),
+            val,
+            cond,
+        }
+    }
+
+    fn fail(&mut self, err: Error) {
+        self.error.get_or_insert(err);
+    }
+}
+
+/// Builder for the body of a conditional, returned by
+/// [`Builder::if16`](struct.Builder.html#method.if16) and friends.
+#[derive(Debug, Clone)]
+pub struct IfBuilder {
+    parent: Builder,
+    addr: Result<Addr, Error>,
+    val: Value,
+    cond: Cond,
+}
+
+impl IfBuilder {
+    /// Adds the operations built by `f` as the body of the conditional and
+    /// returns to the enclosing builder.
+    pub fn then<F>(self, f: F) -> Builder
+    where
+        F: FnOnce(Builder) -> Builder,
+    {
+        let mut parent = self.parent;
+        let body = f(Cheat::builder(""));
+        if let Some(err) = body.error {
+            parent.fail(err);
+        }
+        match self.addr {
+            Ok(addr) => parent.ops.push(Op::If {
+                addr,
+                val: self.val,
+                cond: self.cond,
+                then: body.ops,
+            }),
+            Err(err) => parent.fail(err),
+        }
+        parent
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::op::Width;
+
+    #[test]
+    fn test_builder() {
+        let cheat = Cheat::builder("Test")
+            .write8(0x0031_789a, 0x63)
+            .if_ne16(0x004a_0000, 0)
+            .then(|b| {
+                b.write16(0x0043_afcc, 0xffff)
+                    .if_eq8(0x004a_0003, 2)
+                    .then(|b| b.write32(0x0043_afd0, 1).write32(0x0043_afd4, 2))
+            })
+            .build()
+            .unwrap();
+
+        assert_eq!("Test", cheat.name);
+        assert_eq!(2, cheat.ops.len());
+        assert_eq!(
+            vec![
+                (0x0031_789a, 0x0000_0063),
+                (0xe004_0000, 0x104a_0000),
+                (0x1043_afcc, 0x0000_ffff),
+                (0xe102_0002, 0x004a_0003),
+                (0x2043_afd0, 0x0000_0001),
+                (0x2043_afd4, 0x0000_0002),
+            ],
+            cheat.to_codes().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_builder_errors() {
+        #[rustfmt::skip]
+        let tests = [
+            (Cheat::builder("").write32(0x0200_0000, 0), Error::AddressOutOfRange(0x0200_0000)),
+            (Cheat::builder("").write16(0x0010_0001, 0), Error::Misaligned { addr: 0x0010_0001, width: Width::Half }),
+            (
+                Cheat::builder("").if_eq16(0x004a_0000, 1).then(|b| b.write32(0x0010_0002, 0)),
+                Error::Misaligned { addr: 0x0010_0002, width: Width::Word },
+            ),
+            (
+                Cheat::builder("").if_eq16(0x004a_0001, 1).then(|b| b.write8(0x0010_0000, 0)),
+                Error::Misaligned { addr: 0x004a_0001, width: Width::Half },
+            ),
+            (
+                // the first error wins
+                Cheat::builder("").write32(0x0010_0002, 0).write32(0x0200_0000, 0),
+                Error::Misaligned { addr: 0x0010_0002, width: Width::Word },
+            ),
+        ];
+        for t in tests.iter() {
+            assert_eq!(Err(t.1), t.0.clone().build());
+        }
+    }
+
+    #[test]
+    fn test_encrypt() {
+        let cheat = Cheat::builder("")
+            .write32(0x0043_afcc, 0x2411_ffff)
+            .write32(0x001f_6024, 0)
+            .build()
+            .unwrap();
+        assert_eq!(
+            vec![(0x2aff_014c, 0x2411_ffff), (0x2a97_3dbd, 0x0000_0000)],
+            cheat.encrypt(&mut Codebreaker::new()).unwrap()
+        );
+    }
+}
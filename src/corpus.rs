@@ -0,0 +1,208 @@
+//! Regression tests against a corpus of golden files.
+//!
+//! Archive maintainers often keep code lists together with the output they
+//! expect from a conversion. [`run`] feeds the input of every [`Case`] to a
+//! pipeline and reports each case whose output differs from the expected
+//! one, with a line diff. With the `std` feature, [`load_dir`] reads the
+//! cases from a directory.
+//!
+//! # Example
+//! ```
+//! use codebreaker::corpus::{run, Case};
+//! use codebreaker::Codebreaker;
+//!
+//! let cases = [Case::new("inf-hp", "2A973DBD 00000000\n", "201F6024 00000000\n")];
+//! let mismatches = run(&cases, |input| {
+//!     let mut cb = Codebreaker::new();
+//!     let mut output = String::new();
+//!     for line in input.lines() {
+//!         let code = cb.auto_decrypt(line.parse().unwrap());
+//!         output += &format!("{}\n", code);
+//!     }
+//!     output
+//! });
+//! assert!(mismatches.is_empty());
+//! ```
+
+use crate::std_alloc::{String, Vec};
+
+use core::fmt::Write;
+
+/// An input and the output expected for it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Case {
+    /// The name of the case, e.g. the file name without extension
+    pub name: String,
+    /// The input passed to the pipeline
+    pub input: String,
+    /// The expected output of the pipeline
+    pub expected: String,
+}
+
+impl Case {
+    /// Returns a case.
+    pub fn new(name: &str, input: &str, expected: &str) -> Self {
+        Self {
+            name: name.into(),
+            input: input.into(),
+            expected: expected.into(),
+        }
+    }
+}
+
+/// A case whose output differs from the expected one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mismatch {
+    /// The name of the case
+    pub name: String,
+    /// The actual output of the pipeline
+    pub actual: String,
+    /// The differing lines, see [`diff`]
+    pub diff: String,
+}
+
+/// Runs all cases through a pipeline and returns the mismatches, in the order
+/// of the cases.
+pub fn run<F>(cases: &[Case], mut pipeline: F) -> Vec<Mismatch>
+where
+    F: FnMut(&str) -> String,
+{
+    let mut mismatches = Vec::new();
+    for case in cases.iter() {
+        let actual = pipeline(&case.input);
+        if actual != case.expected {
+            mismatches.push(Mismatch {
+                name: case.name.clone(),
+                diff: diff(&case.expected, &actual),
+                actual,
+            });
+        }
+    }
+    mismatches
+}
+
+/// Compares two texts line by line.
+///
+/// For every line that differs, returns its number, counted from 1, followed
+/// by the expected line prefixed with `-` and the actual line prefixed with
+/// `+`. Missing lines are left out.
+///
+/// # Example
+/// ```
+/// use codebreaker::corpus::diff;
+///
+/// assert_eq!(
+///     "line 2:\n-201F6024 00000000\n+2A973DBD 00000000\n",
+///     diff("2043AFCC 2411FFFF\n201F6024 00000000\n", "2043AFCC 2411FFFF\n2A973DBD 00000000\n")
+/// );
+/// ```
+pub fn diff(expected: &str, actual: &str) -> String {
+    let (mut expected, mut actual) = (expected.lines(), actual.lines());
+    let mut s = String::new();
+    let mut line = 0;
+    loop {
+        line += 1;
+        let (e, a) = (expected.next(), actual.next());
+        if e.is_none() && a.is_none() {
+            return s;
+        }
+        if e != a {
+            // Writing to a String cannot fail
+            let _ = writeln!(s, "line {}:", line);
+            if let Some(e) = e {
+                let _ = writeln!(s, "-{}", e);
+            }
+            if let Some(a) = a {
+                let _ = writeln!(s, "+{}", a);
+            }
+        }
+    }
+}
+
+/// Reads the cases of a directory.
+///
+/// Every file `<name>.in` makes a case together with the file
+/// `<name>.expected` next to it. Other files are ignored. The cases are
+/// sorted by name.
+///
+/// # Errors
+///
+/// Fails if a file cannot be read, if it is not UTF-8, or if the expected
+/// output of an input is missing.
+#[cfg(feature = "std")]
+pub fn load_dir<P: AsRef<std::path::Path>>(dir: P) -> std::io::Result<Vec<Case>> {
+    use std::{fs, io};
+
+    let mut cases = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().map_or(true, |ext| ext != "in") {
+            continue;
+        }
+        let name = match path.file_stem().and_then(|s| s.to_str()) {
+            Some(name) => name,
+            None => return Err(io::Error::new(io::ErrorKind::InvalidData, "file name is not UTF-8")),
+        };
+        cases.push(Case {
+            name: name.into(),
+            input: fs::read_to_string(&path)?,
+            expected: fs::read_to_string(path.with_extension("expected"))?,
+        });
+    }
+    cases.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(cases)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run() {
+        let cases = [
+            Case::new("same", "a\nb\n", "A\nB\n"),
+            Case::new("different", "a\nb\n", "A\nC\n"),
+            Case::new("longer", "a\n", "A\nB\n"),
+        ];
+        let mismatches = run(&cases, |input| input.to_uppercase());
+        assert_eq!(2, mismatches.len());
+        assert_eq!("different", mismatches[0].name);
+        assert_eq!("A\nB\n", mismatches[0].actual);
+        assert_eq!("line 2:\n-C\n+B\n", mismatches[0].diff);
+        assert_eq!("line 2:\n-B\n", mismatches[1].diff);
+    }
+
+    #[test]
+    fn test_diff() {
+        assert_eq!("", diff("a\nb", "a\nb\n"));
+        assert_eq!("line 1:\n+a\n", diff("", "a"));
+        assert_eq!("line 1:\n-a\n+b\nline 3:\n-c\n", diff("a\nx\nc", "b\nx"));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_load_dir() {
+        use std::fs;
+
+        let dir = std::env::temp_dir().join(format!("codebreaker-corpus-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("b.in"), "2A973DBD 00000000\n").unwrap();
+        fs::write(dir.join("b.expected"), "201F6024 00000000\n").unwrap();
+        fs::write(dir.join("a.in"), "").unwrap();
+        fs::write(dir.join("a.expected"), "").unwrap();
+        fs::write(dir.join("README"), "ignored").unwrap();
+
+        let cases = load_dir(&dir).unwrap();
+        assert_eq!(
+            vec![
+                Case::new("a", "", ""),
+                Case::new("b", "2A973DBD 00000000\n", "201F6024 00000000\n")
+            ],
+            cases
+        );
+
+        fs::remove_file(dir.join("a.expected")).unwrap();
+        assert!(load_dir(&dir).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,260 @@
+//! Human-readable explanations of decrypted codes.
+//!
+//! [`describe`] tells what each code of a list does, which helps to audit
+//! codes of unknown origin before running them. The explanations follow the
+//! code types of CB v7.
+//!
+//! # Example
+//! ```
+//! use codebreaker::describe::describe;
+//!
+//! let codes = [(0x2043AFCC, 0x2411FFFF), (0xD0456789, 0x0000FFFF), (0x1043AFD0, 0x00000001)];
+//! let descriptions = describe(&codes);
+//! assert_eq!("32-bit write of 0x2411FFFF to 0x0043AFCC", descriptions[0].text);
+//! assert_eq!("if 16-bit 0x00456789 == 0xFFFF, run the next line", descriptions[1].text);
+//! assert_eq!("16-bit write of 0x0001 to 0x0043AFD0", descriptions[2].text);
+//! ```
+
+use crate::cb7::is_beefcode;
+use crate::op::{Addr, Width};
+use crate::std_alloc::{String, Vec};
+
+use core::fmt;
+
+/// The explanation of a code.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Description {
+    /// Index of the first line of the code
+    pub line: usize,
+    /// The number of lines of the code
+    pub lines: usize,
+    /// What the code does
+    pub text: String,
+}
+
+impl fmt::Display for Description {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+/// Explains each code of a list of decrypted codes.
+///
+/// Codes of more than one line get a single description. A code whose
+/// second line is missing is described as truncated.
+pub fn describe(codes: &[(u32, u32)]) -> Vec<Description> {
+    let mut descriptions = Vec::new();
+    let mut line = 0;
+    while line < codes.len() {
+        let (addr, val) = codes[line];
+        let next = codes.get(line + 1).copied();
+        let (lines, text) = explain(addr, val, next);
+        descriptions.push(Description { line, lines, text });
+        line += lines;
+    }
+    descriptions
+}
+
+// Returns the number of lines of a code and what it does.
+fn explain(addr: u32, val: u32, next: Option<(u32, u32)>) -> (usize, String) {
+    let at = Addr::from_code(addr);
+    let two_lines = |f: &dyn Fn(u32, u32) -> String| match next {
+        Some((a, v)) => (2, f(a, v)),
+        None => (1, format!("truncated {} code", code_type(addr))),
+    };
+    match addr >> 28 {
+        0 => (1, format!("8-bit write of 0x{:02X} to 0x{}", val & 0xff, at)),
+        1 => (1, format!("16-bit write of 0x{:04X} to 0x{}", val & 0xffff, at)),
+        2 => (1, format!("32-bit write of 0x{:08X} to 0x{}", val, at)),
+        3 => {
+            let dest = Addr::from_code(val);
+            let op = |sub| if sub & 1 == 0 { "increment" } else { "decrement" };
+            match addr >> 20 & 0xf {
+                sub @ 0..=1 => (1, format!("8-bit {} of 0x{} by 0x{:02X}", op(sub), dest, addr & 0xff)),
+                sub @ 2..=3 => (
+                    1,
+                    format!("16-bit {} of 0x{} by 0x{:04X}", op(sub), dest, addr & 0xffff),
+                ),
+                sub @ 4..=5 => two_lines(&|amount, _| format!("32-bit {} of 0x{} by 0x{:08X}", op(sub), dest, amount)),
+                _ => (1, String::from("unknown increment/decrement")),
+            }
+        }
+        4 => two_lines(&|first, inc| {
+            format!(
+                "serial write of 0x{:08X} to 0x{}, {} times every {} bytes, adding 0x{:08X}",
+                first,
+                at,
+                val >> 16,
+                (val & 0xffff) * 4,
+                inc
+            )
+        }),
+        5 => two_lines(&|dest, _| format!("copy of {} bytes from 0x{} to 0x{}", val, at, Addr::from_code(dest))),
+        6 => two_lines(&|kind, off| {
+            let width = match kind >> 16 & 0xf {
+                0 => Width::Byte,
+                1 => Width::Half,
+                _ => Width::Word,
+            };
+            format!(
+                "{}-bit pointer write of 0x{:0digits$X} to [0x{}] + 0x{:X}",
+                width.bytes() * 8,
+                val & width.max_value(),
+                at,
+                off,
+                digits = width.bytes() as usize * 2
+            )
+        }),
+        7 => {
+            let v = val & 0xffff;
+            match val >> 20 & 0xf {
+                sub @ 0..=5 => {
+                    let op = ["OR", "AND", "XOR"][(sub / 2) as usize];
+                    if sub & 1 == 0 {
+                        (1, format!("8-bit {} of 0x{} with 0x{:02X}", op, at, v & 0xff))
+                    } else {
+                        (1, format!("16-bit {} of 0x{} with 0x{:04X}", op, at, v))
+                    }
+                }
+                _ => (1, String::from("unknown bitwise operation")),
+            }
+        }
+        0xb if is_beefcode(addr) && addr & 1 != 0 => match next {
+            Some((seed, _)) => (
+                2,
+                format!("encryption key 0x{:08X} with extra seed 0x{:08X}", val, seed),
+            ),
+            None => (1, String::from("truncated encryption key")),
+        },
+        0xb if is_beefcode(addr) => (1, format!("encryption key 0x{:08X}", val)),
+        0xc => (
+            1,
+            format!("if 32-bit 0x{} == 0x{:08X}, run all following codes", at, val),
+        ),
+        0xd => {
+            let (width, v) = conditional_value(val & 0x0100_0000 != 0, val);
+            (
+                1,
+                format!("if {} 0x{} {} {}, run the next line", width, at, cond(val >> 20), v),
+            )
+        }
+        0xe => {
+            let (width, v) = conditional_value(addr & 0x0100_0000 != 0, addr);
+            let n = addr >> 16 & 0xff;
+            let lines = if n == 1 {
+                String::from("the next line")
+            } else {
+                format!("the next {} lines", n)
+            };
+            (
+                1,
+                format!(
+                    "if {} 0x{} {} {}, run {}",
+                    width,
+                    Addr::from_code(val),
+                    cond(val >> 28),
+                    v,
+                    lines
+                ),
+            )
+        }
+        0x9 | 0xf => (1, format!("hook at 0x{:08X}", addr & 0x0fff_ffff)),
+        cmd => (1, format!("unknown code type {:X}", cmd)),
+    }
+}
+
+// Returns the name of a code type that takes two lines.
+const fn code_type(addr: u32) -> &'static str {
+    match addr >> 28 {
+        3 => "increment/decrement",
+        4 => "serial write",
+        5 => "copy",
+        _ => "pointer write",
+    }
+}
+
+// Returns the width and the compared value of a conditional.
+fn conditional_value(byte: bool, val: u32) -> (&'static str, String) {
+    if byte {
+        ("8-bit", format!("0x{:02X}", val & 0xff))
+    } else {
+        ("16-bit", format!("0x{:04X}", val & 0xffff))
+    }
+}
+
+const fn cond(bits: u32) -> &'static str {
+    match bits & 3 {
+        0 => "==",
+        1 => "!=",
+        2 => "<",
+        _ => ">",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(codes: &[(u32, u32)]) -> Vec<String> {
+        describe(codes).into_iter().map(|d| d.text).collect()
+    }
+
+    #[test]
+    fn test_describe() {
+        let codes = [
+            (0x0043_afcc, 0x0000_00ff),
+            (0x3020_0010, 0x0043_afcc),
+            (0x3040_0000, 0x0043_afcc),
+            (0x0000_0100, 0x0000_0000),
+            (0x4043_afcc, 0x0003_0002),
+            (0x2411_ffff, 0x0000_0001),
+            (0x7043_afcc, 0x0030_00f0),
+            (0xe102_0001, 0x1043_afcc),
+            (0xc043_afcc, 0x2411_ffff),
+            (0xbeef_c0de, 0x0000_0000),
+            (0x9029_beac, 0x0c0a_9225),
+            (0x8000_0000, 0x0000_0000),
+        ];
+        assert_eq!(
+            vec![
+                "8-bit write of 0xFF to 0x0043AFCC",
+                "16-bit increment of 0x0043AFCC by 0x0010",
+                "32-bit increment of 0x0043AFCC by 0x00000100",
+                "serial write of 0x2411FFFF to 0x0043AFCC, 3 times every 8 bytes, adding 0x00000001",
+                "16-bit AND of 0x0043AFCC with 0x00F0",
+                "if 8-bit 0x0043AFCC != 0x01, run the next 2 lines",
+                "if 32-bit 0x0043AFCC == 0x2411FFFF, run all following codes",
+                "encryption key 0x00000000",
+                "hook at 0x0029BEAC",
+                "unknown code type 8",
+            ],
+            texts(&codes)
+        );
+        let lines: Vec<_> = describe(&codes).iter().map(|d| (d.line, d.lines)).collect();
+        assert_eq!(
+            vec![
+                (0, 1),
+                (1, 1),
+                (2, 2),
+                (4, 2),
+                (6, 1),
+                (7, 1),
+                (8, 1),
+                (9, 1),
+                (10, 1),
+                (11, 1)
+            ],
+            lines
+        );
+    }
+
+    #[test]
+    fn test_describe_truncated() {
+        assert_eq!(
+            vec!["truncated serial write code"],
+            texts(&[(0x4043_afcc, 0x0003_0002)])
+        );
+        assert_eq!(vec!["truncated encryption key"], texts(&[(0xbeef_c0df, 0x0000_0000)]));
+        assert_eq!(Vec::<String>::new(), texts(&[]));
+    }
+}
@@ -0,0 +1,305 @@
+//! Side-by-side diffs of code lists.
+//!
+//! [`diff`] lines up two lists of codes, e.g. the input and output of a
+//! conversion or two versions of a cheat, and a [`Renderer`] shows the result
+//! in two columns like `diff -y`, optionally colored for terminals.
+//!
+//! # Example
+//! ```
+//! use codebreaker::diff::{diff, Renderer};
+//!
+//! let old = [(0x2043AFCC, 0x2411FFFF), (0x201F6024, 0x00000000)];
+//! let new = [(0x2043AFCC, 0x2411FFFF), (0x201F6024, 0x00000001), (0x2096F5B8, 0x000000BE)];
+//! assert_eq!(
+//!     "2043AFCC 2411FFFF   2043AFCC 2411FFFF\n\
+//!      201F6024 00000000 | 201F6024 00000001\n\
+//!      \x20                 > 2096F5B8 000000BE\n",
+//!     Renderer::new().render(&diff(&old, &new))
+//! );
+//! ```
+
+use crate::std_alloc::{String, Vec};
+use crate::Code;
+
+use core::fmt::Write;
+
+const RED: &str = "\x1b[31m";
+const GREEN: &str = "\x1b[32m";
+const YELLOW: &str = "\x1b[33m";
+const RESET: &str = "\x1b[0m";
+
+/// A line of a diff.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Change {
+    /// The code is in both lists
+    Same(Code),
+    /// The code of the old list was replaced by that of the new one
+    Changed(Code, Code),
+    /// The code is only in the old list
+    Removed(Code),
+    /// The code is only in the new list
+    Added(Code),
+}
+
+impl Change {
+    /// Returns true unless the code is in both lists.
+    pub const fn is_change(&self) -> bool {
+        !matches!(self, Self::Same(_))
+    }
+}
+
+/// Lines up two code lists.
+///
+/// Keeps the longest common sequence of codes. Runs of removed codes
+/// followed by added codes are paired up as changed codes.
+///
+/// The common sequence is found with Hirschberg's algorithm, which takes
+/// memory linear in the length of the lists, after leaving out the codes the
+/// lists start and end with alike.
+pub fn diff(old: &[(u32, u32)], new: &[(u32, u32)]) -> Vec<Change> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let (old_rest, new_rest) = (&old[prefix..], &new[prefix..]);
+    let suffix = old_rest
+        .iter()
+        .rev()
+        .zip(new_rest.iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let mut script = Script::default();
+    old[..prefix].iter().for_each(|&code| script.same(code));
+    align(
+        &old_rest[..old_rest.len() - suffix],
+        &new_rest[..new_rest.len() - suffix],
+        &mut script,
+    );
+    old_rest[old_rest.len() - suffix..]
+        .iter()
+        .for_each(|&code| script.same(code));
+    script.flush();
+    script.changes
+}
+
+// Appends the changes from `old` to `new` by splitting `old` in half and
+// `new` where the longest common sequences of both halves add up to the
+// longest one of the whole.
+fn align(old: &[(u32, u32)], new: &[(u32, u32)], script: &mut Script) {
+    match old {
+        [] => script.added.extend(new.iter().map(|&c| Code::from(c))),
+        _ if new.is_empty() => script.removed.extend(old.iter().map(|&c| Code::from(c))),
+        [code] => match new.iter().position(|c| c == code) {
+            Some(j) => {
+                script.added.extend(new[..j].iter().map(|&c| Code::from(c)));
+                script.same(*code);
+                script.added.extend(new[j + 1..].iter().map(|&c| Code::from(c)));
+            }
+            None => {
+                script.removed.push((*code).into());
+                script.added.extend(new.iter().map(|&c| Code::from(c)));
+            }
+        },
+        _ => {
+            let mid = old.len() / 2;
+            let head = lcs_lengths(old[..mid].iter(), new.iter());
+            let tail = lcs_lengths(old[mid..].iter().rev(), new.iter().rev());
+            let split = (0..=new.len())
+                .max_by_key(|&j| head[j] + tail[new.len() - j])
+                .unwrap_or(0);
+            align(&old[..mid], &new[..split], script);
+            align(&old[mid..], &new[split..], script);
+        }
+    }
+}
+
+// Returns the lengths of the longest common sequences of `old` and the first
+// j codes of `new`, for all j.
+fn lcs_lengths<'a>(
+    old: impl Iterator<Item = &'a (u32, u32)>,
+    new: impl Iterator<Item = &'a (u32, u32)> + Clone,
+) -> Vec<usize> {
+    let mut row = vec![0; new.clone().count() + 1];
+    for a in old {
+        // The length for the previous code of `old` and j codes of `new`
+        let mut diag = 0;
+        for (j, b) in new.clone().enumerate() {
+            let up = row[j + 1];
+            row[j + 1] = if a == b { diag + 1 } else { up.max(row[j]) };
+            diag = up;
+        }
+    }
+    row
+}
+
+// The changes so far, with the current run of removed and added codes kept
+// apart until it is paired up.
+#[derive(Default)]
+struct Script {
+    changes: Vec<Change>,
+    removed: Vec<Code>,
+    added: Vec<Code>,
+}
+
+impl Script {
+    fn same(&mut self, code: (u32, u32)) {
+        self.flush();
+        self.changes.push(Change::Same(code.into()));
+    }
+
+    // Appends the current run of removed and added codes, pairing them up in
+    // order.
+    fn flush(&mut self) {
+        let n = self.removed.len().max(self.added.len());
+        for k in 0..n {
+            self.changes.push(match (self.removed.get(k), self.added.get(k)) {
+                (Some(&old), Some(&new)) => Change::Changed(old, new),
+                (Some(&old), None) => Change::Removed(old),
+                (None, Some(&new)) => Change::Added(new),
+                (None, None) => unreachable!(),
+            });
+        }
+        self.removed.clear();
+        self.added.clear();
+    }
+}
+
+/// Renders diffs in two columns.
+///
+/// The old code is on the left and the new one on the right, separated by
+/// `|` for a changed code, `<` for a removed one, and `>` for an added one.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Renderer {
+    color: bool,
+}
+
+impl Renderer {
+    /// Returns a renderer for plain text.
+    pub const fn new() -> Self {
+        Self { color: false }
+    }
+
+    /// Colors removed codes red, added codes green, and changed codes yellow
+    /// with ANSI escape sequences.
+    pub const fn color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Returns the rendered diff, one line per change.
+    pub fn render(&self, changes: &[Change]) -> String {
+        let mut s = String::new();
+        for change in changes.iter() {
+            let (old, marker, new, color) = match *change {
+                Change::Same(code) => (Some(code), ' ', Some(code), None),
+                Change::Changed(old, new) => (Some(old), '|', Some(new), Some(YELLOW)),
+                Change::Removed(old) => (Some(old), '<', None, Some(RED)),
+                Change::Added(new) => (None, '>', Some(new), Some(GREEN)),
+            };
+            let mut line = String::new();
+            // Writing to a String cannot fail
+            let _ = match old {
+                Some(code) => write!(line, "{}", code),
+                None => write!(line, "{:17}", ""),
+            };
+            let _ = write!(line, " {}", marker);
+            if let Some(code) = new {
+                let _ = write!(line, " {}", code);
+            }
+            match color.filter(|_| self.color) {
+                Some(color) => {
+                    let _ = writeln!(s, "{}{}{}", color, line.trim_end(), RESET);
+                }
+                None => {
+                    let _ = writeln!(s, "{}", line.trim_end());
+                }
+            }
+        }
+        s
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const A: (u32, u32) = (0x2043_afcc, 0x2411_ffff);
+    const B: (u32, u32) = (0x201f_6024, 0x0000_0000);
+    const C: (u32, u32) = (0x2096_f5b8, 0x0000_00be);
+    const D: (u32, u32) = (0x1043_afd0, 0x0000_ffff);
+
+    fn code(c: (u32, u32)) -> Code {
+        c.into()
+    }
+
+    #[test]
+    fn test_diff() {
+        assert_eq!(Vec::<Change>::new(), diff(&[], &[]));
+        assert!(diff(&[A, B], &[A, B]).iter().all(|c| !c.is_change()));
+        assert_eq!(
+            vec![Change::Removed(code(A)), Change::Same(code(B))],
+            diff(&[A, B], &[B])
+        );
+        assert_eq!(
+            vec![
+                Change::Same(code(A)),
+                Change::Changed(code(B), code(D)),
+                Change::Added(code(C)),
+                Change::Same(code(A)),
+            ],
+            diff(&[A, B, A], &[A, D, C, A])
+        );
+    }
+
+    #[test]
+    fn test_diff_long() {
+        // Pseudo-random lists over few codes, so that they share a lot
+        let list = |seed: u32, len| -> Vec<(u32, u32)> {
+            (0..len)
+                .scan(seed, |x, _| {
+                    *x = x.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                    Some([A, B, C, D][(*x >> 16) as usize % 4])
+                })
+                .collect()
+        };
+        let (old, new) = (list(1, 300), list(2, 200));
+        let changes = diff(&old, &new);
+
+        let sides = |f: fn(&Change) -> Option<Code>| -> Vec<(u32, u32)> {
+            changes.iter().filter_map(f).map(Into::into).collect()
+        };
+        assert_eq!(
+            old,
+            sides(|c| match *c {
+                Change::Same(code) | Change::Changed(code, _) | Change::Removed(code) => Some(code),
+                Change::Added(_) => None,
+            })
+        );
+        assert_eq!(
+            new,
+            sides(|c| match *c {
+                Change::Same(code) | Change::Changed(_, code) | Change::Added(code) => Some(code),
+                Change::Removed(_) => None,
+            })
+        );
+        assert_eq!(
+            lcs_lengths(old.iter(), new.iter())[new.len()],
+            changes.iter().filter(|c| !c.is_change()).count()
+        );
+    }
+
+    #[test]
+    fn test_render() {
+        let changes = diff(&[A, B], &[C]);
+        assert_eq!(
+            "2043AFCC 2411FFFF | 2096F5B8 000000BE\n201F6024 00000000 <\n",
+            Renderer::new().render(&changes)
+        );
+        assert_eq!(
+            "\x1b[33m2043AFCC 2411FFFF | 2096F5B8 000000BE\x1b[0m\n\x1b[31m201F6024 00000000 <\x1b[0m\n",
+            Renderer::new().color(true).render(&changes)
+        );
+        assert_eq!(
+            "2043AFCC 2411FFFF   2043AFCC 2411FFFF\n",
+            Renderer::new().color(true).render(&diff(&[A], &[A]))
+        );
+    }
+}
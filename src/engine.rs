@@ -0,0 +1,535 @@
+//! Runs decrypted codes against emulated memory, as the device does.
+//!
+//! The CodeBreaker runtime hooks into the game and executes all codes from
+//! top to bottom once per frame. An [`Engine`] does the same on every call of
+//! [`tick`](struct.Engine.html#method.tick), so plain writes keep their
+//! values frozen. Cheats can be enabled and disabled between frames. A failed
+//! `D` or `E` conditional skips the given number of lines, and a failed `C`
+//! conditional skips all codes after it.
+//!
+//! Memory is accessed through a [`PatchTarget`], which is implemented for
+//! byte slices holding EE RAM.
+//!
+//! # Example
+//! ```
+//! use codebreaker::engine::Engine;
+//! use codebreaker::game::Cheat;
+//!
+//! let mut ram = vec![0; 0x0200_0000];
+//! let mut engine = Engine::new();
+//! let hp = engine.add(Cheat::new("Inf HP", vec![
+//!     (0xD04A0000, 0x00000001), // if the 16 bits at 4A0000 are 1,
+//!     (0x1043AFCC, 0x0000FFFF), // then set the 16 bits at 43AFCC to FFFF
+//! ]));
+//!
+//! engine.tick(&mut ram[..]);
+//! assert_eq!([0x00, 0x00], ram[0x43AFCC..0x43AFCE]);
+//!
+//! ram[0x4A0000] = 1;
+//! engine.tick(&mut ram[..]);
+//! assert_eq!([0xFF, 0xFF], ram[0x43AFCC..0x43AFCE]);
+//!
+//! engine.set_enabled(hp, false);
+//! ram[0x43AFCC] = 0;
+//! engine.tick(&mut ram[..]);
+//! assert_eq!([0x00, 0xFF], ram[0x43AFCC..0x43AFCE]);
+//! assert_eq!(3, engine.frames());
+//! ```
+
+use crate::firmware::Profile;
+use crate::game::Cheat;
+use crate::op::{Addr, Cond, Width};
+use crate::ram::{compare, Ram};
+use crate::std_alloc::Vec;
+
+/// Memory that codes are executed against.
+///
+/// Values are little-endian, as on the PS2.
+pub trait PatchTarget {
+    /// Reads a value of the given width, or returns `None` if the memory does
+    /// not exist.
+    fn read(&self, addr: Addr, width: Width) -> Option<u32>;
+
+    /// Writes a value of the given width. Writes to memory that does not
+    /// exist are ignored.
+    fn write(&mut self, addr: Addr, width: Width, val: u32);
+}
+
+/// EE RAM starting at address 0, which may be shorter than 32 MB.
+impl PatchTarget for [u8] {
+    fn read(&self, addr: Addr, width: Width) -> Option<u32> {
+        Ram::new(self).read(addr, width)
+    }
+
+    fn write(&mut self, addr: Addr, width: Width, val: u32) {
+        let start = addr.get() as usize;
+        let n = width.bytes() as usize;
+        if let Some(bytes) = self.get_mut(start..start + n) {
+            bytes.copy_from_slice(&val.to_le_bytes()[..n]);
+        }
+    }
+}
+
+/// The outcome of a frame.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Frame {
+    /// The number of lines executed
+    pub executed: usize,
+    /// The number of lines skipped by conditionals
+    pub skipped: usize,
+}
+
+/// Executes cheats once per frame.
+///
+/// Cheats run in the order they were added. The codes of all enabled cheats
+/// make up one list, as on the device, so a failed `C` conditional also
+/// skips the cheats after it.
+///
+/// Conditionals are evaluated anew each frame, so the cheats, their flags,
+/// and the frame count are all the state there is. With the `serde` feature,
+/// an engine can be saved with a savestate and resumed from it.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Engine {
+    cheats: Vec<Slot>,
+    frames: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct Slot {
+    cheat: Cheat,
+    enabled: bool,
+}
+
+impl Engine {
+    /// Returns an engine without cheats.
+    pub const fn new() -> Self {
+        Self {
+            cheats: Vec::new(),
+            frames: 0,
+        }
+    }
+
+    /// Adds an enabled cheat after the others and returns its index.
+    pub fn add(&mut self, cheat: Cheat) -> usize {
+        self.cheats.push(Slot { cheat, enabled: true });
+        self.cheats.len() - 1
+    }
+
+    /// Returns the cheat at `index`.
+    pub fn cheat(&self, index: usize) -> Option<&Cheat> {
+        self.cheats.get(index).map(|slot| &slot.cheat)
+    }
+
+    /// Returns the number of cheats.
+    pub fn len(&self) -> usize {
+        self.cheats.len()
+    }
+
+    /// Returns true if the engine has no cheats.
+    pub fn is_empty(&self) -> bool {
+        self.cheats.is_empty()
+    }
+
+    /// Returns true if the cheat at `index` is executed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is no cheat at `index`.
+    pub fn is_enabled(&self, index: usize) -> bool {
+        self.cheats[index].enabled
+    }
+
+    /// Enables or disables the cheat at `index`, starting with the next frame.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is no cheat at `index`.
+    pub fn set_enabled(&mut self, index: usize, enabled: bool) {
+        self.cheats[index].enabled = enabled;
+    }
+
+    /// Returns the number of frames run so far.
+    pub const fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    /// Executes the codes of all enabled cheats once, like the hook of the
+    /// device does each frame.
+    ///
+    /// Beefcodes, hooks, and code types the runtime does not execute are
+    /// passed over, taking as many lines as
+    /// [`Profile::V7`](../firmware/struct.Profile.html#associatedconstant.V7)
+    /// gives them. A multi-line code cut off at the end of the list is not
+    /// executed.
+    pub fn tick<T: PatchTarget + ?Sized>(&mut self, target: &mut T) -> Frame {
+        self.frames += 1;
+        let mut codes = self
+            .cheats
+            .iter()
+            .filter(|slot| slot.enabled)
+            .flat_map(|slot| slot.cheat.codes.iter().copied())
+            .peekable();
+
+        let mut frame = Frame::default();
+        while let Some((addr, val)) = codes.next() {
+            let next = codes.peek().copied();
+            let skip = execute(target, addr, val, next);
+            let lines = Profile::V7.lines(addr);
+            frame.executed += 1 + codes.by_ref().take(lines - 1).count();
+            frame.skipped += codes.by_ref().take(skip).count();
+        }
+        frame
+    }
+}
+
+// The number of lines to skip for a failed C conditional
+const ALL: usize = usize::MAX;
+
+// Executes a code and returns the number of lines to skip after it. `next`
+// is the line after the first line of the code.
+fn execute<T: PatchTarget + ?Sized>(target: &mut T, addr: u32, val: u32, next: Option<(u32, u32)>) -> usize {
+    let at = Addr::from_code(addr);
+    match addr >> 28 {
+        0 => target.write(at, Width::Byte, val & 0xff),
+        1 => target.write(at, Width::Half, val & 0xffff),
+        2 => target.write(at, Width::Word, val),
+        3 => {
+            let dest = Addr::from_code(val);
+            let amount = addr & 0xffff;
+            match addr >> 20 & 0xf {
+                0 => modify(target, dest, Width::Byte, |old| old.wrapping_add(amount & 0xff)),
+                1 => modify(target, dest, Width::Byte, |old| old.wrapping_sub(amount & 0xff)),
+                2 => modify(target, dest, Width::Half, |old| old.wrapping_add(amount)),
+                3 => modify(target, dest, Width::Half, |old| old.wrapping_sub(amount)),
+                4 | 5 => {
+                    if let Some((amount, _)) = next {
+                        if addr >> 20 & 1 == 0 {
+                            modify(target, dest, Width::Word, |old| old.wrapping_add(amount));
+                        } else {
+                            modify(target, dest, Width::Word, |old| old.wrapping_sub(amount));
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        4 => {
+            if let Some((first, inc)) = next {
+                let (count, step) = (val >> 16, (val & 0xffff) * 4);
+                let mut v = first;
+                for i in 0..count {
+                    if let Some(dest) = offset(at, i.wrapping_mul(step)) {
+                        target.write(dest, Width::Word, v);
+                    }
+                    v = v.wrapping_add(inc);
+                }
+            }
+        }
+        5 => {
+            if let Some((dest, _)) = next {
+                let dest = Addr::from_code(dest);
+                for i in 0..val {
+                    match (offset(at, i), offset(dest, i)) {
+                        (Some(from), Some(to)) => {
+                            if let Some(b) = target.read(from, Width::Byte) {
+                                target.write(to, Width::Byte, b);
+                            }
+                        }
+                        _ => break,
+                    }
+                }
+            }
+        }
+        6 => {
+            // Only one level of indirection, as the pointer write takes two
+            // lines
+            if let Some((kind, off)) = next {
+                let width = match kind >> 16 & 0xf {
+                    0 => Width::Byte,
+                    1 => Width::Half,
+                    _ => Width::Word,
+                };
+                if let Some(dest) = target
+                    .read(at.align_down(Width::Word), Width::Word)
+                    .and_then(|ptr| Addr::new(ptr.wrapping_add(off)).ok())
+                {
+                    target.write(dest, width, val & width.max_value());
+                }
+            }
+        }
+        7 => {
+            let v = val & 0xffff;
+            match val >> 20 & 0xf {
+                0 => modify(target, at, Width::Byte, |old| old | (v & 0xff)),
+                1 => modify(target, at, Width::Half, |old| old | v),
+                2 => modify(target, at, Width::Byte, |old| old & (v & 0xff)),
+                3 => modify(target, at, Width::Half, |old| old & v),
+                4 => modify(target, at, Width::Byte, |old| old ^ (v & 0xff)),
+                5 => modify(target, at, Width::Half, |old| old ^ v),
+                _ => {}
+            }
+        }
+        0xc if target.read(at, Width::Word) != Some(val) => return ALL,
+        0xd => {
+            let width = if val & 0x0100_0000 != 0 {
+                Width::Byte
+            } else {
+                Width::Half
+            };
+            if !met(target, at, width, cond(val >> 20), val & width.max_value()) {
+                return 1;
+            }
+        }
+        0xe => {
+            let width = if addr & 0x0100_0000 != 0 {
+                Width::Byte
+            } else {
+                Width::Half
+            };
+            let lines = (addr >> 16 & 0xff) as usize;
+            let at = Addr::from_code(val);
+            if !met(target, at, width, cond(val >> 28), addr & width.max_value()) {
+                return lines;
+            }
+        }
+        _ => {}
+    }
+    0
+}
+
+fn modify<T, F>(target: &mut T, addr: Addr, width: Width, f: F)
+where
+    T: PatchTarget + ?Sized,
+    F: FnOnce(u32) -> u32,
+{
+    if let Some(old) = target.read(addr, width) {
+        target.write(addr, width, f(old) & width.max_value());
+    }
+}
+
+fn offset(addr: Addr, bytes: u32) -> Option<Addr> {
+    addr.offset(bytes).ok()
+}
+
+fn met<T: PatchTarget + ?Sized>(target: &T, addr: Addr, width: Width, cond: Cond, val: u32) -> bool {
+    target
+        .read(addr, width)
+        .is_some_and(|actual| compare(cond, actual, val))
+}
+
+const fn cond(bits: u32) -> Cond {
+    match bits & 3 {
+        0 => Cond::Eq,
+        1 => Cond::Ne,
+        2 => Cond::Lt,
+        _ => Cond::Gt,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cheat;
+
+    fn run(codes: &[(u32, u32)], ram: &mut [u8]) -> Frame {
+        let mut engine = Engine::new();
+        engine.add(Cheat::new("", codes.to_vec()));
+        engine.tick(ram)
+    }
+
+    #[test]
+    fn test_writes() {
+        let mut ram = vec![0; 0x1000];
+        #[rustfmt::skip]
+        let codes = [
+            (0x0000_0100, 0x0000_0012),
+            (0x1000_0102, 0x0000_3456),
+            (0x2000_0104, 0x789a_bcde),
+            (0x3000_0001, 0x0000_0100), // +1 to the byte at 100
+            (0x3030_0001, 0x0000_0102), // -1 from the half at 102
+            (0x3040_0000, 0x0000_0104), // +2 to the word at 104
+            (0x0000_0002, 0x0000_0000),
+            (0x4000_0200, 0x0003_0002), // 3 words, 8 bytes apart
+            (0x0000_0001, 0x0000_0010),
+            (0x5000_0100, 0x0000_0004), // copy 4 bytes from 100 to 300
+            (0x0000_0300, 0x0000_0000),
+            (0x7000_0100, 0x0000_0100), // OR the byte at 100 with 0
+            (0x7000_0101, 0x0050_00ff), // XOR the half at 101 with FF
+        ];
+        let frame = run(&codes, &mut ram);
+        assert_eq!(
+            Frame {
+                executed: 13,
+                skipped: 0
+            },
+            frame
+        );
+
+        assert_eq!([0x13, 0xff, 0x55, 0x34], ram[0x100..0x104]);
+        assert_eq!([0xe0, 0xbc, 0x9a, 0x78], ram[0x104..0x108]);
+        assert_eq!(Some(1), ram.read(Addr::new(0x200).unwrap(), Width::Word));
+        assert_eq!(Some(0x11), ram.read(Addr::new(0x208).unwrap(), Width::Word));
+        assert_eq!(Some(0x21), ram.read(Addr::new(0x210).unwrap(), Width::Word));
+        assert_eq!([0x13, 0x00, 0x55, 0x34], ram[0x300..0x304]);
+    }
+
+    #[test]
+    fn test_lines() {
+        let mut ram = vec![0; 0x1000];
+        #[rustfmt::skip]
+        let codes = [
+            (0xbeef_c0df, 0x0000_0000),
+            (0x0000_0100, 0x0000_0012), // seed line
+            (0x3060_0000, 0x0000_0104), // unknown increment of two lines
+            (0x0000_0108, 0x0000_0034),
+        ];
+        let frame = run(&codes, &mut ram);
+        assert_eq!(
+            Frame {
+                executed: 4,
+                skipped: 0
+            },
+            frame
+        );
+        assert!(ram.iter().all(|&b| b == 0));
+    }
+
+    #[test]
+    fn test_pointer_write() {
+        let mut ram = vec![0; 0x1000];
+        ram.write(Addr::new(0x100).unwrap(), Width::Word, 0x800);
+        run(&[(0x6000_0100, 0x0000_abcd), (0x0001_0001, 0x0000_0010)], &mut ram);
+        assert_eq!(Some(0xabcd), ram.read(Addr::new(0x810).unwrap(), Width::Half));
+
+        // Cut off at the end
+        let frame = run(&[(0x6000_0104, 0x0000_0012)], &mut ram);
+        assert_eq!(1, frame.executed);
+    }
+
+    #[test]
+    fn test_conditionals() {
+        let mut ram = vec![0; 0x1000];
+        ram[0x100] = 5;
+
+        // Same cheats the builder encodes as D and E codes
+        let cheat = cheat::Cheat::builder("")
+            .if_eq8(0x100, 5)
+            .then(|b| b.write8(0x200, 1))
+            .if8(0x100, Cond::Ne, 5)
+            .then(|b| b.write8(0x201, 1).write8(0x202, 1))
+            .if8(0x100, Cond::Gt, 4)
+            .then(|b| b.write8(0x203, 1).write8(0x204, 1))
+            .build()
+            .unwrap();
+        let frame = run(&cheat.to_codes().unwrap(), &mut ram);
+        assert_eq!([1, 0, 0, 1, 1], ram[0x200..0x205]);
+        assert_eq!(
+            Frame {
+                executed: 6,
+                skipped: 2
+            },
+            frame
+        );
+
+        // A failed C code skips everything after it
+        #[rustfmt::skip]
+        let codes = [
+            (0x0000_0300, 0x0000_0001),
+            (0xc000_0100, 0x0000_0006),
+            (0x0000_0301, 0x0000_0001),
+            (0x0000_0302, 0x0000_0001),
+        ];
+        assert_eq!(
+            Frame {
+                executed: 2,
+                skipped: 2
+            },
+            run(&codes, &mut ram)
+        );
+        assert_eq!([1, 0, 0], ram[0x300..0x303]);
+    }
+
+    #[test]
+    fn test_freeze() {
+        let mut ram = vec![0; 0x1000];
+        let mut engine = Engine::new();
+        engine.add(Cheat::new("", vec![(0x1000_0100, 0x0000_03e7)]));
+        for frame in 1..=3 {
+            ram[0x100] = 0;
+            engine.tick(&mut ram[..]);
+            assert_eq!(Some(999), ram.read(Addr::new(0x100).unwrap(), Width::Half));
+            assert_eq!(frame, engine.frames());
+        }
+
+        // Codes cut off at the end and writes out of memory do no harm
+        let frame = run(&[(0x2000_0ffe, 1), (0x4000_0100, 0x0001_0001)], &mut ram);
+        assert_eq!(
+            Frame {
+                executed: 2,
+                skipped: 0
+            },
+            frame
+        );
+    }
+
+    #[test]
+    fn test_schedule() {
+        let mut ram = vec![0; 0x1000];
+        let mut engine = Engine::new();
+        assert!(engine.is_empty());
+        let a = engine.add(Cheat::new("A", vec![(0x0000_0100, 1)]));
+        let b = engine.add(Cheat::new("B", vec![(0x0000_0100, 2), (0x0000_0101, 2)]));
+        assert_eq!(2, engine.len());
+        assert_eq!("B", engine.cheat(b).unwrap().name);
+        assert_eq!(None, engine.cheat(2));
+
+        // Later cheats run last
+        engine.tick(&mut ram[..]);
+        assert_eq!([2, 2], ram[0x100..0x102]);
+
+        engine.set_enabled(b, false);
+        assert!(engine.is_enabled(a));
+        assert!(!engine.is_enabled(b));
+        ram[0x101] = 0;
+        assert_eq!(
+            Frame {
+                executed: 1,
+                skipped: 0
+            },
+            engine.tick(&mut ram[..])
+        );
+        assert_eq!([1, 0], ram[0x100..0x102]);
+
+        // A failed C code in one cheat skips the cheats after it
+        engine.set_enabled(b, true);
+        engine.add(Cheat::new("C", vec![(0xc000_0200, 1)]));
+        engine.add(Cheat::new("D", vec![(0x0000_0102, 3)]));
+        assert_eq!(
+            Frame {
+                executed: 4,
+                skipped: 1
+            },
+            engine.tick(&mut ram[..])
+        );
+        assert_eq!([2, 2, 0], ram[0x100..0x103]);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        let mut ram = vec![0; 0x1000];
+        let mut engine = Engine::new();
+        engine.add(Cheat::new("", vec![(0x3000_0001, 0x0000_0100)]));
+        engine.add(Cheat::new("", vec![(0x0000_0101, 0x0000_0001)]));
+        engine.set_enabled(1, false);
+        engine.tick(&mut ram[..]);
+
+        let json = serde_json::to_string(&engine).unwrap();
+        let mut restored: Engine = serde_json::from_str(&json).unwrap();
+        assert_eq!(engine, restored);
+        restored.tick(&mut ram[..]);
+        assert_eq!([2, 0], ram[0x100..0x102]);
+    }
+}
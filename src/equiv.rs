@@ -0,0 +1,268 @@
+//! Semantic comparison of cheats.
+//!
+//! Two cheats are equivalent if they leave memory in the same state under
+//! the same conditions, no matter how their writes are ordered or split into
+//! 8, 16, and 32-bit chunks. This catches duplicates that differ only
+//! cosmetically, e.g. a 32-bit write versus two 16-bit writes.
+//!
+//! Where writes under different conditions overlap, the final state depends
+//! on their order, which the comparison does not keep. Such cheats are only
+//! equivalent if their operations are the same.
+//!
+//! # Example
+//! ```
+//! use codebreaker::cheat::Cheat;
+//! use codebreaker::equiv::equivalent;
+//!
+//! let a = Cheat::builder("A").write32(0x00410000, 0x12345678).build().unwrap();
+//! let b = Cheat::builder("B")
+//!     .write16(0x00410002, 0x1234)
+//!     .write16(0x00410000, 0x5678)
+//!     .build()
+//!     .unwrap();
+//! assert!(equivalent(&a, &b));
+//! ```
+
+use crate::cheat::Cheat;
+use crate::op::{Addr, Cond, Op, Value};
+use crate::std_alloc::{BTreeMap, Vec};
+
+/// A conditional guarding writes: the address, the value compared against,
+/// and the comparison.
+pub type Guard = (Addr, Value, Cond);
+
+/// The effect of a cheat: the bytes it writes, grouped by the conditionals
+/// guarding them.
+///
+/// Nested conditionals are treated as a set, so their order does not matter.
+/// Within a group, later writes take precedence over earlier ones. Writes of
+/// different groups are kept apart, even if they overlap, in which case the
+/// effect is [order-dependent](#method.is_order_dependent).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Effect {
+    writes: BTreeMap<Vec<Guard>, BTreeMap<u32, u8>>,
+    order_dependent: bool,
+}
+
+impl Effect {
+    /// Returns the effect of a list of operations.
+    ///
+    /// Serial writes beyond EE RAM are ignored, as they cannot be encoded.
+    pub fn of(ops: &[Op]) -> Self {
+        let mut effect = Self::default();
+        effect.collect(ops, &mut Vec::new());
+
+        let mut addrs: Vec<u32> = effect.writes.values().flat_map(|w| w.keys().copied()).collect();
+        let n = addrs.len();
+        addrs.sort_unstable();
+        addrs.dedup();
+        effect.order_dependent = addrs.len() != n;
+        effect
+    }
+
+    /// Returns the bytes written under a set of conditionals, or `None` if
+    /// there are none.
+    pub fn writes(&self, guards: &[Guard]) -> Option<&BTreeMap<u32, u8>> {
+        let mut guards = guards.to_vec();
+        normalize(&mut guards);
+        self.writes.get(&guards)
+    }
+
+    /// Returns true if nothing is written.
+    pub fn is_empty(&self) -> bool {
+        self.writes.is_empty()
+    }
+
+    /// Returns true if bytes are written under more than one set of
+    /// conditionals, so that the final state depends on the order of the
+    /// writes.
+    pub const fn is_order_dependent(&self) -> bool {
+        self.order_dependent
+    }
+
+    fn collect(&mut self, ops: &[Op], guards: &mut Vec<Guard>) {
+        for op in ops.iter() {
+            match *op {
+                Op::Write { addr, val } => self.write(guards, addr.get(), val.get(), val.width().bytes()),
+                Op::SerialWrite {
+                    addr,
+                    val,
+                    count,
+                    step,
+                    inc,
+                } => {
+                    for i in 0..count {
+                        if let Some(Ok(addr)) = i.checked_mul(step).map(|offset| addr.offset(offset)) {
+                            self.write(guards, addr.get(), val.wrapping_add(i.wrapping_mul(inc)), 4);
+                        }
+                    }
+                }
+                Op::If {
+                    addr,
+                    val,
+                    cond,
+                    ref then,
+                } => {
+                    guards.push((addr, val, cond));
+                    self.collect(then, guards);
+                    guards.pop();
+                }
+            }
+        }
+    }
+
+    fn write(&mut self, guards: &[Guard], addr: u32, val: u32, bytes: u32) {
+        let mut key = guards.to_vec();
+        normalize(&mut key);
+        let memory = self.writes.entry(key).or_default();
+        for (i, b) in val.to_le_bytes()[..bytes as usize].iter().enumerate() {
+            memory.insert(addr + i as u32, *b);
+        }
+    }
+}
+
+fn normalize(guards: &mut Vec<Guard>) {
+    guards.sort_unstable();
+    guards.dedup();
+}
+
+/// Returns true if two cheats have the same effect. Their names are ignored.
+///
+/// Cheats with an [order-dependent](struct.Effect.html#method.is_order_dependent)
+/// effect are only equivalent if their operations are the same.
+pub fn equivalent(a: &Cheat, b: &Cheat) -> bool {
+    same(&a.ops, &Effect::of(&a.ops), &b.ops, &Effect::of(&b.ops))
+}
+
+/// Removes cheats that are equivalent to an earlier one.
+pub fn dedup(cheats: &mut Vec<Cheat>) {
+    let mut seen: Vec<(Vec<Op>, Effect)> = Vec::with_capacity(cheats.len());
+    cheats.retain(|cheat| {
+        let effect = Effect::of(&cheat.ops);
+        if seen.iter().any(|(ops, e)| same(ops, e, &cheat.ops, &effect)) {
+            false
+        } else {
+            seen.push((cheat.ops.clone(), effect));
+            true
+        }
+    });
+}
+
+fn same(a: &[Op], a_effect: &Effect, b: &[Op], b_effect: &Effect) -> bool {
+    if a_effect.is_order_dependent() || b_effect.is_order_dependent() {
+        a == b
+    } else {
+        a_effect == b_effect
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_equivalent() {
+        let word = Cheat::builder("").write32(0x0010_0000, 0x1234_5678).build().unwrap();
+        let serial = Cheat {
+            name: "".into(),
+            ops: vec![Op::SerialWrite {
+                addr: Addr::new(0x0010_0000).unwrap(),
+                val: 0x1234_5678,
+                count: 1,
+                step: 4,
+                inc: 0,
+            }],
+        };
+        let bytes = Cheat::builder("")
+            .write8(0x0010_0003, 0x12)
+            .write8(0x0010_0002, 0x34)
+            .write16(0x0010_0000, 0x5678)
+            .build()
+            .unwrap();
+        let overwritten = Cheat::builder("")
+            .write32(0x0010_0000, 0)
+            .write32(0x0010_0000, 0x1234_5678)
+            .build()
+            .unwrap();
+        let different = Cheat::builder("").write32(0x0010_0000, 0x1234_5679).build().unwrap();
+        let conditional = Cheat::builder("")
+            .if_eq16(0x0020_0000, 1)
+            .then(|b| b.write32(0x0010_0000, 0x1234_5678))
+            .build()
+            .unwrap();
+
+        assert!(equivalent(&word, &serial));
+        assert!(equivalent(&word, &bytes));
+        assert!(equivalent(&word, &overwritten));
+        assert!(!equivalent(&word, &different));
+        assert!(!equivalent(&word, &conditional));
+    }
+
+    #[test]
+    fn test_equivalent_conditionals() {
+        let a = Cheat::builder("")
+            .if_eq16(0x0020_0000, 1)
+            .then(|b| b.if_ne16(0x0020_0002, 0).then(|b| b.write8(0x0010_0000, 1)))
+            .write8(0x0010_0001, 2)
+            .build()
+            .unwrap();
+        let b = Cheat::builder("")
+            .write8(0x0010_0001, 2)
+            .if_ne16(0x0020_0002, 0)
+            .then(|b| b.if_eq16(0x0020_0000, 1).then(|b| b.write8(0x0010_0000, 1)))
+            .if_eq8(0x0020_0004, 0)
+            .then(|b| b)
+            .build()
+            .unwrap();
+        assert!(equivalent(&a, &b));
+
+        let effect = Effect::of(&a.ops);
+        let guards = [
+            (Addr::new(0x0020_0002).unwrap(), Value::half(0), Cond::Ne),
+            (Addr::new(0x0020_0000).unwrap(), Value::half(1), Cond::Eq),
+        ];
+        assert_eq!(Some(&0x01), effect.writes(&guards).and_then(|w| w.get(&0x0010_0000)));
+        assert_eq!(Some(&0x02), effect.writes(&[]).and_then(|w| w.get(&0x0010_0001)));
+        assert!(Effect::of(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_dedup() {
+        let mut cheats = vec![
+            Cheat::builder("A").write16(0x0010_0000, 0xffff).build().unwrap(),
+            Cheat::builder("B").write32(0x0010_0004, 1).build().unwrap(),
+            Cheat::builder("C")
+                .write8(0x0010_0001, 0xff)
+                .write8(0x0010_0000, 0xff)
+                .build()
+                .unwrap(),
+        ];
+        dedup(&mut cheats);
+        let names: Vec<_> = cheats.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(vec!["A", "B"], names);
+    }
+
+    #[test]
+    fn test_order_dependent() {
+        let a = Cheat::builder("A")
+            .write16(0x0010_0000, 1)
+            .if_eq16(0x0020_0000, 1)
+            .then(|b| b.write16(0x0010_0000, 2))
+            .build()
+            .unwrap();
+        let b = Cheat::builder("B")
+            .if_eq16(0x0020_0000, 1)
+            .then(|b| b.write16(0x0010_0000, 2))
+            .write16(0x0010_0000, 1)
+            .build()
+            .unwrap();
+        assert!(Effect::of(&a.ops).is_order_dependent());
+        assert!(!equivalent(&a, &b));
+        assert!(equivalent(&a, &a));
+
+        let mut cheats = vec![a.clone(), b, a];
+        dedup(&mut cheats);
+        let names: Vec<_> = cheats.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(vec!["A", "B"], names);
+    }
+}
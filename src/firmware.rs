@@ -0,0 +1,384 @@
+//! Code type tables of CodeBreaker firmware.
+//!
+//! Firmware revisions differ in the code types they know and in how many
+//! lines some of them take. A [`Profile`] describes one interpretation as a
+//! table indexed by the command nibble of a code, i.e. the highest 4 bits of
+//! its address. [`Profile::V7`] is the interpretation of CB v7 that the
+//! processor uses by default; custom profiles can be derived from it and
+//! kept in a [`Registry`].
+//!
+//! [`Registry::compatibility`] tells which of the profiles support all code
+//! types of a list, so lists can be labeled with the firmware they need.
+//! [`Profile::V7`] is the only built-in profile; profiles of other firmware
+//! revisions have to be registered by the caller.
+//!
+//! # Example
+//! ```
+//! use codebreaker::firmware::{CodeType, Lines, Profile, Registry};
+//!
+//! assert_eq!(2, Profile::V7.lines(0x40410000));
+//!
+//! let custom = Profile::V7
+//!     .named("custom")
+//!     .with_type(0x6, Some(CodeType::new("pointer write", Lines::Fixed(3))));
+//! assert_eq!(3, custom.lines(0x60410000));
+//!
+//! let mut registry = Registry::new();
+//! registry.register(custom);
+//! assert!(registry.get("custom").is_some());
+//! ```
+
+use crate::std_alloc::Vec;
+
+use core::fmt;
+
+/// The number of lines a code type takes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Lines {
+    /// Always the same number of lines
+    Fixed(u8),
+    /// One number of lines if any of the address bits in `mask` are set, and
+    /// another one otherwise
+    Flag {
+        /// The address bits to test
+        mask: u32,
+        /// The number of lines if a bit is set
+        set: u8,
+        /// The number of lines if no bit is set
+        unset: u8,
+    },
+}
+
+/// A code type known to a firmware.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CodeType {
+    /// A short description, e.g. "serial write"
+    pub name: &'static str,
+    /// The number of lines of a code
+    pub lines: Lines,
+}
+
+impl CodeType {
+    /// Returns a code type.
+    pub const fn new(name: &'static str, lines: Lines) -> Self {
+        Self { name, lines }
+    }
+}
+
+const fn one(name: &'static str) -> Option<CodeType> {
+    Some(CodeType::new(name, Lines::Fixed(1)))
+}
+
+const fn two(name: &'static str) -> Option<CodeType> {
+    Some(CodeType::new(name, Lines::Fixed(2)))
+}
+
+/// The code types of a firmware, indexed by command nibble.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Profile {
+    /// The name of the profile
+    pub name: &'static str,
+    /// The code types, `None` for commands the firmware does not know
+    pub types: [Option<CodeType>; 16],
+}
+
+impl Profile {
+    /// The code types of CB v7.
+    ///
+    /// Only code types documented for v7 are listed. Codes of other types are
+    /// treated as single-line codes.
+    pub const V7: Self = Self {
+        name: "v7",
+        types: [
+            one("8-bit write"),
+            one("16-bit write"),
+            one("32-bit write"),
+            Some(CodeType::new(
+                "increment/decrement",
+                Lines::Flag {
+                    mask: 0x0040_0000,
+                    set: 2,
+                    unset: 1,
+                },
+            )),
+            two("serial write"),
+            two("copy bytes"),
+            two("pointer write"),
+            one("bitwise operation"),
+            None,
+            one("hook"),
+            None,
+            // BEEFC0DF is followed by an extra seed value
+            Some(CodeType::new(
+                "encryption key",
+                Lines::Flag {
+                    mask: 1,
+                    set: 2,
+                    unset: 1,
+                },
+            )),
+            one("all-codes conditional"),
+            one("one-line conditional"),
+            one("multi-line conditional"),
+            one("hook"),
+        ],
+    };
+
+    /// Returns the profile under another name.
+    pub const fn named(mut self, name: &'static str) -> Self {
+        self.name = name;
+        self
+    }
+
+    /// Returns the profile with the code type of a command replaced, or
+    /// removed if `ty` is `None`. Only the low 4 bits of `cmd` are used.
+    pub const fn with_type(mut self, cmd: u8, ty: Option<CodeType>) -> Self {
+        self.types[(cmd & 0xf) as usize] = ty;
+        self
+    }
+
+    /// Returns the code type of a code given its address.
+    pub const fn code_type(&self, addr: u32) -> Option<&CodeType> {
+        self.types[(addr >> 28) as usize].as_ref()
+    }
+
+    /// Returns the first line of each code type in a list of decrypted codes
+    /// that the firmware does not know, in order of appearance.
+    pub fn unsupported(&self, codes: &[(u32, u32)]) -> Vec<Unsupported> {
+        let mut unsupported: Vec<Unsupported> = Vec::new();
+        let mut continuation = 0;
+        for (line, &(addr, _)) in codes.iter().enumerate() {
+            if continuation > 0 {
+                continuation -= 1;
+                continue;
+            }
+            continuation = self.lines(addr) - 1;
+            let cmd = (addr >> 28) as u8;
+            if self.code_type(addr).is_none() && !unsupported.iter().any(|u| u.cmd == cmd) {
+                unsupported.push(Unsupported { line, cmd });
+            }
+        }
+        unsupported
+    }
+
+    /// Returns the number of lines of a code given its address.
+    pub const fn lines(&self, addr: u32) -> usize {
+        let lines = match self.code_type(addr) {
+            Some(CodeType {
+                lines: Lines::Fixed(n), ..
+            }) => *n,
+            Some(CodeType {
+                lines: Lines::Flag { mask, set, unset },
+                ..
+            }) => {
+                if addr & *mask != 0 {
+                    *set
+                } else {
+                    *unset
+                }
+            }
+            None => 1,
+        };
+        // Every code takes at least its own line
+        if lines == 0 {
+            1
+        } else {
+            lines as usize
+        }
+    }
+}
+
+/// Does the same as [`Profile::V7`].
+impl Default for Profile {
+    fn default() -> Self {
+        Self::V7
+    }
+}
+
+/// A set of profiles selectable by name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Registry {
+    profiles: Vec<Profile>,
+}
+
+/// Does the same as [`new`](#method.new).
+impl Default for Registry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Registry {
+    /// Returns a registry holding the only built-in profile,
+    /// [`Profile::V7`](struct.Profile.html#associatedconstant.V7).
+    pub fn new() -> Self {
+        Self {
+            profiles: vec![Profile::V7],
+        }
+    }
+
+    /// Adds a profile, replacing any profile of the same name.
+    pub fn register(&mut self, profile: Profile) {
+        match self.profiles.iter_mut().find(|p| p.name == profile.name) {
+            Some(p) => *p = profile,
+            None => self.profiles.push(profile),
+        }
+    }
+
+    /// Returns the profile of the given name.
+    pub fn get(&self, name: &str) -> Option<&Profile> {
+        self.profiles.iter().find(|p| p.name == name)
+    }
+
+    /// Returns all profiles in the order they were registered.
+    pub fn profiles(&self) -> &[Profile] {
+        &self.profiles
+    }
+
+    /// Checks a list of decrypted codes against all profiles, in the order
+    /// they were registered.
+    ///
+    /// # Example
+    /// ```
+    /// use codebreaker::firmware::Registry;
+    ///
+    /// let codes = [(0x2043AFCC, 0x2411FFFF), (0x8044A000, 0x00000001)];
+    /// let registry = Registry::new();
+    /// let report = registry.compatibility(&codes);
+    /// assert!(!report[0].is_compatible());
+    /// assert_eq!("v7: unsupported code type 8 (line 2)", format!("{}", report[0]));
+    /// ```
+    pub fn compatibility(&self, codes: &[(u32, u32)]) -> Vec<Compatibility<'_>> {
+        self.profiles
+            .iter()
+            .map(|profile| Compatibility {
+                profile,
+                unsupported: profile.unsupported(codes),
+            })
+            .collect()
+    }
+}
+
+/// A code type that a firmware does not know.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Unsupported {
+    /// The index of the first code of the type
+    pub line: usize,
+    /// The command nibble of the type
+    pub cmd: u8,
+}
+
+/// Whether a firmware supports all codes of a list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Compatibility<'a> {
+    /// The profile of the firmware
+    pub profile: &'a Profile,
+    /// The code types the firmware does not know
+    pub unsupported: Vec<Unsupported>,
+}
+
+impl Compatibility<'_> {
+    /// Returns true if the firmware knows all code types of the list.
+    pub fn is_compatible(&self) -> bool {
+        self.unsupported.is_empty()
+    }
+}
+
+/// Formats the result as a single line, e.g. `v7: compatible`. Lines are
+/// counted from 1.
+impl fmt::Display for Compatibility<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: ", self.profile.name)?;
+        if self.is_compatible() {
+            return f.write_str("compatible");
+        }
+        let plural = if self.unsupported.len() > 1 { "s" } else { "" };
+        write!(f, "unsupported code type{}", plural)?;
+        for (i, u) in self.unsupported.iter().enumerate() {
+            let sep = if i == 0 { " " } else { ", " };
+            write!(f, "{}{:X} (line {})", sep, u.cmd, u.line + 1)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_v7_lines() {
+        #[rustfmt::skip]
+        let tests = [
+            (0x0000_0000, 1), (0x1000_0000, 1), (0x2000_0000, 1),
+            (0x3000_0000, 1), (0x3040_0000, 2), (0x3050_0000, 2),
+            (0x4000_0000, 2), (0x5000_0000, 2), (0x6000_0000, 2),
+            (0x7000_0000, 1), (0x8000_0000, 1), (0x9000_0000, 1),
+            (0xa000_0000, 1), (0xb000_0000, 1), (0xc000_0000, 1),
+            (0xd000_0000, 1), (0xe000_0000, 1), (0xf000_0000, 1),
+            (0xbeef_c0de, 1), (0xbeef_c0df, 2),
+        ];
+        for t in tests.iter() {
+            assert_eq!(t.1, Profile::V7.lines(t.0), "{:08X}", t.0);
+        }
+        assert_eq!(None, Profile::V7.code_type(0x8000_0000));
+        assert_eq!("serial write", Profile::V7.code_type(0x4000_0000).unwrap().name);
+    }
+
+    #[test]
+    fn test_custom_profile() {
+        let profile = Profile::V7
+            .named("custom")
+            .with_type(0x18, Some(CodeType::new("fill", Lines::Fixed(0))))
+            .with_type(0x4, None);
+        assert_eq!("custom", profile.name);
+        assert_eq!("fill", profile.code_type(0x8000_0000).unwrap().name);
+        assert_eq!(1, profile.lines(0x8000_0000));
+        assert_eq!(1, profile.lines(0x4000_0000));
+    }
+
+    #[test]
+    fn test_registry() {
+        let mut registry = Registry::new();
+        assert_eq!(Some(&Profile::V7), registry.get("v7"));
+        assert_eq!(None, registry.get("custom"));
+
+        let custom = Profile::V7.named("custom");
+        registry.register(custom);
+        registry.register(custom.with_type(0x5, None));
+        assert_eq!(2, registry.profiles().len());
+        assert_eq!(None, registry.get("custom").unwrap().code_type(0x5000_0000));
+    }
+
+    #[test]
+    fn test_compatibility() {
+        #[rustfmt::skip]
+        let codes = [
+            (0xbeef_c0df, 0x0000_0000), (0x8000_0000, 0x0000_0000), // seed line
+            (0x4010_0000, 0x0002_0001), (0x8000_0000, 0x0000_0000), // serial write
+            (0x8010_0000, 0x0000_0000),
+            (0xa010_0000, 0x0000_0000),
+            (0x8010_0000, 0x0000_0000),
+        ];
+        let mut registry = Registry::new();
+        registry.register(
+            Profile::V7
+                .named("all")
+                .with_type(0x8, Profile::V7.types[0])
+                .with_type(0xa, Profile::V7.types[0]),
+        );
+
+        let report = registry.compatibility(&codes);
+        assert_eq!(2, report.len());
+        assert_eq!(
+            vec![Unsupported { line: 4, cmd: 0x8 }, Unsupported { line: 5, cmd: 0xa }],
+            report[0].unsupported
+        );
+        assert_eq!(
+            "v7: unsupported code types 8 (line 5), A (line 6)",
+            format!("{}", report[0])
+        );
+        assert!(report[1].is_compatible());
+        assert_eq!("all: compatible", format!("{}", report[1]));
+    }
+}
@@ -0,0 +1,102 @@
+//! The formats of code lists the crate reads and writes.
+//!
+//! Tools built on the crate can list [`formats`] to offer what the build at
+//! hand supports, e.g. in a dropdown. Formats of optional features are only
+//! listed if the feature is enabled.
+//!
+//! # Example
+//! ```
+//! use codebreaker::formats;
+//! use codebreaker::Scheme;
+//!
+//! let cb7 = formats::find("cb7").unwrap();
+//! assert_eq!(Some(Scheme::V7), cb7.scheme);
+//! assert!(cb7.read && cb7.write);
+//! assert!(formats::formats().iter().any(|format| format.name == "ps2rd"));
+//! ```
+
+use crate::std_alloc::Vec;
+use crate::Scheme;
+
+/// A format of code lists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Format {
+    /// A short name, e.g. `cb7`
+    pub name: &'static str,
+    /// What the format holds
+    pub description: &'static str,
+    /// The device or program that uses the format
+    pub device: &'static str,
+    /// The encryption of the codes, for plain lists of codes like those of
+    /// [`text`](../text/index.html), or `None` for formats with a syntax of
+    /// their own
+    pub scheme: Option<Scheme>,
+    /// True if lists in this format can be read
+    pub read: bool,
+    /// True if lists in this format can be written
+    pub write: bool,
+}
+
+const fn code_list(name: &'static str, description: &'static str, device: &'static str, scheme: Scheme) -> Format {
+    Format {
+        name,
+        description,
+        device,
+        scheme: Some(scheme),
+        read: true,
+        write: true,
+    }
+}
+
+/// Returns the supported formats, plain lists of codes first.
+pub fn formats() -> Vec<Format> {
+    let mut formats = vec![
+        code_list("raw", "decrypted codes", "CodeBreaker", Scheme::Raw),
+        code_list(
+            "cb1",
+            "codes encrypted for CB v1 - v6",
+            "CodeBreaker v1 - v6",
+            Scheme::V1,
+        ),
+        code_list("cb7", "codes encrypted for CB v7+", "CodeBreaker v7+", Scheme::V7),
+        Format {
+            name: "ps2rd",
+            description: "cheat file of games and their cheats",
+            device: "ps2rd",
+            scheme: None,
+            read: true,
+            write: true,
+        },
+    ];
+    if cfg!(feature = "pcsx2") {
+        formats.push(Format {
+            name: "pnach",
+            description: "patch file of a game",
+            device: "PCSX2",
+            scheme: None,
+            read: false,
+            write: true,
+        });
+    }
+    formats
+}
+
+/// Returns the supported format with the given name.
+pub fn find(name: &str) -> Option<Format> {
+    formats().into_iter().find(|format| format.name == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find() {
+        assert_eq!(Some(Scheme::Raw), find("raw").unwrap().scheme);
+        assert_eq!(Some(Scheme::V1), find("cb1").unwrap().scheme);
+        assert_eq!(None, find("ps2rd").unwrap().scheme);
+        assert_eq!(cfg!(feature = "pcsx2"), find("pnach").is_some());
+        assert_eq!(None, find("ar2"));
+    }
+}
@@ -0,0 +1,156 @@
+//! Cheats grouped by game, as found in real code lists.
+//!
+//! A [`Game`] holds the named [`Cheat`]s of one title. All codes of a game
+//! are processed by the same processor in order, so a beefcode in the first
+//! cheat, usually the "(M)" enable code, applies to the cheats after it, as
+//! on the device.
+//!
+//! These cheats are plain code lists. To author cheats from operations, see
+//! [`cheat`](../cheat/index.html).
+//!
+//! # Example
+//! ```
+//! use codebreaker::game::{Cheat, Game};
+//! use codebreaker::Codebreaker;
+//!
+//! let mut game = Game::new("Kingdom Hearts");
+//! game.cheats.push(Cheat::new("(M)", vec![(0xB4336FA9, 0x4DFEFB79)]));
+//! game.cheats.push(Cheat::new("Inf HP", vec![(0x973E0B2A, 0xA7D4AF10)]));
+//!
+//! game.auto_decrypt(&mut Codebreaker::new());
+//! assert_eq!(vec![(0xBEEFC0DE, 0x00000000)], game.cheats[0].codes);
+//! assert_eq!(vec![(0x2096F5B8, 0x000000BE)], game.cheats[1].codes);
+//! ```
+
+use crate::std_alloc::{String, Vec};
+use crate::Codebreaker;
+
+/// A named list of codes.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Cheat {
+    /// The name of the cheat
+    pub name: String,
+    /// The codes of the cheat
+    pub codes: Vec<(u32, u32)>,
+}
+
+impl Cheat {
+    /// Returns a cheat with the given name and codes.
+    pub fn new(name: &str, codes: Vec<(u32, u32)>) -> Self {
+        Self {
+            name: name.into(),
+            codes,
+        }
+    }
+
+    /// Encrypts the codes in place, like
+    /// [`encrypt_code_list`](../struct.Codebreaker.html#method.encrypt_code_list).
+    pub fn encrypt(&mut self, cb: &mut Codebreaker) {
+        cb.encrypt_code_list(&mut self.codes);
+    }
+
+    /// Decrypts the codes in place, like
+    /// [`decrypt_code_list`](../struct.Codebreaker.html#method.decrypt_code_list).
+    pub fn decrypt(&mut self, cb: &mut Codebreaker) {
+        cb.decrypt_code_list(&mut self.codes);
+    }
+
+    /// Decrypts the codes in place, like
+    /// [`auto_decrypt_code_mut`](../struct.Codebreaker.html#method.auto_decrypt_code_mut).
+    pub fn auto_decrypt(&mut self, cb: &mut Codebreaker) {
+        for code in self.codes.iter_mut() {
+            cb.auto_decrypt_code_mut(&mut code.0, &mut code.1);
+        }
+    }
+}
+
+/// The cheats of a game.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Game {
+    /// The title of the game
+    pub title: String,
+    /// The cheats in order of appearance
+    pub cheats: Vec<Cheat>,
+}
+
+impl Game {
+    /// Returns a game without cheats.
+    pub fn new(title: &str) -> Self {
+        Self {
+            title: title.into(),
+            cheats: Vec::new(),
+        }
+    }
+
+    /// Encrypts the codes of all cheats in place, in order.
+    pub fn encrypt(&mut self, cb: &mut Codebreaker) {
+        self.cheats.iter_mut().for_each(|cheat| cheat.encrypt(cb));
+    }
+
+    /// Decrypts the codes of all cheats in place, in order.
+    pub fn decrypt(&mut self, cb: &mut Codebreaker) {
+        self.cheats.iter_mut().for_each(|cheat| cheat.decrypt(cb));
+    }
+
+    /// Decrypts the codes of all cheats in place, in order, detecting how
+    /// they are encrypted.
+    pub fn auto_decrypt(&mut self, cb: &mut Codebreaker) {
+        self.cheats.iter_mut().for_each(|cheat| cheat.auto_decrypt(cb));
+    }
+
+    /// Returns the total number of code lines of all cheats.
+    pub fn num_codes(&self) -> usize {
+        self.cheats.iter().map(|cheat| cheat.codes.len()).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn game() -> Game {
+        let mut game = Game::new("Test");
+        game.cheats
+            .push(Cheat::new("(M)", vec![(0x2043_afcc, 0x2411_ffff), (0xbeef_c0de, 0)]));
+        game.cheats.push(Cheat::new("Empty", vec![]));
+        game.cheats.push(Cheat::new(
+            "Inf HP",
+            vec![(0x2096_f5b8, 0x0000_00be), (0x1043_afd0, 0xffff)],
+        ));
+        game
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let mut game = game();
+        game.encrypt(&mut Codebreaker::new());
+        assert_ne!(self::game(), game);
+        assert_eq!(4, game.num_codes());
+
+        // The beefcode of the first cheat applies to the last one
+        let mut codes: Vec<_> = self::game().cheats.into_iter().flat_map(|c| c.codes).collect();
+        Codebreaker::new().encrypt_code_list(&mut codes);
+        assert_eq!(codes[2..], game.cheats[2].codes[..]);
+
+        let mut decrypted = game.clone();
+        decrypted.decrypt(&mut Codebreaker::new());
+        assert_eq!(self::game(), decrypted);
+
+        game.auto_decrypt(&mut Codebreaker::new());
+        assert_eq!(self::game(), game);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        let game = self::game();
+        let json = serde_json::to_string(&game).unwrap();
+        assert_eq!(game, serde_json::from_str(&json).unwrap());
+
+        let cheat = &game.cheats[0];
+        let json = serde_json::to_string(cheat).unwrap();
+        assert_eq!(*cheat, serde_json::from_str(&json).unwrap());
+    }
+}
@@ -0,0 +1,275 @@
+//! Decoder for raw DEFLATE streams (RFC 1951), as found in zip archives.
+//!
+//! Favors simplicity over speed; it decodes one bit at a time, which is fast
+//! enough for the occasional savestate.
+
+use crate::std_alloc::Vec;
+
+const MAX_BITS: usize = 15;
+
+const LEN_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258,
+];
+const LEN_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DIST_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097, 6145,
+    8193, 12289, 16385, 24577,
+];
+const DIST_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13,
+];
+const CODE_LENGTH_ORDER: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+
+/// Decompresses a raw DEFLATE stream, returning `None` if it is invalid,
+/// truncated, or would decompress to more than `limit` bytes.
+pub fn inflate(data: &[u8], limit: usize) -> Option<Vec<u8>> {
+    let mut bits = Bits::new(data);
+    let mut out = Vec::with_capacity(limit);
+
+    loop {
+        let last = bits.read(1)? == 1;
+        match bits.read(2)? {
+            0 => stored(&mut bits, &mut out, limit)?,
+            1 => {
+                let (lit, dist) = fixed_tables()?;
+                codes(&mut bits, &mut out, limit, &lit, &dist)?;
+            }
+            2 => {
+                let (lit, dist) = dynamic_tables(&mut bits)?;
+                codes(&mut bits, &mut out, limit, &lit, &dist)?;
+            }
+            _ => return None,
+        }
+        if last {
+            return Some(out);
+        }
+    }
+}
+
+struct Bits<'a> {
+    data: &'a [u8],
+    pos: usize,
+    buf: u32,
+    count: u32,
+}
+
+impl<'a> Bits<'a> {
+    const fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            pos: 0,
+            buf: 0,
+            count: 0,
+        }
+    }
+
+    // Reads up to 16 bits, least significant bit first.
+    fn read(&mut self, n: u32) -> Option<u32> {
+        while self.count < n {
+            let b = *self.data.get(self.pos)?;
+            self.pos += 1;
+            self.buf |= u32::from(b) << self.count;
+            self.count += 8;
+        }
+        let val = self.buf & ((1 << n) - 1);
+        self.buf >>= n;
+        self.count -= n;
+        Some(val)
+    }
+
+    // Skips to the next byte boundary. Bytes are only loaded on demand, so
+    // the remaining bits all belong to the current byte.
+    fn align(&mut self) {
+        self.buf = 0;
+        self.count = 0;
+    }
+
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        let bytes = self.data.get(self.pos..self.pos + len)?;
+        self.pos += len;
+        Some(bytes)
+    }
+}
+
+// A canonical Huffman code, decoded by walking the code lengths.
+struct Huffman {
+    counts: [u16; MAX_BITS + 1],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Option<Self> {
+        let mut counts = [0; MAX_BITS + 1];
+        for &len in lengths.iter() {
+            counts[len as usize] += 1;
+        }
+        counts[0] = 0;
+
+        // Reject over-subscribed codes; incomplete ones are allowed
+        let mut left = 1i32;
+        for &count in counts[1..].iter() {
+            left = (left << 1) - i32::from(count);
+            if left < 0 {
+                return None;
+            }
+        }
+
+        let mut offsets = [0; MAX_BITS + 1];
+        for len in 1..MAX_BITS {
+            offsets[len + 1] = offsets[len] + counts[len] as usize;
+        }
+        let mut symbols = vec![0; lengths.len()];
+        for (sym, &len) in lengths.iter().enumerate() {
+            if len != 0 {
+                symbols[offsets[len as usize]] = sym as u16;
+                offsets[len as usize] += 1;
+            }
+        }
+        Some(Self { counts, symbols })
+    }
+
+    fn decode(&self, bits: &mut Bits<'_>) -> Option<u16> {
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+        for &count in self.counts[1..].iter() {
+            code |= bits.read(1)? as i32;
+            let count = i32::from(count);
+            if code - first < count {
+                return self.symbols.get((index + code - first) as usize).copied();
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        None
+    }
+}
+
+fn stored(bits: &mut Bits<'_>, out: &mut Vec<u8>, limit: usize) -> Option<()> {
+    bits.align();
+    let len = bits.read(16)?;
+    if bits.read(16)? != !len & 0xffff || out.len() + len as usize > limit {
+        return None;
+    }
+    out.extend_from_slice(bits.take(len as usize)?);
+    Some(())
+}
+
+fn fixed_tables() -> Option<(Huffman, Huffman)> {
+    let mut lengths = [0; 288];
+    lengths[..144].fill(8);
+    lengths[144..256].fill(9);
+    lengths[256..280].fill(7);
+    lengths[280..].fill(8);
+    Some((Huffman::new(&lengths)?, Huffman::new(&[5; 30])?))
+}
+
+fn dynamic_tables(bits: &mut Bits<'_>) -> Option<(Huffman, Huffman)> {
+    let nlen = bits.read(5)? as usize + 257;
+    let ndist = bits.read(5)? as usize + 1;
+    let ncode = bits.read(4)? as usize + 4;
+    if nlen > 286 || ndist > 30 {
+        return None;
+    }
+
+    let mut lengths = [0; 19];
+    for &i in CODE_LENGTH_ORDER[..ncode].iter() {
+        lengths[i] = bits.read(3)? as u8;
+    }
+    let code = Huffman::new(&lengths)?;
+
+    let mut lengths = Vec::with_capacity(nlen + ndist);
+    while lengths.len() < nlen + ndist {
+        let (len, repeat) = match code.decode(bits)? {
+            sym @ 0..=15 => (sym as u8, 1),
+            16 => (*lengths.last()?, 3 + bits.read(2)?),
+            17 => (0, 3 + bits.read(3)?),
+            _ => (0, 11 + bits.read(7)?),
+        };
+        for _ in 0..repeat {
+            lengths.push(len);
+        }
+    }
+    if lengths.len() != nlen + ndist || lengths[256] == 0 {
+        return None;
+    }
+    Some((Huffman::new(&lengths[..nlen])?, Huffman::new(&lengths[nlen..])?))
+}
+
+fn codes(bits: &mut Bits<'_>, out: &mut Vec<u8>, limit: usize, lit: &Huffman, dist: &Huffman) -> Option<()> {
+    loop {
+        let sym = lit.decode(bits)? as usize;
+        match sym {
+            0..=255 if out.len() < limit => out.push(sym as u8),
+            0..=255 => return None,
+            256 => return Some(()),
+            _ => {
+                let i = sym - 257;
+                let len = *LEN_BASE.get(i)? as usize + bits.read(u32::from(LEN_EXTRA[i]))? as usize;
+                let i = dist.decode(bits)? as usize;
+                let back = *DIST_BASE.get(i)? as usize + bits.read(u32::from(DIST_EXTRA[i]))? as usize;
+                if back > out.len() || out.len() + len > limit {
+                    return None;
+                }
+                // Copies may overlap, e.g. to repeat a single byte
+                let start = out.len() - back;
+                for j in 0..len {
+                    out.push(out[start + j]);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stored() {
+        assert_eq!(
+            Some(b"hello".to_vec()),
+            inflate(&[0x01, 0x05, 0x00, 0xfa, 0xff, b'h', b'e', b'l', b'l', b'o'], 5)
+        );
+        assert_eq!(None, inflate(&[0x01, 0x05, 0x00, 0xfa, 0xff, b'h'], 5));
+        assert_eq!(None, inflate(&[0x01, 0x05, 0x00, 0x00, 0x00], 5));
+    }
+
+    #[test]
+    fn test_fixed() {
+        let data = [0xcb, 0x48, 0xcd, 0xc9, 0xc9, 0x57, 0xc8, 0x40, 0x90, 0x00];
+        assert_eq!(Some(b"hello hello hello".to_vec()), inflate(&data, 17));
+        assert_eq!(None, inflate(&data[..8], 17));
+    }
+
+    #[test]
+    fn test_dynamic() {
+        #[rustfmt::skip]
+        let data = [
+            0xed, 0xc5, 0xb1, 0x0d, 0x00, 0x00, 0x08, 0x03, 0xa0, 0x9a, 0xb8, 0xf4, 0xff, 0x87,
+            0xf5, 0x10, 0x58, 0xc8, 0x6c, 0xd3, 0x9d, 0xd8, 0xb6, 0x6d, 0xdb, 0xff, 0x01,
+        ];
+        let expected: Vec<u8> = (0..2592u32).map(|i| (i * i % 16) as u8).collect();
+        assert_eq!(Some(expected), inflate(&data, 2592));
+    }
+
+    #[test]
+    fn test_limit() {
+        assert_eq!(
+            None,
+            inflate(&[0x01, 0x05, 0x00, 0xfa, 0xff, b'h', b'e', b'l', b'l', b'o'], 4)
+        );
+        // the back-reference would exceed the limit
+        let data = [0xcb, 0x48, 0xcd, 0xc9, 0xc9, 0x57, 0xc8, 0x40, 0x90, 0x00];
+        assert_eq!(None, inflate(&data, 16));
+        assert_eq!(None, inflate(&data, 0));
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert_eq!(None, inflate(&[], 0));
+        // reserved block type
+        assert_eq!(None, inflate(&[0x07], 0));
+    }
+}
@@ -0,0 +1,363 @@
+//! Minimal ISO9660 reader for PS2 disc images.
+//!
+//! Reads just enough of a disc image to locate `SYSTEM.CNF` and the boot ELF
+//! it names, so that tools can derive the game serial and the PCSX2 CRC
+//! without extracting files by hand. PS2 DVDs are UDF/ISO9660 bridge discs,
+//! so the ISO9660 file system is always present.
+//!
+//! This module requires the "iso" feature.
+//!
+//! # Example
+//! ```no_run
+//! use codebreaker::iso::Image;
+//!
+//! let data = std::fs::read("game.iso").unwrap();
+//! let image = Image::new(&data).unwrap();
+//! let boot = image.boot_elf().unwrap();
+//! println!("{} {} bytes", boot.serial, boot.data.len());
+//! ```
+
+use crate::std_alloc::String;
+
+use core::fmt;
+use core::str;
+
+/// The size of a sector in bytes.
+pub const SECTOR_SIZE: usize = 2048;
+
+const PVD_SECTOR: usize = 16;
+const ROOT_RECORD: usize = 156;
+const FLAG_DIRECTORY: u8 = 0x02;
+
+/// The error type for reading disc images.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// The image has no primary volume descriptor.
+    NotIso9660,
+    /// A structure points outside of the image.
+    Truncated,
+    /// A file does not exist.
+    NotFound,
+    /// `SYSTEM.CNF` has no valid `BOOT2` line.
+    NoBootEntry,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotIso9660 => f.write_str("not an ISO9660 image"),
+            Self::Truncated => f.write_str("image is truncated"),
+            Self::NotFound => f.write_str("file not found"),
+            Self::NoBootEntry => f.write_str("SYSTEM.CNF has no BOOT2 entry"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+/// The boot ELF of a disc image.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BootElf<'a> {
+    /// File name of the ELF, e.g. `SLUS_209.46`
+    pub name: &'a str,
+    /// Game serial derived from the file name, e.g. `SLUS-20946`
+    pub serial: String,
+    /// Contents of the ELF
+    pub data: &'a [u8],
+}
+
+/// A disc image in memory.
+#[derive(Debug, Clone, Copy)]
+pub struct Image<'a> {
+    data: &'a [u8],
+    root: Extent,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Extent {
+    sector: usize,
+    len: usize,
+}
+
+impl<'a> Image<'a> {
+    /// Opens a disc image by reading its primary volume descriptor.
+    pub fn new(data: &'a [u8]) -> Result<Self, Error> {
+        let pvd = data
+            .get(PVD_SECTOR * SECTOR_SIZE..(PVD_SECTOR + 1) * SECTOR_SIZE)
+            .ok_or(Error::NotIso9660)?;
+        if pvd[0] != 1 || &pvd[1..6] != b"CD001" {
+            return Err(Error::NotIso9660);
+        }
+        let (root, _) = parse_record(&pvd[ROOT_RECORD..]).ok_or(Error::NotIso9660)?;
+        Ok(Self {
+            data,
+            root: root.extent,
+        })
+    }
+
+    /// Returns the contents of a file.
+    ///
+    /// Path components can be separated by `\` or `/` and are compared
+    /// without regard to case or version suffix, so `system.cnf` finds
+    /// `SYSTEM.CNF;1`.
+    pub fn file(&self, path: &str) -> Result<&'a [u8], Error> {
+        let mut extent = self.root;
+        let mut is_dir = true;
+
+        for name in path.split(['\\', '/']).filter(|s| !s.is_empty()) {
+            if !is_dir {
+                return Err(Error::NotFound);
+            }
+            let record = self.find(extent, name)?;
+            extent = record.extent;
+            is_dir = record.is_dir;
+        }
+        if is_dir {
+            return Err(Error::NotFound);
+        }
+        self.read(extent)
+    }
+
+    /// Returns the contents of `SYSTEM.CNF`.
+    pub fn system_cnf(&self) -> Result<&'a [u8], Error> {
+        self.file("SYSTEM.CNF")
+    }
+
+    /// Returns the boot ELF named in `SYSTEM.CNF`.
+    pub fn boot_elf(&self) -> Result<BootElf<'a>, Error> {
+        let path = boot_path(self.system_cnf()?).ok_or(Error::NoBootEntry)?;
+        let file = path.rsplit('\\').next().unwrap_or(path);
+        let name = file.split(';').next().unwrap_or(file);
+        Ok(BootElf {
+            name,
+            serial: serial(name),
+            data: self.file(path)?,
+        })
+    }
+
+    fn read(&self, extent: Extent) -> Result<&'a [u8], Error> {
+        // The extent comes from the image and may be out of range
+        extent
+            .sector
+            .checked_mul(SECTOR_SIZE)
+            .and_then(|start| self.data.get(start..start.checked_add(extent.len)?))
+            .ok_or(Error::Truncated)
+    }
+
+    fn find(&self, dir: Extent, name: &str) -> Result<Record<'a>, Error> {
+        let mut entries = self.read(dir)?;
+        while !entries.is_empty() {
+            match parse_record(entries) {
+                Some((record, len)) => {
+                    if record.name_eq(name) {
+                        return Ok(record);
+                    }
+                    entries = &entries[len..];
+                }
+                // Records do not cross sector boundaries; the rest of the
+                // sector is zero padding.
+                None => {
+                    let offset = SECTOR_SIZE - (dir.len - entries.len()) % SECTOR_SIZE;
+                    entries = entries.get(offset..).unwrap_or_default();
+                }
+            }
+        }
+        Err(Error::NotFound)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Record<'a> {
+    extent: Extent,
+    is_dir: bool,
+    name: &'a [u8],
+}
+
+impl Record<'_> {
+    fn name_eq(&self, name: &str) -> bool {
+        let own = self.name.split(|&c| c == b';').next().unwrap_or(self.name);
+        let name = name.split(';').next().unwrap_or(name);
+        own.eq_ignore_ascii_case(name.as_bytes())
+    }
+}
+
+// Parses a directory record, returning it together with its length.
+fn parse_record(data: &[u8]) -> Option<(Record<'_>, usize)> {
+    let len = usize::from(*data.first()?);
+    if len < 34 || len > data.len() {
+        return None;
+    }
+    let name_len = usize::from(data[32]);
+    let name = data.get(33..33 + name_len)?;
+    let record = Record {
+        extent: Extent {
+            sector: le32(&data[2..6]) as usize,
+            len: le32(&data[10..14]) as usize,
+        },
+        is_dir: data[25] & FLAG_DIRECTORY != 0,
+        name,
+    };
+    Some((record, len))
+}
+
+const fn le32(b: &[u8]) -> u32 {
+    u32::from_le_bytes([b[0], b[1], b[2], b[3]])
+}
+
+/// Returns the path of the boot ELF from the contents of `SYSTEM.CNF`.
+///
+/// # Example
+/// ```
+/// use codebreaker::iso::boot_path;
+///
+/// let cnf = b"BOOT2 = cdrom0:\\SLUS_209.46;1\r\nVER = 1.00\r\nVMODE = NTSC\r\n";
+/// assert_eq!(Some("\\SLUS_209.46;1"), boot_path(cnf));
+/// ```
+pub fn boot_path(system_cnf: &[u8]) -> Option<&str> {
+    let text = str::from_utf8(system_cnf).ok()?;
+    text.lines().find_map(|line| {
+        let (key, value) = line.split_once('=')?;
+        if key.trim() != "BOOT2" {
+            return None;
+        }
+        let path = value.trim().strip_prefix("cdrom0:")?;
+        Some(path)
+    })
+}
+
+/// Returns the game serial belonging to the file name of a boot ELF.
+///
+/// # Example
+/// ```
+/// use codebreaker::iso::serial;
+///
+/// assert_eq!("SLUS-20946", serial("SLUS_209.46;1"));
+/// ```
+pub fn serial(elf_name: &str) -> String {
+    let name = elf_name.split(';').next().unwrap_or(elf_name);
+    name.chars()
+        .filter(|&c| c != '.')
+        .map(|c| if c == '_' { '-' } else { c.to_ascii_uppercase() })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::std_alloc::Vec;
+
+    fn record(name: &[u8], sector: u32, len: u32, is_dir: bool) -> Vec<u8> {
+        let mut rec = vec![0; 33];
+        rec[2..6].copy_from_slice(&sector.to_le_bytes());
+        rec[10..14].copy_from_slice(&len.to_le_bytes());
+        rec[25] = if is_dir { FLAG_DIRECTORY } else { 0 };
+        rec[32] = name.len() as u8;
+        rec.extend_from_slice(name);
+        if rec.len() % 2 == 1 {
+            rec.push(0);
+        }
+        rec[0] = rec.len() as u8;
+        rec
+    }
+
+    fn put(image: &mut [u8], sector: usize, data: &[u8]) {
+        let start = sector * SECTOR_SIZE;
+        image[start..start + data.len()].copy_from_slice(data);
+    }
+
+    // Layout: 16 PVD, 18 root, 19 SYSTEM.CNF, 20 ELF, 21 DATA dir, 22 DATA/README
+    fn image() -> Vec<u8> {
+        let cnf = b"BOOT2 = cdrom0:\\SLUS_209.46;1\r\nVER = 1.00\r\n";
+        let elf = b"\x7fELF\x01\x01\x01\x00";
+        let readme = b"hello";
+
+        let mut image = vec![0; 23 * SECTOR_SIZE];
+
+        let mut pvd = vec![1];
+        pvd.extend_from_slice(b"CD001");
+        pvd.resize(ROOT_RECORD, 0);
+        pvd.extend(record(&[0], 18, SECTOR_SIZE as u32, true));
+        put(&mut image, PVD_SECTOR, &pvd);
+
+        let mut root = Vec::new();
+        root.extend(record(&[0], 18, SECTOR_SIZE as u32, true));
+        root.extend(record(&[1], 18, SECTOR_SIZE as u32, true));
+        root.extend(record(b"DATA", 21, SECTOR_SIZE as u32, true));
+        root.extend(record(b"SLUS_209.46;1", 20, elf.len() as u32, false));
+        root.extend(record(b"SYSTEM.CNF;1", 19, cnf.len() as u32, false));
+        put(&mut image, 18, &root);
+        put(&mut image, 19, cnf);
+        put(&mut image, 20, elf);
+
+        let mut data = Vec::new();
+        data.extend(record(&[0], 21, SECTOR_SIZE as u32, true));
+        data.extend(record(b"README.TXT;1", 22, readme.len() as u32, false));
+        put(&mut image, 21, &data);
+        put(&mut image, 22, readme);
+
+        image
+    }
+
+    #[test]
+    fn test_new() {
+        assert_eq!(Error::NotIso9660, Image::new(&[0; 1024]).unwrap_err());
+        assert_eq!(Error::NotIso9660, Image::new(&[0; 20 * SECTOR_SIZE]).unwrap_err());
+        assert!(Image::new(&image()).is_ok());
+    }
+
+    #[test]
+    fn test_file() {
+        let data = image();
+        let image = Image::new(&data).unwrap();
+        assert_eq!(b"hello", image.file("DATA\\README.TXT").unwrap());
+        assert_eq!(b"hello", image.file("/data/readme.txt;1").unwrap());
+        assert_eq!(Error::NotFound, image.file("README.TXT").unwrap_err());
+        assert_eq!(Error::NotFound, image.file("DATA").unwrap_err());
+        assert_eq!(Error::NotFound, image.file("SYSTEM.CNF\\FOO").unwrap_err());
+    }
+
+    #[test]
+    fn test_file_truncated() {
+        let data = image();
+        let image = Image::new(&data[..20 * SECTOR_SIZE]).unwrap();
+        assert_eq!(Error::Truncated, image.file("SLUS_209.46").unwrap_err());
+
+        // Extents near the end of the address space must not wrap
+        let mut data = self::image();
+        put(&mut data, 18, &record(b"FAR;1", u32::MAX, u32::MAX, false));
+        let image = Image::new(&data).unwrap();
+        assert_eq!(Error::Truncated, image.file("FAR").unwrap_err());
+    }
+
+    #[test]
+    fn test_boot_elf() {
+        let data = image();
+        let boot = Image::new(&data).unwrap().boot_elf().unwrap();
+        assert_eq!("SLUS_209.46", boot.name);
+        assert_eq!("SLUS-20946", boot.serial);
+        assert_eq!(b"\x7fELF\x01\x01\x01\x00", boot.data);
+    }
+
+    #[test]
+    fn test_boot_path() {
+        #[rustfmt::skip]
+        let tests: [(&[u8], Option<&str>); 5] = [
+            (b"BOOT2 = cdrom0:\\SLES_523.77;1\n", Some("\\SLES_523.77;1")),
+            (b"VER = 1.00\nBOOT2=cdrom0:\\SCUS_971.13;1\n", Some("\\SCUS_971.13;1")),
+            (b"BOOT = cdrom:\\SLPS_123.45;1\n", None),
+            (b"VER = 1.00\n", None),
+            (b"\xff\xfe", None),
+        ];
+        for t in tests.iter() {
+            assert_eq!(t.1, boot_path(t.0));
+        }
+    }
+
+    #[test]
+    fn test_serial() {
+        assert_eq!("SLUS-20946", serial("SLUS_209.46;1"));
+        assert_eq!("SLES-52377", serial("sles_523.77"));
+    }
+}
@@ -0,0 +1,144 @@
+//! Allocation-free processing of code lists.
+//!
+//! The functions of this module take codes from any iterator and return a
+//! lazy iterator of results, so codes can be streamed from and to fixed-size
+//! buffers on targets without a heap. Codes can be given as [`Code`]s or as
+//! `(addr, val)` tuples.
+//!
+//! # Example
+//! ```
+//! use codebreaker::{iter, Code, Codebreaker};
+//!
+//! let encrypted = [(0x2AFF014C, 0x2411FFFF), (0xB4336FA9, 0x4DFEFB79), (0x973E0B2A, 0xA7D4AF10)];
+//! let mut decrypted = [Code::default(); 3];
+//!
+//! let mut cb = Codebreaker::new();
+//! for (slot, code) in decrypted.iter_mut().zip(iter::auto_decrypt(&mut cb, encrypted.iter().copied())) {
+//!     *slot = code;
+//! }
+//! assert_eq!(Code::new(0x2096F5B8, 0x000000BE), decrypted[2]);
+//! ```
+
+use crate::cb7::is_beefcode;
+use crate::{Code, Codebreaker, Scheme};
+
+/// Encrypts codes like
+/// [`encrypt_code`](../struct.Codebreaker.html#method.encrypt_code).
+pub fn encrypt<'a, I>(cb: &'a mut Codebreaker, codes: I) -> impl Iterator<Item = Code> + 'a
+where
+    I: IntoIterator + 'a,
+    I::Item: Into<Code>,
+{
+    codes.into_iter().map(move |code| cb.encrypt(code.into()))
+}
+
+/// Decrypts codes like
+/// [`decrypt_code`](../struct.Codebreaker.html#method.decrypt_code).
+pub fn decrypt<'a, I>(cb: &'a mut Codebreaker, codes: I) -> impl Iterator<Item = Code> + 'a
+where
+    I: IntoIterator + 'a,
+    I::Item: Into<Code>,
+{
+    codes.into_iter().map(move |code| cb.decrypt(code.into()))
+}
+
+/// Decrypts codes like
+/// [`auto_decrypt_code`](../struct.Codebreaker.html#method.auto_decrypt_code).
+pub fn auto_decrypt<'a, I>(cb: &'a mut Codebreaker, codes: I) -> impl Iterator<Item = Code> + 'a
+where
+    I: IntoIterator + 'a,
+    I::Item: Into<Code>,
+{
+    codes.into_iter().map(move |code| cb.auto_decrypt(code.into()))
+}
+
+/// Encrypts raw codes for the target scheme like
+/// [`auto_encrypt_code_list`](../struct.Codebreaker.html#method.auto_encrypt_code_list),
+/// which may yield a beefcode before the first code.
+///
+/// The first code is taken from `codes` right away to tell if it is a
+/// beefcode.
+pub fn auto_encrypt<'a, I>(cb: &'a mut Codebreaker, codes: I, target: Scheme) -> impl Iterator<Item = Code> + 'a
+where
+    I: IntoIterator + 'a,
+    I::Item: Into<Code>,
+{
+    let mut codes = codes.into_iter().map(Into::into).peekable();
+    let starts_with_beefcode = codes.peek().is_some_and(|code: &Code| is_beefcode(code.addr));
+    let beefcode = if target == Scheme::V7 && cb.scheme != Scheme::V7 && !starts_with_beefcode && cb.code_lines == 0 {
+        Some(Code::new(0xbeef_c0de, 0))
+    } else {
+        None
+    };
+    beefcode.into_iter().chain(codes).map(move |code| match target {
+        Scheme::Raw => code,
+        _ => cb.auto_encrypt_code(code.addr, code.val).into(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::std_alloc::Vec;
+
+    #[rustfmt::skip]
+    const RAW: [(u32, u32); 3] = [
+        (0x2043_afcc, 0x2411_ffff),
+        (0xbeef_c0de, 0x0000_0000),
+        (0x2096_f5b8, 0x0000_00be),
+    ];
+
+    #[test]
+    fn test_encrypt_decrypt() {
+        let mut expected = RAW;
+        Codebreaker::new().encrypt_code_list(&mut expected);
+
+        let mut cb = Codebreaker::new();
+        let encrypted: Vec<_> = encrypt(&mut cb, RAW.iter().copied()).collect();
+        assert_eq!(expected.iter().map(|&c| Code::from(c)).collect::<Vec<_>>(), encrypted);
+
+        let mut cb = Codebreaker::new();
+        let decrypted: Vec<_> = decrypt(&mut cb, encrypted.iter().copied())
+            .map(<(u32, u32)>::from)
+            .collect();
+        assert_eq!(RAW.to_vec(), decrypted);
+
+        let mut cb = Codebreaker::new();
+        let decrypted: Vec<_> = auto_decrypt(&mut cb, encrypted.iter().copied())
+            .map(<(u32, u32)>::from)
+            .collect();
+        assert_eq!(RAW.to_vec(), decrypted);
+
+        // The slice variants agree
+        let mut codes = RAW.map(Code::from);
+        Codebreaker::new().encrypt_codes(&mut codes);
+        assert_eq!(encrypted[..], codes[..]);
+        let mut decrypted = codes;
+        Codebreaker::new().decrypt_codes(&mut decrypted);
+        assert_eq!(RAW.map(Code::from), decrypted);
+        Codebreaker::new().auto_decrypt_codes(&mut codes);
+        assert_eq!(RAW.map(Code::from), codes);
+    }
+
+    #[test]
+    fn test_auto_encrypt() {
+        let codes = [RAW[0], RAW[2]];
+        for &target in [Scheme::Raw, Scheme::V1, Scheme::V7].iter() {
+            for cb in [Codebreaker::new(), Codebreaker::new_v7()].iter() {
+                let expected = cb.clone().auto_encrypt_code_list(&codes, target);
+                let mut cb = *cb;
+                let encrypted: Vec<_> = auto_encrypt(&mut cb, codes.iter().copied(), target)
+                    .map(<(u32, u32)>::from)
+                    .collect();
+                assert_eq!(expected, encrypted, "{}", target);
+            }
+        }
+
+        // Like with the list API, even no codes get a beefcode
+        let mut cb = Codebreaker::new();
+        assert_eq!(
+            1,
+            auto_encrypt(&mut cb, core::iter::empty::<Code>(), Scheme::V7).count()
+        );
+    }
+}
@@ -38,8 +38,9 @@
 extern crate std;
 
 #[cfg(feature = "std")]
-#[cfg(test)]
+#[allow(unused_imports)]
 mod std_alloc {
+    pub use std::collections::BTreeMap;
     pub use std::string::String;
     pub use std::vec::Vec;
 }
@@ -50,31 +51,395 @@ mod std_alloc {
 extern crate alloc;
 
 #[cfg(not(feature = "std"))]
-#[cfg(test)]
+#[allow(unused_imports)]
 mod std_alloc {
+    pub use alloc::collections::BTreeMap;
     pub use alloc::string::String;
     pub use alloc::vec::Vec;
 }
 
+pub mod analysis;
+pub mod anonymize;
+pub mod audit;
+pub mod bookmarks;
 pub mod cb1;
 pub mod cb7;
+pub mod chat;
+pub mod cheat;
+pub mod corpus;
+pub mod describe;
+pub mod diff;
+#[cfg(feature = "unstable")]
+pub mod engine;
+pub mod equiv;
+pub mod firmware;
+pub mod formats;
+pub mod game;
+#[cfg(feature = "pcsx2")]
+mod inflate;
+#[cfg(feature = "iso")]
+pub mod iso;
+pub mod iter;
+pub mod lint;
+pub mod list;
+#[cfg(feature = "unstable")]
+pub mod machine;
+pub mod mastercode;
+pub mod op;
+pub mod optimize;
+#[cfg(feature = "pcsx2")]
+pub mod pcsx2;
+pub mod pretty;
+pub mod primitives;
+pub mod profile;
+pub mod ps2rd;
+pub mod quarantine;
+pub mod query;
+pub mod ram;
 mod rc4;
+pub mod reference;
+pub mod rename;
+pub mod repair;
+pub mod similar;
+pub mod stream;
+pub mod symbols;
+pub mod text;
+pub mod trainer;
+pub mod verify;
+pub mod workspace;
 
 use cb7::{is_beefcode, Cb7};
+use firmware::Profile;
+use op::Width;
+use std_alloc::{String, Vec};
 
-#[derive(Debug, Clone, Copy, PartialEq)]
-enum Scheme {
+use core::fmt;
+use core::str::FromStr;
+
+/// The encryption scheme of a code.
+///
+/// More schemes may be added in the future, so matches must have a wildcard
+/// arm.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum Scheme {
+    /// Unencrypted
     Raw,
+    /// CB v1 - v6 encryption
     V1,
+    /// CB v7+ encryption
     V7,
 }
 
+impl fmt::Display for Scheme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Raw => f.write_str("RAW"),
+            Self::V1 => f.write_str("V1"),
+            Self::V7 => f.write_str("V7"),
+        }
+    }
+}
+
+/// The direction of a call that processes a code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    /// From decrypted to encrypted
+    Encrypt,
+    /// From encrypted to decrypted
+    Decrypt,
+}
+
+/// The error type for operations on codes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// A code contradicts the scheme the processor was pinned to.
+    SchemeMismatch {
+        /// The scheme the processor was pinned to
+        pinned: Scheme,
+        /// The scheme the code appears to use
+        found: Scheme,
+    },
+    /// An address is outside of the 32 MB of EE RAM.
+    AddressOutOfRange(u32),
+    /// An address is not aligned to the width of the access.
+    Misaligned {
+        /// The address
+        addr: u32,
+        /// The width of the access
+        width: Width,
+    },
+    /// A value does not fit the width of the access.
+    ValueTooLarge {
+        /// The value
+        val: u32,
+        /// The width of the access
+        width: Width,
+    },
+    /// A code type does not support accesses of this width.
+    UnsupportedWidth(Width),
+    /// A conditional guards more lines than its code type can encode.
+    BlockTooLarge(usize),
+    /// A field of a code type cannot hold a value.
+    FieldOverflow {
+        /// The name of the field
+        field: &'static str,
+        /// The value
+        val: u32,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::SchemeMismatch { pinned, found } => {
+                write!(f, "code appears to be {} but processor is pinned to {}", found, pinned)
+            }
+            Self::AddressOutOfRange(addr) => write!(f, "address {:08X} is outside of EE RAM", addr),
+            Self::Misaligned { addr, width } => write!(f, "address {:08X} is not aligned for {} access", addr, width),
+            Self::ValueTooLarge { val, width } => write!(f, "value {:X} does not fit {}", val, width),
+            Self::UnsupportedWidth(width) => write!(f, "{} access is not supported by this code type", width),
+            Self::BlockTooLarge(lines) => write!(f, "conditional block of {} lines is too large", lines),
+            Self::FieldOverflow { field, val } => write!(f, "{} {:X} does not fit the code type", field, val),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+/// A code before and after decryption, as returned by
+/// [`auto_decrypt_code_detailed`](struct.Codebreaker.html#method.auto_decrypt_code_detailed).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Decrypted {
+    /// The code as passed in
+    pub original: (u32, u32),
+    /// The decrypted code
+    pub decrypted: (u32, u32),
+    /// The scheme used for decryption, RAW if the code was passed through
+    pub scheme: Scheme,
+}
+
+/// A code consisting of an address and a value.
+///
+/// Parses from and formats to the usual text form of two 8-digit hex numbers,
+/// and converts from and to the `(addr, val)` tuples used elsewhere.
+///
+/// # Example
+/// ```
+/// use codebreaker::Code;
+///
+/// let code: Code = "2043AFCC 2411FFFF".parse().unwrap();
+/// assert_eq!((0x2043AFCC, 0x2411FFFF), code.into());
+/// assert_eq!("2043AFCC 2411FFFF", format!("{}", code));
+/// ```
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Code {
+    /// The address, including the command in the highest 4 bits
+    pub addr: u32,
+    /// The value
+    pub val: u32,
+}
+
+impl Code {
+    /// Returns a code.
+    pub const fn new(addr: u32, val: u32) -> Self {
+        Self { addr, val }
+    }
+}
+
+impl From<(u32, u32)> for Code {
+    fn from((addr, val): (u32, u32)) -> Self {
+        Self::new(addr, val)
+    }
+}
+
+impl From<Code> for (u32, u32) {
+    fn from(code: Code) -> Self {
+        (code.addr, code.val)
+    }
+}
+
+/// Formats the code as `XXXXXXXX YYYYYYYY`.
+impl fmt::Display for Code {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:08X} {:08X}", self.addr, self.val)
+    }
+}
+
+/// Parses two 8-digit hex numbers, as written in the wild: separated by
+/// whitespace or a dash, or not at all, in any case, and with or without a
+/// `0x` prefix. Leading and trailing whitespace is ignored.
+///
+/// # Example
+/// ```
+/// use codebreaker::{Code, ParseCodeError};
+///
+/// let code = Code::new(0x2043AFCC, 0x2411FFFF);
+/// assert_eq!(Ok(code), "2043AFCC-2411FFFF".parse());
+/// assert_eq!(Ok(code), "2043afcc2411ffff".parse());
+/// assert_eq!(Ok(code), "0x2043AFCC \t 0x2411FFFF".parse());
+/// assert_eq!(
+///     Err(ParseCodeError::InvalidToken(String::from("2411FFF"))),
+///     "2043AFCC 2411FFF".parse::<Code>()
+/// );
+/// ```
+impl FromStr for Code {
+    type Err = ParseCodeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut tokens = s.split_whitespace();
+        let first = tokens.next().ok_or(ParseCodeError::Empty)?;
+        let (addr, val) = match tokens.next() {
+            Some(val) => (first, val),
+            None => split_code(first)?,
+        };
+        let code = Self::new(parse_hex(addr)?, parse_hex(val)?);
+        tokens
+            .next()
+            .map_or(Ok(code), |token| Err(ParseCodeError::TrailingToken(token.into())))
+    }
+}
+
+// Splits a code written as a single token.
+fn split_code(token: &str) -> Result<(&str, &str), ParseCodeError> {
+    if let Some((addr, val)) = token.split_once('-') {
+        if val.is_empty() {
+            return Err(ParseCodeError::MissingValue);
+        }
+        return Ok((addr, val));
+    }
+    let digits = strip_hex_prefix(token);
+    if digits.len() == 16 && digits.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Ok(digits.split_at(8));
+    }
+    parse_hex(token)?;
+    Err(ParseCodeError::MissingValue)
+}
+
+fn strip_hex_prefix(token: &str) -> &str {
+    token
+        .strip_prefix("0x")
+        .or_else(|| token.strip_prefix("0X"))
+        .unwrap_or(token)
+}
+
+fn parse_hex(token: &str) -> Result<u32, ParseCodeError> {
+    let digits = strip_hex_prefix(token);
+    if digits.len() == 8 && digits.bytes().all(|b| b.is_ascii_hexdigit()) {
+        u32::from_str_radix(digits, 16).map_err(|_| ParseCodeError::InvalidToken(token.into()))
+    } else {
+        Err(ParseCodeError::InvalidToken(token.into()))
+    }
+}
+
+/// The error returned when parsing a [`Code`](struct.Code.html) fails.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ParseCodeError {
+    /// The text is empty or only whitespace.
+    Empty,
+    /// A token is not an 8-digit hex number.
+    InvalidToken(String),
+    /// The address is not followed by a value.
+    MissingValue,
+    /// A token follows the value.
+    TrailingToken(String),
+}
+
+impl fmt::Display for ParseCodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => f.write_str("no code"),
+            Self::InvalidToken(token) => write!(f, "{:?} is not an 8-digit hex number", token),
+            Self::MissingValue => f.write_str("code has no value"),
+            Self::TrailingToken(token) => write!(f, "unexpected {:?} after code", token),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ParseCodeError {}
+
 /// A processor for CB v1 and v7 codes.
+///
+/// # Raw beefcodes
+///
+/// Some published lists contain the beefcode in raw form, followed by codes
+/// that are V7-encrypted:
+///
+/// ```text
+/// BEEFC0DE 00000000
+/// D08F3A49 00078A53
+/// 3818DDE5 E72B2B16
+/// ```
+///
+/// [`auto_decrypt_code`](#method.auto_decrypt_code) detects this layout if
+/// the code after a raw `BEEFC0DE` is no plausible raw code, and reports it
+/// via [`raw_beefcode`](#method.raw_beefcode). Otherwise, the list is taken
+/// as decrypted and passed through unchanged. To reproduce such a
+/// list when encrypting, use a processor returned by
+/// [`new_raw_beefcode`](#method.new_raw_beefcode).
+///
+/// # Encryption changes
+///
+/// In a list of V7 codes, the directive `FFFFFFFF 000xnnnn` changes the
+/// encryption of the codes after it. x selects the new scheme: 0 for RAW, 1
+/// for V1, and 7 for V7 keyed like by `BEEFC0DE 0000nnnn`. Other values of x
+/// leave the encryption unchanged. The directive itself is V7-encrypted.
+///
+/// [`auto_decrypt_code`](#method.auto_decrypt_code) and
+/// [`auto_encrypt_code`](#method.auto_encrypt_code) follow the directive
+/// unless the processor is [pinned](#method.pin_scheme). After a change to
+/// RAW or V1, the scheme of each code is detected as usual, and a beefcode
+/// switches back to V7.
+///
+/// ```
+/// use codebreaker::{Codebreaker, Scheme};
+///
+/// let mut cb = Codebreaker::new();
+/// cb.auto_decrypt_code(0xB4336FA9, 0x4DFEFB79);
+/// assert_eq!((0x2043AFCC, 0x2411FFFF), cb.auto_decrypt_code(0x397951B0, 0x41569FE0));
+/// assert_eq!((0xFFFFFFFF, 0x00010000), cb.auto_decrypt_code(0x244CA2B3, 0x116AFBB4));
+/// assert_eq!(Scheme::V1, cb.scheme());
+/// assert_eq!((0x201F6024, 0x00000000), cb.auto_decrypt_code(0x2A973DBD, 0x00000000));
+/// ```
+///
+/// # Beefcode side effects
+///
+/// Beefcodes always take effect in their decrypted form, i.e. the input of
+/// an encrypting call and the output of a decrypting one. A beefcode changes
+/// the encryption of the codes after it, never its own. After `BEEFC0DF`, the
+/// next code is taken as the extra seed, which
+/// [`expects_seed`](#method.expects_seed) tells.
+///
+/// Because both directions key off the same decrypted code, a processor can
+/// encrypt some lines of a list and decrypt others, see
+/// [`crypt_code`](#method.crypt_code). Its state after each line is the same
+/// as if it had processed the whole list in one direction.
+///
+/// # Serialization
+///
+/// With the `serde` feature, the state of a processor can be saved in the
+/// middle of a list and restored later. The firmware profile is not part of
+/// it: a restored processor uses [`Profile::V7`](firmware/struct.Profile.html#associatedconstant.V7),
+/// see [`set_profile`](#method.set_profile).
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Codebreaker {
     scheme: Scheme,
     cb7: Cb7,
     code_lines: usize,
+    raw_beefcode: bool,
+    // Value of a raw BEEFC0DE that keys the following codes if they are V7
+    pending_beefcode: Option<u32>,
+    pinned: bool,
+    #[cfg_attr(feature = "serde", serde(skip, default = "default_profile"))]
+    profile: Profile,
+    beefcode_seen: bool,
 }
 
 /// Does the same as [`new`](#method.new).
@@ -84,6 +449,11 @@ impl Default for Codebreaker {
     }
 }
 
+#[cfg(feature = "serde")]
+const fn default_profile() -> Profile {
+    Profile::V7
+}
+
 impl Codebreaker {
     /// Returns a new processor for encrypting and decrypting a list of CB v1
     /// and v7 codes.
@@ -92,20 +462,272 @@ impl Codebreaker {
             scheme: Scheme::Raw,
             cb7: Cb7::new(),
             code_lines: 0,
+            raw_beefcode: false,
+            pending_beefcode: None,
+            pinned: false,
+            profile: Profile::V7,
+            beefcode_seen: false,
         }
     }
 
     /// Returns a new processor for all CB v7 codes published on CMGSCCC.com.
     ///
     /// Lets you omit `B4336FA9 4DFEFB79` as the first code in the list.
+    /// [`auto_decrypt_code`](#method.auto_decrypt_code) still accepts it, or
+    /// any other beefcode encrypted with V1, before the first V7 code.
+    ///
+    /// # Example
+    /// ```
+    /// use codebreaker::Codebreaker;
+    ///
+    /// let mut cb = Codebreaker::new_v7();
+    /// assert_eq!((0xBEEFC0DE, 0x00000000), cb.auto_decrypt_code(0xB4336FA9, 0x4DFEFB79));
+    /// assert_eq!((0x2096F5B8, 0x000000BE), cb.auto_decrypt_code(0x973E0B2A, 0xA7D4AF10));
+    /// ```
     pub fn new_v7() -> Self {
         Self {
             scheme: Scheme::V7,
             cb7: Cb7::default(),
             code_lines: 0,
+            raw_beefcode: false,
+            pending_beefcode: None,
+            pinned: false,
+            profile: Profile::V7,
+            beefcode_seen: false,
+        }
+    }
+
+    /// Returns a new processor for lists that contain the first beefcode in
+    /// raw form, followed by V7-encrypted codes.
+    ///
+    /// The first beefcode is passed through unchanged when encrypting or
+    /// decrypting; everything else is processed like with [`new`](#method.new).
+    ///
+    /// # Example
+    /// ```
+    /// use codebreaker::Codebreaker;
+    ///
+    /// let mut cb = Codebreaker::new_raw_beefcode();
+    /// assert_eq!((0xBEEFC0DE, 0x00000000), cb.encrypt_code(0xBEEFC0DE, 0x00000000));
+    /// assert_eq!((0xD08F3A49, 0x00078A53), cb.encrypt_code(0x9029BEAC, 0x0C0A9225));
+    /// ```
+    pub const fn new_raw_beefcode() -> Self {
+        Self {
+            scheme: Scheme::Raw,
+            cb7: Cb7::new(),
+            code_lines: 0,
+            raw_beefcode: true,
+            pending_beefcode: None,
+            pinned: false,
+            profile: Profile::V7,
+            beefcode_seen: false,
+        }
+    }
+
+    /// Returns a new processor for lists known to be unencrypted.
+    ///
+    /// Codes are passed through unchanged, like with a processor
+    /// [pinned](#method.pin_scheme) to RAW.
+    ///
+    /// # Example
+    /// ```
+    /// use codebreaker::Codebreaker;
+    ///
+    /// let mut cb = Codebreaker::new_raw();
+    /// assert_eq!((0xBEEFC0DE, 0x00000000), cb.auto_decrypt_code(0xBEEFC0DE, 0x00000000));
+    /// assert_eq!((0x2096F5B8, 0x000000BE), cb.auto_decrypt_code(0x2096F5B8, 0x000000BE));
+    /// ```
+    pub const fn new_raw() -> Self {
+        Self {
+            pinned: true,
+            ..Self::new()
         }
     }
 
+    /// Returns a new processor for lists known to be encrypted with CB v1.
+    ///
+    /// Beefcodes do not switch to V7 encryption, like with a processor
+    /// [pinned](#method.pin_scheme) to V1.
+    ///
+    /// # Example
+    /// ```
+    /// use codebreaker::Codebreaker;
+    ///
+    /// let mut cb = Codebreaker::new_v1();
+    /// assert_eq!((0x201F6024, 0x00000000), cb.auto_decrypt_code(0x2A973DBD, 0x00000000));
+    /// ```
+    pub const fn new_v1() -> Self {
+        Self {
+            scheme: Scheme::V1,
+            pinned: true,
+            ..Self::new()
+        }
+    }
+
+    /// Returns a new processor pinned to the given scheme.
+    ///
+    /// Does the same as [`new_raw`](#method.new_raw) or
+    /// [`new_v1`](#method.new_v1), or for V7 like [`new_v7`](#method.new_v7)
+    /// with the scheme pinned.
+    pub fn with_scheme(scheme: Scheme) -> Self {
+        let mut cb = Self::new();
+        cb.pin_scheme(scheme);
+        cb
+    }
+
+    /// Returns the processor to the state of [`new`](#method.new), ready for
+    /// the next, unrelated list of codes.
+    ///
+    /// Forgets the scheme, beefcodes, pending multi-line codes, and any
+    /// [pinned](#method.pin_scheme) scheme. The firmware profile is kept.
+    ///
+    /// # Example
+    /// ```
+    /// use codebreaker::{Codebreaker, Scheme};
+    ///
+    /// let mut cb = Codebreaker::new();
+    /// cb.auto_decrypt_code(0xB4336FA9, 0x4DFEFB79);
+    /// assert_eq!(Scheme::V7, cb.scheme());
+    /// cb.reset();
+    /// assert_eq!(Scheme::Raw, cb.scheme());
+    /// assert_eq!((0x2043AFCC, 0x2411FFFF), cb.auto_decrypt_code(0x2043AFCC, 0x2411FFFF));
+    /// ```
+    pub fn reset(&mut self) {
+        *self = Self {
+            profile: self.profile,
+            ..Self::new()
+        };
+    }
+
+    /// Returns the processor to the state of [`new_v7`](#method.new_v7),
+    /// keeping the firmware profile like [`reset`](#method.reset).
+    pub fn reset_to_v7(&mut self) {
+        *self = Self {
+            profile: self.profile,
+            ..Self::new_v7()
+        };
+    }
+
+    /// Returns true if the processor passes the first beefcode through
+    /// unchanged, either because it was created with
+    /// [`new_raw_beefcode`](#method.new_raw_beefcode) or because
+    /// [`auto_decrypt_code`](#method.auto_decrypt_code) came across a raw
+    /// beefcode followed by V7 codes.
+    ///
+    /// # Example
+    /// ```
+    /// use codebreaker::Codebreaker;
+    ///
+    /// let mut cb = Codebreaker::new();
+    /// cb.auto_decrypt_code(0xBEEFC0DE, 0x00000000);
+    /// assert_eq!(false, cb.raw_beefcode());
+    /// assert_eq!((0x9029BEAC, 0x0C0A9225), cb.auto_decrypt_code(0xD08F3A49, 0x00078A53));
+    /// assert_eq!(true, cb.raw_beefcode());
+    /// ```
+    pub const fn raw_beefcode(&self) -> bool {
+        self.raw_beefcode
+    }
+
+    /// Returns the scheme the processor currently applies to codes.
+    ///
+    /// # Example
+    /// ```
+    /// use codebreaker::{Codebreaker, Scheme};
+    ///
+    /// let mut cb = Codebreaker::new();
+    /// assert_eq!(Scheme::Raw, cb.scheme());
+    /// cb.auto_decrypt_code(0xB4336FA9, 0x4DFEFB79);
+    /// assert_eq!(Scheme::V7, cb.scheme());
+    /// ```
+    pub const fn scheme(&self) -> Scheme {
+        self.scheme
+    }
+
+    /// Returns true if the processor has switched to V7 encryption because of
+    /// a beefcode.
+    ///
+    /// Processors created with [`new_v7`](#method.new_v7) apply V7 encryption
+    /// from the start; for them this is false until they see another beefcode.
+    ///
+    /// # Example
+    /// ```
+    /// use codebreaker::Codebreaker;
+    ///
+    /// let mut cb = Codebreaker::new();
+    /// assert!(!cb.beefcode_seen());
+    /// cb.auto_decrypt_code(0xB4336FA9, 0x4DFEFB79);
+    /// assert!(cb.beefcode_seen());
+    /// ```
+    pub const fn beefcode_seen(&self) -> bool {
+        self.beefcode_seen
+    }
+
+    // The first beefcode of a raw beefcode list is not encrypted.
+    const fn is_raw_beefcode(&self, addr: u32) -> bool {
+        self.raw_beefcode && is_beefcode(addr)
+    }
+
+    /// Locks the processor to the given scheme.
+    ///
+    /// This is useful for archived lists whose encryption has been declared by
+    /// the submitter. A pinned processor never switches schemes on its own:
+    /// beefcodes do not enable V7 encryption unless pinned to V7, and raw codes
+    /// are passed through unchanged when pinned to RAW.
+    ///
+    /// Use [`try_auto_decrypt_code`](#method.try_auto_decrypt_code) to detect
+    /// codes that contradict the pinned scheme. When pinned to V7, these are
+    /// codes that decrypt to an implausible code, but are plausible as RAW or
+    /// V1 codes.
+    ///
+    /// # Example
+    /// ```
+    /// use codebreaker::{Codebreaker, Error, Scheme};
+    ///
+    /// let mut cb = Codebreaker::new();
+    /// cb.pin_scheme(Scheme::V1);
+    /// assert_eq!(Ok((0x201F6024, 0x00000000)), cb.try_auto_decrypt_code(0x2A973DBD, 0x00000000));
+    /// assert_eq!(
+    ///     Err(Error::SchemeMismatch { pinned: Scheme::V1, found: Scheme::Raw }),
+    ///     cb.try_auto_decrypt_code(0x2096F5B8, 0x000000BE)
+    /// );
+    /// ```
+    pub fn pin_scheme(&mut self, scheme: Scheme) {
+        if scheme == Scheme::V7 && self.scheme != Scheme::V7 {
+            self.cb7 = Cb7::default();
+        }
+        self.scheme = scheme;
+        self.pinned = true;
+    }
+
+    /// Returns the firmware profile used to tell how many lines a code takes.
+    pub const fn profile(&self) -> &Profile {
+        &self.profile
+    }
+
+    /// Sets the firmware profile used to tell how many lines a code takes.
+    ///
+    /// [`auto_decrypt_code`](#method.auto_decrypt_code) needs to know which
+    /// lines continue a multi-line code, as they are never the start of a new
+    /// scheme. The default is [`Profile::V7`](firmware/struct.Profile.html).
+    ///
+    /// # Example
+    /// ```
+    /// use codebreaker::firmware::{CodeType, Lines, Profile};
+    /// use codebreaker::Codebreaker;
+    ///
+    /// let mut cb = Codebreaker::new();
+    /// cb.set_profile(Profile::V7.with_type(0x4, Some(CodeType::new("serial write", Lines::Fixed(3)))));
+    /// assert_eq!(3, cb.profile().lines(0x40410000));
+    /// ```
+    pub fn set_profile(&mut self, profile: Profile) {
+        self.profile = profile;
+    }
+
+    // A processor pinned to RAW or V1 must not switch to V7.
+    const fn may_switch_to_v7(&self) -> bool {
+        !self.pinned || matches!(self.scheme, Scheme::V7)
+    }
+
     /// Encrypts a code and returns the result.
     ///
     /// # Example
@@ -136,15 +758,17 @@ impl Codebreaker {
     pub fn encrypt_code_mut(&mut self, addr: &mut u32, val: &mut u32) {
         let (oldaddr, oldval) = (*addr, *val);
 
-        if self.scheme == Scheme::V7 {
-            self.cb7.encrypt_code_mut(addr, val);
-        } else {
-            cb1::encrypt_code_mut(addr, val);
+        match self.scheme {
+            Scheme::V7 => self.cb7.encrypt_code_mut(addr, val),
+            Scheme::Raw if self.pinned => {}
+            _ if self.is_raw_beefcode(oldaddr) => {}
+            _ => cb1::encrypt_code_mut(addr, val),
         }
 
-        if is_beefcode(oldaddr) {
+        if is_beefcode(oldaddr) && self.may_switch_to_v7() {
             self.cb7.beefcode(oldaddr, oldval);
             self.scheme = Scheme::V7;
+            self.beefcode_seen = true;
         }
     }
 
@@ -201,15 +825,173 @@ impl Codebreaker {
     /// assert_eq!(decrypted, encrypted);
     /// ```
     pub fn decrypt_code_mut(&mut self, addr: &mut u32, val: &mut u32) {
-        if self.scheme == Scheme::V7 {
-            self.cb7.decrypt_code_mut(addr, val);
-        } else {
-            cb1::decrypt_code_mut(addr, val);
+        match self.scheme {
+            Scheme::V7 => self.cb7.decrypt_code_mut(addr, val),
+            Scheme::Raw if self.pinned => {}
+            _ if self.is_raw_beefcode(*addr) => {}
+            _ => cb1::decrypt_code_mut(addr, val),
         }
 
-        if is_beefcode(*addr) {
+        if is_beefcode(*addr) && self.may_switch_to_v7() {
             self.cb7.beefcode(*addr, *val);
             self.scheme = Scheme::V7;
+            self.beefcode_seen = true;
+        }
+    }
+
+    /// Encrypts or decrypts a code depending on `direction`, and returns the
+    /// result.
+    ///
+    /// # Example
+    /// ```
+    /// use codebreaker::{Codebreaker, Direction};
+    ///
+    /// let mut cb = Codebreaker::new();
+    /// assert_eq!((0xB4336FA9, 0x4DFEFB79), cb.crypt_code(Direction::Encrypt, 0xBEEFC0DE, 0x00000000));
+    /// assert_eq!((0x2096F5B8, 0x000000BE), cb.crypt_code(Direction::Decrypt, 0x973E0B2A, 0xA7D4AF10));
+    /// ```
+    pub fn crypt_code(&mut self, direction: Direction, addr: u32, val: u32) -> (u32, u32) {
+        let mut code = (addr, val);
+        self.crypt_code_mut(direction, &mut code.0, &mut code.1);
+        code
+    }
+
+    /// Encrypts or decrypts a code directly depending on `direction`.
+    pub fn crypt_code_mut(&mut self, direction: Direction, addr: &mut u32, val: &mut u32) {
+        match direction {
+            Direction::Encrypt => self.encrypt_code_mut(addr, val),
+            Direction::Decrypt => self.decrypt_code_mut(addr, val),
+        }
+    }
+
+    /// Returns true if the next code is the extra seed of a preceding
+    /// `BEEFC0DF`, rather than a code of its own.
+    ///
+    /// The seed changes the encryption of the codes after it, like a
+    /// beefcode does. It is taken from the decrypted code in both directions.
+    ///
+    /// # Example
+    /// ```
+    /// use codebreaker::Codebreaker;
+    ///
+    /// let mut cb = Codebreaker::new();
+    /// cb.encrypt_code(0xBEEFC0DF, 0x00000000);
+    /// assert!(cb.expects_seed());
+    /// cb.encrypt_code(0x01234567, 0x89ABCDEF);
+    /// assert!(!cb.expects_seed());
+    /// ```
+    pub const fn expects_seed(&self) -> bool {
+        matches!(self.scheme, Scheme::V7) && self.cb7.expects_seed()
+    }
+
+    /// Encrypts a [`Code`](struct.Code.html) like
+    /// [`encrypt_code`](#method.encrypt_code).
+    ///
+    /// # Example
+    /// ```
+    /// use codebreaker::{Code, Codebreaker};
+    ///
+    /// let mut cb = Codebreaker::new();
+    /// let code = cb.encrypt("2043AFCC 2411FFFF".parse().unwrap());
+    /// assert_eq!(Code::new(0x2AFF014C, 0x2411FFFF), code);
+    /// ```
+    pub fn encrypt(&mut self, code: Code) -> Code {
+        self.encrypt_code(code.addr, code.val).into()
+    }
+
+    /// Decrypts a [`Code`](struct.Code.html) like
+    /// [`decrypt_code`](#method.decrypt_code).
+    pub fn decrypt(&mut self, code: Code) -> Code {
+        self.decrypt_code(code.addr, code.val).into()
+    }
+
+    /// Decrypts a [`Code`](struct.Code.html) like
+    /// [`auto_decrypt_code`](#method.auto_decrypt_code).
+    pub fn auto_decrypt(&mut self, code: Code) -> Code {
+        self.auto_decrypt_code(code.addr, code.val).into()
+    }
+
+    /// Encrypts a list of codes in place.
+    ///
+    /// Processes the codes in order, so beefcodes switch the encryption of
+    /// the following codes like with [`encrypt_code_mut`](#method.encrypt_code_mut).
+    ///
+    /// # Example
+    /// ```
+    /// use codebreaker::Codebreaker;
+    ///
+    /// let mut codes = [
+    ///     (0x2043AFCC, 0x2411FFFF),
+    ///     (0xBEEFC0DE, 0x00000000),
+    ///     (0x2096F5B8, 0x000000BE),
+    /// ];
+    /// let mut cb = Codebreaker::new();
+    /// cb.encrypt_code_list(&mut codes);
+    /// assert_eq!(
+    ///     [
+    ///         (0x2AFF014C, 0x2411FFFF),
+    ///         (0xB4336FA9, 0x4DFEFB79),
+    ///         (0x973E0B2A, 0xA7D4AF10),
+    ///     ],
+    ///     codes
+    /// );
+    /// ```
+    pub fn encrypt_code_list(&mut self, codes: &mut [(u32, u32)]) {
+        for code in codes.iter_mut() {
+            self.encrypt_code_mut(&mut code.0, &mut code.1);
+        }
+    }
+
+    /// Decrypts a list of codes in place.
+    ///
+    /// Processes the codes in order, so beefcodes switch the encryption of
+    /// the following codes like with [`decrypt_code_mut`](#method.decrypt_code_mut).
+    ///
+    /// # Example
+    /// ```
+    /// use codebreaker::Codebreaker;
+    ///
+    /// let mut codes = [
+    ///     (0x2AFF014C, 0x2411FFFF),
+    ///     (0xB4336FA9, 0x4DFEFB79),
+    ///     (0x973E0B2A, 0xA7D4AF10),
+    /// ];
+    /// let mut cb = Codebreaker::new();
+    /// cb.decrypt_code_list(&mut codes);
+    /// assert_eq!(
+    ///     [
+    ///         (0x2043AFCC, 0x2411FFFF),
+    ///         (0xBEEFC0DE, 0x00000000),
+    ///         (0x2096F5B8, 0x000000BE),
+    ///     ],
+    ///     codes
+    /// );
+    /// ```
+    pub fn decrypt_code_list(&mut self, codes: &mut [(u32, u32)]) {
+        for code in codes.iter_mut() {
+            self.decrypt_code_mut(&mut code.0, &mut code.1);
+        }
+    }
+
+    /// Like [`encrypt_code_list`](#method.encrypt_code_list), for [`Code`]s.
+    pub fn encrypt_codes(&mut self, codes: &mut [Code]) {
+        for code in codes.iter_mut() {
+            self.encrypt_code_mut(&mut code.addr, &mut code.val);
+        }
+    }
+
+    /// Like [`decrypt_code_list`](#method.decrypt_code_list), for [`Code`]s.
+    pub fn decrypt_codes(&mut self, codes: &mut [Code]) {
+        for code in codes.iter_mut() {
+            self.decrypt_code_mut(&mut code.addr, &mut code.val);
+        }
+    }
+
+    /// Decrypts a list of codes in place like
+    /// [`auto_decrypt_code_mut`](#method.auto_decrypt_code_mut).
+    pub fn auto_decrypt_codes(&mut self, codes: &mut [Code]) {
+        for code in codes.iter_mut() {
+            self.auto_decrypt_code_mut(&mut code.addr, &mut code.val);
         }
     }
 
@@ -246,68 +1028,300 @@ impl Codebreaker {
 
     /// Smart version of [`decrypt_code_mut`](#method.decrypt_code_mut) that
     /// detects if and how a code needs to be decrypted.
+    ///
+    /// A raw beefcode is passed through and switches to V7 decryption for the
+    /// following codes, see [raw beefcodes](#raw-beefcodes).
+    ///
+    /// If the processor is [pinned](#method.pin_scheme), codes that contradict
+    /// the pinned scheme are left unchanged.
     pub fn auto_decrypt_code_mut(&mut self, addr: &mut u32, val: &mut u32) {
+        // Codes contradicting a pinned scheme are not touched.
+        let _ = self.try_auto_decrypt_code_mut(addr, val);
+    }
+
+    /// Like [`auto_decrypt_code`](#method.auto_decrypt_code), but returns an
+    /// error if the code contradicts the [pinned](#method.pin_scheme) scheme.
+    ///
+    /// Processors that are not pinned never return an error.
+    pub fn try_auto_decrypt_code(&mut self, addr: u32, val: u32) -> Result<(u32, u32), Error> {
+        let mut code = (addr, val);
+        self.try_auto_decrypt_code_mut(&mut code.0, &mut code.1)?;
+        Ok(code)
+    }
+
+    /// Like [`auto_decrypt_code_mut`](#method.auto_decrypt_code_mut), but
+    /// returns an error if the code contradicts the
+    /// [pinned](#method.pin_scheme) scheme.
+    ///
+    /// In case of an error, neither the code nor the processor is modified.
+    pub fn try_auto_decrypt_code_mut(&mut self, addr: &mut u32, val: &mut u32) -> Result<(), Error> {
+        self.detect_and_decrypt(addr, val).map(|_| ())
+    }
+
+    /// Like [`auto_decrypt_code`](#method.auto_decrypt_code), but returns both
+    /// the original and the decrypted code along with the scheme that was used
+    /// for decryption.
+    ///
+    /// This allows to display codes side-by-side without running two passes.
+    ///
+    /// # Example
+    /// ```
+    /// use codebreaker::{Codebreaker, Decrypted, Scheme};
+    ///
+    /// let mut cb = Codebreaker::new();
+    /// assert_eq!(
+    ///     Decrypted {
+    ///         original: (0x2A973DBD, 0x00000000),
+    ///         decrypted: (0x201F6024, 0x00000000),
+    ///         scheme: Scheme::V1,
+    ///     },
+    ///     cb.auto_decrypt_code_detailed(0x2A973DBD, 0x00000000)
+    /// );
+    /// ```
+    pub fn auto_decrypt_code_detailed(&mut self, addr: u32, val: u32) -> Decrypted {
+        let mut code = (addr, val);
+        // Codes contradicting a pinned scheme are passed through as is.
+        let scheme = self.detect_and_decrypt(&mut code.0, &mut code.1).unwrap_or(Scheme::Raw);
+        Decrypted {
+            original: (addr, val),
+            decrypted: code,
+            scheme,
+        }
+    }
+
+    /// Smart version of [`encrypt_code`](#method.encrypt_code) that is the
+    /// counterpart of [`auto_decrypt_code`](#method.auto_decrypt_code).
+    ///
+    /// Keeps track of multi-line codes and beefcodes the same way as the
+    /// auto decrypter, so that encrypted lists decrypt back to the original.
+    /// See [`auto_encrypt_code_list`](#method.auto_encrypt_code_list) to
+    /// encrypt a whole list for a target scheme.
+    ///
+    /// # Example
+    /// ```
+    /// use codebreaker::Codebreaker;
+    ///
+    /// let mut cb = Codebreaker::new();
+    /// assert_eq!((0x2AFF014C, 0x2411FFFF), cb.auto_encrypt_code(0x2043AFCC, 0x2411FFFF));
+    /// assert_eq!((0xB4336FA9, 0x4DFEFB79), cb.auto_encrypt_code(0xBEEFC0DE, 0x00000000));
+    /// assert_eq!((0x973E0B2A, 0xA7D4AF10), cb.auto_encrypt_code(0x2096F5B8, 0x000000BE));
+    /// ```
+    pub fn auto_encrypt_code(&mut self, addr: u32, val: u32) -> (u32, u32) {
+        let mut code = (addr, val);
+        self.auto_encrypt_code_mut(&mut code.0, &mut code.1);
+        code
+    }
+
+    /// Smart version of [`encrypt_code_mut`](#method.encrypt_code_mut) that is
+    /// the counterpart of [`auto_decrypt_code_mut`](#method.auto_decrypt_code_mut).
+    pub fn auto_encrypt_code_mut(&mut self, addr: &mut u32, val: &mut u32) {
+        // An encryption change directive starts a group of one line
+        let change = self.code_lines == 0
+            && self.scheme == Scheme::V7
+            && !self.pinned
+            && *addr == 0xffff_ffff
+            && self.profile.lines(*addr) == 1;
+        if self.code_lines == 0 {
+            self.code_lines = self.profile.lines(*addr);
+        }
+        self.code_lines -= 1;
+
+        let switch = is_beefcode(*addr) && self.may_switch_to_v7();
+        let directive = *val;
+        self.encrypt_code_mut(addr, val);
+        if switch {
+            self.code_lines = 1;
+        }
+        if change {
+            self.change_encryption(directive);
+        }
+    }
+
+    /// Encrypts a list of raw codes for the target scheme.
+    ///
+    /// For V7, a `BEEFC0DE 00000000` beefcode is inserted at the start unless
+    /// the list starts with a beefcode or the processor already applies V7.
+    /// For V1, the codes are encrypted as they are; beefcodes in the list
+    /// still switch to V7 encryption. For RAW, the codes are returned
+    /// unchanged.
+    ///
+    /// # Example
+    /// ```
+    /// use codebreaker::{Codebreaker, Scheme};
+    ///
+    /// let codes = [(0x2043AFCC, 0x2411FFFF), (0x2096F5B8, 0x000000BE)];
+    /// let mut cb = Codebreaker::new();
+    /// assert_eq!(
+    ///     vec![
+    ///         (0xB4336FA9, 0x4DFEFB79),
+    ///         (0x397951B0, 0x41569FE0),
+    ///         (0x973E0B2A, 0xA7D4AF10),
+    ///     ],
+    ///     cb.auto_encrypt_code_list(&codes, Scheme::V7)
+    /// );
+    /// ```
+    pub fn auto_encrypt_code_list(&mut self, codes: &[(u32, u32)], target: Scheme) -> Vec<(u32, u32)> {
+        iter::auto_encrypt(self, codes.iter().copied(), target)
+            .map(Into::into)
+            .collect()
+    }
+
+    // Follows the encryption change directive "FFFFFFFF 000xnnnn", see
+    // [encryption changes](#encryption-changes).
+    fn change_encryption(&mut self, val: u32) {
+        match val >> 16 {
+            0 => self.scheme = Scheme::Raw,
+            1 => self.scheme = Scheme::V1,
+            7 => self.cb7.beefcode(0xbeef_c0de, val & 0xffff),
+            _ => {}
+        }
+    }
+
+    // Returns the scheme of a code at the start of a group if it cannot be V7:
+    // it decrypts to an implausible code, but is plausible as RAW or V1.
+    fn contradicts_v7(&self, addr: u32, val: u32) -> Option<Scheme> {
+        let mut cb7 = self.cb7;
+        if is_plausible_raw(cb7.decrypt_code(addr, val)) {
+            return None;
+        }
+        match detect_scheme(addr, val) {
+            Scheme::Raw if is_plausible_raw((addr, val)) => Some(Scheme::Raw),
+            Scheme::V1 if is_plausible_raw(cb1::decrypt_code(addr, val)) => Some(Scheme::V1),
+            _ => None,
+        }
+    }
+
+    // Returns the scheme that was used to decrypt the code.
+    fn detect_and_decrypt(&mut self, addr: &mut u32, val: &mut u32) -> Result<Scheme, Error> {
+        if self.pinned && self.code_lines == 0 {
+            let found = match self.scheme {
+                Scheme::V7 => self.contradicts_v7(*addr, *val),
+                pinned => Some(detect_scheme(*addr, *val)).filter(|&found| found != pinned),
+            };
+            if let Some(found) = found {
+                return Err(Error::SchemeMismatch {
+                    pinned: self.scheme,
+                    found,
+                });
+            }
+        }
+
+        if let Some(seed) = self.pending_beefcode.take() {
+            // A raw beefcode followed by a code that cannot be raw starts a
+            // list of V7 codes. Otherwise, the list is already decrypted.
+            if !is_plausible_raw((*addr, *val)) {
+                self.cb7.beefcode(0xbeef_c0de, seed);
+                self.scheme = Scheme::V7;
+                self.beefcode_seen = true;
+                self.raw_beefcode = true;
+                self.code_lines = 1;
+            }
+        }
+
+        let mut used = self.scheme;
+
         if self.scheme != Scheme::V7 {
             if self.code_lines == 0 {
-                self.code_lines = num_code_lines(*addr);
+                self.code_lines = self.profile.lines(*addr);
                 if (*addr >> 24) & 0x0e != 0 {
                     if is_beefcode(*addr) {
-                        // ignore raw beefcode
                         self.code_lines -= 1;
-                        return;
+                        if !self.raw_beefcode {
+                            // Decide on the next code whether V7 codes follow
+                            if *addr == 0xbeef_c0de && self.may_switch_to_v7() {
+                                self.pending_beefcode = Some(*val);
+                            }
+                            return Ok(Scheme::Raw);
+                        }
+                        // raw beefcode, V7-encrypted codes follow
+                        used = Scheme::Raw;
                     } else {
                         self.scheme = Scheme::V1;
                         self.code_lines -= 1;
                         cb1::decrypt_code_mut(addr, val);
+                        used = Scheme::V1;
                     }
                 } else {
                     self.scheme = Scheme::Raw;
                     self.code_lines -= 1;
+                    used = Scheme::Raw;
                 }
             } else {
                 self.code_lines -= 1;
                 if self.scheme == Scheme::Raw {
-                    return;
+                    return Ok(used);
                 }
                 cb1::decrypt_code_mut(addr, val);
             }
+        } else if self.code_lines == 0 && !self.beefcode_seen && is_beefcode(cb1::decrypt_code(*addr, *val).0) {
+            // V7 codes may be preceded by their beefcode encrypted with V1,
+            // like in lists for processors created with new()
+            cb1::decrypt_code_mut(addr, val);
+            self.cb7 = Cb7::new();
+            used = Scheme::V1;
         } else {
             self.cb7.decrypt_code_mut(addr, val);
             if self.code_lines == 0 {
-                self.code_lines = num_code_lines(*addr);
+                self.code_lines = self.profile.lines(*addr);
                 if self.code_lines == 1 && *addr == 0xffff_ffff {
-                    // XXX: changing encryption via "FFFFFFFF 000xnnnn" is not supported
                     self.code_lines = 0;
-                    return;
+                    if !self.pinned {
+                        self.change_encryption(*val);
+                    }
+                    return Ok(used);
                 }
             }
             self.code_lines -= 1;
         }
 
-        if is_beefcode(*addr) {
+        if is_beefcode(*addr) && self.may_switch_to_v7() {
             self.cb7.beefcode(*addr, *val);
             self.scheme = Scheme::V7;
+            self.beefcode_seen = true;
             self.code_lines = 1;
         }
+
+        Ok(used)
     }
 }
 
-const fn num_code_lines(addr: u32) -> usize {
-    let cmd = addr >> 28;
+// Returns true if a code at the start of a group can be a raw code: it must
+// be of a code type CB v7 knows, and the memory at its address must be in EE
+// RAM and aligned for the access.
+const fn is_plausible_raw(code: (u32, u32)) -> bool {
+    let (addr, val) = code;
+    if is_beefcode(addr) || addr == 0xffff_ffff {
+        return true;
+    }
+    let (align, max) = match addr >> 28 {
+        0 => (1, 0xff),
+        1 => (2, 0xffff),
+        2 | 4 | 6 | 0xc => (4, u32::MAX),
+        7 if matches!(val >> 20 & 0xf, 1 | 3 | 5) => (2, u32::MAX),
+        0xd if val & 0x0100_0000 == 0 => (2, u32::MAX),
+        // Byte accesses, and code types without an address of their own
+        3 | 5 | 7 | 9 | 0xb | 0xd | 0xe | 0xf => (1, u32::MAX),
+        _ => return false,
+    };
+    let at = addr & 0x0fff_ffff;
+    at < 0x0200_0000 && at & (align - 1) == 0 && val <= max
+}
 
-    if cmd < 3 || cmd > 6 {
-        1
-    } else if cmd == 3 {
-        if addr & 0x0040_0000 != 0 {
-            2
-        } else {
-            1
-        }
+// Guesses the scheme of a code at the start of a list.
+const fn detect_scheme(addr: u32, val: u32) -> Scheme {
+    if (addr >> 24) & 0x0e == 0 {
+        Scheme::Raw
+    } else if is_beefcode(addr) || is_beefcode(cb1::decrypt_code(addr, val).0) {
+        Scheme::V7
     } else {
-        2
+        Scheme::V1
     }
 }
 
+const fn num_code_lines(addr: u32) -> usize {
+    Profile::V7.lines(addr)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -361,6 +1375,21 @@ mod tests {
                     "2A03B60A 000000BE",
                 ],
             },
+            Test {
+                cb: Codebreaker::new_raw_beefcode(),
+                decrypted: vec![
+                    "BEEFC0DE 00000000",
+                    "9029BEAC 0C0A9225",
+                    "201F6024 00000000",
+                    "2096F5B8 000000BE",
+                ],
+                encrypted: vec![
+                    "BEEFC0DE 00000000",
+                    "D08F3A49 00078A53",
+                    "3818DDE5 E72B2B16",
+                    "973E0B2A A7D4AF10",
+                ],
+            },
         ]
     }
 
@@ -408,6 +1437,84 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_encrypt_code_list() {
+        for t in tests().iter_mut() {
+            let mut codes: Vec<_> = t.decrypted.iter().map(|line| code::parse(line)).collect();
+            t.cb.encrypt_code_list(&mut codes);
+            let expected: Vec<_> = t.encrypted.iter().map(|line| code::parse(line)).collect();
+            assert_eq!(expected, codes);
+        }
+    }
+
+    #[test]
+    fn test_decrypt_code_list() {
+        for t in tests().iter_mut() {
+            let mut codes: Vec<_> = t.encrypted.iter().map(|line| code::parse(line)).collect();
+            t.cb.decrypt_code_list(&mut codes);
+            let expected: Vec<_> = t.decrypted.iter().map(|line| code::parse(line)).collect();
+            assert_eq!(expected, codes);
+        }
+    }
+
+    #[test]
+    fn test_reset() {
+        let tests = tests();
+        let parse = |lines: &[&str]| -> Vec<_> { lines.iter().map(|line| code::parse(line)).collect() };
+
+        let profile = firmware::Profile::V7.named("custom");
+        let mut cb = Codebreaker::new();
+        cb.set_profile(profile);
+        cb.pin_scheme(Scheme::V7);
+        cb.decrypt_code_list(&mut parse(&tests[1].encrypted));
+
+        cb.reset();
+        assert_eq!(Scheme::Raw, cb.scheme());
+        assert_eq!(&profile, cb.profile());
+        let mut codes = parse(&tests[0].encrypted);
+        cb.decrypt_code_list(&mut codes);
+        assert_eq!(parse(&tests[0].decrypted), codes);
+        assert!(cb.beefcode_seen());
+
+        cb.reset();
+        assert!(!cb.beefcode_seen());
+
+        cb.reset_to_v7();
+        assert_eq!(Scheme::V7, cb.scheme());
+        assert_eq!(&profile, cb.profile());
+        let mut codes = parse(&tests[1].encrypted);
+        cb.decrypt_code_list(&mut codes);
+        assert_eq!(parse(&tests[1].decrypted), codes);
+    }
+
+    #[test]
+    fn test_auto_encrypt_code() {
+        #[rustfmt::skip]
+        let raw = [
+            (0x4041_0000, 0x0002_0001), (0xbeef_c0de, 0x0000_0000), // serial write
+            (0x2043_afcc, 0x2411_ffff),
+            (0xbeef_c0df, 0x1234_5678), (0x0000_0001, 0x0000_0000), // seed line
+            (0x2096_f5b8, 0x0000_00be),
+        ];
+        assert_eq!(
+            &raw[..],
+            &Codebreaker::new().auto_encrypt_code_list(&raw, Scheme::Raw)[..]
+        );
+        for &target in [Scheme::V1, Scheme::V7].iter() {
+            let mut cb = Codebreaker::new();
+            let encrypted = cb.auto_encrypt_code_list(&raw, target);
+
+            let mut cb = Codebreaker::new();
+            let decrypted: Vec<_> = encrypted.iter().map(|c| cb.auto_decrypt_code(c.0, c.1)).collect();
+            if target == Scheme::V7 {
+                assert_eq!((0xbeef_c0de, 0), decrypted[0]);
+                assert_eq!(&raw[..], &decrypted[1..]);
+            } else {
+                assert_eq!(&raw[..], &decrypted[..]);
+            }
+        }
+    }
+
     struct AutoTest {
         input: Vec<&'static str>,
         output: Vec<&'static str>,
@@ -457,6 +1564,21 @@ mod tests {
                     "2096F5B8 000000BE",
                 ],
             },
+            AutoTest {
+                // raw beefcode, v7 encrypted
+                input: vec![
+                    "BEEFC0DE 00000000",
+                    "D08F3A49 00078A53",
+                    "3818DDE5 E72B2B16",
+                    "973E0B2A A7D4AF10",
+                ],
+                output: vec![
+                    "BEEFC0DE 00000000",
+                    "9029BEAC 0C0A9225",
+                    "201F6024 00000000",
+                    "2096F5B8 000000BE",
+                ],
+            },
             AutoTest {
                 // v1 and v7 encrypted
                 input: vec![
@@ -487,6 +1609,19 @@ mod tests {
                     "2096F5B8 000000BE",
                 ],
             },
+            AutoTest {
+                // raw beefcode, decrypted v7
+                input: vec![
+                    "BEEFC0DE 00000000",
+                    "2096F5B8 000000BE",
+                    "1043AFD0 0000FFFF",
+                ],
+                output: vec![
+                    "BEEFC0DE 00000000",
+                    "2096F5B8 000000BE",
+                    "1043AFD0 0000FFFF",
+                ],
+            },
         ]
     }
 
@@ -502,6 +1637,257 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_raw_beefcode_roundtrip() {
+        let t = &auto_tests()[3];
+        let mut cb = Codebreaker::new();
+        for line in t.input.iter() {
+            let code = code::parse(line);
+            cb.auto_decrypt_code(code.0, code.1);
+        }
+        assert!(cb.raw_beefcode());
+
+        let mut cb = Codebreaker::new_raw_beefcode();
+        for (i, line) in t.output.iter().enumerate() {
+            let code = code::parse(line);
+            assert_eq!(t.input[i], code::format(cb.encrypt_code(code.0, code.1)));
+        }
+    }
+
+    #[test]
+    fn test_pin_scheme() {
+        let t = &auto_tests()[1];
+        let mut cb = Codebreaker::new();
+        cb.pin_scheme(Scheme::V1);
+        for (i, line) in t.input.iter().enumerate() {
+            let code = code::parse(line);
+            let result = cb.try_auto_decrypt_code(code.0, code.1).unwrap();
+            assert_eq!(t.output[i], code::format(result));
+        }
+    }
+
+    #[test]
+    fn test_pin_scheme_mismatch() {
+        #[rustfmt::skip]
+        let tests = [
+            (Scheme::Raw, "2A973DBD 00000000", Scheme::V1),
+            (Scheme::Raw, "BEEFC0DE 00000000", Scheme::V7),
+            (Scheme::V1, "201F6024 00000000", Scheme::Raw),
+            (Scheme::V1, "B4336FA9 4DFEFB79", Scheme::V7),
+            (Scheme::V1, "BEEFC0DE 00000000", Scheme::V7),
+            (Scheme::V7, "2043AFCC 2411FFFF", Scheme::Raw),
+            (Scheme::V7, "2AFF014C 2411FFFF", Scheme::V1),
+        ];
+        for t in tests.iter() {
+            let mut cb = Codebreaker::new();
+            cb.pin_scheme(t.0);
+            let code = code::parse(t.1);
+            let err = Error::SchemeMismatch {
+                pinned: t.0,
+                found: t.2,
+            };
+            assert_eq!(Err(err), cb.try_auto_decrypt_code(code.0, code.1));
+            // Infallible version leaves the code alone
+            assert_eq!(code, cb.auto_decrypt_code(code.0, code.1));
+        }
+    }
+
+    #[test]
+    fn test_pin_scheme_v7() {
+        // V7 codes, with or without the leading beefcode encrypted with V1
+        let t = &auto_tests()[2];
+        for skip in 0..2 {
+            let mut cb = Codebreaker::with_scheme(Scheme::V7);
+            for (i, line) in t.input.iter().enumerate().skip(skip) {
+                let code = code::parse(line);
+                let result = cb.try_auto_decrypt_code(code.0, code.1).unwrap();
+                assert_eq!(t.output[i], code::format(result));
+            }
+        }
+    }
+
+    #[test]
+    fn test_mixed_directions() {
+        for t in tests().iter() {
+            let mut cb = t.cb;
+            for (i, (d, e)) in t.decrypted.iter().zip(t.encrypted.iter()).enumerate() {
+                let (d, e) = (code::parse(d), code::parse(e));
+                if i % 2 == 0 {
+                    assert_eq!(e, cb.crypt_code(Direction::Encrypt, d.0, d.1), "line {}", i);
+                } else {
+                    assert_eq!(d, cb.crypt_code(Direction::Decrypt, e.0, e.1), "line {}", i);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_expects_seed() {
+        #[rustfmt::skip]
+        let tests = [
+            (Codebreaker::new(), Direction::Encrypt),
+            (Codebreaker::new(), Direction::Decrypt),
+            (Codebreaker::new_v7(), Direction::Encrypt),
+            (Codebreaker::new_v7(), Direction::Decrypt),
+        ];
+        for t in tests.iter() {
+            let (mut cb, direction) = *t;
+            let mut enc = t.0;
+            let mut code = |cb: &mut Codebreaker, addr, val| match direction {
+                Direction::Encrypt => cb.crypt_code(direction, addr, val),
+                Direction::Decrypt => {
+                    let (addr, val) = enc.encrypt_code(addr, val);
+                    cb.crypt_code(direction, addr, val)
+                }
+            };
+            assert!(!cb.expects_seed());
+            code(&mut cb, 0xbeef_c0df, 0xb16b_00b5);
+            assert!(cb.expects_seed());
+            code(&mut cb, 0x0123_4567, 0x89ab_cdef);
+            assert!(!cb.expects_seed());
+            code(&mut cb, 0x2096_f5b8, 0x0000_00be);
+            assert!(!cb.expects_seed());
+        }
+    }
+
+    #[test]
+    fn test_code() {
+        let code = Code::new(0x2043_afcc, 0x2411_ffff);
+        assert_eq!(Ok(code), "2043AFCC 2411FFFF".parse());
+        assert_eq!(Ok(code), " 2043afcc\t2411ffff\r\n".parse());
+        assert_eq!("2043AFCC 2411FFFF", format!("{}", code));
+        assert_eq!("0000000A 00000000", format!("{}", Code::new(0xa, 0)));
+        assert_eq!(code, Code::from((0x2043_afcc, 0x2411_ffff)));
+        assert_eq!((0x2043_afcc, 0x2411_ffff), <(u32, u32)>::from(code));
+
+        #[rustfmt::skip]
+        let lenient = [
+            "2043AFCC-2411FFFF", "2043afcc2411ffff", "0x2043AFCC 0X2411FFFF",
+            "0x2043afcc-0x2411ffff", "0x2043AFCC2411FFFF", "  2043AFCC \t\t 2411FFFF  ",
+        ];
+        for s in lenient.iter() {
+            assert_eq!(Ok(code), s.parse(), "{:?}", s);
+        }
+
+        let invalid_token = |token: &str| Err(ParseCodeError::InvalidToken(token.into()));
+        #[rustfmt::skip]
+        let invalid = [
+            ("", Err(ParseCodeError::Empty)),
+            (" \t", Err(ParseCodeError::Empty)),
+            ("2043AFCC", Err(ParseCodeError::MissingValue)),
+            ("2043AFCC-", Err(ParseCodeError::MissingValue)),
+            ("0x2043AFCC", Err(ParseCodeError::MissingValue)),
+            ("2043AFCC 2411FFF", invalid_token("2411FFF")),
+            ("2043AFCC 2411FFFFF", invalid_token("2411FFFFF")),
+            ("2043AFCC-2411FFFF-0", invalid_token("2411FFFF-0")),
+            ("2043AFCG 2411FFFF", invalid_token("2043AFCG")),
+            ("+043AFCC 2411FFFF", invalid_token("+043AFCC")),
+            ("0x 2411FFFF", invalid_token("0x")),
+            ("2043AFCC2411FFF", invalid_token("2043AFCC2411FFF")),
+            ("Inf HP", invalid_token("Inf")),
+            ("2043AFCC 2411FFFF 0", Err(ParseCodeError::TrailingToken("0".into()))),
+        ];
+        for t in invalid.iter() {
+            assert_eq!(t.1, t.0.parse::<Code>(), "{:?}", t.0);
+        }
+        assert_eq!(
+            "\"Inf\" is not an 8-digit hex number",
+            format!("{}", "Inf HP".parse::<Code>().unwrap_err())
+        );
+    }
+
+    #[test]
+    fn test_code_api() {
+        for t in tests().iter() {
+            let (mut enc, mut dec) = (t.cb, t.cb);
+            for (i, line) in t.decrypted.iter().enumerate() {
+                let code: Code = line.parse().unwrap();
+                let encrypted = enc.encrypt(code);
+                assert_eq!(t.encrypted[i], format!("{}", encrypted));
+                assert_eq!(code, dec.decrypt(encrypted));
+            }
+        }
+
+        for t in auto_tests().iter() {
+            let mut cb = Codebreaker::new();
+            for (i, line) in t.input.iter().enumerate() {
+                let code = cb.auto_decrypt(line.parse().unwrap());
+                assert_eq!(t.output[i], format!("{}", code));
+            }
+        }
+    }
+
+    #[test]
+    fn test_with_scheme() {
+        let t = &auto_tests()[1];
+        for cb in [Codebreaker::new_v1(), Codebreaker::with_scheme(Scheme::V1)].iter_mut() {
+            assert_eq!(Scheme::V1, cb.scheme());
+            for (i, line) in t.input.iter().enumerate() {
+                let code = code::parse(line);
+                let result = cb.try_auto_decrypt_code(code.0, code.1).unwrap();
+                assert_eq!(t.output[i], code::format(result));
+            }
+        }
+
+        for cb in [Codebreaker::new_raw(), Codebreaker::with_scheme(Scheme::Raw)].iter_mut() {
+            assert_eq!(Scheme::Raw, cb.scheme());
+            assert_eq!((0xBEEFC0DE, 0x00000000), cb.encrypt_code(0xBEEFC0DE, 0x00000000));
+            assert_eq!((0x2043AFCC, 0x2411FFFF), cb.encrypt_code(0x2043AFCC, 0x2411FFFF));
+            assert!(!cb.beefcode_seen());
+        }
+
+        let mut cb = Codebreaker::with_scheme(Scheme::V7);
+        let mut v7 = Codebreaker::new_v7();
+        assert_eq!(Scheme::V7, cb.scheme());
+        assert_eq!(
+            v7.encrypt_code(0x2043AFCC, 0x2411FFFF),
+            cb.encrypt_code(0x2043AFCC, 0x2411FFFF)
+        );
+    }
+
+    #[test]
+    fn test_pin_scheme_no_switch() {
+        let mut cb = Codebreaker::new();
+        cb.pin_scheme(Scheme::V1);
+        assert_eq!((0xB4336FA9, 0x4DFEFB79), cb.encrypt_code(0xBEEFC0DE, 0x00000000));
+        assert_eq!((0x2AFF014C, 0x2411FFFF), cb.encrypt_code(0x2043AFCC, 0x2411FFFF));
+
+        let mut cb = Codebreaker::new();
+        cb.pin_scheme(Scheme::Raw);
+        assert_eq!((0x2043AFCC, 0x2411FFFF), cb.encrypt_code(0x2043AFCC, 0x2411FFFF));
+        assert_eq!((0x2043AFCC, 0x2411FFFF), cb.decrypt_code(0x2043AFCC, 0x2411FFFF));
+
+        let mut cb = Codebreaker::new();
+        cb.pin_scheme(Scheme::V7);
+        assert_eq!(
+            (0x9029BEAC, 0x0C0A9225),
+            cb.try_auto_decrypt_code(0xD08F3A49, 0x00078A53).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_auto_decrypt_code_detailed() {
+        #[rustfmt::skip]
+        let schemes = [
+            vec![Scheme::Raw, Scheme::Raw, Scheme::Raw],
+            vec![Scheme::V1, Scheme::V1, Scheme::V1],
+            vec![Scheme::V1, Scheme::V7, Scheme::V7, Scheme::V7],
+            vec![Scheme::Raw, Scheme::V7, Scheme::V7, Scheme::V7],
+            vec![Scheme::V1, Scheme::V1, Scheme::V1, Scheme::V7],
+            vec![Scheme::Raw, Scheme::V1, Scheme::V1, Scheme::V7],
+        ];
+        for (t, schemes) in auto_tests().iter().zip(schemes.iter()) {
+            let mut cb = Codebreaker::new();
+            for (i, line) in t.input.iter().enumerate() {
+                let code = code::parse(line);
+                let result = cb.auto_decrypt_code_detailed(code.0, code.1);
+                assert_eq!(code, result.original);
+                assert_eq!(t.output[i], code::format(result.decrypted));
+                assert_eq!(schemes[i], result.scheme);
+            }
+        }
+    }
+
     #[test]
     fn test_auto_decrypt_code_mut() {
         for t in auto_tests().iter_mut() {
@@ -513,6 +1899,108 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_encryption_change() {
+        #[rustfmt::skip]
+        let tests = [
+            (
+                // change to V1
+                vec![
+                    "BEEFC0DE 00000000",
+                    "2043AFCC 2411FFFF",
+                    "FFFFFFFF 00010000",
+                    "201F6024 00000000",
+                ],
+                vec![
+                    "B4336FA9 4DFEFB79",
+                    "397951B0 41569FE0",
+                    "244CA2B3 116AFBB4",
+                    "2A973DBD 00000000",
+                ],
+            ),
+            (
+                // new key, then change to RAW and back to V7 by a beefcode
+                vec![
+                    "BEEFC0DE 00000000",
+                    "2043AFCC 2411FFFF",
+                    "FFFFFFFF 00071234",
+                    "201F6024 00000000",
+                    "FFFFFFFF 00000000",
+                    "201F6024 00000000",
+                    "BEEFC0DE 00000000",
+                    "2043AFCC 2411FFFF",
+                ],
+                vec![
+                    "B4336FA9 4DFEFB79",
+                    "397951B0 41569FE0",
+                    "26BB051A BE931204",
+                    "050105F2 04A66A4A",
+                    "F180D529 09F930A5",
+                    "2A973DBD 00000000",
+                    "B4336FA9 4DFEFB79",
+                    "442BCA67 A1E49D6A",
+                ],
+            ),
+        ];
+        for (decrypted, encrypted) in tests.iter() {
+            let (mut enc, mut dec) = (Codebreaker::new(), Codebreaker::new());
+            for (d, e) in decrypted.iter().zip(encrypted.iter()) {
+                let code = code::parse(d);
+                assert_eq!(*e, code::format(enc.auto_encrypt_code(code.0, code.1)));
+                let code = code::parse(e);
+                assert_eq!(*d, code::format(dec.auto_decrypt_code(code.0, code.1)));
+            }
+        }
+
+        // A pinned processor ignores the directive
+        let mut cb = Codebreaker::with_scheme(Scheme::V7);
+        let directive = Codebreaker::new_v7().encrypt_code(0xffff_ffff, 0x0001_0000);
+        cb.auto_decrypt_code(directive.0, directive.1);
+        assert_eq!(Scheme::V7, cb.scheme());
+    }
+
+    #[test]
+    fn test_is_plausible_raw() {
+        assert!(is_plausible_raw((0x2043_afcc, 0x2411_ffff)));
+        assert!(is_plausible_raw((0xd043_afcc, 0x0000_0001)));
+        assert!(is_plausible_raw((0xd043_afcd, 0x0100_0001)));
+        assert!(is_plausible_raw((0xbeef_c0de, 0x0000_0000)));
+        assert!(!is_plausible_raw((0xd08f_3a49, 0x0007_8a53)));
+        assert!(!is_plausible_raw((0x0043_afcc, 0x0000_0100)));
+        assert!(!is_plausible_raw((0x2a43_afcc, 0x2411_ffff)));
+        assert!(!is_plausible_raw((0x8043_afcc, 0x0000_0000)));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_code() {
+        let code = Code::new(0x2043_afcc, 0x2411_ffff);
+        let json = serde_json::to_string(&code).unwrap();
+        assert_eq!(code, serde_json::from_str(&json).unwrap());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_codebreaker() {
+        // Save the processor after each code and decrypt the rest with the
+        // restored one
+        for t in auto_tests().iter() {
+            for split in 0..=t.input.len() {
+                let mut cb = Codebreaker::new();
+                for line in t.input[..split].iter() {
+                    let code = code::parse(line);
+                    cb.auto_decrypt_code(code.0, code.1);
+                }
+                let json = serde_json::to_string(&cb).unwrap();
+                let mut cb: Codebreaker = serde_json::from_str(&json).unwrap();
+                for (i, line) in t.input.iter().enumerate().skip(split) {
+                    let code = code::parse(line);
+                    assert_eq!(t.output[i], code::format(cb.auto_decrypt_code(code.0, code.1)));
+                }
+            }
+        }
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,287 @@
+//! Structural checks of decrypted code lists.
+//!
+//! [`lint`] looks for codes that cannot work as intended: multi-line codes
+//! cut short, addresses outside of EE RAM, misaligned accesses, values too
+//! large for their width, and code types the firmware does not know. Each
+//! [`Finding`] has a [`Severity`], so tools can reject broken lists while
+//! only flagging dubious ones.
+//!
+//! # Example
+//! ```
+//! use codebreaker::firmware::Profile;
+//! use codebreaker::lint::{lint, Severity};
+//!
+//! let codes = [(0x2043AFCC, 0x2411FFFF), (0x2043AFCE, 0x00000000), (0x4043AFCC, 0x00030001)];
+//! let findings = lint(&codes, &Profile::V7);
+//! assert_eq!(2, findings.len());
+//! assert_eq!(Severity::Error, findings[0].severity());
+//! assert_eq!("line 2: error: address 0043AFCE is not aligned for 32-bit access", format!("{}", findings[0]));
+//! assert_eq!("line 3: error: serial write is missing 1 of 2 lines", format!("{}", findings[1]));
+//! ```
+
+use crate::cb7::is_beefcode;
+use crate::firmware::Profile;
+use crate::op::{Addr, Width};
+use crate::std_alloc::Vec;
+use crate::Error;
+
+use core::fmt;
+
+/// How serious a problem is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// The code probably works, but not as its author intended
+    Warning,
+    /// The code cannot work
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Warning => f.write_str("warning"),
+            Self::Error => f.write_str("error"),
+        }
+    }
+}
+
+/// A problem of a code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Problem {
+    /// A multi-line code is cut short by the end of the list.
+    Truncated {
+        /// The name of the code type
+        name: &'static str,
+        /// The number of lines of the code
+        lines: usize,
+        /// The number of lines present
+        present: usize,
+    },
+    /// An address or value is invalid for the access, see [`Error`].
+    Invalid(Error),
+    /// The firmware does not know the code type.
+    UnknownCodeType(u8),
+}
+
+impl Problem {
+    /// Returns how serious the problem is.
+    ///
+    /// Values too large for their width are only warnings, as the firmware
+    /// ignores the excess bits, and so are unknown code types.
+    pub const fn severity(&self) -> Severity {
+        match self {
+            Self::Invalid(Error::ValueTooLarge { .. }) | Self::UnknownCodeType(_) => Severity::Warning,
+            _ => Severity::Error,
+        }
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Truncated { name, lines, present } => {
+                write!(f, "{} is missing {} of {} lines", name, lines - present, lines)
+            }
+            Self::Invalid(err) => write!(f, "{}", err),
+            Self::UnknownCodeType(cmd) => write!(f, "unknown code type {:X}", cmd),
+        }
+    }
+}
+
+/// A problem found in a list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Finding {
+    /// Index of the first line of the code
+    pub line: usize,
+    /// What is wrong with the code
+    pub problem: Problem,
+}
+
+impl Finding {
+    /// Returns how serious the problem is.
+    pub const fn severity(&self) -> Severity {
+        self.problem.severity()
+    }
+}
+
+/// Formats the finding as `line N: severity: problem`. Lines are counted
+/// from 1.
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}: {}", self.line + 1, self.severity(), self.problem)
+    }
+}
+
+/// Checks a list of decrypted codes, with the code types and line counts of
+/// a firmware.
+///
+/// Returns the findings in order of lines. Later lines of a multi-line code
+/// are not checked on their own.
+pub fn lint(codes: &[(u32, u32)], profile: &Profile) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let mut line = 0;
+    while line < codes.len() {
+        let (addr, val) = codes[line];
+        let lines = profile.lines(addr);
+        let mut report = |problem| findings.push(Finding { line, problem });
+
+        let present = lines.min(codes.len() - line);
+        if present < lines {
+            let name = profile.code_type(addr).map_or("code", |ty| ty.name);
+            report(Problem::Truncated { name, lines, present });
+        } else if is_beefcode(addr) {
+            // Nothing more to check
+        } else if profile.code_type(addr).is_none() {
+            report(Problem::UnknownCodeType((addr >> 28) as u8));
+        } else {
+            let next = codes.get(line + 1).copied();
+            for err in access_errors(addr, val, next) {
+                report(Problem::Invalid(err));
+            }
+        }
+        line += lines;
+    }
+    findings
+}
+
+// Returns the problems of the addresses and values a code accesses. The
+// layout of the code types is that of CB v7.
+fn access_errors(addr: u32, val: u32, next: Option<(u32, u32)>) -> Vec<Error> {
+    let mut errors = Vec::new();
+    let mut check = |at: u32, width: Width| {
+        if at > Addr::MAX {
+            errors.push(Error::AddressOutOfRange(at));
+        } else if at & (width.bytes() - 1) != 0 {
+            errors.push(Error::Misaligned { addr: at, width });
+        }
+    };
+    // The address of most code types sits in the low 28 bits
+    let at = addr & 0x0fff_ffff;
+    match addr >> 28 {
+        0 => check(at, Width::Byte),
+        1 => check(at, Width::Half),
+        2 | 4 | 0xc => check(at, Width::Word),
+        3 => match addr >> 20 & 0xf {
+            0 | 1 => check(val, Width::Byte),
+            2 | 3 => check(val, Width::Half),
+            _ => check(val, Width::Word),
+        },
+        5 => {
+            check(at, Width::Byte);
+            if let Some((dest, _)) = next {
+                check(dest, Width::Byte);
+            }
+        }
+        6 => check(at, Width::Word),
+        7 => match val >> 20 & 0xf {
+            1 | 3 | 5 => check(at, Width::Half),
+            _ => check(at, Width::Byte),
+        },
+        0xd if val & 0x0100_0000 != 0 => check(at, Width::Byte),
+        0xd => check(at, Width::Half),
+        0xe if addr & 0x0100_0000 != 0 => check(val & 0x0fff_ffff, Width::Byte),
+        0xe => check(val & 0x0fff_ffff, Width::Half),
+        _ => {}
+    }
+    match addr >> 28 {
+        0 if val > 0xff => errors.push(Error::ValueTooLarge {
+            val,
+            width: Width::Byte,
+        }),
+        1 if val > 0xffff => errors.push(Error::ValueTooLarge {
+            val,
+            width: Width::Half,
+        }),
+        _ => {}
+    }
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn problems(codes: &[(u32, u32)]) -> Vec<(usize, Problem)> {
+        lint(codes, &Profile::V7)
+            .into_iter()
+            .map(|f| (f.line, f.problem))
+            .collect()
+    }
+
+    #[test]
+    fn test_lint() {
+        let codes = [
+            (0x2043_afcc, 0x2411_ffff),
+            (0x1043_afcd, 0x0001_0000),
+            (0x0a00_0000, 0x0000_0001),
+            (0x3040_0000, 0x0043_afce),
+            (0x0000_0001, 0x0000_0000),
+            (0x8000_0000, 0x0000_0000),
+            (0xbeef_c0df, 0x0000_0000),
+            (0x0000_0000, 0x0000_0000),
+            (0xe101_0001, 0x0200_0000),
+        ];
+        assert_eq!(
+            vec![
+                (
+                    1,
+                    Problem::Invalid(Error::Misaligned {
+                        addr: 0x0043_afcd,
+                        width: Width::Half
+                    })
+                ),
+                (
+                    1,
+                    Problem::Invalid(Error::ValueTooLarge {
+                        val: 0x0001_0000,
+                        width: Width::Half
+                    })
+                ),
+                (2, Problem::Invalid(Error::AddressOutOfRange(0x0a00_0000))),
+                (
+                    3,
+                    Problem::Invalid(Error::Misaligned {
+                        addr: 0x0043_afce,
+                        width: Width::Word
+                    })
+                ),
+                (5, Problem::UnknownCodeType(8)),
+                (8, Problem::Invalid(Error::AddressOutOfRange(0x0200_0000))),
+            ],
+            problems(&codes)
+        );
+        assert_eq!(Vec::<(usize, Problem)>::new(), problems(&[]));
+    }
+
+    #[test]
+    fn test_lint_truncated() {
+        assert_eq!(
+            vec![(
+                1,
+                Problem::Truncated {
+                    name: "copy bytes",
+                    lines: 2,
+                    present: 1
+                }
+            )],
+            problems(&[(0x2043_afcc, 0x2411_ffff), (0x5043_afcc, 0x0000_0010)])
+        );
+        let findings = lint(&[(0xbeef_c0df, 0x0000_0000)], &Profile::V7);
+        assert_eq!(Severity::Error, findings[0].severity());
+        assert_eq!(
+            "line 1: error: encryption key is missing 1 of 2 lines",
+            format!("{}", findings[0])
+        );
+    }
+
+    #[test]
+    fn test_severity() {
+        assert_eq!(Severity::Warning, Problem::UnknownCodeType(8).severity());
+        assert_eq!(
+            Severity::Error,
+            Problem::Invalid(Error::AddressOutOfRange(0x0200_0000)).severity()
+        );
+        assert!(Severity::Warning < Severity::Error);
+    }
+}
@@ -0,0 +1,271 @@
+//! Lists of decrypted codes that are always well-formed.
+//!
+//! A [`CodeList`] holds whole codes, never parts of them: a multi-line code
+//! is added and removed with all its lines. A beefcode may only come first,
+//! and there is at most one. Features built on top of a list can rely on
+//! that instead of checking again.
+//!
+//! # Example
+//! ```
+//! use codebreaker::list::{CodeList, ListError};
+//! use codebreaker::Code;
+//!
+//! let mut list = CodeList::new();
+//! list.push(&[Code::new(0x2043AFCC, 0x2411FFFF)]).unwrap();
+//! list.push(&[Code::new(0x4043AFD0, 0x00020001), Code::new(0x00000063, 0)]).unwrap();
+//! assert_eq!(2, list.len());
+//! assert_eq!(3, list.lines().len());
+//!
+//! assert_eq!(
+//!     Err(ListError::Incomplete { expected: 2, found: 1 }),
+//!     list.push(&[Code::new(0x4043AFE0, 0x00020001)])
+//! );
+//! assert_eq!(
+//!     Err(ListError::MisplacedBeefcode),
+//!     list.push(&[Code::new(0xBEEFC0DE, 0)])
+//! );
+//! ```
+
+use crate::cb7::is_beefcode;
+use crate::firmware::Profile;
+use crate::std_alloc::Vec;
+use crate::Code;
+
+use core::fmt;
+
+/// The error returned for changes that would leave a list malformed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ListError {
+    /// The number of lines does not match the code type.
+    Incomplete {
+        /// The number of lines of the code type
+        expected: usize,
+        /// The number of lines passed in
+        found: usize,
+    },
+    /// A beefcode would not be the first code, or a second one.
+    MisplacedBeefcode,
+    /// The index is past the end of the list.
+    OutOfRange(usize),
+}
+
+impl fmt::Display for ListError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Incomplete { expected, found } => write!(f, "code takes {} lines, not {}", expected, found),
+            Self::MisplacedBeefcode => f.write_str("a beefcode may only be the first code"),
+            Self::OutOfRange(index) => write!(f, "no code at index {}", index),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ListError {}
+
+/// A list of complete decrypted codes with at most one, leading beefcode.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct CodeList {
+    lines: Vec<Code>,
+}
+
+impl CodeList {
+    /// Returns an empty list.
+    pub const fn new() -> Self {
+        Self { lines: Vec::new() }
+    }
+
+    /// Returns a list of the given lines if they are well-formed.
+    ///
+    /// # Example
+    /// ```
+    /// use codebreaker::list::{CodeList, ListError};
+    /// use codebreaker::Code;
+    ///
+    /// let lines = vec![Code::new(0xBEEFC0DF, 0), Code::new(0x12345678, 0), Code::new(0x2043AFCC, 0)];
+    /// assert_eq!(2, CodeList::from_lines(lines).unwrap().len());
+    /// assert_eq!(
+    ///     Err(ListError::Incomplete { expected: 2, found: 1 }),
+    ///     CodeList::from_lines(vec![Code::new(0x50000000, 0)])
+    /// );
+    /// ```
+    pub fn from_lines(lines: Vec<Code>) -> Result<Self, ListError> {
+        let mut pos = 0;
+        while pos < lines.len() {
+            let n = Profile::V7.lines(lines[pos].addr);
+            if is_beefcode(lines[pos].addr) && pos > 0 {
+                return Err(ListError::MisplacedBeefcode);
+            }
+            if pos + n > lines.len() {
+                return Err(ListError::Incomplete {
+                    expected: n,
+                    found: lines.len() - pos,
+                });
+            }
+            pos += n;
+        }
+        Ok(Self { lines })
+    }
+
+    /// Returns all lines of all codes.
+    pub fn lines(&self) -> &[Code] {
+        &self.lines
+    }
+
+    /// Returns the number of codes, not lines.
+    pub fn len(&self) -> usize {
+        self.iter().count()
+    }
+
+    /// Returns true if the list holds no codes.
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    /// Returns true if the list starts with a beefcode.
+    pub fn has_beefcode(&self) -> bool {
+        self.lines.first().is_some_and(|code| is_beefcode(code.addr))
+    }
+
+    /// Returns an iterator over the codes, each with all its lines.
+    pub fn iter(&self) -> Iter<'_> {
+        Iter { rest: &self.lines }
+    }
+
+    /// Returns the lines of the code at `index`.
+    pub fn get(&self, index: usize) -> Option<&[Code]> {
+        self.iter().nth(index)
+    }
+
+    /// Appends a code given all its lines.
+    pub fn push(&mut self, code: &[Code]) -> Result<(), ListError> {
+        self.insert(self.len(), code)
+    }
+
+    /// Inserts a code given all its lines, so that it becomes the code at
+    /// `index`.
+    pub fn insert(&mut self, index: usize, code: &[Code]) -> Result<(), ListError> {
+        let pos = self.line_of(index).ok_or(ListError::OutOfRange(index))?;
+        let first = code.first().ok_or(ListError::Incomplete { expected: 1, found: 0 })?;
+        let expected = Profile::V7.lines(first.addr);
+        if code.len() != expected {
+            return Err(ListError::Incomplete {
+                expected,
+                found: code.len(),
+            });
+        }
+        // Nothing may come before or in place of a beefcode
+        if (is_beefcode(first.addr) && pos > 0) || (self.has_beefcode() && pos == 0) {
+            return Err(ListError::MisplacedBeefcode);
+        }
+        self.lines.splice(pos..pos, code.iter().copied());
+        Ok(())
+    }
+
+    /// Removes the code at `index` and returns its lines.
+    pub fn remove(&mut self, index: usize) -> Result<Vec<Code>, ListError> {
+        let n = self.get(index).ok_or(ListError::OutOfRange(index))?.len();
+        let pos = self.line_of(index).ok_or(ListError::OutOfRange(index))?;
+        Ok(self.lines.drain(pos..pos + n).collect())
+    }
+
+    // Returns the index of the first line of the code at `index`, which may
+    // be one past the last code.
+    fn line_of(&self, index: usize) -> Option<usize> {
+        let mut pos = 0;
+        let mut codes = self.iter();
+        for _ in 0..index {
+            pos += codes.next()?.len();
+        }
+        Some(pos)
+    }
+
+    /// Returns the lines of the list, giving up the guarantees.
+    pub fn into_lines(self) -> Vec<Code> {
+        self.lines
+    }
+}
+
+impl<'a> IntoIterator for &'a CodeList {
+    type Item = &'a [Code];
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// An iterator over the codes of a [`CodeList`].
+#[derive(Debug, Clone)]
+pub struct Iter<'a> {
+    rest: &'a [Code],
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = &'a [Code];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let first = self.rest.first()?;
+        let (code, rest) = self.rest.split_at(Profile::V7.lines(first.addr));
+        self.rest = rest;
+        Some(code)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BEEFCODE: [Code; 1] = [Code::new(0xbeef_c0de, 0)];
+    const WRITE: [Code; 1] = [Code::new(0x2043_afcc, 0x2411_ffff)];
+    const SERIAL: [Code; 2] = [Code::new(0x4043_afd0, 0x0002_0001), Code::new(0x0000_0063, 0)];
+
+    #[test]
+    fn test_insert_remove() {
+        let mut list = CodeList::new();
+        assert!(list.is_empty());
+        list.push(&SERIAL).unwrap();
+        list.insert(0, &WRITE).unwrap();
+        list.insert(0, &BEEFCODE).unwrap();
+        assert!(list.has_beefcode());
+        assert_eq!(3, list.len());
+        assert_eq!(&SERIAL[..], list.get(2).unwrap());
+        assert_eq!(None, list.get(3));
+
+        assert_eq!(Err(ListError::MisplacedBeefcode), list.insert(0, &WRITE));
+        assert_eq!(Err(ListError::MisplacedBeefcode), list.insert(0, &BEEFCODE));
+        assert_eq!(Err(ListError::OutOfRange(4)), list.insert(4, &WRITE));
+        assert_eq!(
+            Err(ListError::Incomplete { expected: 2, found: 1 }),
+            list.insert(1, &SERIAL[..1])
+        );
+        assert_eq!(Err(ListError::Incomplete { expected: 1, found: 0 }), list.push(&[]));
+
+        assert_eq!(Ok(SERIAL.to_vec()), list.remove(2));
+        assert_eq!(Err(ListError::OutOfRange(2)), list.remove(2));
+        assert_eq!(Ok(BEEFCODE.to_vec()), list.remove(0));
+        assert_eq!(WRITE.to_vec(), list.into_lines());
+    }
+
+    #[test]
+    fn test_from_lines() {
+        let lines: Vec<Code> = BEEFCODE
+            .iter()
+            .chain(SERIAL.iter())
+            .chain(WRITE.iter())
+            .copied()
+            .collect();
+        let list = CodeList::from_lines(lines.clone()).unwrap();
+        assert_eq!(&lines[..], list.lines());
+        let codes: Vec<_> = list.iter().collect();
+        assert_eq!(vec![&BEEFCODE[..], &SERIAL[..], &WRITE[..]], codes);
+
+        let lines: Vec<Code> = WRITE.iter().chain(BEEFCODE.iter()).copied().collect();
+        assert_eq!(Err(ListError::MisplacedBeefcode), CodeList::from_lines(lines));
+        assert_eq!(
+            Err(ListError::Incomplete { expected: 2, found: 1 }),
+            CodeList::from_lines(SERIAL[..1].to_vec())
+        );
+        assert_eq!(Ok(CodeList::new()), CodeList::from_lines(vec![]));
+    }
+}
@@ -0,0 +1,166 @@
+//! The processor as an explicit state machine.
+//!
+//! [`step`] takes the state of a processor and one input, and returns the
+//! next state, the output, and the events caused by the input. It does the
+//! same as the methods of [`Codebreaker`](../struct.Codebreaker.html), but
+//! with all state passed in and out, which suits model checkers, property
+//! tests, and ports to other languages.
+//!
+//! # Example
+//! ```
+//! use codebreaker::audit::Transition;
+//! use codebreaker::machine::{step, Event, Input};
+//! use codebreaker::{Code, Codebreaker, Scheme};
+//!
+//! let state = Codebreaker::new();
+//! let (state, output, events) = step(state, Input::AutoDecrypt(Code::new(0xB4336FA9, 0x4DFEFB79)));
+//! assert_eq!(Code::new(0xBEEFC0DE, 0x00000000), output);
+//! assert_eq!(
+//!     vec![
+//!         Event::Beefcode(output),
+//!         Event::SchemeChanged(Transition { from: Scheme::Raw, to: Scheme::V7 }),
+//!     ],
+//!     events
+//! );
+//!
+//! let (_, output, events) = step(state, Input::AutoDecrypt(Code::new(0x973E0B2A, 0xA7D4AF10)));
+//! assert_eq!(Code::new(0x2096F5B8, 0x000000BE), output);
+//! assert!(events.is_empty());
+//! ```
+
+use crate::audit::Transition;
+use crate::cb7::is_beefcode;
+use crate::std_alloc::Vec;
+use crate::{Code, Codebreaker, Error};
+
+/// The state of the machine, which is a processor.
+pub type State = Codebreaker;
+
+/// A code to process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Input {
+    /// Encrypt like
+    /// [`encrypt_code`](../struct.Codebreaker.html#method.encrypt_code)
+    Encrypt(Code),
+    /// Decrypt like
+    /// [`decrypt_code`](../struct.Codebreaker.html#method.decrypt_code)
+    Decrypt(Code),
+    /// Decrypt like
+    /// [`try_auto_decrypt_code`](../struct.Codebreaker.html#method.try_auto_decrypt_code)
+    AutoDecrypt(Code),
+}
+
+/// Something that happened while processing an input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Event {
+    /// The decrypted code is a beefcode that changed the key and seeds
+    Beefcode(Code),
+    /// The decrypted code was used as the extra seed of a `BEEFC0DF`
+    Seed(Code),
+    /// The scheme applied to the following codes changed
+    SchemeChanged(Transition),
+    /// The code contradicts the pinned scheme and was passed through
+    /// unchanged; the state is unchanged as well
+    Rejected(Error),
+}
+
+/// Processes one input and returns the next state, the output, and the
+/// events in the order they happened.
+pub fn step(mut state: State, input: Input) -> (State, Code, Vec<Event>) {
+    let from = state.scheme();
+    let seed = state.expects_seed();
+    let may_switch = state.may_switch_to_v7();
+    let mut events = Vec::new();
+
+    let (output, plain) = match input {
+        Input::Encrypt(code) => (state.encrypt(code), code),
+        Input::Decrypt(code) => {
+            let output = state.decrypt(code);
+            (output, output)
+        }
+        Input::AutoDecrypt(code) => match state.try_auto_decrypt_code(code.addr, code.val) {
+            Ok(output) => (output.into(), output.into()),
+            Err(err) => return (state, code, vec![Event::Rejected(err)]),
+        },
+    };
+
+    if seed {
+        events.push(Event::Seed(plain));
+    }
+    if is_beefcode(plain.addr) && may_switch {
+        events.push(Event::Beefcode(plain));
+    }
+    let to = state.scheme();
+    if from != to {
+        events.push(Event::SchemeChanged(Transition { from, to }));
+    }
+    (state, output, events)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Scheme;
+
+    #[test]
+    fn test_step_matches_processor() {
+        #[rustfmt::skip]
+        let codes = [
+            (0x2a97_3dbd, 0x0000_0000),
+            (0xb433_6fa9, 0x4dfe_fb79),
+            (0x973e_0b2a, 0xa7d4_af10),
+        ];
+        let mut cb = Codebreaker::new();
+        let mut state = Codebreaker::new();
+        for &(addr, val) in codes.iter() {
+            let expected = cb.auto_decrypt_code(addr, val);
+            let (next, output, _) = step(state, Input::AutoDecrypt(Code::new(addr, val)));
+            assert_eq!(Code::from(expected), output);
+            state = next;
+        }
+        assert_eq!(cb.scheme(), state.scheme());
+    }
+
+    #[test]
+    fn test_step_events() {
+        let state = Codebreaker::new();
+        let (state, output, events) = step(state, Input::Encrypt(Code::new(0xbeef_c0df, 0xb16b_00b5)));
+        assert_eq!(
+            vec![
+                Event::Beefcode(Code::new(0xbeef_c0df, 0xb16b_00b5)),
+                Event::SchemeChanged(Transition {
+                    from: Scheme::Raw,
+                    to: Scheme::V7
+                }),
+            ],
+            events
+        );
+
+        let mut dec = Codebreaker::new();
+        dec.decrypt(output);
+        let seed = Code::new(0x0123_4567, 0x89ab_cdef);
+        let (enc, output, events) = step(state, Input::Encrypt(seed));
+        assert_eq!(vec![Event::Seed(seed)], events);
+        let (_, output, events) = step(dec, Input::Decrypt(output));
+        assert_eq!(seed, output);
+        assert_eq!(vec![Event::Seed(seed)], events);
+        assert!(!enc.expects_seed());
+    }
+
+    #[test]
+    fn test_step_rejected() {
+        let state = Codebreaker::new_v1();
+        let code = Code::new(0xbeef_c0de, 0x0000_0000);
+        let (state, output, events) = step(state, Input::AutoDecrypt(code));
+        assert_eq!(code, output);
+        assert_eq!(
+            vec![Event::Rejected(Error::SchemeMismatch {
+                pinned: Scheme::V1,
+                found: Scheme::V7
+            })],
+            events
+        );
+        assert_eq!(Scheme::V1, state.scheme());
+    }
+}
@@ -0,0 +1,195 @@
+//! Mastercodes by game ID, for cheat files that need one.
+//!
+//! On ps2rd and Open PS2 Loader, the codes of a game only take effect if it
+//! has a mastercode, a hook code (command 9 or F) that tells the cheat engine
+//! where to run. Lists converted from other devices often lack it. A
+//! [`Mastercodes`] table maps game IDs to their mastercodes, so that
+//! [`ps2rd::write_with_mastercodes`](../ps2rd/fn.write_with_mastercodes.html)
+//! can add the right one to every game that has none.
+//!
+//! The crate ships no mastercodes, as which one works differs between
+//! releases of a game; users supply their own, e.g. with [`Mastercodes::parse`].
+//! PCSX2 applies pnach patches itself and needs no mastercode.
+//!
+//! # Example
+//! ```
+//! use codebreaker::game::Game;
+//! use codebreaker::mastercode::Mastercodes;
+//!
+//! let table = Mastercodes::parse("SLUS-20370 90269C18 0C09A6E6\n").unwrap();
+//! assert_eq!(Some(&[(0x90269C18, 0x0C09A6E6)][..]), table.get("SLUS_203.70;1"));
+//!
+//! let mut game = Game::new("Kingdom Hearts");
+//! assert!(table.attach("SLUS_203.70", &mut game));
+//! assert_eq!("Mastercode", game.cheats[0].name);
+//! ```
+
+use crate::game::{Cheat, Game};
+use crate::std_alloc::{BTreeMap, String, Vec};
+use crate::text::{split_comment, Error};
+use crate::Code;
+
+/// A table of mastercodes by game ID.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Mastercodes {
+    codes: BTreeMap<String, Vec<(u32, u32)>>,
+}
+
+impl Mastercodes {
+    /// Returns an empty table.
+    pub const fn new() -> Self {
+        Self { codes: BTreeMap::new() }
+    }
+
+    /// Reads a table with one code per line, after the ID of its game.
+    ///
+    /// ```text
+    /// # Kingdom Hearts
+    /// SLUS-20370 90269C18 0C09A6E6
+    /// ```
+    ///
+    /// Lines with the same ID add to the mastercode of that game, in order.
+    /// Blank lines, comments, and a leading byte order mark are ignored.
+    pub fn parse(text: &str) -> Result<Self, Error> {
+        let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+        let mut table = Self::new();
+        for (line, content) in text.lines().enumerate() {
+            let (body, _) = split_comment(content);
+            let body = body.trim();
+            if body.is_empty() {
+                continue;
+            }
+            let (id, code) = body.split_once(char::is_whitespace).unwrap_or((body, ""));
+            let code: Code = code.parse().map_err(|reason| Error { line, reason })?;
+            table.codes.entry(normalize_id(id)).or_default().push(code.into());
+        }
+        Ok(table)
+    }
+
+    /// Sets the mastercode of a game, returning the one it replaces.
+    pub fn insert(&mut self, id: &str, codes: Vec<(u32, u32)>) -> Option<Vec<(u32, u32)>> {
+        self.codes.insert(normalize_id(id), codes)
+    }
+
+    /// Returns the mastercode of a game.
+    pub fn get(&self, id: &str) -> Option<&[(u32, u32)]> {
+        self.codes.get(&normalize_id(id)).map(Vec::as_slice)
+    }
+
+    /// Returns the number of games in the table.
+    pub fn len(&self) -> usize {
+        self.codes.len()
+    }
+
+    /// Returns true if the table holds no mastercodes.
+    pub fn is_empty(&self) -> bool {
+        self.codes.is_empty()
+    }
+
+    /// Adds the mastercode of the game with the given ID as its first cheat,
+    /// named `Mastercode`, unless the game already has one.
+    ///
+    /// Returns true if a mastercode was added. The codes of the game must be
+    /// decrypted.
+    pub fn attach(&self, id: &str, game: &mut Game) -> bool {
+        match self.get(id) {
+            Some(codes) if !has_mastercode(game) => {
+                game.cheats.insert(0, Cheat::new("Mastercode", codes.into()));
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Returns a game ID in the form of the table, e.g. `SLUS-20370` for the
+/// boot file name `SLUS_203.70;1`.
+///
+/// The ID is uppercased, its file version and dots are removed, and its
+/// underscore becomes a dash.
+pub fn normalize_id(id: &str) -> String {
+    let id = id.split(';').next().unwrap_or("").trim();
+    id.chars()
+        .filter(|&c| c != '.')
+        .map(|c| if c == '_' { '-' } else { c.to_ascii_uppercase() })
+        .collect()
+}
+
+/// Returns true if a cheat holds a hook code, judging by its decrypted codes.
+pub fn is_mastercode(cheat: &Cheat) -> bool {
+    cheat.codes.iter().any(|&(addr, _)| matches!(addr >> 28, 0x9 | 0xf))
+}
+
+/// Returns true if any cheat of a game is a mastercode.
+pub fn has_mastercode(game: &Game) -> bool {
+    game.cheats.iter().any(is_mastercode)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ParseCodeError;
+
+    #[test]
+    fn test_normalize_id() {
+        assert_eq!("SLUS-20370", normalize_id("SLUS_203.70;1"));
+        assert_eq!("SLUS-20370", normalize_id("slus-20370"));
+        assert_eq!("SCES-50490", normalize_id(" SCES_504.90 "));
+        assert_eq!("", normalize_id(""));
+    }
+
+    #[test]
+    fn test_parse() {
+        let table = Mastercodes::parse(
+            "\u{feff}# Kingdom Hearts
+SLUS-20370 90269C18 0C09A6E6
+SLUS_203.70 F0100008 0010C240 // second line
+
+slus-20946 9025D0A8 0C093E0D
+",
+        )
+        .unwrap();
+        assert_eq!(2, table.len());
+        assert_eq!(
+            Some(&[(0x9026_9c18, 0x0c09_a6e6), (0xf010_0008, 0x0010_c240)][..]),
+            table.get("SLUS-20370")
+        );
+        assert_eq!(Some(&[(0x9025_d0a8, 0x0c09_3e0d)][..]), table.get("SLUS_209.46;1"));
+        assert_eq!(None, table.get("SLUS-20000"));
+        assert!(Mastercodes::parse("").unwrap().is_empty());
+
+        assert_eq!(
+            Err(Error {
+                line: 1,
+                reason: ParseCodeError::Empty
+            }),
+            Mastercodes::parse("SLUS-20370 90269C18 0C09A6E6\nSLUS-20370\n")
+        );
+        assert_eq!(
+            Err(Error {
+                line: 0,
+                reason: ParseCodeError::MissingValue
+            }),
+            Mastercodes::parse("SLUS-20370 90269C18\n")
+        );
+    }
+
+    #[test]
+    fn test_attach() {
+        let mut table = Mastercodes::new();
+        assert_eq!(None, table.insert("SLUS_203.70", vec![(0x9026_9c18, 0x0c09_a6e6)]));
+
+        let mut game = Game::new("Kingdom Hearts");
+        game.cheats.push(Cheat::new("Inf HP", vec![(0x2096_f5b8, 0x0000_00be)]));
+        assert!(!has_mastercode(&game));
+        assert!(!table.attach("SLUS-20000", &mut game));
+        assert!(table.attach("SLUS-20370", &mut game));
+        assert_eq!(
+            Cheat::new("Mastercode", vec![(0x9026_9c18, 0x0c09_a6e6)]),
+            game.cheats[0]
+        );
+        assert!(has_mastercode(&game));
+        assert!(!table.attach("SLUS-20370", &mut game));
+        assert_eq!(2, game.cheats.len());
+    }
+}
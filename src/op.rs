@@ -0,0 +1,538 @@
+//! Structured representation of decrypted codes.
+//!
+//! Raw codes are plain `(u32, u32)` pairs, which makes it easy to mix up
+//! addresses and values. The types in this module carry their meaning and
+//! enforce the invariants of CodeBreaker codes. An [`Op`] describes what a
+//! code does and can be encoded back into raw codes.
+//!
+//! # Example
+//! ```
+//! use codebreaker::op::{Addr, Value, Width};
+//!
+//! let addr = Addr::new(0x0043AFCC).unwrap();
+//! let val = Value::new(0xFFFF, Width::Half).unwrap();
+//! assert!(addr.is_aligned(val.width()));
+//! assert_eq!("0043AFCC", format!("{}", addr));
+//! assert_eq!("FFFF", format!("{}", val));
+//! ```
+
+use crate::std_alloc::Vec;
+use crate::Error;
+
+use core::convert::TryFrom;
+use core::fmt;
+
+/// The width of a memory access.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Width {
+    /// 8 bits
+    Byte,
+    /// 16 bits
+    Half,
+    /// 32 bits
+    Word,
+}
+
+impl Width {
+    /// Returns the number of bytes accessed.
+    pub const fn bytes(self) -> u32 {
+        match self {
+            Self::Byte => 1,
+            Self::Half => 2,
+            Self::Word => 4,
+        }
+    }
+
+    /// Returns the largest value of this width.
+    pub const fn max_value(self) -> u32 {
+        match self {
+            Self::Byte => 0xff,
+            Self::Half => 0xffff,
+            Self::Word => 0xffff_ffff,
+        }
+    }
+}
+
+impl fmt::Display for Width {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-bit", self.bytes() * 8)
+    }
+}
+
+/// An address in the 32 MB of EE RAM.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Addr(u32);
+
+impl Addr {
+    /// The highest valid address.
+    pub const MAX: u32 = 0x01ff_ffff;
+
+    /// Returns an address, or an error if it is outside of EE RAM.
+    pub const fn new(addr: u32) -> Result<Self, Error> {
+        if addr > Self::MAX {
+            return Err(Error::AddressOutOfRange(addr));
+        }
+        Ok(Self(addr))
+    }
+
+    /// Returns an address that is suitably aligned for an access of the given
+    /// width, or an error.
+    pub const fn new_aligned(addr: u32, width: Width) -> Result<Self, Error> {
+        match Self::new(addr) {
+            Ok(a) if a.is_aligned(width) => Ok(a),
+            Ok(_) => Err(Error::Misaligned { addr, width }),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Extracts the address from the first word of a code.
+    pub const fn from_code(addr: u32) -> Self {
+        Self(addr & Self::MAX)
+    }
+
+    /// Returns the address as a plain integer.
+    pub const fn get(self) -> u32 {
+        self.0
+    }
+
+    /// Returns true if an access of the given width is aligned.
+    pub const fn is_aligned(self, width: Width) -> bool {
+        self.0 & (width.bytes() - 1) == 0
+    }
+
+    /// Rounds the address down to the given alignment.
+    pub const fn align_down(self, width: Width) -> Self {
+        Self(self.0 & !(width.bytes() - 1))
+    }
+
+    /// Returns the address `bytes` after this one, or an error if it is
+    /// outside of EE RAM.
+    pub const fn offset(self, bytes: u32) -> Result<Self, Error> {
+        match self.0.checked_add(bytes) {
+            Some(addr) => Self::new(addr),
+            None => Err(Error::AddressOutOfRange(u32::MAX)),
+        }
+    }
+}
+
+impl TryFrom<u32> for Addr {
+    type Error = Error;
+
+    fn try_from(addr: u32) -> Result<Self, Self::Error> {
+        Self::new(addr)
+    }
+}
+
+impl From<Addr> for u32 {
+    fn from(addr: Addr) -> Self {
+        addr.0
+    }
+}
+
+impl fmt::Display for Addr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:08X}", self.0)
+    }
+}
+
+/// A value of a given width.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Value {
+    val: u32,
+    width: Width,
+}
+
+impl Value {
+    /// Returns a value, or an error if it does not fit the width.
+    pub const fn new(val: u32, width: Width) -> Result<Self, Error> {
+        if val > width.max_value() {
+            return Err(Error::ValueTooLarge { val, width });
+        }
+        Ok(Self { val, width })
+    }
+
+    /// Returns an 8-bit value.
+    pub const fn byte(val: u8) -> Self {
+        Self {
+            val: val as u32,
+            width: Width::Byte,
+        }
+    }
+
+    /// Returns a 16-bit value.
+    pub const fn half(val: u16) -> Self {
+        Self {
+            val: val as u32,
+            width: Width::Half,
+        }
+    }
+
+    /// Returns a 32-bit value.
+    pub const fn word(val: u32) -> Self {
+        Self {
+            val,
+            width: Width::Word,
+        }
+    }
+
+    /// Returns the value as a plain integer.
+    pub const fn get(self) -> u32 {
+        self.val
+    }
+
+    /// Returns the width of the value.
+    pub const fn width(self) -> Width {
+        self.width
+    }
+}
+
+impl From<Value> for u32 {
+    fn from(val: Value) -> Self {
+        val.val
+    }
+}
+
+/// Formats the value with as many hex digits as its width.
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.width {
+            Width::Byte => write!(f, "{:02X}", self.val),
+            Width::Half => write!(f, "{:04X}", self.val),
+            Width::Word => write!(f, "{:08X}", self.val),
+        }
+    }
+}
+
+/// The comparison of a conditional.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Cond {
+    /// Memory is equal to the value
+    Eq,
+    /// Memory is not equal to the value
+    Ne,
+    /// Memory is less than the value
+    Lt,
+    /// Memory is greater than the value
+    Gt,
+}
+
+impl Cond {
+    const fn bits(self) -> u32 {
+        match self {
+            Self::Eq => 0,
+            Self::Ne => 1,
+            Self::Lt => 2,
+            Self::Gt => 3,
+        }
+    }
+}
+
+/// A single operation of a cheat.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Op {
+    /// Writes a value to memory.
+    Write {
+        /// The address to write to
+        addr: Addr,
+        /// The value and width of the write
+        val: Value,
+    },
+    /// Writes a series of 32-bit values to equally spaced addresses.
+    SerialWrite {
+        /// The first address to write to
+        addr: Addr,
+        /// The first value to write
+        val: u32,
+        /// The number of writes
+        count: u32,
+        /// The distance between two addresses in bytes, a multiple of 4
+        step: u32,
+        /// The amount added to the value after each write
+        inc: u32,
+    },
+    /// Executes operations only if memory compares to a value.
+    If {
+        /// The address to compare
+        addr: Addr,
+        /// The value and width to compare against, 8 or 16 bits
+        val: Value,
+        /// The comparison
+        cond: Cond,
+        /// The operations to execute
+        then: Vec<Self>,
+    },
+}
+
+impl Op {
+    /// Appends the decrypted codes of the operation to `codes`.
+    ///
+    /// A conditional guarding a single line is encoded as a one-line `D`
+    /// code, larger blocks as an `E` code with a line count.
+    pub fn encode(&self, codes: &mut Vec<(u32, u32)>) -> Result<(), Error> {
+        match self {
+            Self::Write { addr, val } => {
+                let width = val.width();
+                if !addr.is_aligned(width) {
+                    return Err(Error::Misaligned {
+                        addr: addr.get(),
+                        width,
+                    });
+                }
+                let cmd = match width {
+                    Width::Byte => 0,
+                    Width::Half => 1,
+                    Width::Word => 2,
+                };
+                codes.push((cmd << 28 | addr.get(), val.get()));
+            }
+            Self::SerialWrite {
+                addr,
+                val,
+                count,
+                step,
+                inc,
+            } => {
+                if !addr.is_aligned(Width::Word) {
+                    return Err(Error::Misaligned {
+                        addr: addr.get(),
+                        width: Width::Word,
+                    });
+                }
+                if *count > 0xffff {
+                    return Err(Error::FieldOverflow {
+                        field: "count",
+                        val: *count,
+                    });
+                }
+                if step & 3 != 0 || step / 4 > 0xffff {
+                    return Err(Error::FieldOverflow {
+                        field: "step",
+                        val: *step,
+                    });
+                }
+                codes.push((0x4000_0000 | addr.get(), count << 16 | (step / 4)));
+                codes.push((*val, *inc));
+            }
+            Self::If { addr, val, cond, then } => {
+                let width = val.width();
+                if width == Width::Word {
+                    return Err(Error::UnsupportedWidth(width));
+                }
+                if width == Width::Half && !addr.is_aligned(width) {
+                    return Err(Error::Misaligned {
+                        addr: addr.get(),
+                        width,
+                    });
+                }
+                let start = codes.len();
+                codes.push((0, 0));
+                for op in then.iter() {
+                    op.encode(codes)?;
+                }
+                let lines = codes.len() - start - 1;
+                let bits8 = u32::from(width == Width::Byte);
+                codes[start] = match lines {
+                    0 => {
+                        codes.pop();
+                        return Ok(());
+                    }
+                    1 => (0xd000_0000 | addr.get(), bits8 << 24 | cond.bits() << 20 | val.get()),
+                    2..=0xff => (
+                        0xe000_0000 | bits8 << 24 | (lines as u32) << 16 | val.get(),
+                        cond.bits() << 28 | addr.get(),
+                    ),
+                    _ => return Err(Error::BlockTooLarge(lines)),
+                };
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::std_alloc::Vec;
+
+    #[test]
+    fn test_addr() {
+        assert_eq!(Ok(0x0043_afcc), Addr::new(0x0043_afcc).map(Addr::get));
+        assert_eq!(Ok(Addr::MAX), Addr::new(Addr::MAX).map(u32::from));
+        assert_eq!(Err(Error::AddressOutOfRange(0x0200_0000)), Addr::new(0x0200_0000));
+        assert_eq!(Addr::new(0x0043_afcc), Addr::try_from(0x0043_afcc));
+        assert_eq!(Addr(0x0043_afcc), Addr::from_code(0x2043_afcc));
+    }
+
+    #[test]
+    fn test_addr_alignment() {
+        #[rustfmt::skip]
+        let tests = [
+            (0x0010_0000, Width::Word, true, 0x0010_0000),
+            (0x0010_0002, Width::Word, false, 0x0010_0000),
+            (0x0010_0002, Width::Half, true, 0x0010_0002),
+            (0x0010_0003, Width::Half, false, 0x0010_0002),
+            (0x0010_0003, Width::Byte, true, 0x0010_0003),
+        ];
+        for t in tests.iter() {
+            let addr = Addr::new(t.0).unwrap();
+            assert_eq!(t.2, addr.is_aligned(t.1));
+            assert_eq!(t.3, addr.align_down(t.1).get());
+            assert_eq!(t.2, Addr::new_aligned(t.0, t.1).is_ok());
+        }
+        assert_eq!(
+            Err(Error::Misaligned {
+                addr: 0x0010_0002,
+                width: Width::Word
+            }),
+            Addr::new_aligned(0x0010_0002, Width::Word)
+        );
+    }
+
+    #[test]
+    fn test_addr_offset() {
+        let addr = Addr::new(0x01ff_fffc).unwrap();
+        assert_eq!(Addr::new(0x01ff_fffe), addr.offset(2));
+        assert_eq!(Err(Error::AddressOutOfRange(0x0200_0000)), addr.offset(4));
+        assert_eq!(Err(Error::AddressOutOfRange(u32::MAX)), addr.offset(u32::MAX));
+    }
+
+    #[test]
+    fn test_value() {
+        assert_eq!(Ok(Value::byte(0xff)), Value::new(0xff, Width::Byte));
+        assert_eq!(Ok(Value::half(0xffff)), Value::new(0xffff, Width::Half));
+        assert_eq!(Ok(Value::word(0xffff_ffff)), Value::new(0xffff_ffff, Width::Word));
+        assert_eq!(
+            Err(Error::ValueTooLarge {
+                val: 0x100,
+                width: Width::Byte
+            }),
+            Value::new(0x100, Width::Byte)
+        );
+        assert_eq!(Width::Half, Value::half(1).width());
+    }
+
+    fn write(addr: u32, val: Value) -> Op {
+        Op::Write {
+            addr: Addr::new(addr).unwrap(),
+            val,
+        }
+    }
+
+    fn encode(op: &Op) -> Result<Vec<(u32, u32)>, Error> {
+        let mut codes = Vec::new();
+        op.encode(&mut codes).map(|_| codes)
+    }
+
+    #[test]
+    fn test_encode_write() {
+        #[rustfmt::skip]
+        let tests = [
+            (write(0x0031_789a, Value::byte(0x63)), (0x0031_789a, 0x0000_0063)),
+            (write(0x0043_afcc, Value::half(0xffff)), (0x1043_afcc, 0x0000_ffff)),
+            (write(0x0043_afcc, Value::word(0x2411_ffff)), (0x2043_afcc, 0x2411_ffff)),
+        ];
+        for t in tests.iter() {
+            assert_eq!(Ok(vec![t.1]), encode(&t.0));
+        }
+        assert_eq!(
+            Err(Error::Misaligned {
+                addr: 0x0043_afcd,
+                width: Width::Half
+            }),
+            encode(&write(0x0043_afcd, Value::half(1)))
+        );
+    }
+
+    #[test]
+    fn test_encode_serial_write() {
+        let serial = |addr, count, step| Op::SerialWrite {
+            addr: Addr::new(addr).unwrap(),
+            val: 0x63,
+            count,
+            step,
+            inc: 1,
+        };
+        assert_eq!(
+            Ok(vec![(0x4041_0000, 0x000a_0002), (0x0000_0063, 0x0000_0001)]),
+            encode(&serial(0x0041_0000, 10, 8))
+        );
+        assert_eq!(
+            Err(Error::Misaligned {
+                addr: 0x0041_0002,
+                width: Width::Word
+            }),
+            encode(&serial(0x0041_0002, 10, 8))
+        );
+        assert_eq!(
+            Err(Error::FieldOverflow {
+                field: "count",
+                val: 0x1_0000
+            }),
+            encode(&serial(0x0041_0000, 0x1_0000, 8))
+        );
+        assert_eq!(
+            Err(Error::FieldOverflow { field: "step", val: 6 }),
+            encode(&serial(0x0041_0000, 10, 6))
+        );
+    }
+
+    #[test]
+    fn test_encode_if() {
+        let cond = |val, cond, then| Op::If {
+            addr: Addr::new(0x004a_0000).unwrap(),
+            val,
+            cond,
+            then,
+        };
+        let w = write(0x0043_afcc, Value::half(0xffff));
+
+        assert_eq!(
+            Ok(vec![(0xd04a_0000, 0x0000_0001), (0x1043_afcc, 0x0000_ffff)]),
+            encode(&cond(Value::half(1), Cond::Eq, vec![w.clone()]))
+        );
+        assert_eq!(
+            Ok(vec![(0xd04a_0000, 0x0130_0001), (0x1043_afcc, 0x0000_ffff)]),
+            encode(&cond(Value::byte(1), Cond::Gt, vec![w.clone()]))
+        );
+        assert_eq!(
+            Ok(vec![
+                (0xe002_0001, 0x104a_0000),
+                (0x1043_afcc, 0x0000_ffff),
+                (0x1043_afcc, 0x0000_ffff)
+            ]),
+            encode(&cond(Value::half(1), Cond::Ne, vec![w.clone(), w.clone()]))
+        );
+        assert_eq!(
+            Ok(vec![
+                (0xe103_0001, 0x204a_0000),
+                (0xd04a_0000, 0x0000_0002),
+                (0x1043_afcc, 0x0000_ffff),
+                (0x1043_afcc, 0x0000_ffff)
+            ]),
+            encode(&cond(
+                Value::byte(1),
+                Cond::Lt,
+                vec![cond(Value::half(2), Cond::Eq, vec![w.clone()]), w.clone()]
+            ))
+        );
+        assert_eq!(Ok(vec![]), encode(&cond(Value::half(1), Cond::Eq, vec![])));
+        assert_eq!(
+            Err(Error::UnsupportedWidth(Width::Word)),
+            encode(&cond(Value::word(1), Cond::Eq, vec![w.clone()]))
+        );
+        assert_eq!(
+            Err(Error::BlockTooLarge(256)),
+            encode(&cond(Value::half(1), Cond::Eq, vec![w; 256]))
+        );
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!("0043AFCC", format!("{}", Addr::new(0x0043_afcc).unwrap()));
+        assert_eq!("0F", format!("{}", Value::byte(0xf)));
+        assert_eq!("00FF", format!("{}", Value::half(0xff)));
+        assert_eq!("000000FF", format!("{}", Value::word(0xff)));
+        assert_eq!("16-bit", format!("{}", Width::Half));
+    }
+}
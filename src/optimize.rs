@@ -0,0 +1,236 @@
+//! Shrinking cheat sets for devices with a line limit.
+//!
+//! Some devices cap the number of code lines per game. [`optimize`]
+//! restructures the writes of each cheat to save lines: redundant writes are
+//! dropped, neighboring writes are merged, and regular runs of writes are
+//! turned into serial writes.
+//!
+//! # Example
+//! ```
+//! use codebreaker::cheat::Cheat;
+//! use codebreaker::optimize::optimize;
+//!
+//! let cheat = Cheat::builder("Max Items")
+//!     .write32(0x00410000, 0x63)
+//!     .write32(0x00410004, 0x63)
+//!     .write32(0x00410008, 0x63)
+//!     .write32(0x0041000C, 0x63)
+//!     .build()
+//!     .unwrap();
+//!
+//! let (cheats, report) = optimize(&[cheat], 3).unwrap();
+//! assert_eq!(
+//!     vec![(0x40410000, 0x00040001), (0x00000063, 0x00000000)],
+//!     cheats[0].to_codes().unwrap()
+//! );
+//! assert_eq!(4, report.lines_before);
+//! assert_eq!(2, report.lines_after);
+//! assert!(report.fits());
+//! ```
+
+use crate::cheat::Cheat;
+use crate::op::{Op, Value};
+use crate::std_alloc::Vec;
+use crate::trainer::{compact, Freeze};
+use crate::Error;
+
+/// Line counts before and after optimization.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Report {
+    /// The number of lines of the original cheats
+    pub lines_before: usize,
+    /// The number of lines of the optimized cheats
+    pub lines_after: usize,
+    /// The maximum number of lines allowed
+    pub budget: usize,
+}
+
+impl Report {
+    /// Returns true if the optimized cheats are within the line budget.
+    pub const fn fits(&self) -> bool {
+        self.lines_after <= self.budget
+    }
+}
+
+/// Returns the optimized cheats and a report on the line counts.
+///
+/// Only consecutive unconditional writes are restructured, so the effect of
+/// each cheat stays the same. Conditionals are kept in place and their bodies
+/// are optimized in turn. If the cheats still exceed `budget` afterwards, the
+/// report tells by how much; it is up to the caller to drop cheats.
+pub fn optimize(cheats: &[Cheat], budget: usize) -> Result<(Vec<Cheat>, Report), Error> {
+    let mut optimized = Vec::with_capacity(cheats.len());
+    let mut lines_before = 0;
+    let mut lines_after = 0;
+
+    for cheat in cheats.iter() {
+        let ops = optimize_ops(&cheat.ops)?;
+        lines_before += lines(&cheat.ops)?;
+        lines_after += lines(&ops)?;
+        optimized.push(Cheat {
+            name: cheat.name.clone(),
+            ops,
+        });
+    }
+
+    let report = Report {
+        lines_before,
+        lines_after,
+        budget,
+    };
+    Ok((optimized, report))
+}
+
+fn optimize_ops(ops: &[Op]) -> Result<Vec<Op>, Error> {
+    let mut optimized = Vec::new();
+    let mut start = 0;
+
+    for (i, op) in ops.iter().enumerate() {
+        if let Op::If { addr, val, cond, then } = op {
+            optimized.extend(optimize_writes(&ops[start..i])?);
+            optimized.push(Op::If {
+                addr: *addr,
+                val: *val,
+                cond: *cond,
+                then: optimize_ops(then)?,
+            });
+            start = i + 1;
+        }
+    }
+    optimized.extend(optimize_writes(&ops[start..])?);
+    Ok(optimized)
+}
+
+// Compacts a run of unconditional writes, unless that would not save lines.
+fn optimize_writes(ops: &[Op]) -> Result<Vec<Op>, Error> {
+    let mut freezes = Vec::new();
+    for op in ops.iter() {
+        match *op {
+            Op::Write { addr, val } => freezes.push(Freeze { addr, val }),
+            Op::SerialWrite {
+                addr,
+                val,
+                count,
+                step,
+                inc,
+            } => {
+                for i in 0..count {
+                    freezes.push(Freeze {
+                        addr: addr.offset(i.saturating_mul(step))?,
+                        val: Value::word(val.wrapping_add(i.wrapping_mul(inc))),
+                    });
+                }
+            }
+            Op::If { .. } => unreachable!("conditionals are handled by optimize_ops"),
+        }
+    }
+
+    let compacted = compact(&freezes)?;
+    if lines(&compacted)? < lines(ops)? {
+        Ok(compacted)
+    } else {
+        Ok(ops.to_vec())
+    }
+}
+
+fn lines(ops: &[Op]) -> Result<usize, Error> {
+    let mut codes = Vec::new();
+    for op in ops.iter() {
+        op.encode(&mut codes)?;
+    }
+    Ok(codes.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::op::{Addr, Cond};
+
+    fn serial(addr: u32, val: u32, count: u32, step: u32, inc: u32) -> Op {
+        Op::SerialWrite {
+            addr: Addr::new(addr).unwrap(),
+            val,
+            count,
+            step,
+            inc,
+        }
+    }
+
+    #[test]
+    fn test_optimize() {
+        let cheats = [
+            // redundant and mergeable writes
+            Cheat::builder("A")
+                .write16(0x0010_0000, 0x1111)
+                .write16(0x0010_0000, 0x5678)
+                .write16(0x0010_0002, 0x1234)
+                .build()
+                .unwrap(),
+            // writes are not moved across conditionals
+            Cheat::builder("B")
+                .write8(0x0020_0000, 1)
+                .if_eq16(0x0030_0000, 1)
+                .then(|b| b.write8(0x0030_0002, 1).write8(0x0030_0003, 2))
+                .write8(0x0020_0001, 2)
+                .build()
+                .unwrap(),
+        ];
+
+        let (optimized, report) = optimize(&cheats, 5).unwrap();
+        assert_eq!("A", optimized[0].name);
+        assert_eq!(vec![(0x2010_0000, 0x1234_5678)], optimized[0].to_codes().unwrap());
+        assert_eq!(
+            vec![
+                (0x0020_0000, 0x0000_0001),
+                (0xd030_0000, 0x0000_0001),
+                (0x1030_0002, 0x0000_0201),
+                (0x0020_0001, 0x0000_0002),
+            ],
+            optimized[1].to_codes().unwrap()
+        );
+        assert_eq!(
+            Report {
+                lines_before: 8,
+                lines_after: 5,
+                budget: 5
+            },
+            report
+        );
+        assert!(report.fits());
+    }
+
+    #[test]
+    fn test_optimize_keeps_smaller_original() {
+        // interleaved serial writes do not form a single run
+        let cheat = Cheat {
+            name: "C".into(),
+            ops: vec![serial(0x0010_0000, 1, 4, 8, 0), serial(0x0010_0004, 2, 4, 8, 0)],
+        };
+        let (optimized, report) = optimize(core::slice::from_ref(&cheat), 2).unwrap();
+        assert_eq!(cheat, optimized[0]);
+        assert_eq!(4, report.lines_after);
+        assert!(!report.fits());
+    }
+
+    #[test]
+    fn test_optimize_nested() {
+        let cheat = Cheat {
+            name: "D".into(),
+            ops: vec![Op::If {
+                addr: Addr::new(0x0030_0000).unwrap(),
+                val: Value::half(1),
+                cond: Cond::Ne,
+                then: vec![serial(0x0010_0000, 1, 2, 4, 1), serial(0x0010_0008, 3, 2, 4, 1)],
+            }],
+        };
+        let (optimized, _) = optimize(&[cheat], 0).unwrap();
+        assert_eq!(
+            vec![
+                (0xe002_0001, 0x1030_0000),
+                (0x4010_0000, 0x0004_0001),
+                (0x0000_0001, 0x0000_0001)
+            ],
+            optimized[0].to_codes().unwrap()
+        );
+    }
+}
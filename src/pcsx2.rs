@@ -0,0 +1,608 @@
+//! Helpers for the PCSX2 emulator.
+//!
+//! PCSX2 looks up cheats in pnach files named after the CRC of the game's
+//! boot ELF, e.g. `BB3D833A.pnach`. Despite its name, the CRC is simply the
+//! XOR of all 32-bit little-endian words of the ELF.
+//!
+//! [`write_pnach`] writes decrypted codes as the `patch=` lines of a pnach
+//! file, so they can be used with PCSX2 directly. [`write_pnach_with`] writes
+//! other [`Dialect`]s of pnach files, for older and newer versions of PCSX2.
+//!
+//! PCSX2 savestates are zip archives. [`ee_memory`] extracts the EE RAM image
+//! from one, for use with the [ram](../ram/index.html) module.
+//!
+//! This module requires the "pcsx2" feature.
+//!
+//! # Example
+//! ```
+//! use codebreaker::pcsx2;
+//!
+//! let elf = [0x7f, b'E', b'L', b'F', 0x01, 0x01, 0x01, 0x00];
+//! let crc = pcsx2::elf_crc(&elf);
+//! assert_eq!(0x464D447E, crc);
+//! assert_eq!("464D447E.pnach", pcsx2::pnach_file_name(crc));
+//! ```
+
+use crate::cb7::is_beefcode;
+use crate::firmware::Profile;
+use crate::game::Game;
+use crate::inflate::inflate;
+use crate::std_alloc::{String, Vec};
+
+use core::fmt::{self, Write};
+
+const ELF_MAGIC: [u8; 4] = [0x7f, b'E', b'L', b'F'];
+
+/// The size of EE RAM in bytes.
+pub const EE_RAM_SIZE: usize = 0x0200_0000;
+
+const EE_MEMORY_FILE: &[u8] = b"eeMemory.bin";
+
+const EOCD_SIGNATURE: u32 = 0x0605_4b50;
+const CENTRAL_SIGNATURE: u32 = 0x0201_4b50;
+const LOCAL_SIGNATURE: u32 = 0x0403_4b50;
+const EOCD_SIZE: usize = 22;
+const CENTRAL_SIZE: usize = 46;
+const LOCAL_SIZE: usize = 30;
+
+const METHOD_STORED: u16 = 0;
+const METHOD_DEFLATE: u16 = 8;
+
+/// The error type for reading savestates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// The data is not a zip archive.
+    NotSavestate,
+    /// A structure points outside of the archive.
+    Truncated,
+    /// The savestate holds no EE RAM image.
+    NotFound,
+    /// The EE RAM image uses a compression method other than deflate, such
+    /// as zstd.
+    UnsupportedCompression(u16),
+    /// The EE RAM image does not decompress to the expected size.
+    Corrupt,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotSavestate => f.write_str("not a savestate"),
+            Self::Truncated => f.write_str("savestate is truncated"),
+            Self::NotFound => f.write_str("savestate has no EE RAM image"),
+            Self::UnsupportedCompression(method) => write!(f, "unsupported compression method {}", method),
+            Self::Corrupt => f.write_str("EE RAM image is corrupt"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+/// Returns true if the data starts with the ELF magic number.
+pub fn is_elf(data: &[u8]) -> bool {
+    data.starts_with(&ELF_MAGIC)
+}
+
+/// Computes the CRC that PCSX2 uses to identify a game from its boot ELF.
+///
+/// Trailing bytes that do not form a complete 32-bit word are ignored, just
+/// like PCSX2 does.
+pub fn elf_crc(elf: &[u8]) -> u32 {
+    elf.chunks_exact(4)
+        .map(|w| u32::from_le_bytes([w[0], w[1], w[2], w[3]]))
+        .fold(0, |crc, w| crc ^ w)
+}
+
+/// Returns the name of the pnach file PCSX2 loads for a game CRC.
+pub fn pnach_file_name(crc: u32) -> String {
+    format!("{:08X}.pnach", crc)
+}
+
+/// A dialect of pnach files.
+///
+/// PCSX2 reads several kinds of patches, and converters often mix them up.
+/// Only [`Extended`](Self::Extended) and [`Sections`](Self::Sections) take
+/// any CodeBreaker code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[non_exhaustive]
+pub enum Dialect {
+    /// Plain `byte`, `short`, and `word` writes, which all versions of PCSX2
+    /// read. Other codes cannot be written this way.
+    Legacy,
+    /// `extended` patches, which take raw CodeBreaker codes
+    #[default]
+    Extended,
+    /// `extended` patches in a `[name]` section per cheat, which newer
+    /// versions of PCSX2 list as cheats to enable one by one
+    Sections,
+}
+
+impl Dialect {
+    /// Returns true if all decrypted codes can be written in this dialect.
+    /// Beefcodes are always left out.
+    ///
+    /// # Example
+    /// ```
+    /// use codebreaker::pcsx2::Dialect;
+    ///
+    /// assert!(Dialect::Legacy.supports(&[(0x2096F5B8, 0x000000BE)]));
+    /// assert!(!Dialect::Legacy.supports(&[(0xD096F5B8, 0x000000BE)]));
+    /// assert!(Dialect::Extended.supports(&[(0xD096F5B8, 0x000000BE)]));
+    /// ```
+    pub fn supports(self, codes: &[(u32, u32)]) -> bool {
+        match self {
+            Self::Legacy => patches(codes)
+                .iter()
+                .all(|&(addr, val)| legacy_patch(addr, val).is_some()),
+            Self::Extended | Self::Sections => true,
+        }
+    }
+}
+
+// Returns the type, address, and value of a legacy patch for a code.
+const fn legacy_patch(addr: u32, val: u32) -> Option<(&'static str, u32, u32)> {
+    let target = addr & 0x0fff_ffff;
+    match addr >> 28 {
+        0x0 => Some(("byte", target, val & 0xff)),
+        0x1 => Some(("short", target, val & 0xffff)),
+        0x2 => Some(("word", target, val)),
+        _ => None,
+    }
+}
+
+// Returns the codes without beefcodes and the lines that follow them.
+fn patches(codes: &[(u32, u32)]) -> Vec<(u32, u32)> {
+    let mut patches = Vec::with_capacity(codes.len());
+    let mut skip = 0;
+    for &(addr, val) in codes.iter() {
+        if skip > 0 {
+            skip -= 1;
+        } else if is_beefcode(addr) {
+            skip = Profile::V7.lines(addr) - 1;
+        } else {
+            patches.push((addr, val));
+        }
+    }
+    patches
+}
+
+/// Writes decrypted codes as pnach patches, one `patch=1,EE,...` line per
+/// code in the "extended" format, which takes raw CodeBreaker codes.
+///
+/// Beefcodes are left out, as the codes are no longer encrypted.
+pub fn write_pnach_codes<W: Write>(w: &mut W, codes: &[(u32, u32)]) -> fmt::Result {
+    write_pnach_codes_with(w, codes, Dialect::Extended)
+}
+
+/// Writes decrypted codes as pnach patches of the given dialect, one
+/// `patch=1,EE,...` line per code.
+///
+/// Beefcodes are left out, as the codes are no longer encrypted. If the
+/// dialect does not [support](Dialect::supports) the codes, all of them are
+/// written as commented out `extended` patches instead, as writing only
+/// some would change what the others do.
+pub fn write_pnach_codes_with<W: Write>(w: &mut W, codes: &[(u32, u32)], dialect: Dialect) -> fmt::Result {
+    let patches = patches(codes);
+    if dialect == Dialect::Legacy {
+        let legacy: Option<Vec<_>> = patches.iter().map(|&(addr, val)| legacy_patch(addr, val)).collect();
+        if let Some(legacy) = legacy {
+            for (kind, addr, val) in legacy {
+                writeln!(w, "patch=1,EE,{:08X},{},{:08X}", addr, kind, val)?;
+            }
+            return Ok(());
+        }
+    }
+    let prefix = if dialect == Dialect::Legacy { "// " } else { "" };
+    for &(addr, val) in patches.iter() {
+        writeln!(w, "{}patch=1,EE,{:08X},extended,{:08X}", prefix, addr, val)?;
+    }
+    Ok(())
+}
+
+/// Writes the decrypted cheats of a game as a pnach file, with the title as
+/// `gametitle` and the name of each cheat as a comment before its patches.
+///
+/// Titles and names are written up to their first line break, as anything
+/// after it would be read as another line of the file.
+///
+/// # Example
+/// ```
+/// use codebreaker::game::{Cheat, Game};
+/// use codebreaker::pcsx2;
+///
+/// let mut game = Game::new("Kingdom Hearts");
+/// game.cheats.push(Cheat::new("(M)", vec![(0xBEEFC0DE, 0x00000000)]));
+/// game.cheats.push(Cheat::new("Inf HP", vec![(0x2096F5B8, 0x000000BE)]));
+///
+/// let mut pnach = String::new();
+/// pcsx2::write_pnach(&mut pnach, &game).unwrap();
+/// assert_eq!(
+///     "gametitle=Kingdom Hearts\n\n// (M)\n\n// Inf HP\npatch=1,EE,2096F5B8,extended,000000BE\n",
+///     pnach
+/// );
+/// ```
+pub fn write_pnach<W: Write>(w: &mut W, game: &Game) -> fmt::Result {
+    write_pnach_with(w, game, Dialect::Extended)
+}
+
+/// Writes the decrypted cheats of a game as a pnach file of the given
+/// dialect, like [`write_pnach`].
+///
+/// With [`Dialect::Sections`], each cheat starts a section named after it
+/// instead of a comment. Brackets in names become parentheses, as they would
+/// end the section name.
+///
+/// # Example
+/// ```
+/// use codebreaker::game::{Cheat, Game};
+/// use codebreaker::pcsx2::{self, Dialect};
+///
+/// let mut game = Game::new("Kingdom Hearts");
+/// game.cheats.push(Cheat::new("Inf HP", vec![(0x2096F5B8, 0x000000BE)]));
+///
+/// let mut pnach = String::new();
+/// pcsx2::write_pnach_with(&mut pnach, &game, Dialect::Legacy).unwrap();
+/// assert_eq!("gametitle=Kingdom Hearts\n\n// Inf HP\npatch=1,EE,0096F5B8,word,000000BE\n", pnach);
+///
+/// pnach.clear();
+/// pcsx2::write_pnach_with(&mut pnach, &game, Dialect::Sections).unwrap();
+/// assert_eq!("gametitle=Kingdom Hearts\n\n[Inf HP]\npatch=1,EE,2096F5B8,extended,000000BE\n", pnach);
+/// ```
+pub fn write_pnach_with<W: Write>(w: &mut W, game: &Game, dialect: Dialect) -> fmt::Result {
+    if !game.title.is_empty() {
+        writeln!(w, "gametitle={}", first_line(&game.title))?;
+    }
+    for (i, cheat) in game.cheats.iter().enumerate() {
+        if i > 0 || !game.title.is_empty() {
+            w.write_char('\n')?;
+        }
+        let name = first_line(&cheat.name);
+        if dialect == Dialect::Sections {
+            let name = name.trim().replace('[', "(").replace(']', ")");
+            writeln!(w, "[{}]", if name.is_empty() { "Unnamed" } else { &name })?;
+        } else {
+            writeln!(w, "// {}", name)?;
+        }
+        write_pnach_codes_with(w, &cheat.codes, dialect)?;
+    }
+    Ok(())
+}
+
+/// Returns the decrypted cheats of a game as a pnach file, like
+/// [`write_pnach`].
+pub fn to_pnach(game: &Game) -> String {
+    to_pnach_with(game, Dialect::Extended)
+}
+
+/// Returns the decrypted cheats of a game as a pnach file of the given
+/// dialect, like [`write_pnach_with`].
+pub fn to_pnach_with(game: &Game, dialect: Dialect) -> String {
+    let mut s = String::new();
+    // Writing to a String cannot fail
+    let _ = write_pnach_with(&mut s, game, dialect);
+    s
+}
+
+// Cuts at a lone carriage return too, which some readers take as a line break.
+fn first_line(s: &str) -> &str {
+    s.split(['\r', '\n']).next().unwrap_or("")
+}
+
+/// Extracts the EE RAM image from a PCSX2 savestate (`.p2s`).
+///
+/// Savestates store the memory stored or deflated. Newer PCSX2 versions can
+/// be configured to use zstd instead, which is not supported.
+///
+/// # Example
+/// ```no_run
+/// use codebreaker::pcsx2;
+/// use codebreaker::ram::Ram;
+///
+/// let savestate = std::fs::read("game.p2s").unwrap();
+/// let memory = pcsx2::ee_memory(&savestate).unwrap();
+/// let ram = Ram::new(&memory);
+/// ```
+pub fn ee_memory(savestate: &[u8]) -> Result<Vec<u8>, Error> {
+    let entry = find_entry(savestate, EE_MEMORY_FILE)?;
+    // Don't let a forged size make us allocate more than EE RAM
+    if entry.size > EE_RAM_SIZE {
+        return Err(Error::Corrupt);
+    }
+    let data = match entry.method {
+        METHOD_STORED => entry.data.to_vec(),
+        METHOD_DEFLATE => inflate(entry.data, entry.size).ok_or(Error::Corrupt)?,
+        method => return Err(Error::UnsupportedCompression(method)),
+    };
+    if data.len() != entry.size {
+        return Err(Error::Corrupt);
+    }
+    Ok(data)
+}
+
+struct Entry<'a> {
+    method: u16,
+    size: usize,
+    data: &'a [u8],
+}
+
+// Looks up a file in the central directory of a zip archive.
+fn find_entry<'a>(zip: &'a [u8], name: &[u8]) -> Result<Entry<'a>, Error> {
+    if zip.len() < EOCD_SIZE {
+        return Err(Error::NotSavestate);
+    }
+    // The end of central directory record is followed by a comment of up to
+    // 64 KB
+    let eocd = (zip.len().saturating_sub(EOCD_SIZE + 0xffff)..=zip.len() - EOCD_SIZE)
+        .rev()
+        .find(|&i| le32(zip, i) == Some(EOCD_SIGNATURE))
+        .ok_or(Error::NotSavestate)?;
+    let entries = le16(zip, eocd + 10).ok_or(Error::Truncated)?;
+    let mut pos = le32(zip, eocd + 16).ok_or(Error::Truncated)? as usize;
+
+    for _ in 0..entries {
+        let header = get(zip, pos, CENTRAL_SIZE).ok_or(Error::Truncated)?;
+        if le32(header, 0) != Some(CENTRAL_SIGNATURE) {
+            return Err(Error::Truncated);
+        }
+        let field = |offset| le16(header, offset).unwrap_or_default() as usize;
+        let (name_len, extra_len, comment_len) = (field(28), field(30), field(32));
+        let entry_name = pos
+            .checked_add(CENTRAL_SIZE)
+            .and_then(|start| get(zip, start, name_len));
+
+        if entry_name == Some(name) {
+            let method = field(10) as u16;
+            let compressed = le32(header, 20).unwrap_or_default() as usize;
+            let size = le32(header, 24).unwrap_or_default() as usize;
+            let local = le32(header, 42).unwrap_or_default() as usize;
+
+            let local_header = get(zip, local, LOCAL_SIZE).ok_or(Error::Truncated)?;
+            if le32(local_header, 0) != Some(LOCAL_SIGNATURE) {
+                return Err(Error::Truncated);
+            }
+            let start = local.checked_add(
+                LOCAL_SIZE
+                    + le16(local_header, 26).unwrap_or_default() as usize
+                    + le16(local_header, 28).unwrap_or_default() as usize,
+            );
+            let data = start
+                .and_then(|start| get(zip, start, compressed))
+                .ok_or(Error::Truncated)?;
+            return Ok(Entry { method, size, data });
+        }
+        pos = pos
+            .checked_add(CENTRAL_SIZE + name_len + extra_len + comment_len)
+            .ok_or(Error::Truncated)?;
+    }
+    Err(Error::NotFound)
+}
+
+// Returns `len` bytes at `start`, which may be out of range as offsets and
+// sizes come from the archive.
+fn get(data: &[u8], start: usize, len: usize) -> Option<&[u8]> {
+    data.get(start..start.checked_add(len)?)
+}
+
+fn le16(data: &[u8], offset: usize) -> Option<u16> {
+    let b = get(data, offset, 2)?;
+    Some(u16::from_le_bytes([b[0], b[1]]))
+}
+
+fn le32(data: &[u8], offset: usize) -> Option<u32> {
+    let b = get(data, offset, 4)?;
+    Some(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::std_alloc::Vec;
+
+    #[test]
+    fn test_is_elf() {
+        assert!(is_elf(b"\x7fELF\x01\x01\x01"));
+        assert!(!is_elf(b"\x7fEL"));
+        assert!(!is_elf(b"MZ\x90\x00"));
+    }
+
+    #[test]
+    fn test_elf_crc() {
+        assert_eq!(0, elf_crc(&[]));
+        assert_eq!(0, elf_crc(&[0xff, 0xff, 0xff]));
+        assert_eq!(0x1234_5678, elf_crc(&[0x78, 0x56, 0x34, 0x12]));
+        assert_eq!(0, elf_crc(&[0x78, 0x56, 0x34, 0x12, 0x78, 0x56, 0x34, 0x12]));
+
+        let elf: Vec<u8> = (0..=255).collect();
+        let crc = (0..64u32).fold(0, |crc, i| {
+            let b = 4 * i;
+            crc ^ (b | (b + 1) << 8 | (b + 2) << 16 | (b + 3) << 24)
+        });
+        assert_eq!(crc, elf_crc(&elf));
+    }
+
+    #[test]
+    fn test_pnach_file_name() {
+        assert_eq!("BB3D833A.pnach", pnach_file_name(0xbb3d_833a));
+        assert_eq!("0000ABCD.pnach", pnach_file_name(0xabcd));
+    }
+
+    #[test]
+    fn test_write_pnach_codes() {
+        let codes = [
+            (0x2043_afcc, 0x2411_ffff),
+            (0xbeef_c0df, 0x0000_0000),
+            (0x1234_5678, 0x0000_0000),
+            (0xbeef_c0de, 0x0000_0000),
+            (0x1043_afd0, 0x0000_ffff),
+        ];
+        let mut s = String::new();
+        write_pnach_codes(&mut s, &codes).unwrap();
+        assert_eq!(
+            "patch=1,EE,2043AFCC,extended,2411FFFF\npatch=1,EE,1043AFD0,extended,0000FFFF\n",
+            s
+        );
+    }
+
+    #[test]
+    fn test_to_pnach() {
+        use crate::game::Cheat;
+
+        let mut game = Game::new("");
+        game.cheats.push(Cheat::new("Inf HP", vec![(0x2043_afcc, 0x2411_ffff)]));
+        game.cheats.push(Cheat::new("Max MP", vec![]));
+        assert_eq!(
+            "// Inf HP\npatch=1,EE,2043AFCC,extended,2411FFFF\n\n// Max MP\n",
+            to_pnach(&game)
+        );
+        assert_eq!("", to_pnach(&Game::new("")));
+    }
+
+    #[test]
+    fn test_write_pnach_codes_with() {
+        let codes = [
+            (0xbeef_c0de, 0x0000_0000),
+            (0x0031_d3a0, 0x0000_0163),
+            (0x1043_afd0, 0x0001_ffff),
+            (0x2043_afcc, 0x2411_ffff),
+        ];
+        let mut s = String::new();
+        write_pnach_codes_with(&mut s, &codes, Dialect::Legacy).unwrap();
+        assert_eq!(
+            "patch=1,EE,0031D3A0,byte,00000063\n\
+             patch=1,EE,0043AFD0,short,0000FFFF\n\
+             patch=1,EE,0043AFCC,word,2411FFFF\n",
+            s
+        );
+        for dialect in [Dialect::Extended, Dialect::Sections] {
+            let mut extended = String::new();
+            write_pnach_codes_with(&mut extended, &codes, dialect).unwrap();
+            let mut expected = String::new();
+            write_pnach_codes(&mut expected, &codes).unwrap();
+            assert_eq!(expected, extended);
+        }
+
+        // A conditional must not be left out of the patches it guards
+        let codes = [(0xd043_afd0, 0x0000_ffff), (0x2043_afcc, 0x2411_ffff)];
+        assert!(!Dialect::Legacy.supports(&codes));
+        s.clear();
+        write_pnach_codes_with(&mut s, &codes, Dialect::Legacy).unwrap();
+        assert_eq!(
+            "// patch=1,EE,D043AFD0,extended,0000FFFF\n// patch=1,EE,2043AFCC,extended,2411FFFF\n",
+            s
+        );
+    }
+
+    #[test]
+    fn test_to_pnach_with() {
+        use crate::game::Cheat;
+
+        let mut game = Game::new("KH");
+        game.cheats.push(Cheat::new("(M)", vec![(0xbeef_c0de, 0)]));
+        game.cheats
+            .push(Cheat::new(" [P1] Inf HP\nagain", vec![(0x2043_afcc, 0x2411_ffff)]));
+        game.cheats.push(Cheat::new("", vec![]));
+        assert_eq!(
+            "gametitle=KH\n\n[(M)]\n\n[(P1) Inf HP]\npatch=1,EE,2043AFCC,extended,2411FFFF\n\n[Unnamed]\n",
+            to_pnach_with(&game, Dialect::Sections)
+        );
+        assert_eq!(to_pnach(&game), to_pnach_with(&game, Dialect::default()));
+    }
+
+    #[test]
+    fn test_to_pnach_line_breaks() {
+        use crate::game::Cheat;
+
+        let mut game = Game::new("Kingdom Hearts\npatch=1,EE,00000000,word,00000000");
+        game.cheats
+            .push(Cheat::new("Inf HP\rpatch=1,EE,00000000,word,00000000", vec![]));
+        assert_eq!("gametitle=Kingdom Hearts\n\n// Inf HP\n", to_pnach(&game));
+        assert_eq!("gametitle=Kingdom Hearts\n", to_pnach(&Game::new("Kingdom Hearts\r\n")));
+    }
+
+    // Builds a zip archive with a single file.
+    fn zip(name: &[u8], method: u16, data: &[u8], size: u32) -> Vec<u8> {
+        let mut zip = Vec::new();
+        zip.extend_from_slice(&LOCAL_SIGNATURE.to_le_bytes());
+        zip.extend_from_slice(&[20, 0, 0, 0]);
+        zip.extend_from_slice(&method.to_le_bytes());
+        zip.extend_from_slice(&[0; 8]);
+        zip.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        zip.extend_from_slice(&size.to_le_bytes());
+        zip.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        zip.extend_from_slice(&[0, 0]);
+        zip.extend_from_slice(name);
+        zip.extend_from_slice(data);
+
+        let central = zip.len();
+        zip.extend_from_slice(&CENTRAL_SIGNATURE.to_le_bytes());
+        zip.extend_from_slice(&[20, 0, 20, 0, 0, 0]);
+        zip.extend_from_slice(&method.to_le_bytes());
+        zip.extend_from_slice(&[0; 8]);
+        zip.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        zip.extend_from_slice(&size.to_le_bytes());
+        zip.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        zip.extend_from_slice(&[0; 12]);
+        zip.extend_from_slice(&[0; 4]);
+        zip.extend_from_slice(name);
+
+        let central_len = zip.len() - central;
+        zip.extend_from_slice(&EOCD_SIGNATURE.to_le_bytes());
+        zip.extend_from_slice(&[0, 0, 0, 0, 1, 0, 1, 0]);
+        zip.extend_from_slice(&(central_len as u32).to_le_bytes());
+        zip.extend_from_slice(&(central as u32).to_le_bytes());
+        zip.extend_from_slice(b"\x03\x00PCS");
+        zip
+    }
+
+    #[test]
+    fn test_ee_memory() {
+        assert_eq!(Ok(b"hello".to_vec()), ee_memory(&zip(EE_MEMORY_FILE, 0, b"hello", 5)));
+
+        let deflated = [0xcb, 0x48, 0xcd, 0xc9, 0xc9, 0x57, 0xc8, 0x40, 0x90, 0x00];
+        assert_eq!(
+            Ok(b"hello hello hello".to_vec()),
+            ee_memory(&zip(EE_MEMORY_FILE, 8, &deflated, 17))
+        );
+    }
+
+    #[test]
+    fn test_ee_memory_errors() {
+        let deflated = [0xcb, 0x48, 0xcd, 0xc9, 0xc9, 0x57, 0xc8, 0x40, 0x90, 0x00];
+        assert_eq!(Err(Error::NotSavestate), ee_memory(b"\x7fELF"));
+        assert_eq!(Err(Error::NotSavestate), ee_memory(&[0; 100]));
+        assert_eq!(Err(Error::NotFound), ee_memory(&zip(b"iopMemory.bin", 0, b"hello", 5)));
+        assert_eq!(
+            Err(Error::UnsupportedCompression(93)),
+            ee_memory(&zip(EE_MEMORY_FILE, 93, b"hello", 5))
+        );
+        assert_eq!(Err(Error::Corrupt), ee_memory(&zip(EE_MEMORY_FILE, 8, &deflated, 16)));
+        assert_eq!(
+            Err(Error::Corrupt),
+            ee_memory(&zip(EE_MEMORY_FILE, 8, &deflated, EE_RAM_SIZE as u32 + 1))
+        );
+        assert_eq!(
+            Err(Error::Corrupt),
+            ee_memory(&zip(EE_MEMORY_FILE, 8, &deflated[..6], 17))
+        );
+
+        let mut zip = zip(EE_MEMORY_FILE, 0, b"hello", 5);
+        zip.drain(..10);
+        assert_eq!(Err(Error::Truncated), ee_memory(&zip));
+    }
+
+    #[test]
+    fn test_ee_memory_offsets() {
+        // Offsets and sizes near the end of the address space must not wrap;
+        // the archive ends with a comment of 3 bytes
+        let zip = zip(EE_MEMORY_FILE, 0, b"hello", 5);
+        let eocd = zip.len() - EOCD_SIZE - 3;
+        let central = le32(&zip, eocd + 16).unwrap() as usize;
+        for &offset in [eocd + 16, central + 20, central + 42].iter() {
+            let mut zip = zip.clone();
+            zip[offset..offset + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+            assert_eq!(Err(Error::Truncated), ee_memory(&zip));
+        }
+    }
+}
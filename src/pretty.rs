@@ -0,0 +1,232 @@
+//! Pretty-printing of decrypted code lists.
+//!
+//! A [`Printer`] renders codes for reviews of big cheat sets. It can indent
+//! the continuation lines of multi-line codes, align annotations in a
+//! column, separate codes writing to different 16-byte regions, and
+//! annotate codes with the names of the addresses they write to. For old
+//! Windows tools, it can end lines with CRLF and start with a byte order
+//! mark.
+//!
+//! # Example
+//! ```
+//! use codebreaker::pretty::Printer;
+//! use std::collections::BTreeMap;
+//!
+//! let mut symbols = BTreeMap::new();
+//! symbols.insert(0x0043AFCC, String::from("player_hp"));
+//!
+//! let codes = [(0x1043AFCC, 0x0000FFFF), (0x4041000C, 0x00020001), (0x00000063, 0x00000000)];
+//! let printer = Printer::new().align(true).group(true).symbols(&symbols);
+//! assert_eq!(
+//!     "1043AFCC 0000FFFF    // player_hp\n\
+//!      \n\
+//!      4041000C 00020001\n  \
+//!        00000063 00000000\n",
+//!     printer.to_string(&codes)
+//! );
+//! ```
+
+use crate::analysis::write_address;
+use crate::num_code_lines;
+use crate::std_alloc::{BTreeMap, String};
+
+use core::fmt::{self, Write};
+
+const CODE_WIDTH: usize = 17;
+const INDENT: &str = "  ";
+const BOM: char = '\u{feff}';
+
+/// A lookup of names for addresses.
+pub trait Symbols {
+    /// Returns the name of an address, if any.
+    fn lookup(&self, addr: u32) -> Option<&str>;
+}
+
+impl Symbols for BTreeMap<u32, String> {
+    fn lookup(&self, addr: u32) -> Option<&str> {
+        self.get(&addr).map(String::as_str)
+    }
+}
+
+/// Renders lists of decrypted codes.
+#[derive(Clone, Copy, Default)]
+pub struct Printer<'a> {
+    align: bool,
+    group: bool,
+    crlf: bool,
+    bom: bool,
+    symbols: Option<&'a dyn Symbols>,
+}
+
+impl fmt::Debug for Printer<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Printer")
+            .field("align", &self.align)
+            .field("group", &self.group)
+            .field("crlf", &self.crlf)
+            .field("bom", &self.bom)
+            .field("symbols", &self.symbols.is_some())
+            .finish()
+    }
+}
+
+impl<'a> Printer<'a> {
+    /// Returns a printer that renders one code per line.
+    pub const fn new() -> Self {
+        Self {
+            align: false,
+            group: false,
+            crlf: false,
+            bom: false,
+            symbols: None,
+        }
+    }
+
+    /// Indents continuation lines and aligns annotations in a column.
+    pub const fn align(mut self, align: bool) -> Self {
+        self.align = align;
+        self
+    }
+
+    /// Separates codes writing to different 16-byte regions with a blank
+    /// line.
+    pub const fn group(mut self, group: bool) -> Self {
+        self.group = group;
+        self
+    }
+
+    /// Ends lines with CRLF instead of LF.
+    pub const fn crlf(mut self, crlf: bool) -> Self {
+        self.crlf = crlf;
+        self
+    }
+
+    /// Starts the output with a UTF-8 byte order mark.
+    pub const fn bom(mut self, bom: bool) -> Self {
+        self.bom = bom;
+        self
+    }
+
+    /// Annotates codes with the names of the addresses they write to.
+    pub const fn symbols(mut self, symbols: &'a dyn Symbols) -> Self {
+        self.symbols = Some(symbols);
+        self
+    }
+
+    /// Writes the rendered codes, one line each.
+    pub fn write<W: Write>(&self, w: &mut W, codes: &[(u32, u32)]) -> fmt::Result {
+        let mut continuation = 0;
+        let mut region = None;
+        let newline = if self.crlf { "\r\n" } else { "\n" };
+
+        if self.bom {
+            w.write_char(BOM)?;
+        }
+
+        for &(addr, val) in codes.iter() {
+            let first = continuation == 0;
+            let mut name = None;
+
+            if first {
+                continuation = num_code_lines(addr) - 1;
+                if let Some(target) = write_address(addr, val) {
+                    if self.group && region.is_some_and(|r| r != target & !0xf) {
+                        w.write_str(newline)?;
+                    }
+                    region = Some(target & !0xf);
+                    name = self.symbols.and_then(|s| s.lookup(target));
+                }
+            } else {
+                continuation -= 1;
+            }
+
+            let indent = if self.align && !first { INDENT } else { "" };
+            write!(w, "{}{:08X} {:08X}", indent, addr, val)?;
+            if let Some(name) = name {
+                let pad = if self.align { INDENT.len() + 2 } else { 1 };
+                write!(w, "{:pad$}// {}", "", name, pad = pad)?;
+            }
+            w.write_str(newline)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the rendered codes.
+    pub fn to_string(&self, codes: &[(u32, u32)]) -> String {
+        let mut s = String::with_capacity(codes.len() * (CODE_WIDTH + 2));
+        // Writing to a String cannot fail
+        let _ = self.write(&mut s, codes);
+        s
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn codes() -> [(u32, u32); 6] {
+        [
+            (0xbeef_c0df, 0xb16b_00b5),
+            (0x0123_4567, 0x89ab_cdef),
+            (0x0031_7890, 0x0000_0063),
+            (0x1031_789a, 0x0000_ffff),
+            (0x3040_0000, 0x0031_78a0),
+            (0x0000_0001, 0x0000_0000),
+        ]
+    }
+
+    #[test]
+    fn test_plain() {
+        assert_eq!(
+            "BEEFC0DF B16B00B5\n\
+             01234567 89ABCDEF\n\
+             00317890 00000063\n\
+             1031789A 0000FFFF\n\
+             30400000 003178A0\n\
+             00000001 00000000\n",
+            Printer::new().to_string(&codes())
+        );
+    }
+
+    #[test]
+    fn test_options() {
+        let mut symbols = BTreeMap::new();
+        symbols.insert(0x0031_789a, String::from("hp"));
+        symbols.insert(0x0031_78a0, String::from("lives"));
+        symbols.insert(0x0123_4567, String::from("not a write"));
+
+        assert_eq!(
+            "BEEFC0DF B16B00B5\n\
+             01234567 89ABCDEF\n\
+             00317890 00000063\n\
+             1031789A 0000FFFF // hp\n\
+             30400000 003178A0 // lives\n\
+             00000001 00000000\n",
+            Printer::new().symbols(&symbols).to_string(&codes())
+        );
+        assert_eq!(
+            "BEEFC0DF B16B00B5\n  \
+               01234567 89ABCDEF\n\
+             00317890 00000063\n\
+             1031789A 0000FFFF    // hp\n\
+             \n\
+             30400000 003178A0    // lives\n  \
+               00000001 00000000\n",
+            Printer::new()
+                .align(true)
+                .group(true)
+                .symbols(&symbols)
+                .to_string(&codes())
+        );
+    }
+
+    #[test]
+    fn test_line_endings() {
+        let codes = &codes()[3..];
+        assert_eq!(
+            "\u{feff}1031789A 0000FFFF\r\n\r\n30400000 003178A0\r\n00000001 00000000\r\n",
+            Printer::new().group(true).crlf(true).bom(true).to_string(codes)
+        );
+        assert_eq!("\u{feff}", Printer::new().bom(true).to_string(&[]));
+    }
+}
@@ -0,0 +1,252 @@
+//! The building blocks of CB v7 encryption.
+//!
+//! [`Cb7`](../cb7/struct.Cb7.html) encrypts a code in four steps, all of
+//! which are available here:
+//!
+//! 1. [`mul_encrypt`] of address and value with parts of the key
+//! 2. [`rc4_crypt`] with the key
+//! 3. [`rsa_crypt`] with [`RSA_ENC_KEY`]
+//! 4. [`seed_encrypt`], 64 rounds with the seeds
+//!
+//! Decryption does the inverse steps in reverse order. A beefcode sets up a
+//! new key with [`derive_key`] and then [`scramble_seeds`].
+//!
+//! # Example
+//! ```
+//! use codebreaker::primitives::*;
+//!
+//! let key: [u32; 5] = [0x1234_5678, 0x9abc_def0, 0x0fed_cba9, 0x8765_4321, 0x0f0f_0f0f];
+//! let seeds = [[0xa5; 256]; 5];
+//!
+//! let (addr, val) = (0x2043AFCC, 0x2411FFFF);
+//! let (a, v) = (mul_encrypt(addr, key[0].wrapping_sub(key[1])), mul_encrypt(val, key[2].wrapping_add(key[3])));
+//! let (a, v) = rc4_crypt(&key, a, v);
+//! let (a, v) = rsa_crypt(a, v, RSA_ENC_KEY);
+//! let (a, v) = seed_encrypt(a, v, &seeds);
+//!
+//! let (a, v) = seed_decrypt(a, v, &seeds);
+//! let (a, v) = rsa_crypt(a, v, RSA_DEC_KEY);
+//! let (a, v) = rc4_crypt(&key, a, v);
+//! let (a, v) = (mul_decrypt(a, key[0].wrapping_sub(key[1])), mul_decrypt(v, key[2].wrapping_add(key[3])));
+//! assert_eq!((addr, val), (a, v));
+//! ```
+
+use crate::rc4::Rc4;
+
+use bytemuck::{bytes_of, bytes_of_mut, cast_slice};
+
+/// The key that a first beefcode starts from.
+pub const RC4_KEY: [u32; 5] = [0xd0db_a9d7, 0x13a0_a96c, 0x8041_0df0, 0x2ccd_be1f, 0xe570_a86b];
+
+/// The RSA exponent used for decryption.
+pub const RSA_DEC_KEY: u64 = 11;
+// This is how I calculated the encryption key e from d (some number theory):
+//
+//   d = 11
+//   n = 18446744073709551605
+//   e = d^(-1) mod phi(n)
+//
+//   n factored:
+//   n = 5 * 2551 * 1446236305269271
+//     = p*q*r, only single prime factors
+//
+//   phi(n) = phi(p*q*r)
+//          = phi(p) * phi(q) * phi(r), phi(p) = p - 1
+//          = (p-1)*(q-1)*(r-1)
+//          = (5-1) * (2551-1) * (1446236305269271-1)
+//          = 4 * 2550 * 1446236305269270
+//          = 14751610313746554000
+//
+//   e = 11^(-1) mod 14751610313746554000
+//   e = 2682110966135737091
+/// The RSA exponent used for encryption.
+pub const RSA_ENC_KEY: u64 = 2_682_110_966_135_737_091;
+/// The RSA modulus, `0xFFFFFFFFFFFFFFF5`.
+pub const RSA_MODULUS: u64 = 18_446_744_073_709_551_605;
+
+/// Multiplies `a` by `b` made odd, modulo 2^32.
+#[inline(always)]
+pub const fn mul_encrypt(a: u32, b: u32) -> u32 {
+    a.wrapping_mul(b | 1)
+}
+
+/// Undoes [`mul_encrypt`] by multiplying with the inverse of `b` made odd.
+#[inline(always)]
+pub const fn mul_decrypt(a: u32, b: u32) -> u32 {
+    a.wrapping_mul(mod_inverse(b | 1))
+}
+
+/// Returns the multiplicative inverse of an odd `x` modulo 2^32.
+///
+/// The result is meaningless for even numbers.
+///
+/// # Example
+/// ```
+/// use codebreaker::primitives::mod_inverse;
+///
+/// assert_eq!(1, 0x4FD931FFu32.wrapping_mul(mod_inverse(0x4FD931FF)));
+/// ```
+pub const fn mod_inverse(x: u32) -> u32 {
+    // The code is based on Newton's method as explained in this blog post:
+    // https://lemire.me/blog/2017/09/18/computing-the-inverse-of-odd-integers/
+    let mut y = x;
+    // Call this recurrence formula 4 times for 32-bit values:
+    // f(y) = y * (2 - y * x) modulo 2^32
+    y = y.wrapping_mul(2u32.wrapping_sub(y.wrapping_mul(x)));
+    y = y.wrapping_mul(2u32.wrapping_sub(y.wrapping_mul(x)));
+    y = y.wrapping_mul(2u32.wrapping_sub(y.wrapping_mul(x)));
+    y = y.wrapping_mul(2u32.wrapping_sub(y.wrapping_mul(x)));
+    y
+}
+
+/// Encrypts or decrypts a code with RC4 keyed by the 20 bytes of `key`.
+pub fn rc4_crypt(key: &[u32; 5], addr: u32, val: u32) -> (u32, u32) {
+    let mut code = [addr, val];
+    Rc4::new(bytes_of(key)).crypt(bytes_of_mut(&mut code));
+    code.into()
+}
+
+/// Raises the code, taken as the 64-bit number `addr:val`, to the power of
+/// `key` modulo [`RSA_MODULUS`].
+///
+/// Codes that are not below the modulus cannot be inverted and are returned
+/// unchanged, as by the device.
+pub fn rsa_crypt(addr: u32, val: u32, key: u64) -> (u32, u32) {
+    use num_bigint::BigUint;
+
+    let code = BigUint::from_slice(&[val, addr]);
+    let m = BigUint::from(RSA_MODULUS);
+
+    // Exponentiation is only invertible if code < modulus
+    if code < m {
+        let digits = code.modpow(&BigUint::from(key), &m).to_u32_digits();
+        // Leading zero digits are left out
+        let digit = |i: usize| digits.get(i).copied().unwrap_or(0);
+        (digit(1), digit(0))
+    } else {
+        (addr, val)
+    }
+}
+
+/// Runs the 64 rounds of encryption with the seeds.
+pub fn seed_encrypt(mut addr: u32, mut val: u32, seeds: &[[u8; 256]; 5]) -> (u32, u32) {
+    let s: &[u32] = cast_slice(seeds);
+    for i in 0..64 {
+        addr = (addr.wrapping_add(s[2 * 64 + i]) ^ s[i]).wrapping_sub(val ^ s[4 * 64 + i]);
+        val = (val.wrapping_sub(s[3 * 64 + i]) ^ s[64 + i]).wrapping_add(addr ^ s[4 * 64 + i]);
+    }
+    (addr, val)
+}
+
+/// Undoes [`seed_encrypt`].
+pub fn seed_decrypt(mut addr: u32, mut val: u32, seeds: &[[u8; 256]; 5]) -> (u32, u32) {
+    let s: &[u32] = cast_slice(seeds);
+    for i in (0..64).rev() {
+        val = (val.wrapping_sub(addr ^ s[4 * 64 + i]) ^ s[64 + i]).wrapping_add(s[3 * 64 + i]);
+        addr = (addr.wrapping_add(val ^ s[4 * 64 + i]) ^ s[i]).wrapping_sub(s[2 * 64 + i]);
+    }
+    (addr, val)
+}
+
+/// Mixes the seed value of a beefcode into the first four words of a key.
+///
+/// Each byte of `val` picks a byte from each of the first four seeds.
+pub fn derive_key(seeds: &[[u8; 256]; 5], val: u32) -> [u32; 4] {
+    let idx = val.to_le_bytes().map(usize::from);
+    let mut key = [0; 4];
+    for (i, k) in key.iter_mut().enumerate() {
+        *k = u32::from(seeds[(i + 3) % 4][idx[3]]) << 24
+            | u32::from(seeds[(i + 2) % 4][idx[2]]) << 16
+            | u32::from(seeds[(i + 1) % 4][idx[1]]) << 8
+            | u32::from(seeds[i % 4][idx[0]]);
+    }
+    key
+}
+
+/// Encrypts each seed with RC4, keyed by the key, which is itself encrypted
+/// for the next seed.
+pub fn scramble_seeds(key: &mut [u32; 5], seeds: &mut [[u8; 256]; 5]) {
+    let k = bytes_of_mut(key);
+    for seed in seeds.iter_mut() {
+        let mut rc4 = Rc4::new(k);
+        rc4.crypt(seed);
+        rc4.crypt(k);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::std_alloc::Vec;
+
+    fn mul_tests() -> Vec<(u32, u32, u32)> {
+        vec![
+            (0x0000_0000, 0xa686_d3b6, 0x0000_0000),
+            (0x000e_0000, 0xa686_d3b6, 0xac62_0000),
+            (0x0067_bd20, 0x4fd9_31ff, 0x2008_02e0),
+            (0x2ba0_a76e, 0xa686_d3b6, 0x2405_0002),
+            (0x4adf_d954, 0x4fd9_31ff, 0x9029_beac),
+            (0x7c01_6806, 0x2912_dedd, 0x0000_00be),
+            (0xa942_2f21, 0xa686_d3b6, 0x03d2_03e7),
+            (0xfff5_76e0, 0xa686_d3b6, 0x27bd_0020),
+        ]
+    }
+
+    #[test]
+    fn test_mul_encrypt() {
+        for t in mul_tests().iter() {
+            assert_eq!(t.0, mul_encrypt(t.2, t.1));
+        }
+    }
+
+    #[test]
+    fn test_mul_decrypt() {
+        for t in mul_tests().iter() {
+            assert_eq!(t.2, mul_decrypt(t.0, t.1));
+        }
+    }
+
+    #[test]
+    fn test_mod_inverse() {
+        let tests = vec![
+            (0x0d31_3243, 0x6c7b_2a6b),
+            (0x0efd_8231, 0xd4c0_96d1),
+            (0x2912_dedd, 0xe09d_e975),
+            (0x4fd9_31ff, 0x9a62_cdff),
+            (0x5a53_abb5, 0x58f4_2a9d),
+            (0x9ab2_af6d, 0x1043_b265),
+            (0xa686_d3b7, 0x57ed_7a07),
+            (0xec35_a92f, 0xd274_3dcf),
+            (0x0000_0000, 0x0000_0000), // Technically, 0 has no inverse
+            (0x0000_0001, 0x0000_0001),
+            (0xffff_ffff, 0xffff_ffff),
+        ];
+        for t in tests.iter() {
+            assert_eq!(t.1, mod_inverse(t.0));
+        }
+    }
+
+    #[test]
+    fn test_rsa_crypt() {
+        for &(addr, val) in [(0x0123_4567, 0x89ab_cdef), (0, 1), (0, 0)].iter() {
+            let (a, v) = rsa_crypt(addr, val, RSA_ENC_KEY);
+            assert_eq!((addr, val), rsa_crypt(a, v, RSA_DEC_KEY));
+        }
+        // Not below the modulus
+        assert_eq!(
+            (0xffff_ffff, 0xffff_fff5),
+            rsa_crypt(0xffff_ffff, 0xffff_fff5, RSA_ENC_KEY)
+        );
+    }
+
+    #[test]
+    fn test_seed_crypt() {
+        let mut key = RC4_KEY;
+        let mut seeds = [[0; 256]; 5];
+        scramble_seeds(&mut key, &mut seeds);
+        assert_ne!(RC4_KEY, key);
+        let (addr, val) = seed_encrypt(0x2043_afcc, 0x2411_ffff, &seeds);
+        assert_ne!((0x2043_afcc, 0x2411_ffff), (addr, val));
+        assert_eq!((0x2043_afcc, 0x2411_ffff), seed_decrypt(addr, val, &seeds));
+    }
+}
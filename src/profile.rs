@@ -0,0 +1,260 @@
+//! Cheat profiles, the cheats picked for a game and where they are used.
+//!
+//! A [`CheatProfile`] bundles the selected cheats of a [`Game`] with the
+//! [`Target`]s they are meant for, e.g. PCSX2 on the desktop and a real
+//! CodeBreaker on the console. [`export`](CheatProfile::export) writes the
+//! files of all targets from the same cheats in one call, so they do not
+//! drift apart.
+//!
+//! Not to be confused with [`firmware::Profile`](../firmware/enum.Profile.html),
+//! the code types of a CodeBreaker version.
+//!
+//! # Example
+//! ```
+//! use codebreaker::game::{Cheat, Game};
+//! use codebreaker::profile::{CheatProfile, Target};
+//!
+//! let mut game = Game::new("Kingdom Hearts");
+//! game.cheats.push(Cheat::new("Inf HP", vec![(0x2096F5B8, 0x000000BE)]));
+//! game.cheats.push(Cheat::new("Inf MP", vec![(0x2096F5BC, 0x00000064)]));
+//!
+//! let profile = CheatProfile::new(&game, &[0]).target(Target::Ps2rd).target(Target::Cb7);
+//! let artifacts = profile.export();
+//! assert_eq!("\"Kingdom Hearts\"\nInf HP\n2096F5B8 000000BE\n", artifacts[0].text);
+//! assert_eq!(
+//!     "\"Kingdom Hearts\"\n(M)\nB4336FA9 4DFEFB79\nInf HP\n973E0B2A A7D4AF10\n",
+//!     artifacts[1].text
+//! );
+//! ```
+
+use crate::cb7::is_beefcode;
+use crate::firmware::Profile;
+use crate::game::{Cheat, Game};
+use crate::mastercode::{is_mastercode, Mastercodes};
+#[cfg(feature = "pcsx2")]
+use crate::pcsx2::{self, Dialect};
+use crate::ps2rd;
+use crate::std_alloc::{String, Vec};
+use crate::Codebreaker;
+
+/// A device or program to export cheats for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Target {
+    /// The PCSX2 emulator, with a pnach file of the given dialect. Requires
+    /// the "pcsx2" feature.
+    #[cfg(feature = "pcsx2")]
+    Pcsx2(Dialect),
+    /// ps2rd and Open PS2 Loader, with a cheat file of decrypted codes
+    Ps2rd,
+    /// A real CodeBreaker v7+, with the codes encrypted for it
+    Cb7,
+}
+
+/// An exported file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Artifact {
+    /// The target the file is for
+    pub target: Target,
+    /// The contents of the file
+    pub text: String,
+}
+
+/// The selected cheats of a game and the targets they are exported for.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CheatProfile {
+    /// The title of the game and the selected cheats, decrypted
+    pub game: Game,
+    /// The targets in order of export
+    pub targets: Vec<Target>,
+    /// Mastercodes to add for ps2rd, by the ID in the title
+    pub mastercodes: Mastercodes,
+}
+
+impl CheatProfile {
+    /// Returns a profile of the cheats of a decrypted game at the indexes in
+    /// `selected`, without targets.
+    ///
+    /// Cheats keep their order in the game, as their codes may depend on
+    /// each other. Indexes past the last cheat are ignored.
+    pub fn new(game: &Game, selected: &[usize]) -> Self {
+        let mut profile = Self {
+            game: Game::new(&game.title),
+            ..Self::default()
+        };
+        for (i, cheat) in game.cheats.iter().enumerate() {
+            if selected.contains(&i) {
+                profile.game.cheats.push(cheat.clone());
+            }
+        }
+        profile
+    }
+
+    /// Adds a target, unless the profile already has it.
+    #[must_use]
+    pub fn target(mut self, target: Target) -> Self {
+        if !self.targets.contains(&target) {
+            self.targets.push(target);
+        }
+        self
+    }
+
+    /// Sets the mastercodes added for ps2rd, as with
+    /// [`ps2rd::write_with_mastercodes`](../ps2rd/fn.write_with_mastercodes.html).
+    #[must_use]
+    pub fn mastercodes(mut self, mastercodes: Mastercodes) -> Self {
+        self.mastercodes = mastercodes;
+        self
+    }
+
+    /// Writes the file of each target, in order.
+    ///
+    /// - PCSX2 gets a pnach file, written like
+    ///   [`pcsx2::write_pnach_with`](../pcsx2/fn.write_pnach_with.html).
+    /// - ps2rd gets a cheat file without beefcodes, which only CodeBreaker
+    ///   knows. Cheats left without codes are dropped.
+    /// - CodeBreaker gets the codes encrypted for CB v7, in the layout of a
+    ///   ps2rd cheat file. A game without a beefcode gets one, in front of its
+    ///   mastercode or else as a cheat of its own named `(M)`.
+    pub fn export(&self) -> Vec<Artifact> {
+        self.targets
+            .iter()
+            .map(|&target| Artifact {
+                target,
+                text: self.export_to(target),
+            })
+            .collect()
+    }
+
+    fn export_to(&self, target: Target) -> String {
+        match target {
+            #[cfg(feature = "pcsx2")]
+            Target::Pcsx2(dialect) => pcsx2::to_pnach_with(&self.game, dialect),
+            Target::Ps2rd => {
+                let mut game = Game::new(&self.game.title);
+                for cheat in self.game.cheats.iter() {
+                    let codes = without_beefcodes(&cheat.codes);
+                    if !codes.is_empty() || cheat.codes.is_empty() {
+                        game.cheats.push(Cheat::new(&cheat.name, codes));
+                    }
+                }
+                let mut text = String::new();
+                // Writing to a String cannot fail
+                let _ = ps2rd::write_with_mastercodes(&mut text, &[game], &self.mastercodes);
+                text
+            }
+            Target::Cb7 => {
+                let mut game = self.game.clone();
+                let has_beefcode = game
+                    .cheats
+                    .iter()
+                    .any(|cheat| cheat.codes.iter().any(|&(addr, _)| is_beefcode(addr)));
+                if !has_beefcode {
+                    match game.cheats.first_mut() {
+                        Some(cheat) if is_mastercode(cheat) => cheat.codes.insert(0, (0xbeef_c0de, 0)),
+                        _ => game.cheats.insert(0, Cheat::new("(M)", vec![(0xbeef_c0de, 0)])),
+                    }
+                }
+                game.encrypt(&mut Codebreaker::new());
+                ps2rd::to_string(&[game])
+            }
+        }
+    }
+}
+
+// Returns the codes without beefcodes and the lines that follow them.
+fn without_beefcodes(codes: &[(u32, u32)]) -> Vec<(u32, u32)> {
+    let mut rest = Vec::with_capacity(codes.len());
+    let mut skip = 0;
+    for &(addr, val) in codes.iter() {
+        if skip > 0 {
+            skip -= 1;
+        } else if is_beefcode(addr) {
+            skip = Profile::V7.lines(addr) - 1;
+        } else {
+            rest.push((addr, val));
+        }
+    }
+    rest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn game() -> Game {
+        let mut game = Game::new("Kingdom Hearts /ID SLUS_203.70");
+        game.cheats.push(Cheat::new("(M)", vec![(0xbeef_c0de, 0)]));
+        game.cheats.push(Cheat::new("Inf HP", vec![(0x2096_f5b8, 0x0000_00be)]));
+        game.cheats.push(Cheat::new("Notes", vec![]));
+        game.cheats.push(Cheat::new("Inf MP", vec![(0x2096_f5bc, 0x0000_0064)]));
+        game
+    }
+
+    #[test]
+    fn test_new() {
+        let profile = CheatProfile::new(&game(), &[3, 1, 9])
+            .target(Target::Cb7)
+            .target(Target::Cb7);
+        let names: Vec<_> = profile.game.cheats.iter().map(|cheat| cheat.name.as_str()).collect();
+        assert_eq!(vec!["Inf HP", "Inf MP"], names);
+        assert_eq!("Kingdom Hearts /ID SLUS_203.70", profile.game.title);
+        assert_eq!(vec![Target::Cb7], profile.targets);
+    }
+
+    #[test]
+    fn test_export_ps2rd() {
+        let mut mastercodes = Mastercodes::new();
+        mastercodes.insert("SLUS-20370", vec![(0x9026_9c18, 0x0c09_a6e6)]);
+        let profile = CheatProfile::new(&game(), &[0, 1, 2])
+            .target(Target::Ps2rd)
+            .mastercodes(mastercodes);
+        assert_eq!(
+            vec![Artifact {
+                target: Target::Ps2rd,
+                text: "\"Kingdom Hearts /ID SLUS_203.70\"
+Mastercode
+90269C18 0C09A6E6
+Inf HP
+2096F5B8 000000BE
+Notes
+"
+                .into(),
+            }],
+            profile.export()
+        );
+    }
+
+    #[test]
+    fn test_export_cb7() {
+        let profile = CheatProfile::new(&game(), &[0, 1]).target(Target::Cb7);
+        let text = "\"Kingdom Hearts /ID SLUS_203.70\"\n(M)\nB4336FA9 4DFEFB79\nInf HP\n973E0B2A A7D4AF10\n";
+        assert_eq!(text, profile.export()[0].text);
+
+        // The beefcode goes in front of the mastercode
+        let mut game = Game::new("KH");
+        game.cheats
+            .push(Cheat::new("Mastercode", vec![(0x9026_9c18, 0x0c09_a6e6)]));
+        let text = &CheatProfile::new(&game, &[0]).target(Target::Cb7).export()[0].text;
+        let mut cheats = ps2rd::parse(text).unwrap().remove(0).cheats;
+        assert_eq!(1, cheats.len());
+        cheats[0].decrypt(&mut Codebreaker::new());
+        assert_eq!(vec![(0xbeef_c0de, 0), (0x9026_9c18, 0x0c09_a6e6)], cheats[0].codes);
+    }
+
+    #[cfg(feature = "pcsx2")]
+    #[test]
+    fn test_export_pcsx2() {
+        let game = game();
+        let profile = CheatProfile::new(&game, &[0, 1])
+            .target(Target::Pcsx2(Dialect::Legacy))
+            .target(Target::Pcsx2(Dialect::Extended));
+        let artifacts = profile.export();
+        assert_eq!(2, artifacts.len());
+        assert_eq!(
+            "gametitle=Kingdom Hearts /ID SLUS_203.70\n\n// (M)\n\n// Inf HP\npatch=1,EE,0096F5B8,word,000000BE\n",
+            artifacts[0].text
+        );
+        assert_eq!(pcsx2::to_pnach(&profile.game), artifacts[1].text);
+    }
+}
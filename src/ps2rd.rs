@@ -0,0 +1,342 @@
+//! Cheat files of ps2rd and Open PS2 Loader.
+//!
+//! OPL and ps2rd read cheats from a `cheats.txt` file that lists games by
+//! their quoted title, each followed by its cheats: a name on a line of its
+//! own, then the decrypted codes of the cheat, one per line.
+//!
+//! ```text
+//! "Kingdom Hearts /ID SLUS_203.70"
+//! Mastercode
+//! 90269C18 0C09A6E6
+//! // Player 1
+//! Inf HP
+//! 2096F5B8 000000BE
+//! ```
+//!
+//! The mastercode is simply the first cheat of a game. Comments start with
+//! `//` or `#`. [`parse`] reads such a file into [`Game`]s and [`write`](fn@write)
+//! writes them back; comments are not kept. [`write_with_mastercodes`] also
+//! adds missing mastercodes from a [`Mastercodes`] table, by the ID in the
+//! title.
+//!
+//! # Example
+//! ```
+//! use codebreaker::ps2rd;
+//!
+//! let text = "\"Kingdom Hearts\"\nMastercode\n90269C18 0C09A6E6\nInf HP\n2096F5B8 000000BE\n";
+//! let games = ps2rd::parse(text).unwrap();
+//! assert_eq!("Kingdom Hearts", games[0].title);
+//! assert_eq!("Mastercode", games[0].cheats[0].name);
+//! assert_eq!(vec![(0x2096F5B8, 0x000000BE)], games[0].cheats[1].codes);
+//! assert_eq!(text, ps2rd::to_string(&games));
+//! ```
+
+use crate::game::{Cheat, Game};
+use crate::mastercode::Mastercodes;
+use crate::std_alloc::{String, Vec};
+use crate::text::{looks_like_code, split_comment};
+use crate::{Code, ParseCodeError};
+
+use core::fmt::{self, Write};
+
+/// The error type for reading cheat files.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// A cheat comes before the first game, at the given line.
+    NoGame(usize),
+    /// A code comes before the first cheat of a game, at the given line.
+    NoCheat(usize),
+    /// A title lacks its closing quote, at the given line.
+    UnterminatedTitle(usize),
+    /// A line that starts like a code is not a valid code.
+    InvalidCode {
+        /// Index of the offending line
+        line: usize,
+        /// Why the line is not a code
+        reason: ParseCodeError,
+    },
+}
+
+/// Lines are counted from 1.
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoGame(line) => write!(f, "line {}: cheat outside of a game", line + 1),
+            Self::NoCheat(line) => write!(f, "line {}: code outside of a cheat", line + 1),
+            Self::UnterminatedTitle(line) => write!(f, "line {}: title is not closed", line + 1),
+            Self::InvalidCode { line, reason } => write!(f, "line {}: {}", line + 1, reason),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+/// Reads the games of a cheat file.
+///
+/// Anything after the closing quote of a title is ignored, as are blank
+/// lines, comments, and a leading byte order mark. Games and cheats keep
+/// their order.
+pub fn parse(text: &str) -> Result<Vec<Game>, Error> {
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    let mut games: Vec<Game> = Vec::new();
+    for (line, content) in text.lines().enumerate() {
+        let content = content.trim();
+        if content.is_empty() || content.starts_with("//") || content.starts_with('#') {
+            continue;
+        }
+        if let Some(rest) = content.strip_prefix('"') {
+            let (title, _) = rest.split_once('"').ok_or(Error::UnterminatedTitle(line))?;
+            games.push(Game::new(title));
+            continue;
+        }
+        let game = games.last_mut().ok_or(Error::NoGame(line))?;
+        let (body, _) = split_comment(content);
+        match body.parse::<Code>() {
+            Ok(code) => {
+                let cheat = game.cheats.last_mut().ok_or(Error::NoCheat(line))?;
+                cheat.codes.push(code.into());
+            }
+            Err(reason) if looks_like_code(body) => return Err(Error::InvalidCode { line, reason }),
+            Err(_) => game.cheats.push(Cheat::new(content, Vec::new())),
+        }
+    }
+    Ok(games)
+}
+
+/// Writes games as a cheat file, separated by blank lines.
+///
+/// Titles and names are written up to the end of their first line, as a
+/// line break would end them. Cheats without a name are written as
+/// `Unnamed`, and names that would be read as a comment, title, or code get a
+/// `- ` prefix, so that [`parse`] reads the file back.
+pub fn write<W: Write>(w: &mut W, games: &[Game]) -> fmt::Result {
+    for (i, game) in games.iter().enumerate() {
+        if i > 0 {
+            w.write_char('\n')?;
+        }
+        // A quote would end the title early
+        let title = first_line(&game.title).replace('"', "'");
+        writeln!(w, "\"{}\"", title)?;
+        for cheat in game.cheats.iter() {
+            let name = first_line(&cheat.name).trim();
+            if name.is_empty() {
+                writeln!(w, "Unnamed")?;
+            } else if is_name(name) {
+                writeln!(w, "{}", name)?;
+            } else {
+                writeln!(w, "- {}", name)?;
+            }
+            for &(addr, val) in cheat.codes.iter() {
+                writeln!(w, "{:08X} {:08X}", addr, val)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Writes games as a cheat file like [`write`](fn@write), adding the mastercode from
+/// `mastercodes` to each game that has none.
+///
+/// Games are looked up by the [`game_id`] of their title; games without an
+/// ID or mastercode in the table are written as they are.
+///
+/// # Example
+/// ```
+/// use codebreaker::game::{Cheat, Game};
+/// use codebreaker::mastercode::Mastercodes;
+/// use codebreaker::ps2rd;
+///
+/// let mastercodes = Mastercodes::parse("SLUS-20370 90269C18 0C09A6E6\n").unwrap();
+/// let mut game = Game::new("Kingdom Hearts /ID SLUS_203.70");
+/// game.cheats.push(Cheat::new("Inf HP", vec![(0x2096F5B8, 0x000000BE)]));
+///
+/// let mut text = String::new();
+/// ps2rd::write_with_mastercodes(&mut text, &[game], &mastercodes).unwrap();
+/// assert_eq!(
+///     "\"Kingdom Hearts /ID SLUS_203.70\"\nMastercode\n90269C18 0C09A6E6\nInf HP\n2096F5B8 000000BE\n",
+///     text
+/// );
+/// ```
+pub fn write_with_mastercodes<W: Write>(w: &mut W, games: &[Game], mastercodes: &Mastercodes) -> fmt::Result {
+    let games: Vec<Game> = games
+        .iter()
+        .map(|game| {
+            let mut copy = game.clone();
+            if let Some(id) = game_id(&game.title) {
+                mastercodes.attach(id, &mut copy);
+            }
+            copy
+        })
+        .collect();
+    write(w, &games)
+}
+
+/// Returns the game ID given in a title after `/ID`, e.g. `SLUS_203.70` for
+/// `Kingdom Hearts /ID SLUS_203.70`.
+pub fn game_id(title: &str) -> Option<&str> {
+    let (_, rest) = title.split_once("/ID")?;
+    rest.split_whitespace().next()
+}
+
+/// Returns games as a cheat file, like [`write`](fn@write).
+pub fn to_string(games: &[Game]) -> String {
+    let mut s = String::new();
+    // Writing to a String cannot fail
+    let _ = write(&mut s, games);
+    s
+}
+
+fn first_line(s: &str) -> &str {
+    s.lines().next().unwrap_or("")
+}
+
+// Returns true if parse reads the line as the name of a cheat.
+fn is_name(line: &str) -> bool {
+    let (body, _) = split_comment(line);
+    !(line.starts_with("//") || line.starts_with('#') || line.starts_with('"'))
+        && body.parse::<Code>().is_err()
+        && !looks_like_code(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHEATS: &str = "\u{feff}// Exported by hand
+\"Kingdom Hearts /ID SLUS_203.70\" extra
+
+Enable Code (Must Be On)
+90269C18 0C09A6E6
+# Player 1
+Inf HP
+2096F5B8 000000BE // BE is max
+1043AFD0 0000FFFF
+
+\"Final Fantasy X\"
+Max Gil
+";
+
+    #[test]
+    fn test_parse() {
+        let games = parse(CHEATS).unwrap();
+        let mut kh = Game::new("Kingdom Hearts /ID SLUS_203.70");
+        kh.cheats
+            .push(Cheat::new("Enable Code (Must Be On)", vec![(0x9026_9c18, 0x0c09_a6e6)]));
+        kh.cheats.push(Cheat::new(
+            "Inf HP",
+            vec![(0x2096_f5b8, 0x0000_00be), (0x1043_afd0, 0x0000_ffff)],
+        ));
+        let mut ffx = Game::new("Final Fantasy X");
+        ffx.cheats.push(Cheat::new("Max Gil", vec![]));
+        assert_eq!(vec![kh, ffx], games);
+        assert_eq!(Ok(vec![]), parse(""));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(Err(Error::NoGame(0)), parse("Inf HP\n"));
+        assert_eq!(Err(Error::NoCheat(1)), parse("\"KH\"\n2096F5B8 000000BE\n"));
+        assert_eq!(Err(Error::UnterminatedTitle(0)), parse("\"KH\n"));
+        assert_eq!(
+            Err(Error::InvalidCode {
+                line: 2,
+                reason: ParseCodeError::MissingValue
+            }),
+            parse("\"KH\"\nInf HP\n2096F5B8\n")
+        );
+        assert_eq!(
+            "line 3: code has no value",
+            format!("{}", parse("\"KH\"\nInf HP\n2096F5B8\n").unwrap_err())
+        );
+    }
+
+    #[test]
+    fn test_write() {
+        let games = parse(CHEATS).unwrap();
+        let text = to_string(&games);
+        assert_eq!(
+            "\"Kingdom Hearts /ID SLUS_203.70\"
+Enable Code (Must Be On)
+90269C18 0C09A6E6
+Inf HP
+2096F5B8 000000BE
+1043AFD0 0000FFFF
+
+\"Final Fantasy X\"
+Max Gil
+",
+            text
+        );
+        assert_eq!(Ok(games), parse(&text));
+
+        let mut game = Game::new("Say \"Hi\"\nagain");
+        game.cheats.push(Cheat::new("Two\nlines", vec![]));
+        assert_eq!("\"Say 'Hi'\"\nTwo\n", to_string(&[game]));
+        assert_eq!("", to_string(&[]));
+    }
+
+    #[test]
+    fn test_game_id() {
+        assert_eq!(Some("SLUS_203.70"), game_id("Kingdom Hearts /ID SLUS_203.70"));
+        assert_eq!(Some("SLUS-20946"), game_id("/ID SLUS-20946 Kingdom Hearts"));
+        assert_eq!(None, game_id("Kingdom Hearts /ID "));
+        assert_eq!(None, game_id("Kingdom Hearts"));
+    }
+
+    #[test]
+    fn test_write_with_mastercodes() {
+        let mut mastercodes = Mastercodes::new();
+        mastercodes.insert("SLUS-20370", vec![(0x9026_9c18, 0x0c09_a6e6)]);
+        let mut games = parse(CHEATS).unwrap();
+        games[0].cheats.remove(0);
+        games[1].title.push_str(" /ID SLUS-20370");
+        let mut text = String::new();
+        write_with_mastercodes(&mut text, &games, &mastercodes).unwrap();
+        let written = parse(&text).unwrap();
+        assert_eq!("Mastercode", written[0].cheats[0].name);
+        assert_eq!(2, written[0].cheats.len());
+        assert_eq!("Mastercode", written[1].cheats[0].name);
+
+        // Games that have one keep their own
+        let games = parse(CHEATS).unwrap();
+        text.clear();
+        write_with_mastercodes(&mut text, &games, &mastercodes).unwrap();
+        assert_eq!(to_string(&games), text);
+    }
+
+    #[test]
+    fn test_write_names() {
+        let mut game = Game::new("KH");
+        for name in [
+            "",
+            "# Player 1",
+            "// Max",
+            "\"Inf HP\"",
+            "2096F5B8 000000BE",
+            "2096F5B8",
+            " Inf MP ",
+        ] {
+            game.cheats.push(Cheat::new(name, vec![(0x2096_f5b8, 0x0000_00be)]));
+        }
+        let text = to_string(&[game]);
+        let names: Vec<_> = parse(&text).unwrap()[0]
+            .cheats
+            .iter()
+            .map(|cheat| (cheat.name.clone(), cheat.codes.len()))
+            .collect();
+        assert_eq!(
+            vec![
+                ("Unnamed".into(), 1),
+                ("- # Player 1".into(), 1),
+                ("- // Max".into(), 1),
+                ("- \"Inf HP\"".into(), 1),
+                ("- 2096F5B8 000000BE".into(), 1),
+                ("- 2096F5B8".into(), 1),
+                ("Inf MP".into(), 1),
+            ],
+            names
+        );
+    }
+}
@@ -0,0 +1,235 @@
+//! Lossless import of code lists with codes that cannot be decrypted.
+//!
+//! Archives collect lists for many devices, and a list may hold codes of an
+//! encryption the processor does not know. Decrypting such codes anyway
+//! yields garbage, and failing loses the whole list. [`import`] instead keeps
+//! every line of a list as an [`Entry`]: codes are decrypted, while lines
+//! that cannot be decrypted are kept as they are, with a [`Note`] telling
+//! why. [`to_encrypted`] and [`to_decrypted`] write the list back, with the
+//! quarantined lines unchanged.
+//!
+//! A code is quarantined if it contradicts a
+//! [pinned](../struct.Codebreaker.html#method.pin_scheme) scheme or decrypts
+//! to an [implausible](../repair/fn.is_plausible.html) code. Quarantined
+//! codes do not change the state of the processor, so they do not affect the
+//! codes after them.
+//!
+//! # Example
+//! ```
+//! use codebreaker::quarantine::{self, Note};
+//! use codebreaker::Codebreaker;
+//!
+//! let text = "Inf HP\n2A973DBD 00000000\n24CCDD1D 12345678\n";
+//! let entries = quarantine::import(&mut Codebreaker::new(), text);
+//! let notes: Vec<_> = quarantine::notes(&entries).collect();
+//! assert_eq!(vec![(2, &Note::Implausible((0x2E000000, 0x12345678)))], notes);
+//!
+//! assert_eq!("Inf HP\n201F6024 00000000\n24CCDD1D 12345678\n", quarantine::to_decrypted(&entries));
+//! assert_eq!(text, quarantine::to_encrypted(&mut Codebreaker::new(), &entries));
+//! ```
+
+use crate::firmware::Profile;
+use crate::repair::is_plausible;
+use crate::std_alloc::{String, Vec};
+use crate::text::{looks_like_code, split_comment};
+use crate::{Code, Codebreaker, Error, ParseCodeError};
+
+use core::fmt::{self, Write};
+
+/// Why a line was quarantined.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Note {
+    /// The line starts like a code but is not a valid code.
+    Malformed(ParseCodeError),
+    /// The code contradicts the scheme the processor was pinned to.
+    Rejected(Error),
+    /// The code decrypts to the given implausible code.
+    Implausible((u32, u32)),
+}
+
+impl fmt::Display for Note {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Malformed(reason) => write!(f, "malformed code: {}", reason),
+            Self::Rejected(err) => write!(f, "rejected: {}", err),
+            Self::Implausible((addr, val)) => write!(f, "decrypts to implausible code {:08X} {:08X}", addr, val),
+        }
+    }
+}
+
+/// A line of an imported list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Entry {
+    /// A line without a code, like a cheat name, a comment, or a blank line
+    Text(String),
+    /// A code, decrypted
+    Code {
+        /// The decrypted code
+        code: Code,
+        /// The rest of the line after the code, including the spacing
+        /// before a comment
+        comment: String,
+    },
+    /// A line that could not be decrypted, kept as is
+    Quarantined {
+        /// The line as imported
+        text: String,
+        /// Why the line was quarantined
+        note: Note,
+    },
+}
+
+/// Imports a list, one entry per line, decrypting its codes with `cb`.
+///
+/// Later lines of a multi-line code are taken as they decrypt, as their
+/// values need not look like codes.
+pub fn import(cb: &mut Codebreaker, text: &str) -> Vec<Entry> {
+    let mut entries = Vec::new();
+    let mut continuation = 0;
+    for line in text.lines() {
+        let (body, _) = split_comment(line);
+        if body.trim().is_empty() {
+            entries.push(Entry::Text(line.into()));
+            continue;
+        }
+        let quarantine = |note| Entry::Quarantined {
+            text: line.into(),
+            note,
+        };
+        let code = match body.parse::<Code>() {
+            Ok(code) => code,
+            Err(reason) if looks_like_code(body) => {
+                entries.push(quarantine(Note::Malformed(reason)));
+                continue;
+            }
+            Err(_) => {
+                entries.push(Entry::Text(line.into()));
+                continue;
+            }
+        };
+        // Only keep the state of the processor for codes that are kept
+        let mut next = *cb;
+        let entry = match next.try_auto_decrypt_code(code.addr, code.val) {
+            Err(err) => quarantine(Note::Rejected(err)),
+            Ok(decrypted) if continuation == 0 && !is_plausible(decrypted) => quarantine(Note::Implausible(decrypted)),
+            Ok(decrypted) => {
+                *cb = next;
+                continuation = if continuation > 0 {
+                    continuation - 1
+                } else {
+                    Profile::V7.lines(decrypted.0) - 1
+                };
+                Entry::Code {
+                    code: decrypted.into(),
+                    comment: line[body.trim_end().len()..].into(),
+                }
+            }
+        };
+        entries.push(entry);
+    }
+    entries
+}
+
+/// Returns the quarantined lines of a list with their notes, as pairs of
+/// line index and note.
+pub fn notes(entries: &[Entry]) -> impl Iterator<Item = (usize, &Note)> {
+    entries.iter().enumerate().filter_map(|(line, entry)| match entry {
+        Entry::Quarantined { note, .. } => Some((line, note)),
+        _ => None,
+    })
+}
+
+/// Returns the list as text, with codes encrypted by `cb` like
+/// [`auto_encrypt_code`](../struct.Codebreaker.html#method.auto_encrypt_code)
+/// and all other lines unchanged.
+pub fn to_encrypted(cb: &mut Codebreaker, entries: &[Entry]) -> String {
+    write(entries, |code| cb.auto_encrypt_code(code.addr, code.val).into())
+}
+
+/// Returns the list as text, with decrypted codes and all other lines
+/// unchanged.
+pub fn to_decrypted(entries: &[Entry]) -> String {
+    write(entries, |code| code)
+}
+
+fn write<F: FnMut(Code) -> Code>(entries: &[Entry], mut f: F) -> String {
+    let mut s = String::new();
+    // Writing to a String cannot fail
+    for entry in entries.iter() {
+        let _ = match entry {
+            Entry::Text(text) | Entry::Quarantined { text, .. } => writeln!(s, "{}", text),
+            Entry::Code { code, comment } => writeln!(s, "{}{}", f(*code), comment),
+        };
+    }
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Scheme;
+
+    #[test]
+    fn test_import() {
+        let text =
+            "// Kingdom Hearts\n\nInf HP\n2A973DBD 00000000 ; v1\n2A973DBD\nB4336FA9 4DFEFB79\n973E0B2A A7D4AF10\n";
+        let entries = import(&mut Codebreaker::new(), text);
+        assert_eq!(7, entries.len());
+        assert_eq!(Entry::Text("// Kingdom Hearts".into()), entries[0]);
+        assert_eq!(Entry::Text("".into()), entries[1]);
+        assert_eq!(Entry::Text("Inf HP".into()), entries[2]);
+        assert_eq!(
+            Entry::Code {
+                code: Code::new(0x201f_6024, 0),
+                comment: " ; v1".into()
+            },
+            entries[3]
+        );
+        assert_eq!(
+            Entry::Quarantined {
+                text: "2A973DBD".into(),
+                note: Note::Malformed(ParseCodeError::MissingValue)
+            },
+            entries[4]
+        );
+        assert_eq!(
+            vec![Code::new(0xbeef_c0de, 0), Code::new(0x2096_f5b8, 0xbe)],
+            entries[5..]
+                .iter()
+                .map(|e| match e {
+                    Entry::Code { code, .. } => *code,
+                    _ => unreachable!(),
+                })
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(text, to_encrypted(&mut Codebreaker::new(), &entries));
+    }
+
+    #[test]
+    fn test_import_quarantined() {
+        let mut cb = Codebreaker::new();
+        cb.pin_scheme(Scheme::Raw);
+        let entries = import(&mut cb, "2A973DBD 00000000\n2043AFCC 2411FFFF\n");
+        let notes: Vec<_> = notes(&entries)
+            .map(|(line, note)| (line, format!("{}", note)))
+            .collect();
+        assert_eq!(
+            vec![(
+                0,
+                String::from("rejected: code appears to be V1 but processor is pinned to RAW")
+            )],
+            notes
+        );
+        assert_eq!("2A973DBD 00000000\n2043AFCC 2411FFFF\n", to_decrypted(&entries));
+    }
+
+    #[test]
+    fn test_import_multi_line() {
+        // The value of a serial write is no plausible code on its own
+        let text = "4043AFCC 00020001\n2411FFFF 00000001\n";
+        let entries = import(&mut Codebreaker::new(), text);
+        assert_eq!(0, notes(&entries).count());
+        assert_eq!(text, to_decrypted(&entries));
+    }
+}
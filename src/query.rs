@@ -0,0 +1,344 @@
+//! A small query language to search games for cheats and codes.
+//!
+//! A query is a list of terms separated by whitespace, all of which must
+//! match:
+//!
+//! - `game:TEXT` and `cheat:TEXT` match titles and cheat names containing
+//!   the text, ignoring case. A word without a key is the same as `cheat:`.
+//! - `type:NAME` matches codes of a type: `write8`, `write16`, `write32`,
+//!   `increment`, `serial`, `copy`, `pointer`, `bitwise`, `key`, `if`, or
+//!   `hook`.
+//! - `addr:N` and `value:N` match the address and value of codes, where N is
+//!   a number, `>N`, `>=N`, `<N`, `<=N`, or a range `A..B` excluding `B`.
+//!   Numbers are decimal or hex with a `0x` prefix.
+//!
+//! Text with spaces can be quoted, e.g. `game:"Final Fantasy X"`. Queries
+//! run against decrypted codes.
+//!
+//! # Example
+//! ```
+//! use codebreaker::game::{Cheat, Game};
+//! use codebreaker::query::{Hit, Query};
+//!
+//! let mut game = Game::new("Final Fantasy X");
+//! game.cheats.push(Cheat::new("Inf HP", vec![(0x2043AFCC, 0x2411FFFF), (0x104A1000, 0x0000270F)]));
+//!
+//! let query: Query = r#"game:"fantasy x" type:write16 addr:0x004A0000..0x004B0000 value:>100"#.parse().unwrap();
+//! assert_eq!(vec![Hit { game: 0, cheat: 0, line: 1 }], query.search(&[game]));
+//! ```
+
+use crate::firmware::Profile;
+use crate::game::{Cheat, Game};
+use crate::op::Addr;
+use crate::std_alloc::{String, Vec};
+
+use core::fmt;
+use core::str::FromStr;
+
+/// The names of code types and the command nibbles they stand for.
+const TYPES: [(&str, &[u8]); 11] = [
+    ("write8", &[0x0]),
+    ("write16", &[0x1]),
+    ("write32", &[0x2]),
+    ("increment", &[0x3]),
+    ("serial", &[0x4]),
+    ("copy", &[0x5]),
+    ("pointer", &[0x6]),
+    ("bitwise", &[0x7]),
+    ("key", &[0xb]),
+    ("if", &[0xc, 0xd, 0xe]),
+    ("hook", &[0x9, 0xf]),
+];
+
+/// The error returned when parsing a query fails.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// A term has a key that is not known.
+    UnknownKey(String),
+    /// A `type:` term names no code type.
+    UnknownType(String),
+    /// An `addr:` or `value:` term holds no valid number.
+    InvalidNumber(String),
+    /// A quote is not closed.
+    UnterminatedQuote,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownKey(key) => write!(f, "unknown key {:?}", key),
+            Self::UnknownType(name) => write!(f, "unknown code type {:?}", name),
+            Self::InvalidNumber(s) => write!(f, "{:?} is not a number or range", s),
+            Self::UnterminatedQuote => f.write_str("quote is not closed"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+// An inclusive range of numbers, empty if lo > hi.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Range {
+    lo: u32,
+    hi: u32,
+}
+
+impl Range {
+    const fn contains(self, n: u32) -> bool {
+        self.lo <= n && n <= self.hi
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Term {
+    Game(String),
+    Cheat(String),
+    Type(&'static [u8]),
+    Addr(Range),
+    Value(Range),
+}
+
+/// A parsed query.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Query {
+    terms: Vec<Term>,
+}
+
+/// A code line matching a query.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Hit {
+    /// Index of the game
+    pub game: usize,
+    /// Index of the cheat in the game
+    pub cheat: usize,
+    /// Index of the first line of the code in the cheat
+    pub line: usize,
+}
+
+impl Query {
+    /// Parses a query. The empty query matches everything.
+    pub fn parse(s: &str) -> Result<Self, Error> {
+        let terms = tokens(s)?
+            .into_iter()
+            .map(|token| {
+                let (key, arg) = match token.split_once(':') {
+                    Some((key, arg)) => (key, arg),
+                    None => ("cheat", token.as_str()),
+                };
+                Ok(match key {
+                    "game" => Term::Game(arg.to_lowercase()),
+                    "cheat" => Term::Cheat(arg.to_lowercase()),
+                    "type" => match TYPES.iter().find(|(name, _)| *name == arg) {
+                        Some(&(_, cmds)) => Term::Type(cmds),
+                        None => return Err(Error::UnknownType(arg.into())),
+                    },
+                    "addr" => Term::Addr(range(arg)?),
+                    "value" => Term::Value(range(arg)?),
+                    _ => return Err(Error::UnknownKey(key.into())),
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { terms })
+    }
+
+    /// Returns true if the title of the game matches all `game:` terms.
+    pub fn matches_game(&self, game: &Game) -> bool {
+        let title = game.title.to_lowercase();
+        self.terms.iter().all(|term| match term {
+            Term::Game(text) => title.contains(text.as_str()),
+            _ => true,
+        })
+    }
+
+    /// Returns true if the name of the cheat matches all `cheat:` terms.
+    pub fn matches_cheat(&self, cheat: &Cheat) -> bool {
+        let name = cheat.name.to_lowercase();
+        self.terms.iter().all(|term| match term {
+            Term::Cheat(text) => name.contains(text.as_str()),
+            _ => true,
+        })
+    }
+
+    /// Returns true if a decrypted code matches all `type:`, `addr:`, and
+    /// `value:` terms.
+    ///
+    /// The address is the one the code accesses. The value is the second
+    /// word, except for increments and multi-line conditionals, which keep
+    /// their value in the first word.
+    pub fn matches_code(&self, code: (u32, u32)) -> bool {
+        let (addr, val) = code;
+        let cmd = (addr >> 28) as u8;
+        let (at, value) = match cmd {
+            0x3 | 0xe => (val, addr & 0xffff),
+            _ => (addr, val),
+        };
+        self.terms.iter().all(|term| match term {
+            Term::Type(cmds) => cmds.contains(&cmd),
+            Term::Addr(range) => range.contains(Addr::from_code(at).get()),
+            Term::Value(range) => range.contains(value),
+            _ => true,
+        })
+    }
+
+    /// Returns the codes of all games that match the query, in order.
+    ///
+    /// Only the first line of a multi-line code is matched, with the line
+    /// counts of CB v7.
+    pub fn search(&self, games: &[Game]) -> Vec<Hit> {
+        let mut hits = Vec::new();
+        for (g, game) in games.iter().enumerate().filter(|(_, game)| self.matches_game(game)) {
+            for (c, cheat) in game.cheats.iter().enumerate() {
+                if !self.matches_cheat(cheat) {
+                    continue;
+                }
+                let mut line = 0;
+                while let Some(&code) = cheat.codes.get(line) {
+                    if self.matches_code(code) {
+                        hits.push(Hit {
+                            game: g,
+                            cheat: c,
+                            line,
+                        });
+                    }
+                    line += Profile::V7.lines(code.0);
+                }
+            }
+        }
+        hits
+    }
+}
+
+impl FromStr for Query {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+// Splits a query at whitespace outside of quotes and removes the quotes.
+fn tokens(s: &str) -> Result<Vec<String>, Error> {
+    let mut tokens = Vec::new();
+    let mut token: Option<String> = None;
+    let mut quoted = false;
+    for c in s.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                token.get_or_insert_with(String::new);
+            }
+            c if c.is_whitespace() && !quoted => tokens.extend(token.take()),
+            c => token.get_or_insert_with(String::new).push(c),
+        }
+    }
+    if quoted {
+        return Err(Error::UnterminatedQuote);
+    }
+    tokens.extend(token);
+    Ok(tokens)
+}
+
+fn range(s: &str) -> Result<Range, Error> {
+    let number = |n: &str| number(n).ok_or_else(|| Error::InvalidNumber(s.into()));
+    let all = Range { lo: 0, hi: u32::MAX };
+    let empty = Range { lo: 1, hi: 0 };
+    if let Some((lo, hi)) = s.split_once("..") {
+        let lo = number(lo)?;
+        return Ok(number(hi)?.checked_sub(1).map_or(empty, |hi| Range { lo, hi }));
+    }
+    Ok(if let Some(n) = s.strip_prefix(">=") {
+        Range { lo: number(n)?, ..all }
+    } else if let Some(n) = s.strip_prefix("<=") {
+        Range { hi: number(n)?, ..all }
+    } else if let Some(n) = s.strip_prefix('>') {
+        number(n)?.checked_add(1).map_or(empty, |lo| Range { lo, ..all })
+    } else if let Some(n) = s.strip_prefix('<') {
+        number(n)?.checked_sub(1).map_or(empty, |hi| Range { hi, ..all })
+    } else {
+        let n = number(s.strip_prefix('=').unwrap_or(s))?;
+        Range { lo: n, hi: n }
+    })
+}
+
+fn number(s: &str) -> Option<u32> {
+    s.strip_prefix("0x")
+        .or_else(|| s.strip_prefix("0X"))
+        .map_or_else(|| s.parse().ok(), |hex| u32::from_str_radix(hex, 16).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn games() -> Vec<Game> {
+        let mut ffx = Game::new("Final Fantasy X");
+        ffx.cheats
+            .push(Cheat::new("(M)", vec![(0xbeef_c0de, 0), (0x9029_beac, 0x0c0a_9225)]));
+        ffx.cheats.push(Cheat::new(
+            "Inf HP",
+            vec![
+                (0x104a_1000, 0x0000_270f),
+                (0xd04a_1000, 0x0000_0000),
+                (0x204a_2000, 0x0000_0001),
+            ],
+        ));
+        let mut kh = Game::new("Kingdom Hearts");
+        kh.cheats.push(Cheat::new(
+            "Max Munny",
+            vec![
+                (0x4043_afcc, 0x0002_0001),
+                (0x0000_0064, 0x0000_0000),
+                (0x3040_0000, 0x004a_1000),
+                (0x64, 0),
+            ],
+        ));
+        vec![ffx, kh]
+    }
+
+    fn search(query: &str) -> Vec<(usize, usize, usize)> {
+        let query = Query::parse(query).unwrap();
+        query
+            .search(&games())
+            .iter()
+            .map(|h| (h.game, h.cheat, h.line))
+            .collect()
+    }
+
+    #[test]
+    fn test_search() {
+        assert_eq!(7, search("").len());
+        assert_eq!(vec![(1, 0, 0), (1, 0, 2)], search("game:kingdom"));
+        assert_eq!(vec![(0, 1, 0), (0, 1, 1), (0, 1, 2)], search(r#"game:"fantasy x" hp"#));
+        assert_eq!(vec![(0, 1, 0)], search("type:write16"));
+        assert_eq!(vec![(0, 1, 1)], search("type:if"));
+        assert_eq!(vec![(0, 0, 0)], search("type:key"));
+        assert_eq!(vec![(0, 0, 1)], search("type:hook"));
+        assert_eq!(vec![(0, 1, 0), (0, 1, 1), (1, 0, 2)], search("addr:0x004A1000"));
+        assert_eq!(
+            vec![(0, 1, 0), (0, 1, 1), (0, 1, 2), (1, 0, 2)],
+            search("addr:0x004A0000..0x004B0000")
+        );
+        assert_eq!(vec![(0, 0, 1), (0, 1, 0), (1, 0, 0)], search("value:>100"));
+        assert_eq!(vec![(0, 1, 0), (0, 1, 2)], search("value:>=1 value:<=9999 game:final"));
+        assert_eq!(vec![(0, 1, 1)], search("cheat:HP value:<1"));
+        assert_eq!(Vec::<(usize, usize, usize)>::new(), search("value:<0"));
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Ok(Query::default()), Query::parse("  "));
+        assert_eq!(Err(Error::UnknownKey("size".into())), Query::parse("size:2"));
+        assert_eq!(Err(Error::UnknownType("write64".into())), Query::parse("type:write64"));
+        assert_eq!(
+            Err(Error::InvalidNumber("0x12..z".into())),
+            Query::parse("addr:0x12..z")
+        );
+        assert_eq!(Err(Error::UnterminatedQuote), Query::parse("game:\"ffx"));
+        assert_eq!(
+            "\"12..z\" is not a number or range",
+            format!("{}", Error::InvalidNumber("12..z".into()))
+        );
+    }
+}
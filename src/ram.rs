@@ -0,0 +1,201 @@
+//! Offline checks of cheats against EE RAM dumps.
+//!
+//! When a cheat "does nothing", its conditionals are often not met. Given a
+//! dump of EE RAM, e.g. from an emulator savestate, [`check`] evaluates the
+//! conditionals of a cheat against the actual memory contents and reports
+//! whether the cheat would currently fire.
+//!
+//! # Example
+//! ```
+//! use codebreaker::cheat::Cheat;
+//! use codebreaker::ram::{check, Ram};
+//!
+//! let mut dump = vec![0; 0x0200_0000];
+//! dump[0x004A0000] = 1;
+//! let ram = Ram::new(&dump);
+//!
+//! let cheat = Cheat::builder("Inf Health")
+//!     .if_eq16(0x004A0000, 1)
+//!     .then(|b| b.write16(0x0043AFCC, 0xFFFF))
+//!     .build()
+//!     .unwrap();
+//! let report = check(&cheat, &ram);
+//! assert!(report.fires());
+//! assert!(report.conditions[0].met);
+//! ```
+
+use crate::cheat::Cheat;
+use crate::op::{Addr, Cond, Op, Value, Width};
+use crate::std_alloc::Vec;
+
+/// A dump of EE RAM.
+#[derive(Debug, Clone, Copy)]
+pub struct Ram<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Ram<'a> {
+    /// Wraps a memory dump starting at address 0.
+    ///
+    /// The dump may be shorter than the full 32 MB; reads beyond its end
+    /// return `None`.
+    pub const fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    /// Reads a little-endian value of the given width.
+    pub fn read(&self, addr: Addr, width: Width) -> Option<u32> {
+        let start = addr.get() as usize;
+        let bytes = self.data.get(start..start + width.bytes() as usize)?;
+        Some(bytes.iter().rev().fold(0, |val, &b| val << 8 | u32::from(b)))
+    }
+}
+
+/// The outcome of a conditional.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Condition {
+    /// The address compared
+    pub addr: Addr,
+    /// The comparison
+    pub cond: Cond,
+    /// The value compared against
+    pub expected: Value,
+    /// The value in memory, `None` if outside of the dump
+    pub actual: Option<u32>,
+    /// Whether the comparison holds
+    pub met: bool,
+}
+
+/// The result of checking a cheat against memory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Report {
+    /// All conditionals of the cheat, including nested ones, in order
+    pub conditions: Vec<Condition>,
+    /// The number of writes of the cheat
+    pub writes: usize,
+    /// The number of writes whose conditionals are all met
+    pub active_writes: usize,
+}
+
+impl Report {
+    /// Returns true if at least one write of the cheat would be executed.
+    pub const fn fires(&self) -> bool {
+        self.active_writes > 0
+    }
+}
+
+/// Evaluates the conditionals of a cheat against memory.
+///
+/// Conditionals on memory outside of the dump are treated as not met.
+pub fn check(cheat: &Cheat, ram: &Ram<'_>) -> Report {
+    let mut report = Report {
+        conditions: Vec::new(),
+        writes: 0,
+        active_writes: 0,
+    };
+    walk(&cheat.ops, ram, true, &mut report);
+    report
+}
+
+fn walk(ops: &[Op], ram: &Ram<'_>, active: bool, report: &mut Report) {
+    for op in ops.iter() {
+        match op {
+            Op::Write { .. } | Op::SerialWrite { .. } => {
+                report.writes += 1;
+                if active {
+                    report.active_writes += 1;
+                }
+            }
+            Op::If { addr, val, cond, then } => {
+                let actual = ram.read(*addr, val.width());
+                let met = actual.is_some_and(|actual| compare(*cond, actual, val.get()));
+                report.conditions.push(Condition {
+                    addr: *addr,
+                    cond: *cond,
+                    expected: *val,
+                    actual,
+                    met,
+                });
+                walk(then, ram, active && met, report);
+            }
+        }
+    }
+}
+
+pub(crate) const fn compare(cond: Cond, actual: u32, expected: u32) -> bool {
+    match cond {
+        Cond::Eq => actual == expected,
+        Cond::Ne => actual != expected,
+        Cond::Lt => actual < expected,
+        Cond::Gt => actual > expected,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dump() -> Vec<u8> {
+        let mut dump = vec![0; 0x1000];
+        dump[0x100..0x104].copy_from_slice(&[0x78, 0x56, 0x34, 0x12]);
+        dump
+    }
+
+    #[test]
+    fn test_read() {
+        let dump = dump();
+        let ram = Ram::new(&dump);
+        let addr = Addr::new(0x100).unwrap();
+        assert_eq!(Some(0x78), ram.read(addr, Width::Byte));
+        assert_eq!(Some(0x5678), ram.read(addr, Width::Half));
+        assert_eq!(Some(0x1234_5678), ram.read(addr, Width::Word));
+        assert_eq!(None, ram.read(Addr::new(0xffe).unwrap(), Width::Word));
+    }
+
+    #[test]
+    fn test_check() {
+        let dump = dump();
+        let ram = Ram::new(&dump);
+
+        #[rustfmt::skip]
+        let tests = [
+            (Cheat::builder("").write8(0x200, 1), 1, true),
+            (Cheat::builder("").if_eq16(0x100, 0x5678).then(|b| b.write8(0x200, 1)), 1, true),
+            (Cheat::builder("").if_ne16(0x100, 0x5678).then(|b| b.write8(0x200, 1)), 0, false),
+            (Cheat::builder("").if8(0x101, Cond::Gt, 0x55).then(|b| b.write8(0x200, 1)), 1, true),
+            (Cheat::builder("").if8(0x101, Cond::Lt, 0x55).then(|b| b.write8(0x200, 1)), 0, false),
+            // memory outside of the dump
+            (Cheat::builder("").if_ne16(0x2000, 0).then(|b| b.write8(0x200, 1)), 0, false),
+            // nested conditional not met, outer write still active
+            (
+                Cheat::builder("")
+                    .if_eq8(0x100, 0x78)
+                    .then(|b| b.write8(0x200, 1).if_eq8(0x100, 0).then(|b| b.write8(0x201, 1))),
+                1,
+                true,
+            ),
+        ];
+        for t in tests.iter() {
+            let report = check(&t.0.clone().build().unwrap(), &ram);
+            assert_eq!(t.1, report.active_writes);
+            assert_eq!(t.2, report.fires());
+        }
+    }
+
+    #[test]
+    fn test_check_conditions() {
+        let dump = dump();
+        let cheat = Cheat::builder("")
+            .if_eq16(0x100, 1)
+            .then(|b| b.if_eq8(0x100, 0x78).then(|b| b.write8(0x200, 1)))
+            .build()
+            .unwrap();
+        let report = check(&cheat, &Ram::new(&dump));
+        assert_eq!(2, report.conditions.len());
+        assert_eq!(Some(0x5678), report.conditions[0].actual);
+        assert!(!report.conditions[0].met);
+        assert!(report.conditions[1].met);
+        assert_eq!(1, report.writes);
+        assert!(!report.fires());
+    }
+}
@@ -0,0 +1,510 @@
+//! Reference implementation of CodeBreaker PS2 encryption, serving as an
+//! executable specification.
+//!
+//! The code in this module is a deliberately simple port of the original
+//! algorithms. It favors readability over speed: no table casting, no bignum
+//! library, no clever tricks. The optimized implementations in
+//! [cb1](../cb1/index.html) and [cb7](../cb7/index.html) are expected to
+//! produce exactly the same results, which can be checked for arbitrary
+//! inputs with [`check_cb1`] and [`check_cb7`].
+//!
+//! # Example
+//! ```
+//! use codebreaker::reference;
+//!
+//! let codes = [(0x2043AFCC, 0x2411FFFF), (0x12345678, 0x9ABCDEF0)];
+//! for code in codes.iter() {
+//!     assert_eq!(Ok(()), reference::check_cb1(code.0, code.1));
+//! }
+//! assert_eq!(Ok(()), reference::check_cb7((0xBEEFC0DE, 0x00000000), &codes));
+//! ```
+
+use crate::cb1 as fast_cb1;
+use crate::cb7 as fast_cb7;
+
+/// The operation in which an optimized implementation and the reference
+/// disagreed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    /// [`cb1::encrypt_code`](../cb1/fn.encrypt_code.html)
+    Cb1Encrypt,
+    /// [`cb1::decrypt_code`](../cb1/fn.decrypt_code.html)
+    Cb1Decrypt,
+    /// [`Cb7::encrypt_code`](../cb7/struct.Cb7.html#method.encrypt_code)
+    Cb7Encrypt,
+    /// [`Cb7::decrypt_code`](../cb7/struct.Cb7.html#method.decrypt_code)
+    Cb7Decrypt,
+}
+
+/// Details about a code for which an optimized implementation and the
+/// reference produced different results.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Mismatch {
+    /// The operation that was performed.
+    pub operation: Operation,
+    /// Index of the offending code in the input list.
+    pub index: usize,
+    /// The input code.
+    pub input: (u32, u32),
+    /// The result of the reference implementation.
+    pub expected: (u32, u32),
+    /// The result of the optimized implementation.
+    pub actual: (u32, u32),
+}
+
+/// Compares [cb1](../cb1/index.html) against the reference for a single code,
+/// in both directions.
+///
+/// # Example
+/// ```
+/// use codebreaker::reference;
+///
+/// assert_eq!(Ok(()), reference::check_cb1(0x1023CED8, 0x000003E7));
+/// ```
+pub fn check_cb1(addr: u32, val: u32) -> Result<(), Mismatch> {
+    let input = (addr, val);
+
+    let expected = cb1::encrypt_code(addr, val);
+    let actual = fast_cb1::encrypt_code(addr, val);
+    if expected != actual {
+        return Err(Mismatch {
+            operation: Operation::Cb1Encrypt,
+            index: 0,
+            input,
+            expected,
+            actual,
+        });
+    }
+
+    let expected = cb1::decrypt_code(addr, val);
+    let actual = fast_cb1::decrypt_code(addr, val);
+    if expected != actual {
+        return Err(Mismatch {
+            operation: Operation::Cb1Decrypt,
+            index: 0,
+            input,
+            expected,
+            actual,
+        });
+    }
+
+    Ok(())
+}
+
+/// Compares [cb7](../cb7/index.html) against the reference for a list of
+/// codes, in both directions.
+///
+/// Both processors are set up with the given beefcode first. The codes are
+/// then encrypted in sequence and, starting over, decrypted in sequence, so
+/// that any beefcodes inside the list are covered as well.
+///
+/// # Example
+/// ```
+/// use codebreaker::reference;
+///
+/// let codes = [(0x9029BEAC, 0x0C0A9225), (0xBEEFC0DF, 0xB16B00B5), (0x01234567, 0x89ABCDEF)];
+/// assert_eq!(Ok(()), reference::check_cb7((0xBEEFC0DE, 0xDEADFACE), &codes));
+/// ```
+pub fn check_cb7(beefcode: (u32, u32), codes: &[(u32, u32)]) -> Result<(), Mismatch> {
+    let mut reference = cb7::Cb7::new();
+    let mut fast = fast_cb7::Cb7::new();
+    reference.beefcode(beefcode.0, beefcode.1);
+    fast.beefcode(beefcode.0, beefcode.1);
+
+    for (index, &input) in codes.iter().enumerate() {
+        let expected = reference.encrypt_code(input.0, input.1);
+        let actual = fast.encrypt_code(input.0, input.1);
+        if expected != actual {
+            return Err(Mismatch {
+                operation: Operation::Cb7Encrypt,
+                index,
+                input,
+                expected,
+                actual,
+            });
+        }
+    }
+
+    let mut reference = cb7::Cb7::new();
+    let mut fast = fast_cb7::Cb7::new();
+    reference.beefcode(beefcode.0, beefcode.1);
+    fast.beefcode(beefcode.0, beefcode.1);
+
+    for (index, &input) in codes.iter().enumerate() {
+        let expected = reference.decrypt_code(input.0, input.1);
+        let actual = fast.decrypt_code(input.0, input.1);
+        if expected != actual {
+            return Err(Mismatch {
+                operation: Operation::Cb7Decrypt,
+                index,
+                input,
+                expected,
+                actual,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Reference implementation of CB v1 - v6 encryption.
+pub mod cb1 {
+    use crate::cb1::SEEDS;
+
+    /// Encrypts a code and returns the result.
+    pub const fn encrypt_code(addr: u32, val: u32) -> (u32, u32) {
+        let cmd = (addr >> 28) as usize;
+        let [lo, mid, hi, top] = addr.to_le_bytes();
+
+        // Move the lowest address byte to the top of the 24-bit address field
+        let rotated = u32::from_le_bytes([mid, hi, lo, 0]);
+        let sum = rotated.wrapping_add(SEEDS[1][cmd]) & 0x00ff_ffff;
+        let new_addr = ((top as u32) << 24 | sum) ^ SEEDS[0][cmd];
+
+        // Only some code types have their value encrypted
+        let new_val = if cmd > 2 {
+            new_addr ^ val.wrapping_add(SEEDS[2][cmd])
+        } else {
+            val
+        };
+
+        (new_addr, new_val)
+    }
+
+    /// Decrypts a code and returns the result.
+    pub const fn decrypt_code(addr: u32, val: u32) -> (u32, u32) {
+        let cmd = (addr >> 28) as usize;
+
+        let new_val = if cmd > 2 {
+            (addr ^ val).wrapping_sub(SEEDS[2][cmd])
+        } else {
+            val
+        };
+
+        let xored = addr ^ SEEDS[0][cmd];
+        let top = (xored >> 24) as u8;
+        let [mid, hi, lo, _] = xored.wrapping_sub(SEEDS[1][cmd]).to_le_bytes();
+        let new_addr = u32::from_le_bytes([lo, mid, hi, top]);
+
+        (new_addr, new_val)
+    }
+}
+
+/// Reference implementation of CB v7+ encryption.
+pub mod cb7 {
+    use crate::cb7::{is_beefcode, BEEFCODE, SEEDS};
+    use crate::primitives::{RC4_KEY, RSA_DEC_KEY, RSA_ENC_KEY, RSA_MODULUS};
+
+    /// A reference processor for CB v7+ codes.
+    #[derive(Debug, Clone)]
+    pub struct Cb7 {
+        seeds: [[u8; 256]; 5],
+        key: [u32; 5],
+        beefcodf: bool,
+        initialized: bool,
+    }
+
+    /// Implements the default CB v7 encryption used by former CMGSCCC.com.
+    impl Default for Cb7 {
+        fn default() -> Self {
+            let mut cb7 = Self::new();
+            cb7.beefcode(BEEFCODE, 0);
+            cb7
+        }
+    }
+
+    impl Cb7 {
+        /// Returns a new reference processor.
+        pub const fn new() -> Self {
+            Self {
+                seeds: [[0; 256]; 5],
+                key: [0; 5],
+                beefcodf: false,
+                initialized: false,
+            }
+        }
+
+        /// Generates or changes the encryption key and seeds.
+        pub fn beefcode(&mut self, addr: u32, val: u32) {
+            assert!(is_beefcode(addr));
+
+            if !self.initialized {
+                self.key = RC4_KEY;
+                if val != 0 {
+                    self.seeds = SEEDS;
+                    self.derive_key(val);
+                } else {
+                    self.seeds = [[0; 256]; 5];
+                }
+                self.initialized = true;
+            } else if val != 0 {
+                self.derive_key(val);
+            } else {
+                self.seeds = [[0; 256]; 5];
+                for i in 0..4 {
+                    self.key[i] = 0;
+                }
+            }
+
+            // Each round encrypts one seed table followed by the key itself,
+            // using the same RC4 stream.
+            for i in 0..5 {
+                let key = key_bytes(&self.key);
+                let mut buf = [0; 256 + 20];
+                buf[..256].copy_from_slice(&self.seeds[i]);
+                buf[256..].copy_from_slice(&key);
+                rc4(&key, &mut buf);
+                self.seeds[i].copy_from_slice(&buf[..256]);
+                self.key = key_words(&buf[256..]);
+            }
+
+            self.beefcodf = addr & 1 != 0;
+        }
+
+        fn derive_key(&mut self, val: u32) {
+            let idx = val.to_le_bytes();
+            for i in 0..4 {
+                let b0 = self.seeds[i % 4][idx[0] as usize];
+                let b1 = self.seeds[(i + 1) % 4][idx[1] as usize];
+                let b2 = self.seeds[(i + 2) % 4][idx[2] as usize];
+                let b3 = self.seeds[(i + 3) % 4][idx[3] as usize];
+                self.key[i] = u32::from_le_bytes([b0, b1, b2, b3]);
+            }
+        }
+
+        const fn seed(&self, table: usize, i: usize) -> u32 {
+            let s = &self.seeds[table];
+            u32::from_le_bytes([s[4 * i], s[4 * i + 1], s[4 * i + 2], s[4 * i + 3]])
+        }
+
+        /// Encrypts a code and returns the result.
+        pub fn encrypt_code(&mut self, addr: u32, val: u32) -> (u32, u32) {
+            let (mut a, mut v) = (addr, val);
+
+            a = a.wrapping_mul(self.key[0].wrapping_sub(self.key[1]) | 1);
+            v = v.wrapping_mul(self.key[2].wrapping_add(self.key[3]) | 1);
+
+            let (x, y) = rc4_code(&key_bytes(&self.key), a, v);
+            a = x;
+            v = y;
+
+            let (x, y) = rsa(a, v, RSA_ENC_KEY);
+            a = x;
+            v = y;
+
+            for i in 0..64 {
+                a = (a.wrapping_add(self.seed(2, i)) ^ self.seed(0, i)).wrapping_sub(v ^ self.seed(4, i));
+                v = (v.wrapping_sub(self.seed(3, i)) ^ self.seed(1, i)).wrapping_add(a ^ self.seed(4, i));
+            }
+
+            if is_beefcode(addr) {
+                self.beefcode(addr, val);
+            } else if self.beefcodf {
+                self.crypt_seeds(addr, val);
+            }
+
+            (a, v)
+        }
+
+        /// Decrypts a code and returns the result.
+        pub fn decrypt_code(&mut self, addr: u32, val: u32) -> (u32, u32) {
+            let (mut a, mut v) = (addr, val);
+
+            for i in (0..64).rev() {
+                v = (v.wrapping_sub(a ^ self.seed(4, i)) ^ self.seed(1, i)).wrapping_add(self.seed(3, i));
+                a = (a.wrapping_add(v ^ self.seed(4, i)) ^ self.seed(0, i)).wrapping_sub(self.seed(2, i));
+            }
+
+            let (x, y) = rsa(a, v, RSA_DEC_KEY);
+            a = x;
+            v = y;
+
+            let (x, y) = rc4_code(&key_bytes(&self.key), a, v);
+            a = x;
+            v = y;
+
+            a = a.wrapping_mul(inverse(self.key[0].wrapping_sub(self.key[1]) | 1));
+            v = v.wrapping_mul(inverse(self.key[2].wrapping_add(self.key[3]) | 1));
+
+            if self.beefcodf {
+                self.crypt_seeds(a, v);
+            } else if is_beefcode(a) {
+                self.beefcode(a, v);
+            }
+
+            (a, v)
+        }
+
+        // The second line of BEEFC0DF is used as RC4 key for all seeds.
+        fn crypt_seeds(&mut self, addr: u32, val: u32) {
+            let mut key = [0; 8];
+            key[..4].copy_from_slice(&addr.to_le_bytes());
+            key[4..].copy_from_slice(&val.to_le_bytes());
+
+            let mut buf = [0; 5 * 256];
+            for (i, seeds) in self.seeds.iter().enumerate() {
+                buf[i * 256..(i + 1) * 256].copy_from_slice(seeds);
+            }
+            rc4(&key, &mut buf);
+            for (i, seeds) in self.seeds.iter_mut().enumerate() {
+                seeds.copy_from_slice(&buf[i * 256..(i + 1) * 256]);
+            }
+
+            self.beefcodf = false;
+        }
+    }
+
+    fn key_bytes(key: &[u32; 5]) -> [u8; 20] {
+        let mut bytes = [0; 20];
+        for (i, k) in key.iter().enumerate() {
+            bytes[4 * i..4 * i + 4].copy_from_slice(&k.to_le_bytes());
+        }
+        bytes
+    }
+
+    fn key_words(bytes: &[u8]) -> [u32; 5] {
+        let mut key = [0; 5];
+        for (i, k) in key.iter_mut().enumerate() {
+            *k = u32::from_le_bytes([bytes[4 * i], bytes[4 * i + 1], bytes[4 * i + 2], bytes[4 * i + 3]]);
+        }
+        key
+    }
+
+    // Textbook RC4: key scheduling followed by the keystream XOR
+    fn rc4(key: &[u8], data: &mut [u8]) {
+        let mut s = [0u8; 256];
+        for (i, x) in s.iter_mut().enumerate() {
+            *x = i as u8;
+        }
+
+        let mut j = 0;
+        for i in 0..256 {
+            j = (j + s[i] as usize + key[i % key.len()] as usize) % 256;
+            s.swap(i, j);
+        }
+
+        let (mut i, mut j) = (0, 0);
+        for b in data.iter_mut() {
+            i = (i + 1) % 256;
+            j = (j + s[i] as usize) % 256;
+            s.swap(i, j);
+            *b ^= s[(s[i] as usize + s[j] as usize) % 256];
+        }
+    }
+
+    fn rc4_code(key: &[u8], addr: u32, val: u32) -> (u32, u32) {
+        let mut buf = [0; 8];
+        buf[..4].copy_from_slice(&addr.to_le_bytes());
+        buf[4..].copy_from_slice(&val.to_le_bytes());
+        rc4(key, &mut buf);
+        (
+            u32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]),
+            u32::from_le_bytes([buf[4], buf[5], buf[6], buf[7]]),
+        )
+    }
+
+    // Square-and-multiply with 128-bit intermediates. The address is the high
+    // word of the 64-bit message. Messages not smaller than the modulus are
+    // left alone because exponentiation would not be invertible.
+    fn rsa(addr: u32, val: u32, exp: u64) -> (u32, u32) {
+        let m = u128::from(RSA_MODULUS);
+        let msg = u128::from(addr) << 32 | u128::from(val);
+        if msg >= m {
+            return (addr, val);
+        }
+
+        let (mut result, mut base, mut e) = (1u128, msg, exp);
+        while e > 0 {
+            if e & 1 != 0 {
+                result = result * base % m;
+            }
+            base = base * base % m;
+            e >>= 1;
+        }
+
+        ((result >> 32) as u32, result as u32)
+    }
+
+    // Multiplicative inverse modulo 2^32 via the extended Euclidean algorithm
+    fn inverse(x: u32) -> u32 {
+        let (mut r0, mut r1) = (1i128 << 32, i128::from(x));
+        let (mut t0, mut t1) = (0i128, 1i128);
+        while r1 != 0 {
+            let q = r0 / r1;
+            let r = r0 - q * r1;
+            r0 = r1;
+            r1 = r;
+            let t = t0 - q * t1;
+            t0 = t1;
+            t1 = t;
+        }
+        t0.rem_euclid(1i128 << 32) as u32
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn test_inverse() {
+            for x in [1u32, 3, 0x2912_dedd, 0xa686_d3b7, 0xffff_ffff].iter() {
+                assert_eq!(1, x.wrapping_mul(inverse(*x)));
+            }
+        }
+
+        #[test]
+        fn test_rsa() {
+            let (addr, val) = rsa(0x0123_4567, 0x89ab_cdef, RSA_ENC_KEY);
+            assert_eq!((0x0123_4567, 0x89ab_cdef), rsa(addr, val, RSA_DEC_KEY));
+            assert_eq!((0xffff_ffff, 0xffff_fff5), rsa(0xffff_ffff, 0xffff_fff5, RSA_ENC_KEY));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::std_alloc::Vec;
+
+    // xorshift32, good enough to generate arbitrary test inputs
+    fn random_codes(seed: u32, n: usize) -> Vec<(u32, u32)> {
+        let mut x = seed;
+        let mut next = move || {
+            x ^= x << 13;
+            x ^= x >> 17;
+            x ^= x << 5;
+            x
+        };
+        (0..n).map(|_| (next(), next())).collect()
+    }
+
+    #[test]
+    fn test_check_cb1() {
+        for code in random_codes(0x1234_5678, 10_000).iter() {
+            assert_eq!(Ok(()), check_cb1(code.0, code.1));
+        }
+    }
+
+    #[test]
+    fn test_check_cb7() {
+        let beefcodes = [
+            (0xbeef_c0de, 0x0000_0000),
+            (0xbeef_c0de, 0xdead_face),
+            (0xbeef_c0df, 0xb16b_00b5),
+        ];
+        for (i, beefcode) in beefcodes.iter().enumerate() {
+            let mut codes = random_codes(0xcafe_babe + i as u32, 200);
+            // Sprinkle in beefcodes to exercise key changes mid-list
+            codes[50] = (0xbeef_c0de, 0x0000_0000);
+            codes[100] = (0xbeef_c0df, codes[100].1);
+            codes[150] = (0xbeef_c0de, codes[150].1);
+            assert_eq!(Ok(()), check_cb7(*beefcode, &codes));
+        }
+    }
+
+    #[test]
+    fn test_reference_cb7_default() {
+        let mut cb7 = cb7::Cb7::default();
+        assert_eq!((0x3979_51b0, 0x4156_9fe0), cb7.encrypt_code(0x2043_afcc, 0x2411_ffff));
+    }
+}
@@ -0,0 +1,345 @@
+//! Batch renaming of games and cheats.
+//!
+//! Collections gathered from many sources name the same thing in many ways,
+//! e.g. "Inf. HP", "Infinite HP", and "Inf HP". A [`Renamer`] applies a list
+//! of rules to the titles of games and the names of cheats. Rules map whole
+//! names, or replace phrases wherever they stand as whole words. Rules can
+//! also be read from a mapping table.
+//! [`preview`](struct.Renamer.html#method.preview) reports the changes a
+//! renamer would make without making them.
+//!
+//! # Example
+//! ```
+//! use codebreaker::game::{Cheat, Game};
+//! use codebreaker::rename::Renamer;
+//!
+//! let mut game = Game::new("FFX");
+//! game.cheats.push(Cheat::new("Inf. HP", vec![]));
+//! game.cheats.push(Cheat::new("Infinite MP", vec![]));
+//!
+//! let renamer = Renamer::new().map("FFX", "Final Fantasy X").replace("Inf.", "Infinite");
+//! let mut games = [game];
+//! let renames = renamer.preview(&games);
+//! assert_eq!(2, renames.len());
+//! assert_eq!("\"Inf. HP\" -> \"Infinite HP\"", format!("{}", renames[1]));
+//!
+//! renamer.apply(&mut games);
+//! assert_eq!("Final Fantasy X", games[0].title);
+//! assert_eq!("Infinite HP", games[0].cheats[0].name);
+//! ```
+
+use crate::game::Game;
+use crate::std_alloc::{String, Vec};
+
+use core::fmt;
+
+/// The separator of the two sides of a mapping table entry.
+const ARROW: &str = "=>";
+
+/// The error returned for a malformed mapping table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Error {
+    /// Index of the offending line
+    pub line: usize,
+}
+
+/// Lines are counted from 1.
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: expected \"FROM {} TO\"", self.line + 1, ARROW)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Rule {
+    // Replaces a whole name
+    Map { from: String, to: String },
+    // Replaces a phrase standing as whole words
+    Replace { from: String, to: String },
+}
+
+/// A name changed, or to be changed, by a [`Renamer`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rename {
+    /// Index of the game
+    pub game: usize,
+    /// Index of the cheat in the game, or `None` for the title of the game
+    pub cheat: Option<usize>,
+    /// The name before renaming
+    pub old: String,
+    /// The name after renaming
+    pub new: String,
+}
+
+/// Formats the rename as `"old" -> "new"`.
+impl fmt::Display for Rename {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?} -> {:?}", self.old, self.new)
+    }
+}
+
+/// Renames games and cheats by a list of rules.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Renamer {
+    rules: Vec<Rule>,
+    titles: bool,
+    cheats: bool,
+}
+
+/// Does the same as [`new`](#method.new).
+impl Default for Renamer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Renamer {
+    /// Returns a renamer without rules that renames both games and cheats.
+    pub const fn new() -> Self {
+        Self {
+            rules: Vec::new(),
+            titles: true,
+            cheats: true,
+        }
+    }
+
+    /// Returns a renamer with a replacement rule for each entry of a mapping
+    /// table.
+    ///
+    /// Each line of the table holds a phrase and its replacement, separated
+    /// by `=>`. Blank lines and lines starting with `#` are ignored.
+    ///
+    /// # Example
+    /// ```
+    /// use codebreaker::rename::Renamer;
+    ///
+    /// let table = "# Abbreviations\nInf. => Infinite\nHP => Health\n";
+    /// let renamer = Renamer::from_table(table).unwrap();
+    /// assert_eq!(Some("Infinite Health".into()), renamer.rename("Inf. HP"));
+    /// assert!(Renamer::from_table("Inf.").is_err());
+    /// ```
+    pub fn from_table(table: &str) -> Result<Self, Error> {
+        let mut renamer = Self::new();
+        for (line, entry) in table.lines().enumerate() {
+            let entry = entry.trim();
+            if entry.is_empty() || entry.starts_with('#') {
+                continue;
+            }
+            match entry.split_once(ARROW) {
+                Some((from, to)) if !from.trim().is_empty() => renamer = renamer.replace(from.trim(), to.trim()),
+                _ => return Err(Error { line }),
+            }
+        }
+        Ok(renamer)
+    }
+
+    /// Adds a rule renaming anything named exactly `from` to `to`.
+    pub fn map(mut self, from: &str, to: &str) -> Self {
+        self.rules.push(Rule::Map {
+            from: from.into(),
+            to: to.into(),
+        });
+        self
+    }
+
+    /// Adds a rule replacing `from` with `to` wherever it stands as whole
+    /// words, i.e. not next to a letter or digit.
+    ///
+    /// # Example
+    /// ```
+    /// use codebreaker::rename::Renamer;
+    ///
+    /// let renamer = Renamer::new().replace("Inf", "Infinite");
+    /// assert_eq!(Some("Infinite HP/Infinite MP".into()), renamer.rename("Inf HP/Inf MP"));
+    /// assert_eq!(None, renamer.rename("Infinite HP"));
+    /// ```
+    pub fn replace(mut self, from: &str, to: &str) -> Self {
+        self.rules.push(Rule::Replace {
+            from: from.into(),
+            to: to.into(),
+        });
+        self
+    }
+
+    /// Sets whether to rename games.
+    pub const fn titles(mut self, titles: bool) -> Self {
+        self.titles = titles;
+        self
+    }
+
+    /// Sets whether to rename cheats.
+    pub const fn cheats(mut self, cheats: bool) -> Self {
+        self.cheats = cheats;
+        self
+    }
+
+    /// Returns the name resulting from applying all rules in order, or
+    /// `None` if the name stays the same.
+    pub fn rename(&self, name: &str) -> Option<String> {
+        let mut new = String::from(name);
+        for rule in self.rules.iter() {
+            match rule {
+                Rule::Map { from, to } if new == *from => new = to.clone(),
+                Rule::Map { .. } => {}
+                Rule::Replace { from, to } => new = replace_words(&new, from, to),
+            }
+        }
+        if new == name {
+            None
+        } else {
+            Some(new)
+        }
+    }
+
+    /// Returns the renames the renamer would make, in order of games and
+    /// their cheats, without changing anything.
+    pub fn preview(&self, games: &[Game]) -> Vec<Rename> {
+        let mut renames = Vec::new();
+        for (g, game) in games.iter().enumerate() {
+            if self.titles {
+                if let Some(new) = self.rename(&game.title) {
+                    renames.push(Rename {
+                        game: g,
+                        cheat: None,
+                        old: game.title.clone(),
+                        new,
+                    });
+                }
+            }
+            if self.cheats {
+                for (c, cheat) in game.cheats.iter().enumerate() {
+                    if let Some(new) = self.rename(&cheat.name) {
+                        renames.push(Rename {
+                            game: g,
+                            cheat: Some(c),
+                            old: cheat.name.clone(),
+                            new,
+                        });
+                    }
+                }
+            }
+        }
+        renames
+    }
+
+    /// Renames games and cheats in place and returns the renames made, like
+    /// [`preview`](#method.preview).
+    pub fn apply(&self, games: &mut [Game]) -> Vec<Rename> {
+        let renames = self.preview(games);
+        for rename in renames.iter() {
+            let game = &mut games[rename.game];
+            match rename.cheat {
+                Some(c) => game.cheats[c].name = rename.new.clone(),
+                None => game.title = rename.new.clone(),
+            }
+        }
+        renames
+    }
+}
+
+// Replaces each occurrence of `from` in `s` that is not next to a letter or
+// digit.
+fn replace_words(s: &str, from: &str, to: &str) -> String {
+    if from.is_empty() {
+        return s.into();
+    }
+    let is_word = |c: Option<char>| c.is_some_and(char::is_alphanumeric);
+    let mut result = String::new();
+    let mut rest = s;
+    while let Some(pos) = rest.find(from) {
+        let end = pos + from.len();
+        let before = rest[..pos].chars().next_back();
+        let after = rest[end..].chars().next();
+        // Only a word character at the edge of the phrase needs a boundary
+        let starts_word = from.chars().next().is_some_and(char::is_alphanumeric);
+        let ends_word = from.chars().next_back().is_some_and(char::is_alphanumeric);
+        if (starts_word && is_word(before)) || (ends_word && is_word(after)) {
+            let next = pos + rest[pos..].chars().next().map_or(1, char::len_utf8);
+            result.push_str(&rest[..next]);
+            rest = &rest[next..];
+        } else {
+            result.push_str(&rest[..pos]);
+            result.push_str(to);
+            rest = &rest[end..];
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Cheat;
+
+    #[test]
+    fn test_replace_words() {
+        let tests = [
+            ("Inf HP", "Inf", "Infinite", "Infinite HP"),
+            ("Inf. HP", "Inf.", "Infinite", "Infinite HP"),
+            ("Infinite HP", "Inf", "Infinite", "Infinite HP"),
+            ("HP Inf", "Inf", "Infinite", "HP Infinite"),
+            ("Max$ Max", "Max$", "Max Money", "Max Money Max"),
+            ("(M)", "(M)", "Master Code", "Master Code"),
+            ("MaxMax Max", "Max", "Maximum", "MaxMax Maximum"),
+            ("Inf HP", "", "x", "Inf HP"),
+        ];
+        for t in tests.iter() {
+            assert_eq!(t.3, replace_words(t.0, t.1, t.2), "{:?}", t);
+        }
+    }
+
+    #[test]
+    fn test_rename() {
+        let renamer = Renamer::new()
+            .map("Inf HP", "Infinite Health")
+            .replace("Inf", "Infinite")
+            .replace("Infinite Health", "Health");
+        assert_eq!(Some("Health".into()), renamer.rename("Inf HP"));
+        assert_eq!(Some("Infinite MP".into()), renamer.rename("Inf MP"));
+        assert_eq!(None, renamer.rename("Max Money"));
+    }
+
+    #[test]
+    fn test_apply() {
+        let mut game = Game::new("Inf Game");
+        game.cheats.push(Cheat::new("Inf HP", vec![]));
+        game.cheats.push(Cheat::new("Max MP", vec![]));
+        let mut games = vec![Game::new("Other"), game];
+
+        let renamer = Renamer::new().replace("Inf", "Infinite").titles(false);
+        let renames = renamer.apply(&mut games);
+        assert_eq!(
+            vec![Rename {
+                game: 1,
+                cheat: Some(0),
+                old: "Inf HP".into(),
+                new: "Infinite HP".into()
+            }],
+            renames
+        );
+        assert_eq!("Inf Game", games[1].title);
+        assert_eq!("Infinite HP", games[1].cheats[0].name);
+
+        let renames = Renamer::new()
+            .replace("Inf", "Infinite")
+            .cheats(false)
+            .apply(&mut games);
+        assert_eq!(
+            vec![(1, None)],
+            renames.iter().map(|r| (r.game, r.cheat)).collect::<Vec<_>>()
+        );
+        assert_eq!("Infinite Game", games[1].title);
+    }
+
+    #[test]
+    fn test_from_table() {
+        let renamer = Renamer::from_table("\n# comment\n  Inf.  =>  Infinite \n(M) =>\n").unwrap();
+        assert_eq!(Renamer::new().replace("Inf.", "Infinite").replace("(M)", ""), renamer);
+        assert_eq!(Err(Error { line: 1 }), Renamer::from_table("a => b\n => b\n"));
+        assert_eq!("line 2: expected \"FROM => TO\"", format!("{}", Error { line: 1 }));
+    }
+}
@@ -0,0 +1,185 @@
+//! Heuristic repair of typos in encrypted codes.
+//!
+//! Encrypted codes are copied around by hand a lot, and a single mistyped
+//! character turns the decrypted code into garbage. If a decrypted code is
+//! not [plausible](fn.is_plausible.html), [`candidates`] searches all
+//! single-character corrections of the encrypted code and yields those that
+//! decrypt to a plausible code.
+//!
+//! # Example
+//! ```
+//! use codebreaker::repair::{candidates, is_plausible};
+//! use codebreaker::Codebreaker;
+//!
+//! // Typo in "2A973DBD 00000000", the 4th character should be "7"
+//! let typo = (0x2A953DBD, 0x00000000);
+//!
+//! let cb = Codebreaker::new();
+//! assert!(!is_plausible(cb.clone().auto_decrypt_code(typo.0, typo.1)));
+//! assert!(candidates(&cb, typo.0, typo.1).any(|c| c.encrypted == (0x2A973DBD, 0x00000000)));
+//! ```
+
+use crate::cb7::is_beefcode;
+use crate::Codebreaker;
+
+/// Returns true if a decrypted code looks like a valid CodeBreaker code.
+///
+/// This is a heuristic. It checks that the address is within the 32 MB of EE
+/// RAM, and that writes are aligned and use values that fit their width.
+///
+/// # Example
+/// ```
+/// use codebreaker::repair::is_plausible;
+///
+/// assert_eq!(true, is_plausible((0x2043AFCC, 0x2411FFFF)));
+/// assert_eq!(false, is_plausible((0x2043AFCD, 0x2411FFFF)));
+/// assert_eq!(false, is_plausible((0x1A973DBD, 0x00000000)));
+/// ```
+pub const fn is_plausible(code: (u32, u32)) -> bool {
+    let (addr, val) = code;
+
+    // Beefcodes and the encryption change directive are special
+    if is_beefcode(addr) || addr == 0xffff_ffff {
+        return true;
+    }
+    if (addr >> 24) & 0x0e != 0 {
+        return false;
+    }
+
+    match addr >> 28 {
+        0 => val <= 0xff,
+        1 => addr & 1 == 0 && val <= 0xffff,
+        2 => addr & 3 == 0,
+        _ => true,
+    }
+}
+
+/// A single-character correction of an encrypted code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Candidate {
+    /// Position of the corrected character, counting from the left of
+    /// `XXXXXXXX YYYYYYYY` without the space
+    pub position: usize,
+    /// The corrected encrypted code
+    pub encrypted: (u32, u32),
+    /// The plausible code it decrypts to
+    pub decrypted: (u32, u32),
+}
+
+/// Returns an iterator over all single-character corrections of an encrypted
+/// code that decrypt to a plausible code.
+///
+/// Each candidate is decrypted with
+/// [`auto_decrypt_code`](../struct.Codebreaker.html#method.auto_decrypt_code)
+/// on a copy of `cb`, which is expected to carry the state from processing all
+/// previous lines of the list.
+pub fn candidates(cb: &Codebreaker, addr: u32, val: u32) -> Candidates {
+    Candidates {
+        cb: *cb,
+        code: u64::from(addr) << 32 | u64::from(val),
+        position: 0,
+        digit: 0,
+    }
+}
+
+/// Iterator returned by [`candidates`].
+#[derive(Debug, Clone)]
+pub struct Candidates {
+    cb: Codebreaker,
+    code: u64,
+    position: usize,
+    digit: u64,
+}
+
+impl Iterator for Candidates {
+    type Item = Candidate;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.position < 16 {
+            let shift = 60 - 4 * self.position;
+            let digit = self.digit;
+            let position = self.position;
+
+            self.digit += 1;
+            if self.digit == 16 {
+                self.digit = 0;
+                self.position += 1;
+            }
+
+            if (self.code >> shift) & 0xf == digit {
+                continue;
+            }
+
+            let code = self.code & !(0xf << shift) | digit << shift;
+            let encrypted = ((code >> 32) as u32, code as u32);
+            let mut cb = self.cb;
+            let decrypted = cb.auto_decrypt_code(encrypted.0, encrypted.1);
+            if is_plausible(decrypted) {
+                return Some(Candidate {
+                    position,
+                    encrypted,
+                    decrypted,
+                });
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::code;
+    use crate::std_alloc::Vec;
+
+    #[test]
+    fn test_is_plausible() {
+        #[rustfmt::skip]
+        let tests = [
+            ("0031789A 00000063", true),
+            ("0031789A 00000163", false),
+            ("1031A028 0000FFFF", true),
+            ("1031A029 0000FFFF", false),
+            ("1031A028 0001FFFF", false),
+            ("201F6024 00000000", true),
+            ("201F6026 00000000", false),
+            ("2A1F6024 00000000", false),
+            ("9029BEAC 0C0A9225", true),
+            ("BEEFC0DE 00000000", true),
+            ("FFFFFFFF 00010002", true),
+        ];
+        for t in tests.iter() {
+            assert_eq!(t.1, is_plausible(code::parse(t.0)), "{}", t.0);
+        }
+    }
+
+    #[test]
+    fn test_candidates() {
+        #[rustfmt::skip]
+        let tests = [
+            // v1 encrypted, position 3 mistyped
+            (Codebreaker::new(), "2A953DBD 00000000", "2A973DBD 00000000", "201F6024 00000000"),
+            // v7 encrypted, position 10 mistyped
+            (Codebreaker::new_v7(), "D08F3A49 00378A53", "D08F3A49 00078A53", "9029BEAC 0C0A9225"),
+        ];
+        for t in tests.iter() {
+            let typo = code::parse(t.1);
+            let found: Vec<Candidate> = candidates(&t.0, typo.0, typo.1).collect();
+            assert!(found.iter().all(|c| is_plausible(c.decrypted)));
+            assert!(found
+                .iter()
+                .any(|c| code::format(c.encrypted) == t.2 && code::format(c.decrypted) == t.3));
+        }
+    }
+
+    #[test]
+    fn test_candidates_single_change() {
+        let typo = (0x2a95_3dbd, 0x0000_0000);
+        for c in candidates(&Codebreaker::new(), typo.0, typo.1) {
+            let diff = (u64::from(c.encrypted.0) << 32 | u64::from(c.encrypted.1))
+                ^ (u64::from(typo.0) << 32 | u64::from(typo.1));
+            assert_eq!(0xf << (60 - 4 * c.position), diff | (0xf << (60 - 4 * c.position)));
+            assert_ne!(0, diff);
+        }
+    }
+}
@@ -0,0 +1,188 @@
+//! Search for cheats with nearly the same codes.
+//!
+//! Re-submitted cheats often differ from the original only in their values,
+//! e.g. 999 instead of 99 lives, or in a line added or left out.
+//! [`find_similar`] finds such cheats in a list of games by the
+//! [`distance`] of their codes, a Levenshtein distance that counts a line
+//! with the same address but another value as one change.
+//!
+//! Like [`query`](../query/index.html), the search runs against decrypted
+//! codes.
+//!
+//! # Example
+//! ```
+//! use codebreaker::game::{Cheat, Game};
+//! use codebreaker::similar::{find_similar, Similar};
+//!
+//! let mut game = Game::new("Final Fantasy X");
+//! game.cheats.push(Cheat::new("Max Gil", vec![(0x2031D1E0, 0x05F5E0FF)]));
+//! game.cheats.push(Cheat::new("99 Potions", vec![(0x0031D3A0, 0x00000063)]));
+//!
+//! let cheat = Cheat::new("Lots of Gil", vec![(0x2031D1E0, 0x000F423F)]);
+//! assert_eq!(
+//!     vec![Similar { game: 0, cheat: 0, distance: 1 }],
+//!     find_similar(&[game], &cheat, 1)
+//! );
+//! ```
+
+use crate::game::{Cheat, Game};
+use crate::std_alloc::Vec;
+
+/// A cheat similar to the one searched for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Similar {
+    /// Index of the game
+    pub game: usize,
+    /// Index of the cheat in the game
+    pub cheat: usize,
+    /// The distance of the codes, 0 if they are the same
+    pub distance: usize,
+}
+
+/// Returns the number of changes that turn the codes `a` into `b`.
+///
+/// A change adds a line, removes a line, or replaces the value of a line,
+/// keeping its address. Lines are compared as a whole, so this also works
+/// for multi-line codes.
+///
+/// # Example
+/// ```
+/// use codebreaker::similar::distance;
+///
+/// let a = [(0x2031D1E0, 0x05F5E0FF), (0x1031D1E4, 0x0000FFFF)];
+/// assert_eq!(0, distance(&a, &a));
+/// assert_eq!(1, distance(&a, &[(0x2031D1E0, 0x000F423F), (0x1031D1E4, 0x0000FFFF)]));
+/// assert_eq!(1, distance(&a, &a[..1]));
+/// // Another address is a line removed and one added
+/// assert_eq!(2, distance(&a[..1], &[(0x2031D1E4, 0x05F5E0FF)]));
+/// ```
+pub fn distance(a: &[(u32, u32)], b: &[(u32, u32)]) -> usize {
+    // One row of the edit distance table at a time
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, &(addr, val)) in a.iter().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, &other) in b.iter().enumerate() {
+            let replace = match other {
+                _ if other == (addr, val) => Some(diagonal),
+                (other_addr, _) if other_addr == addr => Some(diagonal + 1),
+                _ => None,
+            };
+            let edit = (row[j].min(row[j + 1]) + 1).min(replace.unwrap_or(usize::MAX));
+            diagonal = row[j + 1];
+            row[j + 1] = edit;
+        }
+    }
+    row[b.len()]
+}
+
+/// Returns the cheats of all games whose codes are at most `max_changes`
+/// changes away from those of `cheat`, closest first.
+///
+/// Only cheats that share an address with `cheat` count, so short cheats of
+/// unrelated codes are not found for a large `max_changes`. A cheat with
+/// the same codes is found with a distance of 0.
+pub fn find_similar(games: &[Game], cheat: &Cheat, max_changes: usize) -> Vec<Similar> {
+    let mut found = Vec::new();
+    for (g, game) in games.iter().enumerate() {
+        for (c, other) in game.cheats.iter().enumerate() {
+            let shared = other
+                .codes
+                .iter()
+                .any(|code| cheat.codes.iter().any(|own| own.0 == code.0));
+            if !shared {
+                continue;
+            }
+            let distance = distance(&cheat.codes, &other.codes);
+            if distance <= max_changes {
+                found.push(Similar {
+                    game: g,
+                    cheat: c,
+                    distance,
+                });
+            }
+        }
+    }
+    // The sort is stable, so equally close cheats stay in order
+    found.sort_by_key(|similar| similar.distance);
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_distance() {
+        let a = [
+            (0x2031_d1e0, 0x05f5_e0ff),
+            (0x1031_d1e4, 0x0000_ffff),
+            (0x0031_d3a0, 0x63),
+        ];
+        let tests: &[(&[(u32, u32)], usize)] = &[
+            (&a, 0),
+            (&[], 3),
+            (&a[1..], 1),
+            (&[a[0], a[2]], 1),
+            (&[a[0], a[1], a[2], a[2]], 1),
+            (&[(0x2031_d1e0, 0), (0x1031_d1e4, 0), (0x0031_d3a0, 0)], 3),
+            (&[a[2], a[1], a[0]], 4),
+        ];
+        for &(b, expected) in tests.iter() {
+            assert_eq!(expected, distance(&a, b), "{:X?}", b);
+            assert_eq!(expected, distance(b, &a), "{:X?}", b);
+        }
+    }
+
+    #[test]
+    fn test_find_similar() {
+        let mut game = Game::new("Test");
+        game.cheats.push(Cheat::new("(M)", vec![(0xbeef_c0de, 0)]));
+        game.cheats
+            .push(Cheat::new("Max Gil", vec![(0x2031_d1e0, 0x05f5_e0ff)]));
+        game.cheats.push(Cheat::new("Other", vec![(0x2031_d1e4, 0x05f5_e0ff)]));
+        game.cheats.push(Cheat::new(
+            "Max Gil + MP",
+            vec![(0x2031_d1e0, 0x000f_423f), (0x1031_d1f0, 0x0000_03e7)],
+        ));
+        let mut copy = Game::new("Copy");
+        copy.cheats.push(game.cheats[1].clone());
+
+        let cheat = Cheat::new("Gil", vec![(0x2031_d1e0, 0x000f_423f)]);
+        assert_eq!(
+            vec![
+                Similar {
+                    game: 0,
+                    cheat: 1,
+                    distance: 1
+                },
+                Similar {
+                    game: 0,
+                    cheat: 3,
+                    distance: 1
+                },
+                Similar {
+                    game: 1,
+                    cheat: 0,
+                    distance: 1
+                },
+            ],
+            find_similar(&[game.clone(), copy.clone()], &cheat, 5)
+        );
+        assert_eq!(
+            vec![
+                Similar {
+                    game: 0,
+                    cheat: 1,
+                    distance: 0
+                },
+                Similar {
+                    game: 1,
+                    cheat: 0,
+                    distance: 0
+                }
+            ],
+            find_similar(&[game, copy], &Cheat::new("", vec![(0x2031_d1e0, 0x05f5_e0ff)]), 0)
+        );
+    }
+}
@@ -0,0 +1,149 @@
+//! Processing of long lists in bounded steps.
+//!
+//! Interactive hosts like browsers and bots must not block on huge inputs.
+//! A [`Continuation`] processes at most a given number of lines per call and
+//! hands back a token to resume with, so the latency of each call is bounded
+//! no matter how long the list is.
+//!
+//! # Example
+//! ```
+//! use codebreaker::stream::Continuation;
+//! use codebreaker::Codebreaker;
+//!
+//! let mut codes = vec![
+//!     (0x2AFF014C, 0x2411FFFF),
+//!     (0xB4336FA9, 0x4DFEFB79),
+//!     (0x973E0B2A, 0xA7D4AF10),
+//! ];
+//!
+//! let mut token = Some(Continuation::new(Codebreaker::new()));
+//! while let Some(t) = token {
+//!     // do other work between calls
+//!     token = t.decrypt(&mut codes, 2);
+//! }
+//! assert_eq!((0x2096F5B8, 0x000000BE), codes[2]);
+//! ```
+
+use crate::Codebreaker;
+
+/// The state needed to resume processing a list.
+///
+/// The list must be passed in unchanged on every call, including the lines
+/// that were processed already.
+#[derive(Debug, Clone, Copy)]
+pub struct Continuation {
+    cb: Codebreaker,
+    next: usize,
+}
+
+impl Continuation {
+    /// Returns a token to process a list from the start with the given
+    /// processor.
+    pub const fn new(cb: Codebreaker) -> Self {
+        Self { cb, next: 0 }
+    }
+
+    /// Returns the index of the next line to process.
+    pub const fn next_line(&self) -> usize {
+        self.next
+    }
+
+    /// Returns the processor in its current state.
+    pub const fn codebreaker(&self) -> &Codebreaker {
+        &self.cb
+    }
+
+    /// Encrypts up to `max_lines` codes in place, like
+    /// [`encrypt_code_mut`](../struct.Codebreaker.html#method.encrypt_code_mut).
+    ///
+    /// Returns `None` once the end of the list is reached.
+    pub fn encrypt(self, codes: &mut [(u32, u32)], max_lines: usize) -> Option<Self> {
+        self.run(codes, max_lines, Codebreaker::encrypt_code_mut)
+    }
+
+    /// Decrypts up to `max_lines` codes in place, like
+    /// [`decrypt_code_mut`](../struct.Codebreaker.html#method.decrypt_code_mut).
+    ///
+    /// Returns `None` once the end of the list is reached.
+    pub fn decrypt(self, codes: &mut [(u32, u32)], max_lines: usize) -> Option<Self> {
+        self.run(codes, max_lines, Codebreaker::decrypt_code_mut)
+    }
+
+    /// Decrypts up to `max_lines` codes in place, like
+    /// [`auto_decrypt_code_mut`](../struct.Codebreaker.html#method.auto_decrypt_code_mut).
+    ///
+    /// Returns `None` once the end of the list is reached.
+    pub fn auto_decrypt(self, codes: &mut [(u32, u32)], max_lines: usize) -> Option<Self> {
+        self.run(codes, max_lines, Codebreaker::auto_decrypt_code_mut)
+    }
+
+    fn run<F>(mut self, codes: &mut [(u32, u32)], max_lines: usize, mut f: F) -> Option<Self>
+    where
+        F: FnMut(&mut Codebreaker, &mut u32, &mut u32),
+    {
+        let start = self.next.min(codes.len());
+        let end = start.saturating_add(max_lines).min(codes.len());
+        for code in codes[start..end].iter_mut() {
+            f(&mut self.cb, &mut code.0, &mut code.1);
+        }
+        self.next = end;
+        if end < codes.len() {
+            Some(self)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::code;
+    use crate::std_alloc::Vec;
+
+    fn codes() -> Vec<(u32, u32)> {
+        [
+            "2043AFCC 2411FFFF",
+            "BEEFC0DE 00000000",
+            "2096F5B8 000000BE",
+            "2043AFD0 2411FFFF",
+            "2043AFD4 2411FFFF",
+        ]
+        .iter()
+        .map(|line| code::parse(line))
+        .collect()
+    }
+
+    #[test]
+    fn test_bounded_steps() {
+        let mut cb = Codebreaker::new();
+        let expected: Vec<_> = codes().iter().map(|c| cb.encrypt_code(c.0, c.1)).collect();
+
+        let mut encrypted = codes();
+        let mut token = Some(Continuation::new(Codebreaker::new()));
+        let mut calls = 0;
+        while let Some(t) = token {
+            assert_eq!(2 * calls, t.next_line());
+            token = t.encrypt(&mut encrypted, 2);
+            calls += 1;
+        }
+        assert_eq!(3, calls);
+        assert_eq!(expected, encrypted);
+
+        let mut token = Some(Continuation::new(Codebreaker::new()));
+        while let Some(t) = token {
+            token = t.auto_decrypt(&mut encrypted, 1);
+        }
+        assert_eq!(codes(), encrypted);
+    }
+
+    #[test]
+    fn test_edge_cases() {
+        let mut codes = codes();
+        let token = Continuation::new(Codebreaker::new());
+        // no progress with a limit of zero
+        assert_eq!(0, token.decrypt(&mut codes, 0).unwrap().next_line());
+        assert!(token.decrypt(&mut codes, usize::MAX).is_none());
+        assert!(token.decrypt(&mut [], 1).is_none());
+    }
+}
@@ -0,0 +1,179 @@
+//! Symbol maps from reverse-engineering projects.
+//!
+//! A [`SymbolMap`] names addresses of EE RAM, so codes can be reported as
+//! writing to `player_hp` instead of `0043AFCC`. It implements
+//! [`Symbols`](../pretty/trait.Symbols.html) for use with the
+//! [printer](../pretty/struct.Printer.html).
+//!
+//! # Example
+//! ```
+//! use codebreaker::symbols::SymbolMap;
+//!
+//! let map = SymbolMap::parse("0043AFCC player_hp\n0043AFD0 player_mp\n");
+//! assert_eq!(Some("player_hp"), map.get(0x0043AFCC));
+//! assert_eq!(Some(("player_mp", 2)), map.resolve(0x0043AFD2));
+//! ```
+
+use crate::op::Addr;
+use crate::pretty::Symbols;
+use crate::std_alloc::{BTreeMap, String};
+
+/// A table of address names.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SymbolMap {
+    symbols: BTreeMap<u32, String>,
+}
+
+impl SymbolMap {
+    /// Returns an empty symbol map.
+    pub const fn new() -> Self {
+        Self {
+            symbols: BTreeMap::new(),
+        }
+    }
+
+    /// Parses a symbol table.
+    ///
+    /// Every line holding a hex address and a name, in either order and
+    /// separated by whitespace, `,` or `=`, defines a symbol. This covers
+    /// simple tables, `.sym` files exported by debuggers, and the symbol
+    /// lines of GNU ld `.map` files. All other lines are ignored, as are
+    /// comments starting with `#` or `;`.
+    ///
+    /// Addresses are taken modulo the 32 MB of EE RAM, so kernel segment and
+    /// uncached addresses name the same memory as physical ones. A leading
+    /// byte order mark is skipped.
+    pub fn parse(text: &str) -> Self {
+        let mut map = Self::new();
+        let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+        for line in text.lines() {
+            if let Some((addr, name)) = parse_line(line) {
+                map.insert(addr, name);
+            }
+        }
+        map
+    }
+
+    /// Adds a symbol, replacing any previous name of the address.
+    pub fn insert(&mut self, addr: u32, name: &str) {
+        self.symbols.insert(addr & Addr::MAX, name.into());
+    }
+
+    /// Returns the name of an address.
+    pub fn get(&self, addr: u32) -> Option<&str> {
+        self.symbols.get(&(addr & Addr::MAX)).map(String::as_str)
+    }
+
+    /// Returns the name of the closest symbol at or below an address, along
+    /// with the offset from it.
+    pub fn resolve(&self, addr: u32) -> Option<(&str, u32)> {
+        let addr = addr & Addr::MAX;
+        self.symbols
+            .range(..=addr)
+            .next_back()
+            .map(|(start, name)| (name.as_str(), addr - start))
+    }
+
+    /// Returns the number of symbols.
+    pub fn len(&self) -> usize {
+        self.symbols.len()
+    }
+
+    /// Returns true if there are no symbols.
+    pub fn is_empty(&self) -> bool {
+        self.symbols.is_empty()
+    }
+}
+
+impl Symbols for SymbolMap {
+    fn lookup(&self, addr: u32) -> Option<&str> {
+        self.get(addr)
+    }
+}
+
+fn parse_line(line: &str) -> Option<(u32, &str)> {
+    let line = line.split(['#', ';']).next().unwrap_or_default();
+    let mut fields = line
+        .split(|c: char| c.is_whitespace() || c == ',' || c == '=')
+        .filter(|f| !f.is_empty());
+
+    let (a, b) = (fields.next()?, fields.next()?);
+    if fields.next().is_some() {
+        return None;
+    }
+    match (parse_hex(a), parse_hex(b)) {
+        (Some(addr), None) if is_name(b) => Some((addr, b)),
+        (None, Some(addr)) if is_name(a) => Some((addr, a)),
+        _ => None,
+    }
+}
+
+fn parse_hex(s: &str) -> Option<u32> {
+    let digits = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")).unwrap_or(s);
+    if digits.is_empty() || digits.len() > 16 {
+        return None;
+    }
+    // ld map files print 64-bit addresses
+    u64::from_str_radix(digits, 16).ok().map(|addr| addr as u32)
+}
+
+fn is_name(s: &str) -> bool {
+    s.chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == '.')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let text = "\
+# simple table
+0043AFCC player_hp
+player_mp = 0x0043AFD0
+0x0043AFD4,player_lives
+8043B000 kseg0_alias ; .sym style
+ .text          0x0000000000100000     0x1234 main.o
+                0x00000000001000a0                main
+not a symbol line
+";
+        let map = SymbolMap::parse(text);
+        assert_eq!(5, map.len());
+        assert_eq!(
+            map,
+            SymbolMap::parse(&format!("\u{feff}{}", text.replace('\n', "\r\n")))
+        );
+        assert_eq!(Some("player_hp"), map.get(0x0043_afcc));
+        assert_eq!(Some("player_mp"), map.get(0x0043_afd0));
+        assert_eq!(Some("player_lives"), map.get(0x0043_afd4));
+        assert_eq!(Some("kseg0_alias"), map.get(0x0043_b000));
+        assert_eq!(Some("main"), map.get(0x0010_00a0));
+        assert_eq!(None, map.get(0x0010_0000));
+    }
+
+    #[test]
+    fn test_resolve() {
+        let mut map = SymbolMap::new();
+        assert!(map.is_empty());
+        map.insert(0x0043_afcc, "hp");
+        map.insert(0x0043_afd0, "mp");
+
+        assert_eq!(None, map.resolve(0x0043_afcb));
+        assert_eq!(Some(("hp", 0)), map.resolve(0x0043_afcc));
+        assert_eq!(Some(("hp", 3)), map.resolve(0x2043_afcf));
+        assert_eq!(Some(("mp", 0x30)), map.resolve(0x0043_b000));
+    }
+
+    #[test]
+    fn test_symbols() {
+        use crate::pretty::Printer;
+
+        let map = SymbolMap::parse("0043AFCC player_hp");
+        assert_eq!(
+            "1043AFCC 0000FFFF // player_hp\n",
+            Printer::new().symbols(&map).to_string(&[(0x1043_afcc, 0xffff)])
+        );
+    }
+}
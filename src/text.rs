@@ -0,0 +1,371 @@
+//! Conversion of code lists in text form.
+//!
+//! Most tools convert a pasted block of codes, one `XXXXXXXX YYYYYYYY` per
+//! line, into another block of codes. [`decrypt`] and [`encrypt`] do that in
+//! one call, processing the codes in order with a single processor. A
+//! [`Converter`] can also keep or drop lines that do not hold a code, and
+//! keep the comments, blank lines, and cheat names of annotated lists.
+//!
+//! # Example
+//! ```
+//! use codebreaker::text;
+//!
+//! let input = "2A973DBD 00000000\nB4336FA9 4DFEFB79\n973E0B2A A7D4AF10\n";
+//! assert_eq!(
+//!     Ok(String::from("201F6024 00000000\nBEEFC0DE 00000000\n2096F5B8 000000BE\n")),
+//!     text::decrypt(input)
+//! );
+//! ```
+
+use crate::std_alloc::{String, Vec};
+use crate::{strip_hex_prefix, Code, Codebreaker, ParseCodeError};
+
+use core::fmt::{self, Write};
+
+/// The error returned for text that is not a list of codes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Error {
+    /// Index of the offending line
+    pub line: usize,
+    /// Why the line is not a code
+    pub reason: ParseCodeError,
+}
+
+/// Lines are counted from 1.
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line + 1, self.reason)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+/// What to do with lines that do not hold a code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Policy {
+    /// Fail with an [`Error`]
+    Abort,
+    /// Copy the line to the output unchanged
+    PassThrough,
+    /// Leave the line out and report it in
+    /// [`Conversion::dropped`](struct.Conversion.html#structfield.dropped)
+    Drop,
+}
+
+/// Does the same as [`Policy::Abort`].
+impl Default for Policy {
+    fn default() -> Self {
+        Self::Abort
+    }
+}
+
+/// The result of a conversion.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conversion {
+    /// The converted text
+    pub text: String,
+    /// The lines left out with [`Policy::Drop`]
+    pub dropped: Vec<Error>,
+}
+
+/// Converts code lists in text form.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Converter {
+    unknown: Policy,
+    annotations: bool,
+}
+
+impl Converter {
+    /// Returns a converter that fails on lines without a code.
+    pub const fn new() -> Self {
+        Self {
+            unknown: Policy::Abort,
+            annotations: false,
+        }
+    }
+
+    /// Sets what to do with lines that do not hold a code.
+    ///
+    /// # Example
+    /// ```
+    /// use codebreaker::text::{Converter, Policy};
+    /// use codebreaker::Codebreaker;
+    ///
+    /// let input = "Inf HP\n2A973DBD 00000000\n";
+    /// let converter = Converter::new().unknown_lines(Policy::PassThrough);
+    /// let result = converter.decrypt(&mut Codebreaker::new(), input).unwrap();
+    /// assert_eq!("Inf HP\n201F6024 00000000\n", result.text);
+    ///
+    /// let converter = Converter::new().unknown_lines(Policy::Drop);
+    /// let result = converter.decrypt(&mut Codebreaker::new(), input).unwrap();
+    /// assert_eq!("201F6024 00000000\n", result.text);
+    /// assert_eq!(0, result.dropped[0].line);
+    /// assert_eq!("line 1: \"Inf\" is not an 8-digit hex number", format!("{}", result.dropped[0]));
+    /// ```
+    pub const fn unknown_lines(mut self, policy: Policy) -> Self {
+        self.unknown = policy;
+        self
+    }
+
+    /// Keeps comments, blank lines, and cheat names in the output.
+    ///
+    /// Comments start with `//`, `#`, or `;`, either on a line of their own or
+    /// after a code. A line that does not hold a code is taken as the name of
+    /// a cheat, unless it starts with 8 hex digits like a malformed code; only
+    /// such lines are subject to [`unknown_lines`](#method.unknown_lines).
+    ///
+    /// # Example
+    /// ```
+    /// use codebreaker::text::Converter;
+    /// use codebreaker::Codebreaker;
+    ///
+    /// let input = "Inf HP\n2A973DBD 00000000 // player 1\n\n; more to come\n";
+    /// let converter = Converter::new().annotations(true);
+    /// let result = converter.decrypt(&mut Codebreaker::new(), input).unwrap();
+    /// assert_eq!("Inf HP\n201F6024 00000000 // player 1\n\n; more to come\n", result.text);
+    /// ```
+    pub const fn annotations(mut self, annotations: bool) -> Self {
+        self.annotations = annotations;
+        self
+    }
+
+    /// Decrypts the codes of `text` with `cb`, detecting how they are
+    /// encrypted.
+    pub fn decrypt(&self, cb: &mut Codebreaker, text: &str) -> Result<Conversion, Error> {
+        self.convert(text, |code| cb.auto_decrypt(code))
+    }
+
+    /// Encrypts the decrypted codes of `text` with `cb`.
+    pub fn encrypt(&self, cb: &mut Codebreaker, text: &str) -> Result<Conversion, Error> {
+        self.convert(text, |code| cb.encrypt(code))
+    }
+
+    /// Encrypts the decrypted codes of `text` with `cb` like
+    /// [`auto_encrypt_code`](../struct.Codebreaker.html#method.auto_encrypt_code),
+    /// so that [`decrypt`](#method.decrypt) gives them back.
+    ///
+    /// # Example
+    /// ```
+    /// use codebreaker::text::Converter;
+    /// use codebreaker::Codebreaker;
+    ///
+    /// let input = "BEEFC0DE 00000000\n2096F5B8 000000BE\n";
+    /// let result = Converter::new().auto_encrypt(&mut Codebreaker::new(), input).unwrap();
+    /// assert_eq!("B4336FA9 4DFEFB79\n973E0B2A A7D4AF10\n", result.text);
+    /// ```
+    pub fn auto_encrypt(&self, cb: &mut Codebreaker, text: &str) -> Result<Conversion, Error> {
+        self.convert(text, |code| cb.auto_encrypt_code(code.addr, code.val).into())
+    }
+
+    fn convert<F>(&self, text: &str, mut f: F) -> Result<Conversion, Error>
+    where
+        F: FnMut(Code) -> Code,
+    {
+        let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+        let mut conversion = Conversion {
+            text: String::new(),
+            dropped: Vec::new(),
+        };
+        // Writing to a String cannot fail
+        let s = &mut conversion.text;
+        for (line, content) in text.lines().enumerate() {
+            let (body, comment) = if self.annotations {
+                split_comment(content)
+            } else {
+                (content, "")
+            };
+            if body.trim().is_empty() {
+                if self.annotations {
+                    let _ = writeln!(s, "{}", content.trim());
+                }
+                continue;
+            }
+            match body.parse() {
+                Ok(code) if comment.is_empty() => {
+                    let _ = writeln!(s, "{}", f(code));
+                }
+                Ok(code) => {
+                    // Keep the spacing before the comment
+                    let _ = writeln!(s, "{}{}", f(code), content[body.trim_end().len()..].trim_end());
+                }
+                Err(_) if self.annotations && !looks_like_code(body) => {
+                    let _ = writeln!(s, "{}", content.trim());
+                }
+                Err(reason) => match self.unknown {
+                    Policy::Abort => return Err(Error { line, reason }),
+                    Policy::PassThrough => {
+                        let _ = writeln!(s, "{}", content);
+                    }
+                    Policy::Drop => conversion.dropped.push(Error { line, reason }),
+                },
+            }
+        }
+        Ok(conversion)
+    }
+}
+
+// Splits a line at the first comment marker.
+pub(crate) fn split_comment(line: &str) -> (&str, &str) {
+    let start = ["//", "#", ";"].iter().filter_map(|m| line.find(m)).min();
+    line.split_at(start.unwrap_or(line.len()))
+}
+
+// Malformed codes start with 8 hex digits, while cheat names rarely do.
+pub(crate) fn looks_like_code(line: &str) -> bool {
+    let token = line.split_whitespace().next().unwrap_or("");
+    let digits = strip_hex_prefix(token);
+    digits.len() >= 8 && digits.bytes().take(8).all(|b| b.is_ascii_hexdigit())
+}
+
+/// Decrypts a list of codes with a new processor, detecting how the codes
+/// are encrypted.
+///
+/// Blank lines and a leading byte order mark are ignored; any other line
+/// must hold a code. Returns one decrypted code per line.
+pub fn decrypt(text: &str) -> Result<String, Error> {
+    decrypt_with(&mut Codebreaker::new(), text)
+}
+
+/// Encrypts a list of decrypted codes with a new processor.
+///
+/// Codes are encrypted with CB v1 until a beefcode switches to CB v7. Use
+/// [`encrypt_with`] and [`new_v7`](../struct.Codebreaker.html#method.new_v7)
+/// for lists without a beefcode.
+pub fn encrypt(text: &str) -> Result<String, Error> {
+    encrypt_with(&mut Codebreaker::new(), text)
+}
+
+/// Like [`decrypt`], but continues with the state of the given processor.
+pub fn decrypt_with(cb: &mut Codebreaker, text: &str) -> Result<String, Error> {
+    Converter::new().decrypt(cb, text).map(|c| c.text)
+}
+
+/// Like [`encrypt`], but continues with the state of the given processor.
+pub fn encrypt_with(cb: &mut Codebreaker, text: &str) -> Result<String, Error> {
+    Converter::new().encrypt(cb, text).map(|c| c.text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encrypt() {
+        let input = "\u{feff}2043AFCC 2411FFFF\r\n\r\nbeefc0de 00000000\n2096F5B8 000000BE";
+        assert_eq!(
+            Ok(String::from(
+                "2AFF014C 2411FFFF\nB4336FA9 4DFEFB79\n973E0B2A A7D4AF10\n"
+            )),
+            encrypt(input)
+        );
+        assert_eq!(Ok(String::new()), encrypt(""));
+
+        let mut cb = Codebreaker::new_v7();
+        assert_eq!(
+            Ok(String::from("D08F3A49 00078A53\n")),
+            encrypt_with(&mut cb, "9029BEAC 0C0A9225")
+        );
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let input = "2043AFCC 2411FFFF\nBEEFC0DE 00000000\n2096F5B8 000000BE\n";
+        assert_eq!(Ok(String::from(input)), decrypt(&encrypt(input).unwrap()));
+    }
+
+    #[test]
+    fn test_invalid() {
+        let err = decrypt("2A973DBD 00000000\n\nInf HP\n").unwrap_err();
+        assert_eq!(2, err.line);
+        assert_eq!(ParseCodeError::InvalidToken("Inf".into()), err.reason);
+        assert_eq!("line 3: \"Inf\" is not an 8-digit hex number", format!("{}", err));
+    }
+
+    #[test]
+    fn test_policy() {
+        let input = "// pasted from a forum\n2A973DBD 00000000\nInf HP\n\nB4336FA9 4DFEFB79\n";
+        let decrypt = |policy| {
+            Converter::new()
+                .unknown_lines(policy)
+                .decrypt(&mut Codebreaker::new(), input)
+        };
+
+        let error = |line, token: &str| Error {
+            line,
+            reason: ParseCodeError::InvalidToken(token.into()),
+        };
+        assert_eq!(Err(error(0, "//")), decrypt(Policy::Abort));
+        assert_eq!(
+            Ok(Conversion {
+                text: String::from("// pasted from a forum\n201F6024 00000000\nInf HP\nBEEFC0DE 00000000\n"),
+                dropped: vec![],
+            }),
+            decrypt(Policy::PassThrough)
+        );
+        assert_eq!(
+            Ok(Conversion {
+                text: String::from("201F6024 00000000\nBEEFC0DE 00000000\n"),
+                dropped: vec![error(0, "//"), error(2, "Inf")],
+            }),
+            decrypt(Policy::Drop)
+        );
+
+        let mut cb = Codebreaker::new();
+        let result = Converter::new()
+            .unknown_lines(Policy::PassThrough)
+            .encrypt(&mut cb, "Inf HP\n201F6024 00000000");
+        assert_eq!("Inf HP\n2A973DBD 00000000\n", result.unwrap().text);
+    }
+
+    #[test]
+    fn test_annotations() {
+        let input = "\
+Kingdom Hearts
+# enable code
+B4336FA9 4DFEFB79;must be on
+\t
+\"Inf HP\" (P1)
+973E0B2A A7D4AF10   // 0x2096F5B8
+";
+        let converter = Converter::new().annotations(true);
+        assert_eq!(
+            "\
+Kingdom Hearts
+# enable code
+BEEFC0DE 00000000;must be on
+
+\"Inf HP\" (P1)
+2096F5B8 000000BE   // 0x2096F5B8
+",
+            converter.decrypt(&mut Codebreaker::new(), input).unwrap().text
+        );
+
+        // A name is not a code, so encrypting and decrypting it is lossless
+        let decrypted = converter.decrypt(&mut Codebreaker::new(), input).unwrap().text;
+        let encrypted = converter.encrypt(&mut Codebreaker::new(), &decrypted).unwrap().text;
+        assert_eq!(input.replace("\t\n", "\n"), encrypted);
+    }
+
+    #[test]
+    fn test_annotations_malformed() {
+        let converter = Converter::new().annotations(true);
+        let err = converter
+            .decrypt(&mut Codebreaker::new(), "Inf HP\n2A973DBD 0000000 // typo")
+            .unwrap_err();
+        assert_eq!(1, err.line);
+
+        let converter = converter.unknown_lines(Policy::Drop);
+        let result = converter
+            .decrypt(&mut Codebreaker::new(), "0x2A973DBD\nAdd 99 Potions\n")
+            .unwrap();
+        assert_eq!("Add 99 Potions\n", result.text);
+        assert_eq!(0, result.dropped[0].line);
+
+        // Without annotations, comments are unknown lines
+        assert!(Converter::new()
+            .decrypt(&mut Codebreaker::new(), "2A973DBD 00000000 // inf hp")
+            .is_err());
+    }
+}
@@ -0,0 +1,255 @@
+//! Generation of freeze lists for trainers.
+//!
+//! Trainer authors typically start with a set of memory locations to freeze
+//! at fixed values. [`freeze`] turns such a set into a compact cheat:
+//! neighboring 8-bit and 16-bit freezes are merged into wider writes, and
+//! 32-bit writes that follow a regular pattern are combined into serial
+//! writes.
+//!
+//! # Example
+//! ```
+//! use codebreaker::op::Width;
+//! use codebreaker::trainer::{freeze, Freeze};
+//!
+//! let freezes = [
+//!     Freeze::new(0x00410000, 0x63, Width::Word).unwrap(),
+//!     Freeze::new(0x00410004, 0x63, Width::Word).unwrap(),
+//!     Freeze::new(0x00410008, 0x63, Width::Word).unwrap(),
+//!     Freeze::new(0x0043AFCC, 0xFF, Width::Byte).unwrap(),
+//!     Freeze::new(0x0043AFCD, 0xFF, Width::Byte).unwrap(),
+//! ];
+//! let (cheat, report) = freeze("Max Items", &freezes).unwrap();
+//! assert_eq!(
+//!     vec![(0x40410000, 0x00030001), (0x00000063, 0x00000000), (0x1043AFCC, 0x0000FFFF)],
+//!     cheat.to_codes().unwrap()
+//! );
+//! assert_eq!(5, report.naive_lines);
+//! assert_eq!(3, report.lines);
+//! assert!(report.requires_mastercode);
+//! ```
+
+use crate::cheat::Cheat;
+use crate::op::{Addr, Op, Value, Width};
+use crate::std_alloc::{BTreeMap, Vec};
+use crate::Error;
+
+// Serial writes take two lines, so they only pay off for three or more writes.
+const MIN_SERIAL_COUNT: usize = 3;
+
+/// A memory location frozen at a value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Freeze {
+    /// The address to freeze
+    pub addr: Addr,
+    /// The value and width to freeze
+    pub val: Value,
+}
+
+impl Freeze {
+    /// Returns a freeze, or an error if the address or value is invalid for
+    /// the width.
+    pub const fn new(addr: u32, val: u32, width: Width) -> Result<Self, Error> {
+        let addr = match Addr::new_aligned(addr, width) {
+            Ok(addr) => addr,
+            Err(err) => return Err(err),
+        };
+        match Value::new(val, width) {
+            Ok(val) => Ok(Self { addr, val }),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+/// Statistics about a generated freeze list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Report {
+    /// The number of lines needed with one write per freeze
+    pub naive_lines: usize,
+    /// The number of lines of the generated cheat
+    pub lines: usize,
+    /// Whether the cheat depends on the game's mastercode
+    ///
+    /// Freezes must be re-applied continuously, which CodeBreaker only does
+    /// with the mastercode (hook) of the game enabled.
+    pub requires_mastercode: bool,
+}
+
+/// Returns a compact cheat applying all freezes, along with a report.
+///
+/// If freezes overlap, later ones take precedence. Writes are ordered by
+/// address. Serial writes are found greedily, so the result is compact but
+/// not guaranteed to be minimal.
+pub fn freeze(name: &str, freezes: &[Freeze]) -> Result<(Cheat, Report), Error> {
+    let cheat = Cheat {
+        name: name.into(),
+        ops: compact(freezes)?,
+    };
+    let lines = cheat.to_codes()?.len();
+    let report = Report {
+        naive_lines: freezes.len(),
+        lines,
+        requires_mastercode: lines > 0,
+    };
+    Ok((cheat, report))
+}
+
+// Returns the writes applying all freezes with as few lines as possible.
+pub(crate) fn compact(freezes: &[Freeze]) -> Result<Vec<Op>, Error> {
+    // EE memory is little-endian
+    let mut bytes = BTreeMap::new();
+    for f in freezes.iter() {
+        for i in 0..f.val.width().bytes() {
+            bytes.insert(f.addr.get() + i, (f.val.get() >> (8 * i)) as u8);
+        }
+    }
+
+    let mut words = Vec::new();
+    let mut ops = Vec::new();
+    let mut bases: Vec<u32> = bytes.keys().map(|addr| addr & !3).collect();
+    bases.dedup();
+
+    for base in bases {
+        let byte = |i: u32| bytes.get(&(base + i)).copied();
+        if let (Some(b0), Some(b1), Some(b2), Some(b3)) = (byte(0), byte(1), byte(2), byte(3)) {
+            words.push((base, u32::from_le_bytes([b0, b1, b2, b3])));
+            continue;
+        }
+        for half in [0, 2].iter() {
+            match (byte(*half), byte(half + 1)) {
+                (Some(lo), Some(hi)) => ops.push(write(base + half, Value::half(u16::from_le_bytes([lo, hi])))?),
+                (Some(lo), None) => ops.push(write(base + half, Value::byte(lo))?),
+                (None, Some(hi)) => ops.push(write(base + half + 1, Value::byte(hi))?),
+                (None, None) => {}
+            }
+        }
+    }
+    ops.extend(serial_writes(&words)?);
+    ops.sort_by_key(start_addr);
+    Ok(ops)
+}
+
+// Combines runs of equally spaced words with evenly incrementing values.
+fn serial_writes(words: &[(u32, u32)]) -> Result<Vec<Op>, Error> {
+    let mut ops = Vec::new();
+    let mut i = 0;
+
+    while i < words.len() {
+        let mut n = 1;
+        if let Some(next) = words.get(i + 1) {
+            let step = next.0 - words[i].0;
+            let inc = next.1.wrapping_sub(words[i].1);
+            while let Some(w) = words.get(i + n) {
+                let prev = words[i + n - 1];
+                if w.0 - prev.0 != step || w.1.wrapping_sub(prev.1) != inc || step / 4 > 0xffff || n == 0xffff {
+                    break;
+                }
+                n += 1;
+            }
+            if n >= MIN_SERIAL_COUNT {
+                ops.push(Op::SerialWrite {
+                    addr: Addr::new(words[i].0)?,
+                    val: words[i].1,
+                    count: n as u32,
+                    step,
+                    inc,
+                });
+                i += n;
+                continue;
+            }
+        }
+        ops.push(write(words[i].0, Value::word(words[i].1))?);
+        i += 1;
+    }
+    Ok(ops)
+}
+
+fn write(addr: u32, val: Value) -> Result<Op, Error> {
+    Ok(Op::Write {
+        addr: Addr::new(addr)?,
+        val,
+    })
+}
+
+const fn start_addr(op: &Op) -> Addr {
+    match op {
+        Op::Write { addr, .. } | Op::SerialWrite { addr, .. } | Op::If { addr, .. } => *addr,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Test = (&'static [(u32, u32, Width)], &'static [(u32, u32)]);
+
+    fn freezes(list: &[(u32, u32, Width)]) -> Vec<Freeze> {
+        list.iter().map(|f| Freeze::new(f.0, f.1, f.2).unwrap()).collect()
+    }
+
+    #[test]
+    fn test_freeze_new() {
+        assert!(Freeze::new(0x0010_0000, 0xffff, Width::Half).is_ok());
+        assert_eq!(
+            Err(Error::Misaligned {
+                addr: 0x0010_0001,
+                width: Width::Half
+            }),
+            Freeze::new(0x0010_0001, 0, Width::Half)
+        );
+        assert_eq!(
+            Err(Error::ValueTooLarge {
+                val: 0x100,
+                width: Width::Byte
+            }),
+            Freeze::new(0x0010_0000, 0x100, Width::Byte)
+        );
+    }
+
+    #[test]
+    fn test_freeze() {
+        #[rustfmt::skip]
+        let tests: [Test; 6] = [
+            // nothing to do
+            (&[], &[]),
+            // bytes merged into a word
+            (
+                &[(0x0010_0003, 0x12, Width::Byte), (0x0010_0000, 0x78, Width::Byte),
+                  (0x0010_0001, 0x56, Width::Byte), (0x0010_0002, 0x34, Width::Byte)],
+                &[(0x2010_0000, 0x1234_5678)],
+            ),
+            // halves, partial words
+            (
+                &[(0x0010_0000, 0x5678, Width::Half), (0x0010_0005, 0x12, Width::Byte),
+                  (0x0010_0006, 0x34, Width::Byte)],
+                &[(0x1010_0000, 0x0000_5678), (0x0010_0005, 0x0000_0012), (0x0010_0006, 0x0000_0034)],
+            ),
+            // later freezes take precedence
+            (
+                &[(0x0010_0000, 0x1111_1111, Width::Word), (0x0010_0002, 0xffff, Width::Half)],
+                &[(0x2010_0000, 0xffff_1111)],
+            ),
+            // serial write with increment, too short run left alone
+            (
+                &[(0x0020_0000, 5, Width::Word), (0x0020_0010, 6, Width::Word), (0x0020_0020, 7, Width::Word),
+                  (0x0020_0030, 0, Width::Word), (0x0020_0034, 0, Width::Word)],
+                &[(0x4020_0000, 0x0003_0004), (0x0000_0005, 0x0000_0001),
+                  (0x2020_0030, 0x0000_0000), (0x2020_0034, 0x0000_0000)],
+            ),
+            // serial write sorted among other writes
+            (
+                &[(0x0030_0000, 1, Width::Byte), (0x0020_0000, 9, Width::Word), (0x0020_0004, 9, Width::Word),
+                  (0x0020_0008, 9, Width::Word), (0x0010_0000, 2, Width::Byte)],
+                &[(0x0010_0000, 0x0000_0002), (0x4020_0000, 0x0003_0001), (0x0000_0009, 0x0000_0000),
+                  (0x0030_0000, 0x0000_0001)],
+            ),
+        ];
+        for t in tests.iter() {
+            let (cheat, report) = freeze("", &freezes(t.0)).unwrap();
+            let codes = cheat.to_codes().unwrap();
+            assert_eq!(t.1, &codes[..]);
+            assert_eq!(t.0.len(), report.naive_lines);
+            assert_eq!(t.1.len(), report.lines);
+            assert_eq!(!t.1.is_empty(), report.requires_mastercode);
+        }
+    }
+}
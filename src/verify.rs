@@ -0,0 +1,185 @@
+//! Verify published lists of encrypted codes against their claimed decrypted
+//! equivalent.
+//!
+//! Cheat sites often publish codes in both encrypted and decrypted form.
+//! [`verify_list`] checks that the two lists actually correspond to each
+//! other, which is handy for moderating submissions.
+//!
+//! # Example
+//! ```
+//! use codebreaker::verify::{verify_list, Divergence};
+//! use codebreaker::Scheme;
+//!
+//! let encrypted = [(0x2AFF014C, 0x2411FFFF), (0xB4336FA9, 0x4DFEFB79), (0x973E0B2A, 0xA7D4AF10)];
+//! let decrypted = [(0x2043AFCC, 0x2411FFFF), (0xBEEFC0DE, 0x00000000), (0x2096F5B8, 0x000000BE)];
+//! assert_eq!(Ok(Scheme::V7), verify_list(&encrypted, &decrypted));
+//!
+//! let wrong = [(0x2043AFCC, 0x2411FFFF), (0xBEEFC0DE, 0x00000000), (0x2096F5B8, 0x000000FF)];
+//! assert_eq!(
+//!     Err(Divergence {
+//!         line: 2,
+//!         scheme: Scheme::V7,
+//!         expected: Some((0x2096F5B8, 0x000000FF)),
+//!         actual: Some((0x2096F5B8, 0x000000BE)),
+//!     }),
+//!     verify_list(&encrypted, &wrong)
+//! );
+//! ```
+
+use crate::{Codebreaker, Scheme};
+
+/// The first line where an encrypted list and its claimed decrypted
+/// equivalent differ.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Divergence {
+    /// Index of the first differing line
+    pub line: usize,
+    /// The scheme detected up to this line
+    pub scheme: Scheme,
+    /// The claimed decrypted code, `None` if the decrypted list is too short
+    pub expected: Option<(u32, u32)>,
+    /// The actually decrypted code, `None` if the encrypted list is too short
+    pub actual: Option<(u32, u32)>,
+}
+
+/// Checks that `encrypted` decrypts to `decrypted`.
+///
+/// The encrypted list is decrypted with
+/// [`auto_decrypt_code`](../struct.Codebreaker.html#method.auto_decrypt_code),
+/// and, for lists published without a beefcode, with the default CB v7
+/// encryption of [`new_v7`](../struct.Codebreaker.html#method.new_v7).
+///
+/// Returns the highest scheme found in the list if both lists correspond.
+/// Otherwise, returns the first divergence of the candidate that matched the
+/// most lines.
+pub fn verify_list(encrypted: &[(u32, u32)], decrypted: &[(u32, u32)]) -> Result<Scheme, Divergence> {
+    let auto = check(Codebreaker::new(), encrypted, decrypted, |cb, code| {
+        let result = cb.auto_decrypt_code_detailed(code.0, code.1);
+        (result.decrypted, result.scheme)
+    });
+    let a = match auto {
+        Ok(scheme) => return Ok(scheme),
+        Err(a) => a,
+    };
+
+    let v7 = check(Codebreaker::new_v7(), encrypted, decrypted, |cb, code| {
+        (cb.decrypt_code(code.0, code.1), Scheme::V7)
+    });
+    let b = match v7 {
+        Ok(scheme) => return Ok(scheme),
+        Err(b) => b,
+    };
+
+    Err(if b.line > a.line { b } else { a })
+}
+
+fn check<F>(
+    mut cb: Codebreaker,
+    encrypted: &[(u32, u32)],
+    decrypted: &[(u32, u32)],
+    mut decrypt: F,
+) -> Result<Scheme, Divergence>
+where
+    F: FnMut(&mut Codebreaker, (u32, u32)) -> ((u32, u32), Scheme),
+{
+    let mut highest = Scheme::Raw;
+
+    for (line, &code) in encrypted.iter().enumerate() {
+        let (actual, scheme) = decrypt(&mut cb, code);
+        highest = max_scheme(highest, scheme);
+
+        let expected = decrypted.get(line).copied();
+        if expected != Some(actual) {
+            return Err(Divergence {
+                line,
+                scheme: highest,
+                expected,
+                actual: Some(actual),
+            });
+        }
+    }
+
+    if decrypted.len() > encrypted.len() {
+        return Err(Divergence {
+            line: encrypted.len(),
+            scheme: highest,
+            expected: Some(decrypted[encrypted.len()]),
+            actual: None,
+        });
+    }
+
+    Ok(highest)
+}
+
+const fn max_scheme(a: Scheme, b: Scheme) -> Scheme {
+    match (a, b) {
+        (Scheme::V7, _) | (_, Scheme::V7) => Scheme::V7,
+        (Scheme::V1, _) | (_, Scheme::V1) => Scheme::V1,
+        _ => Scheme::Raw,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::code;
+    use crate::std_alloc::Vec;
+
+    fn parse(lines: &[&str]) -> Vec<(u32, u32)> {
+        lines.iter().map(|line| code::parse(line)).collect()
+    }
+
+    #[test]
+    fn test_verify_list() {
+        #[rustfmt::skip]
+        let tests = [
+            (
+                // raw
+                vec!["9029BEAC 0C0A9225", "201F6024 00000000"],
+                vec!["9029BEAC 0C0A9225", "201F6024 00000000"],
+                Scheme::Raw,
+            ),
+            (
+                // v1 encrypted
+                vec!["9A545CC6 188CBCFB", "2A973DBD 00000000"],
+                vec!["9029BEAC 0C0A9225", "201F6024 00000000"],
+                Scheme::V1,
+            ),
+            (
+                // v7 encrypted with beefcode
+                vec!["B4336FA9 4DFEFB79", "D08F3A49 00078A53", "3818DDE5 E72B2B16"],
+                vec!["BEEFC0DE 00000000", "9029BEAC 0C0A9225", "201F6024 00000000"],
+                Scheme::V7,
+            ),
+            (
+                // v7 encrypted without beefcode
+                vec!["D08F3A49 00078A53", "3818DDE5 E72B2B16", "973E0B2A A7D4AF10"],
+                vec!["9029BEAC 0C0A9225", "201F6024 00000000", "2096F5B8 000000BE"],
+                Scheme::V7,
+            ),
+        ];
+        for t in tests.iter() {
+            assert_eq!(Ok(t.2), verify_list(&parse(&t.0), &parse(&t.1)));
+        }
+    }
+
+    #[test]
+    fn test_verify_list_divergence() {
+        let encrypted = parse(&["D08F3A49 00078A53", "3818DDE5 E72B2B16", "973E0B2A A7D4AF10"]);
+
+        let decrypted = parse(&["9029BEAC 0C0A9225", "201F6024 00000001", "2096F5B8 000000BE"]);
+        let result = verify_list(&encrypted, &decrypted);
+        assert_eq!(1, result.unwrap_err().line);
+        assert_eq!(Some((0x201f_6024, 0)), result.unwrap_err().actual);
+
+        let decrypted = parse(&["9029BEAC 0C0A9225", "201F6024 00000000"]);
+        let result = verify_list(&encrypted, &decrypted);
+        assert_eq!(2, result.unwrap_err().line);
+        assert_eq!(None, result.unwrap_err().expected);
+
+        let decrypted = parse(&["9029BEAC 0C0A9225"]);
+        let result = verify_list(&encrypted[..0], &decrypted);
+        assert_eq!(0, result.unwrap_err().line);
+        assert_eq!(None, result.unwrap_err().actual);
+    }
+}
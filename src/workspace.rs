@@ -0,0 +1,431 @@
+//! Workspaces of many cheat documents.
+//!
+//! Curating cheats means working with many sources at once, e.g. one cheat
+//! file per downloaded archive. A [`Workspace`] holds each source as a
+//! [`Document`] of [`Game`]s, so that they can be searched together, cleared
+//! of cheats that several sources share, and merged after a preview of what
+//! would change. With the `std` feature, a workspace is saved to and loaded
+//! from a directory with a ps2rd cheat file per document.
+//!
+//! Documents hold decrypted codes, like those read by
+//! [`ps2rd::parse`](../ps2rd/fn.parse.html).
+//!
+//! # Example
+//! ```
+//! use codebreaker::game::{Cheat, Game};
+//! use codebreaker::query::Query;
+//! use codebreaker::workspace::{Document, Workspace};
+//!
+//! let mut game = Game::new("Kingdom Hearts");
+//! game.cheats.push(Cheat::new("Inf HP", vec![(0x2096F5B8, 0x000000BE)]));
+//!
+//! let mut workspace = Workspace::new();
+//! workspace.documents.push(Document::new("cmgsccc", vec![game.clone()]));
+//! workspace.documents.push(Document::new("gamehacking", vec![game]));
+//!
+//! let query = Query::parse("addr:0x0096F5B8").unwrap();
+//! assert_eq!(2, workspace.search(&query).len());
+//! assert_eq!(1, workspace.dedup());
+//! ```
+
+use crate::diff::{diff, Change};
+use crate::game::{Cheat, Game};
+use crate::query::{Hit, Query};
+use crate::std_alloc::{String, Vec};
+
+/// A source of cheats, such as a cheat file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Document {
+    /// The name of the document, unique within a workspace
+    pub name: String,
+    /// The games in order of appearance
+    pub games: Vec<Game>,
+}
+
+impl Document {
+    /// Returns a document of the given games.
+    pub fn new(name: &str, games: Vec<Game>) -> Self {
+        Self {
+            name: name.into(),
+            games,
+        }
+    }
+}
+
+/// A code line matching a query, in one of the documents.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Found {
+    /// Index of the document
+    pub document: usize,
+    /// Where the code is in the document
+    pub hit: Hit,
+}
+
+/// The place of a cheat in a workspace.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Location {
+    /// Index of the document
+    pub document: usize,
+    /// Index of the game in the document
+    pub game: usize,
+    /// Index of the cheat in the game
+    pub cheat: usize,
+}
+
+/// A cheat with the same codes as an earlier one of the same game.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Duplicate {
+    /// The earlier cheat, which is kept
+    pub original: Location,
+    /// The cheat with the same codes
+    pub copy: Location,
+}
+
+/// What merging one document into another would do with a game or cheat of
+/// the document merged from.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Merge {
+    /// The game would be added, as there is none with its title.
+    AddGame {
+        /// Index of the game
+        game: usize,
+    },
+    /// The cheat would be added to the game of the same title.
+    AddCheat {
+        /// Index of the game
+        game: usize,
+        /// Index of the cheat in the game
+        cheat: usize,
+    },
+    /// A cheat of the same name has other codes. The cheat would be added
+    /// next to it.
+    Conflict {
+        /// Index of the game
+        game: usize,
+        /// Index of the cheat in the game
+        cheat: usize,
+        /// The changes from the codes of the cheat of the same name
+        changes: Vec<Change>,
+    },
+}
+
+/// Documents to curate together.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Workspace {
+    /// The documents in order
+    pub documents: Vec<Document>,
+}
+
+impl Workspace {
+    /// Returns a workspace without documents.
+    pub const fn new() -> Self {
+        Self { documents: Vec::new() }
+    }
+
+    /// Returns the document with the given name.
+    pub fn document(&self, name: &str) -> Option<&Document> {
+        self.documents.iter().find(|document| document.name == name)
+    }
+
+    /// Returns the codes of all documents that match a query, in order, like
+    /// [`Query::search`](../query/struct.Query.html#method.search).
+    pub fn search(&self, query: &Query) -> Vec<Found> {
+        let mut found = Vec::new();
+        for (d, document) in self.documents.iter().enumerate() {
+            found.extend(
+                query
+                    .search(&document.games)
+                    .into_iter()
+                    .map(|hit| Found { document: d, hit }),
+            );
+        }
+        found
+    }
+
+    /// Returns the cheats with the same codes as an earlier cheat of a game
+    /// with the same title, in any document. Cheat names are ignored, as
+    /// sources often name the same cheat differently.
+    pub fn duplicates(&self) -> Vec<Duplicate> {
+        let mut seen: Vec<(&Game, &Cheat, Location)> = Vec::new();
+        let mut duplicates = Vec::new();
+        for (d, document) in self.documents.iter().enumerate() {
+            for (g, game) in document.games.iter().enumerate() {
+                for (c, cheat) in game.cheats.iter().enumerate() {
+                    if cheat.codes.is_empty() {
+                        continue;
+                    }
+                    let copy = Location {
+                        document: d,
+                        game: g,
+                        cheat: c,
+                    };
+                    let original = seen
+                        .iter()
+                        .find(|(other_game, other, _)| other_game.title == game.title && other.codes == cheat.codes);
+                    match original {
+                        Some(&(_, _, original)) => duplicates.push(Duplicate { original, copy }),
+                        None => seen.push((game, cheat, copy)),
+                    }
+                }
+            }
+        }
+        duplicates
+    }
+
+    /// Removes the [`duplicates`](#method.duplicates), keeping the earliest
+    /// cheat of each. Returns the number of cheats removed.
+    pub fn dedup(&mut self) -> usize {
+        let duplicates = self.duplicates();
+        // Removing from the back keeps the indexes of the others valid
+        for duplicate in duplicates.iter().rev() {
+            let copy = duplicate.copy;
+            self.documents[copy.document].games[copy.game].cheats.remove(copy.cheat);
+        }
+        duplicates.len()
+    }
+
+    /// Returns what [`merge`](#method.merge) would do, without changing the
+    /// workspace. Cheats that are already there with the same name and codes
+    /// are left out.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a document index is out of bounds.
+    pub fn merge_preview(&self, from: usize, into: usize) -> Vec<Merge> {
+        let target = &self.documents[into];
+        let mut merges = Vec::new();
+        for (g, game) in self.documents[from].games.iter().enumerate() {
+            let existing = match target.games.iter().find(|other| other.title == game.title) {
+                Some(existing) => existing,
+                None => {
+                    merges.push(Merge::AddGame { game: g });
+                    continue;
+                }
+            };
+            for (c, cheat) in game.cheats.iter().enumerate() {
+                let named: Vec<_> = existing
+                    .cheats
+                    .iter()
+                    .filter(|other| other.name == cheat.name)
+                    .collect();
+                if named.iter().any(|other| other.codes == cheat.codes) {
+                    continue;
+                }
+                match named.first() {
+                    Some(other) => merges.push(Merge::Conflict {
+                        game: g,
+                        cheat: c,
+                        changes: diff(&other.codes, &cheat.codes),
+                    }),
+                    None => merges.push(Merge::AddCheat { game: g, cheat: c }),
+                }
+            }
+        }
+        merges
+    }
+
+    /// Merges the games and cheats of one document into another, as shown
+    /// by [`merge_preview`](#method.merge_preview). Games are added at the
+    /// end, and cheats at the end of the game of the same title.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a document index is out of bounds.
+    pub fn merge(&mut self, from: usize, into: usize) {
+        let merges = self.merge_preview(from, into);
+        let source = self.documents[from].games.clone();
+        let target = &mut self.documents[into].games;
+        for merge in merges {
+            match merge {
+                Merge::AddGame { game } => target.push(source[game].clone()),
+                Merge::AddCheat { game, cheat } | Merge::Conflict { game, cheat, .. } => {
+                    let title = &source[game].title;
+                    if let Some(existing) = target.iter_mut().find(|other| &other.title == title) {
+                        existing.cheats.push(source[game].cheats[cheat].clone());
+                    }
+                }
+            }
+        }
+    }
+
+    /// Writes each document to `<name>.txt` in a directory as a ps2rd cheat
+    /// file, creating the directory if needed.
+    ///
+    /// Files of documents no longer in the workspace are left alone.
+    ///
+    /// # Errors
+    ///
+    /// Fails if a file cannot be written, or if the name of a document is
+    /// not a plain file name.
+    #[cfg(feature = "std")]
+    pub fn save<P: AsRef<std::path::Path>>(&self, dir: P) -> std::io::Result<()> {
+        use std::{fs, io};
+
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir)?;
+        for document in self.documents.iter() {
+            let name = &document.name;
+            if name.is_empty() || name.starts_with('.') || name.contains(['/', '\\']) {
+                let message = format!("{:?} is not a file name", name);
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, message));
+            }
+            fs::write(
+                dir.join(format!("{}.txt", name)),
+                crate::ps2rd::to_string(&document.games),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Reads a workspace from the `.txt` files of a directory, as written by
+    /// [`save`](#method.save). Other files are ignored. The documents are
+    /// sorted by name.
+    ///
+    /// # Errors
+    ///
+    /// Fails if a file cannot be read, if it is not UTF-8, or if it is not a
+    /// ps2rd cheat file.
+    #[cfg(feature = "std")]
+    pub fn load<P: AsRef<std::path::Path>>(dir: P) -> std::io::Result<Self> {
+        use std::{fs, io};
+
+        let mut workspace = Self::new();
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().map_or(true, |ext| ext != "txt") {
+                continue;
+            }
+            let name = match path.file_stem().and_then(|s| s.to_str()) {
+                Some(name) => name,
+                None => return Err(io::Error::new(io::ErrorKind::InvalidData, "file name is not UTF-8")),
+            };
+            let games = crate::ps2rd::parse(&fs::read_to_string(&path)?)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), e)))?;
+            workspace.documents.push(Document::new(name, games));
+        }
+        workspace.documents.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(workspace)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Code;
+
+    fn game(title: &str, cheats: &[(&str, u32)]) -> Game {
+        let mut game = Game::new(title);
+        for &(name, val) in cheats.iter() {
+            game.cheats.push(Cheat::new(name, vec![(0x2096_f5b8, val)]));
+        }
+        game
+    }
+
+    fn sources() -> Workspace {
+        let mut workspace = Workspace::new();
+        workspace.documents.push(Document::new(
+            "a",
+            vec![game("KH", &[("Inf HP", 0xbe), ("Max HP", 0xff)])],
+        ));
+        workspace.documents.push(Document::new(
+            "b",
+            vec![
+                game("KH", &[("Infinite HP", 0xbe), ("Max HP", 0x63), ("Low HP", 1)]),
+                game("FFX", &[("Inf HP", 0xbe)]),
+            ],
+        ));
+        workspace
+    }
+
+    #[test]
+    fn test_search() {
+        let workspace = sources();
+        let found = workspace.search(&Query::parse("game:FFX").unwrap());
+        assert_eq!(
+            vec![Found {
+                document: 1,
+                hit: Hit {
+                    game: 1,
+                    cheat: 0,
+                    line: 0
+                }
+            }],
+            found
+        );
+        assert_eq!(6, workspace.search(&Query::default()).len());
+        assert_eq!("b", workspace.document("b").unwrap().name);
+        assert_eq!(None, workspace.document("c"));
+    }
+
+    #[test]
+    fn test_dedup() {
+        let mut workspace = sources();
+        let location = |document, game, cheat| Location { document, game, cheat };
+        assert_eq!(
+            vec![Duplicate {
+                original: location(0, 0, 0),
+                copy: location(1, 0, 0)
+            }],
+            workspace.duplicates()
+        );
+        assert_eq!(1, workspace.dedup());
+        assert_eq!(2, workspace.documents[1].games[0].cheats.len());
+        assert_eq!("Max HP", workspace.documents[1].games[0].cheats[0].name);
+        assert_eq!(0, workspace.dedup());
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut workspace = sources();
+        assert_eq!(
+            vec![
+                Merge::AddCheat { game: 0, cheat: 0 },
+                Merge::Conflict {
+                    game: 0,
+                    cheat: 1,
+                    changes: vec![Change::Changed(
+                        Code::new(0x2096_f5b8, 0xff),
+                        Code::new(0x2096_f5b8, 0x63)
+                    )]
+                },
+                Merge::AddCheat { game: 0, cheat: 2 },
+                Merge::AddGame { game: 1 },
+            ],
+            workspace.merge_preview(1, 0)
+        );
+        assert_eq!(sources(), workspace);
+
+        workspace.merge(1, 0);
+        let games = &workspace.documents[0].games;
+        assert_eq!(2, games.len());
+        assert_eq!(5, games[0].cheats.len());
+        assert_eq!("FFX", games[1].title);
+        assert_eq!(Vec::<Merge>::new(), workspace.merge_preview(1, 0));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_save_load() {
+        use std::fs;
+
+        let dir = std::env::temp_dir().join(format!("codebreaker-workspace-{}", std::process::id()));
+        let workspace = sources();
+        workspace.save(&dir).unwrap();
+        fs::write(dir.join("README"), "ignored").unwrap();
+        assert_eq!(workspace, Workspace::load(&dir).unwrap());
+
+        fs::write(dir.join("c.txt"), "Inf HP\n").unwrap();
+        let err = Workspace::load(&dir).unwrap_err();
+        assert!(
+            format!("{}", err).ends_with("c.txt: line 1: cheat outside of a game"),
+            "{}",
+            err
+        );
+
+        let mut invalid = Workspace::new();
+        invalid.documents.push(Document::new("../a", vec![]));
+        assert_eq!(std::io::ErrorKind::InvalidInput, invalid.save(&dir).unwrap_err().kind());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}